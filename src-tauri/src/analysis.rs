@@ -0,0 +1,223 @@
+/**
+ * 文本分析模块
+ * 提供分词、停用词过滤与词频统计
+ */
+
+use crate::models::{EntryAnalysis, MemoryEntry};
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet};
+
+/// 内置英文停用词
+const ENGLISH_STOPWORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "but", "by", "for", "from", "had", "has",
+    "have", "he", "her", "his", "i", "in", "is", "it", "its", "me", "my", "not", "of", "on",
+    "or", "our", "she", "so", "that", "the", "their", "them", "they", "this", "to", "was",
+    "we", "were", "with", "you", "your",
+];
+
+/// 内置中文停用词（中文按单字切分，因此均为单字）
+const CHINESE_STOPWORDS: &[&str] = &[
+    "的", "了", "是", "在", "和", "就", "都", "也", "很", "着", "吗", "呢", "吧", "啊",
+    "这", "那", "我", "你", "他", "她", "它", "们", "个", "与", "及", "而",
+];
+
+/// 判断字符是否为中日韩表意文字或假名
+pub fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30FF}'
+        | '\u{3400}'..='\u{4DBF}'
+        | '\u{4E00}'..='\u{9FFF}'
+        | '\u{F900}'..='\u{FAFF}'
+        | '\u{AC00}'..='\u{D7AF}'
+        | '\u{20000}'..='\u{2A6DF}')
+}
+
+/// 分词：拉丁文字按连续字母数字切分并转为小写，中日韩文字每个字符为一个词
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+
+    for c in text.chars() {
+        if is_cjk(c) {
+            if !current.is_empty() {
+                tokens.push(std::mem::take(&mut current));
+            }
+            tokens.push(c.to_string());
+        } else if c.is_alphanumeric() || (c == '\'' && !current.is_empty()) {
+            current.extend(c.to_lowercase());
+        } else if !current.is_empty() {
+            tokens.push(std::mem::take(&mut current));
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+
+    // 去掉词尾残留的撇号
+    tokens.into_iter()
+        .map(|t| t.trim_end_matches('\'').to_string())
+        .filter(|t| !t.is_empty())
+        .collect()
+}
+
+/// 构建停用词集合（内置列表加上额外停用词）
+pub fn stopwords(extra: &[String]) -> HashSet<String> {
+    ENGLISH_STOPWORDS.iter()
+        .chain(CHINESE_STOPWORDS.iter())
+        .map(|w| w.to_string())
+        .chain(extra.iter().map(|w| w.trim().to_lowercase()))
+        .collect()
+}
+
+/// 统计句子数量
+fn count_sentences(text: &str) -> usize {
+    text.split(['.', '!', '?', '。', '！', '？', '\n'])
+        .filter(|s| s.chars().any(|c| c.is_alphanumeric()))
+        .count()
+}
+
+/// 统计非停用词的词频（次数降序，相同次数按字典序）
+pub fn word_frequencies(tokens: &[String], stopwords: &HashSet<String>) -> Vec<(String, u32)> {
+    let mut counts: HashMap<&str, u32> = HashMap::new();
+    for token in tokens.iter().filter(|t| !stopwords.contains(*t)) {
+        *counts.entry(token).or_insert(0) += 1;
+    }
+
+    let mut frequencies: Vec<(String, u32)> = counts.into_iter()
+        .map(|(word, count)| (word.to_string(), count))
+        .collect();
+    frequencies.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    frequencies
+}
+
+/// 分析文本：高频词、总词数、不同词数与平均句长
+pub fn analyze_text(text: &str, top_n: usize, extra_stopwords: &[String]) -> EntryAnalysis {
+    let tokens = tokenize(text);
+    let stopwords = stopwords(extra_stopwords);
+
+    let mut top_words = word_frequencies(&tokens, &stopwords);
+    top_words.truncate(top_n);
+
+    let unique_words = tokens.iter().collect::<HashSet<_>>().len();
+    let sentences = count_sentences(text);
+    let average_sentence_length = if sentences == 0 {
+        0.0
+    } else {
+        tokens.len() as f32 / sentences as f32
+    };
+
+    EntryAnalysis {
+        top_words,
+        total_words: tokens.len() as u32,
+        unique_words: unique_words as u32,
+        average_sentence_length,
+    }
+}
+
+/// 全局词云缓存的失效键：条目数量、最新更新时间和额外停用词
+type WordCloudKey = (usize, Option<DateTime<Utc>>, Vec<String>);
+
+/// 全局词云缓存
+///
+/// 任何条目新增、删除或修改都会改变失效键，从而触发重新计算。
+#[derive(Debug, Default)]
+pub struct WordCloudCache {
+    key: Option<WordCloudKey>,
+    frequencies: Vec<(String, u32)>,
+    computations: u32,
+}
+
+impl WordCloudCache {
+    /// 获取所有条目正文的高频词，缓存有效时直接返回
+    pub fn top_words(&mut self, entries: &[MemoryEntry], top_n: usize, extra_stopwords: &[String]) -> Vec<(String, u32)> {
+        let key = (
+            entries.len(),
+            entries.iter().map(|e| e.updated_at).max(),
+            extra_stopwords.to_vec(),
+        );
+
+        if self.key.as_ref() != Some(&key) {
+            let stopwords = stopwords(extra_stopwords);
+            let tokens: Vec<String> = entries.iter().flat_map(|e| tokenize(&e.content)).collect();
+            self.frequencies = word_frequencies(&tokens, &stopwords);
+            self.key = Some(key);
+            self.computations += 1;
+        }
+
+        self.frequencies.iter().take(top_n).cloned().collect()
+    }
+
+    /// 清空缓存的词频（会话锁定时调用）
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_analyze_english_paragraph() {
+        let text = "The rain fell. The rain stopped, and the sun came out!";
+        let analysis = analyze_text(text, 3, &[]);
+
+        assert_eq!(analysis.total_words, 11);
+        assert_eq!(analysis.unique_words, 8);
+        assert_eq!(analysis.average_sentence_length, 5.5);
+        assert_eq!(analysis.top_words, vec![
+            ("rain".to_string(), 2),
+            ("came".to_string(), 1),
+            ("fell".to_string(), 1),
+        ]);
+    }
+
+    #[test]
+    fn test_analyze_chinese_with_extra_stopwords() {
+        let text = "春雨落下了。春雨停了，桃花开。";
+        let analysis = analyze_text(text, 2, &["雨".to_string()]);
+
+        assert_eq!(analysis.total_words, 12);
+        assert_eq!(analysis.average_sentence_length, 6.0);
+        assert_eq!(analysis.top_words, vec![
+            ("春".to_string(), 2),
+            ("下".to_string(), 1),
+        ]);
+    }
+
+    #[test]
+    fn test_tokenize_mixed_text() {
+        assert_eq!(tokenize("Don't 忘记 Kyoto2023"), vec!["don't", "忘", "记", "kyoto2023"]);
+    }
+
+    #[test]
+    fn test_word_cloud_aggregates_and_caches() {
+        use crate::models::MemoryType;
+
+        let mut entries = vec![
+            MemoryEntry::new("一".to_string(), "The rain fell on the garden".to_string(), MemoryType::Text),
+            MemoryEntry::new("二".to_string(), "Rain again, 桃花 in the rain".to_string(), MemoryType::Text),
+            MemoryEntry::new("三".to_string(), "桃花 garden".to_string(), MemoryType::Text),
+        ];
+        let mut cache = WordCloudCache::default();
+
+        let top = cache.top_words(&entries, 3, &[]);
+        assert_eq!(top, vec![
+            ("rain".to_string(), 3),
+            ("garden".to_string(), 2),
+            ("桃".to_string(), 2),
+        ]);
+
+        // 没有修改时直接命中缓存
+        assert_eq!(cache.top_words(&entries, 1, &[]), vec![("rain".to_string(), 3)]);
+        assert_eq!(cache.computations, 1);
+
+        // 停用词变化或条目修改都会重新计算
+        assert_eq!(cache.top_words(&entries, 1, &["rain".to_string()])[0].0, "garden");
+        assert_eq!(cache.computations, 2);
+
+        entries[2].update(None, Some("garden garden garden garden".to_string()), &Default::default());
+        assert_eq!(cache.top_words(&entries, 1, &["rain".to_string()]), vec![("garden".to_string(), 5)]);
+        assert_eq!(cache.computations, 3);
+    }
+}
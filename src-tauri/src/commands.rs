@@ -3,10 +3,20 @@
  * 定义前端可以调用的所有后端命令
  */
 
-use crate::models::{MemoryEntry, SearchFilter, ApiResponse, MemoryType};
-use crate::crypto::{BackendEncryption, DecryptionParams};
-use crate::storage::StorageManager;
-use tauri::{AppHandle, Manager};
+use crate::models::{MemoryEntry, Attachment, CaptureLocation, EntryPatch, AttachmentReport, BulkResult, OrphanReport, OrphanedAttachment, ExportReport, StorageReport, StorageUsage, StoreStatus, ReencryptProgress, SearchFilter, SearchSort, TagNode, SearchPage, ApiResponse, ErrorCode, MemoryType, EmotionTag, EntryAnalysis, EntryHighlights, ImportReport, MergeReport, MergeStrategy, RepairReport, RestoreReport, BackupValidation, IntegrityReport, SanitizeReport, SettingField, UserSettings, VaultInfo, VaultSecurityInfo, DreamConfig, EntryTemplate, WeekSummary, WritingStreak, DateRange, EmotionCount, EmotionPair, MemoryStats, MonthGroup};
+use crate::crypto::{BackendEncryption, DecryptError, DecryptionParams, EncryptionConfig, FileEncryption, LossyDecryption};
+use crate::importers::{ExternalFormat, GenericFieldMap};
+use crate::storage::{Storage, StorageCache, StorageManager, TagIndexCache, DEFAULT_PAGE_SIZE, reveal_entry_content, reveal_locked_entries, sort_entries, unlock_entry_content};
+use crate::throttle::AttemptThrottle;
+use crate::session::{UiLock, VaultSession};
+use crate::vaults::{VaultRegistry, DEFAULT_VAULT};
+use crate::analysis::WordCloudCache;
+use crate::undo::{UndoRecord, UndoStack};
+use crate::reminder;
+use crate::templates;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri::async_runtime::JoinHandle;
+use tauri_plugin_notification::NotificationExt;
 use std::sync::Mutex;
 use std::collections::HashMap;
 
@@ -15,20 +25,259 @@ use std::collections::HashMap;
 #[allow(dead_code)]
 type StorageManagerMap = Mutex<HashMap<String, StorageManager>>;
 
-/// 获取或创建存储管理器
-async fn get_storage_manager(app: &AppHandle) -> Result<StorageManager, String> {
+/// 解密尝试限流状态（按会话记录连续失败次数）
+#[derive(Default)]
+pub struct DecryptThrottle(Mutex<AttemptThrottle>);
+
+/// 将解密错误映射为错误码（非解密错误返回 None）
+fn decrypt_error_code(error: &anyhow::Error) -> Option<ErrorCode> {
+    match error.downcast_ref::<DecryptError>()? {
+        DecryptError::WrongPassword => Some(ErrorCode::InvalidPassword),
+        DecryptError::CorruptData(_) => Some(ErrorCode::CorruptData),
+    }
+}
+
+/// 解密失败：错误信息及供前端区分“重试密码”与“文件损坏”的错误码
+struct DecryptFailure {
+    message: String,
+    code: Option<ErrorCode>,
+}
+
+impl DecryptFailure {
+    fn new(message: String) -> Self {
+        Self { message, code: None }
+    }
+
+    /// 生成带错误码的错误响应
+    fn into_response<T>(self, data: Option<T>) -> ApiResponse<T> {
+        let response = match data {
+            Some(data) => ApiResponse::error_with_data(data, self.message),
+            None => ApiResponse::error(self.message),
+        };
+        match self.code {
+            Some(code) => response.with_error_code(code),
+            None => response,
+        }
+    }
+}
+
+impl DecryptThrottle {
+    /// 在限流保护下执行解密，密码错误计数，成功重置
+    ///
+    /// 密文损坏不计入失败次数，因为换密码重试也无法成功。
+    fn run<T>(&self, decrypt: impl FnOnce() -> anyhow::Result<T>) -> Result<T, DecryptFailure> {
+        let now = chrono::Utc::now();
+        if let Err(wait) = self.0.lock().map_err(|e| DecryptFailure::new(e.to_string()))?.check(now) {
+            let wait_secs = (wait.num_milliseconds() + 999) / 1000;
+            return Err(DecryptFailure::new(format!("Too many failed attempts, please wait {} seconds", wait_secs)));
+        }
+
+        let result = decrypt();
+
+        let mut throttle = self.0.lock().map_err(|e| DecryptFailure::new(e.to_string()))?;
+        match result {
+            Ok(value) => {
+                throttle.record_success();
+                Ok(value)
+            }
+            Err(e) => {
+                let code = decrypt_error_code(&e).unwrap_or(ErrorCode::InvalidPassword);
+                if code != ErrorCode::CorruptData {
+                    throttle.record_failure(chrono::Utc::now());
+                }
+                Err(DecryptFailure { message: format!("Decryption failed: {}", e), code: Some(code) })
+            }
+        }
+    }
+}
+
+/// 全局词云缓存（跨命令调用保留）
+#[derive(Default)]
+pub struct WordCloudState(Mutex<WordCloudCache>);
+
+/// 已用标签与心情缓存（跨命令调用保留）
+#[derive(Default)]
+pub struct TagIndexState(Mutex<TagIndexCache>);
+
+/// 当前保险库的存储管理器（启动时打开，切换保险库或数据目录后重新打开）
+#[derive(Default)]
+pub struct StorageState(StorageCache);
+
+/// 解锁会话状态（缓存解锁后的密码）
+#[derive(Default)]
+pub struct SessionState(Mutex<VaultSession>);
+
+impl SessionState {
+    /// 使用缓存的密码；因无活动超时而锁定时一并清除内存中的解密数据
+    fn access(&self, app: &AppHandle) -> Result<Option<String>, String> {
+        let mut session = self.0.lock().map_err(|e| e.to_string())?;
+        let was_unlocked = session.is_unlocked();
+        let password = session.access(chrono::Utc::now());
+        if was_unlocked && password.is_none() {
+            forget_decrypted(app);
+        }
+        Ok(password)
+    }
+
+    /// 立即锁定，清除缓存的密码与内存中的解密数据
+    fn lock(&self, app: &AppHandle) -> Result<(), String> {
+        self.0.lock().map_err(|e| e.to_string())?.lock();
+        forget_decrypted(app);
+        Ok(())
+    }
+
+    /// 本次密码与已解锁会话的密码一致时返回会话令牌，用作解密结果缓存的键
+    fn token_for(&self, password: Option<&str>) -> Result<Option<u128>, String> {
+        let session = self.0.lock().map_err(|e| e.to_string())?;
+        Ok(password.and_then(|password| session.token_for(password)))
+    }
+}
+
+/// 清除内存中保存解密内容的状态：撤销记录、已用标签与心情缓存、词云缓存
+///
+/// 会话锁定或存储被整体替换时调用，锁定后不能再从内存中读到解密后的内容。
+fn forget_decrypted(app: &AppHandle) {
+    app.state::<UndoState>().invalidate();
+    if let Ok(mut cache) = app.state::<TagIndexState>().0.lock() {
+        cache.clear();
+    }
+    if let Ok(mut cache) = app.state::<WordCloudState>().0.lock() {
+        cache.clear();
+    }
+}
+
+/// 界面只读锁状态（审阅或共享设备时禁止修改）
+#[derive(Default)]
+pub struct UiLockState(Mutex<UiLock>);
+
+impl UiLockState {
+    /// 界面锁定时把存储切换为只读，写入由存储层的只读检查统一拒绝
+    fn apply(&self, storage: StorageManager) -> Result<StorageManager, String> {
+        let locked = self.0.lock().map_err(|e| e.to_string())?.is_locked();
+        Ok(if locked { storage.into_read_only() } else { storage })
+    }
+}
+
+/// 存储只读（界面已锁定）时返回带 `ReadOnly` 错误码的响应，修改类命令在写入前直接返回它
+///
+/// 漏掉这一检查的命令写入时仍会被存储层拒绝，只是没有可识别的错误码。
+fn reject_read_only<T>(storage: &StorageManager) -> Option<ApiResponse<T>> {
+    storage.is_read_only().then(|| {
+        ApiResponse::error("界面已锁定为只读，请先解锁后再修改".to_string()).with_error_code(ErrorCode::ReadOnly)
+    })
+}
+
+/// 已安排的写作提醒任务，重新安排时取消旧任务
+#[derive(Default)]
+pub struct ReminderState(Mutex<Option<JoinHandle<()>>>);
+
+/// 撤销/重做状态（仅保存在内存中，重启后清空）
+#[derive(Default)]
+pub struct UndoState(Mutex<UndoStack>);
+
+impl UndoState {
+    /// 比较存储记录的修改前后条目，记录一次可撤销的修改
+    ///
+    /// 存储需通过 `with_journal` 开启记录；修改中读写的条目直接复用，不再额外读取存储。
+    fn record(&self, label: &str, storage: &StorageManager) {
+        let Some((before, after)) = storage.take_journal() else { return };
+        if let Some(record) = UndoRecord::diff(label, &before, &after) {
+            if let Ok(mut stack) = self.0.lock() {
+                stack.push(record);
+            }
+        }
+    }
+
+    /// 存储被整体替换（切换保险库、恢复备份等）后清空撤销记录
+    fn invalidate(&self) {
+        if let Ok(mut stack) = self.0.lock() {
+            stack.invalidate();
+        }
+    }
+}
+
+/// 获取应用默认数据目录
+fn default_data_dir(app: &AppHandle) -> Result<String, String> {
     let app_data_dir = app
         .path()
         .app_data_dir()
         .map_err(|e| format!("Failed to get app data dir: {}", e))?;
-    
-    let data_dir_str = app_data_dir
+
+    app_data_dir
         .to_str()
-        .ok_or("Invalid app data directory path")?;
+        .map(str::to_string)
+        .ok_or_else(|| "Invalid app data directory path".to_string())
+}
 
-    StorageManager::new(data_dir_str)
-        .await
-        .map_err(|e| format!("Failed to create storage manager: {}", e))
+/// 获取当前保险库的存储管理器（复用已打开的实例）
+///
+/// 界面锁定为只读时返回只读的存储，所有写入都会被拒绝。
+async fn get_storage_manager(app: &AppHandle) -> Result<StorageManager, String> {
+    let storage = app.state::<StorageState>()
+        .0
+        .get_or_open(|| open_storage_manager(app))
+        .await?;
+    app.state::<UiLockState>().apply(storage)
+}
+
+/// 解析本次调用使用的存储密码：命令传入的密码优先，未传时使用已解锁会话缓存的密码
+///
+/// 所有接受存储密码的命令都先经过这里，解锁后无需每次传入密码。
+/// 每次调用都计为一次用户活动，推迟自动锁定，无需前端另行调用 `record_activity`。
+fn resolve_password(app: &AppHandle, password: Option<String>) -> Result<Option<String>, String> {
+    let cached = app.state::<SessionState>().access(app)?;
+    Ok(password.or(cached))
+}
+
+/// 默认列表与回顾中去掉已归档条目，`include_archived` 为 true 时全部保留（与 `SearchFilter` 一致）
+fn retain_unarchived(entries: &mut Vec<MemoryEntry>, include_archived: Option<bool>) {
+    if !include_archived.unwrap_or(false) {
+        entries.retain(|e| !e.is_archived);
+    }
+}
+
+/// 启动时预先打开存储管理器
+///
+/// 打开失败时不缓存，首次使用时重新打开，错误由该命令返回给前端。
+pub async fn preload_storage_manager(app: &AppHandle) {
+    let _ = get_storage_manager(app).await;
+}
+
+/// 打开当前保险库的存储管理器
+///
+/// 默认保险库遵循设置中的数据目录覆盖，命名保险库位于应用数据目录的 `vaults` 下。
+async fn open_storage_manager(app: &AppHandle) -> Result<StorageManager, String> {
+    let data_dir_str = default_data_dir(app)?;
+    let registry = VaultRegistry::new(&data_dir_str);
+
+    let storage = match registry.active().await.as_str() {
+        DEFAULT_VAULT => StorageManager::open_with_override(&data_dir_str).await,
+        name => StorageManager::new(&registry.vault_dir(name).to_string_lossy()).await,
+    };
+    storage.map_err(|e| format!("Failed to create storage manager: {}", e))
+}
+
+/// 获取读取用的存储管理器，`read_only` 为 true 时以只读方式打开，不会写入数据目录
+async fn get_reader_storage(app: &AppHandle, read_only: Option<bool>) -> Result<StorageManager, String> {
+    if !read_only.unwrap_or(false) {
+        return get_storage_manager(app).await;
+    }
+
+    let data_dir_str = default_data_dir(app)?;
+    let registry = VaultRegistry::new(&data_dir_str);
+
+    let storage = match registry.active().await.as_str() {
+        DEFAULT_VAULT => StorageManager::open_read_only_with_override(&data_dir_str).await,
+        name => StorageManager::new_read_only(&registry.vault_dir(name).to_string_lossy()).await,
+    };
+    storage.map_err(|e| format!("Failed to open storage read-only: {}", e))
+}
+
+/// 解析情感标签字符串（规范化后，非内置标签作为自定义情感，忽略空标签）
+fn parse_emotion_tags(tags: Vec<String>) -> Vec<EmotionTag> {
+    tags.into_iter()
+        .filter_map(|tag_str| EmotionTag::try_from(tag_str).ok())
+        .collect()
 }
 
 /// 初始化应用
@@ -41,19 +290,115 @@ pub async fn initialize_app(app: AppHandle) -> Result<ApiResponse<String>, Strin
 }
 
 /// 创建记忆条目
+///
+/// 提供 `client_id` 时以它作为条目 ID；该 ID 的条目已存在时直接返回已有条目，
+/// 便于前端安全地重试创建请求。
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn create_memory_entry(
     app: AppHandle,
+    undo: State<'_, UndoState>,
+    client_id: Option<String>,
     title: String,
     content: String,
     memory_type: String,
     emotion_tags: Vec<String>,
+    emotion_intensities: Option<HashMap<String, u8>>,
+    tags: Option<Vec<String>>,
+    mood: Option<String>,
+    location: Option<String>,
+    weather: Option<String>,
     password: Option<String>,
 ) -> Result<ApiResponse<MemoryEntry>, String> {
-    let storage = get_storage_manager(&app).await?;
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?.with_password(password.clone()).with_journal();
+    if let Some(response) = reject_read_only(&storage) {
+        return Ok(response);
+    }
+
+    // 计算元数据
+    let settings = storage
+        .load_settings(password.as_deref())
+        .await
+        .map_err(|e| format!("Failed to load settings: {}", e))?;
+    settings.check_content_length(&content)?;
+    let mut entry = build_new_entry(title, content, &memory_type, emotion_tags, tags, &settings);
+    entry.set_emotion_intensities(emotion_intensities.unwrap_or_default())?;
+    entry.set_mood(mood);
+    entry.set_context(location, weather);
+    if let Some(client_id) = client_id {
+        let client_id = client_id.trim();
+        if client_id.is_empty() {
+            return Err("Client id cannot be empty".to_string());
+        }
+        entry.id = client_id.to_string();
+    }
+
+    // 保存条目（同 ID 条目已存在时返回已有条目）
+    let entry = storage
+        .save_new_entry(&entry, password.as_deref())
+        .await
+        .map_err(|e| format!("Failed to save entry: {}", e))?;
+    undo.record("创建条目", &storage);
     
+    Ok(ApiResponse::success(entry))
+}
+
+/// 创建记忆条目并记录位置与天气
+///
+/// 位置与天气仅在设置中开启 `capture_location` 时保存；提供坐标时校验经纬度范围。
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn create_memory_entry_with_context(
+    app: AppHandle,
+    undo: State<'_, UndoState>,
+    title: String,
+    content: String,
+    memory_type: String,
+    emotion_tags: Vec<String>,
+    emotion_intensities: Option<HashMap<String, u8>>,
+    tags: Option<Vec<String>>,
+    location: Option<CaptureLocation>,
+    weather: Option<String>,
+    password: Option<String>,
+) -> Result<ApiResponse<MemoryEntry>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?.with_password(password.clone()).with_journal();
+    if let Some(response) = reject_read_only(&storage) {
+        return Ok(response);
+    }
+
+    let settings = storage
+        .load_settings(password.as_deref())
+        .await
+        .map_err(|e| format!("Failed to load settings: {}", e))?;
+    let location = location.as_ref().map(CaptureLocation::to_metadata).transpose()?.flatten();
+    settings.check_content_length(&content)?;
+
+    let mut entry = build_new_entry(title, content, &memory_type, emotion_tags, tags, &settings);
+    entry.set_emotion_intensities(emotion_intensities.unwrap_or_default())?;
+    set_captured_context(&mut entry, location, weather, &settings);
+
+    storage
+        .save_entry(&entry, password.as_deref())
+        .await
+        .map_err(|e| format!("Failed to save entry: {}", e))?;
+    undo.record("创建条目", &storage);
+
+    Ok(ApiResponse::success(entry))
+}
+
+/// 根据前端输入构建新条目：解析记忆类型与情感标签、计算字数并设置标签
+fn build_new_entry(
+    title: String,
+    content: String,
+    memory_type: &str,
+    emotion_tags: Vec<String>,
+    tags: Option<Vec<String>>,
+    settings: &UserSettings,
+) -> MemoryEntry {
     // 解析记忆类型
-    let memory_type = match memory_type.as_str() {
+    let memory_type = match memory_type {
         "text" => MemoryType::Text,
         "image" => MemoryType::Image,
         "audio" => MemoryType::Audio,
@@ -65,45 +410,51 @@ pub async fn create_memory_entry(
     let mut entry = MemoryEntry::new(title, content, memory_type);
     
     // 添加情感标签
-    for tag_str in emotion_tags {
-        if let Ok(tag) = serde_json::from_str(&format!("\"{}\"", tag_str)) {
-            entry.add_emotion_tag(tag);
-        }
+    for tag in parse_emotion_tags(emotion_tags) {
+        entry.add_emotion_tag(tag);
     }
     
-    // 计算元数据
-    let word_count = entry.content.chars().count() as u32;
-    let reading_time = (word_count / 200).max(1);
-    
-    entry.metadata = Some(crate::models::MemoryMetadata {
-        word_count: Some(word_count),
-        reading_time: Some(reading_time),
-        location: None,
-        weather: None,
-        mood: None,
-        tags: None,
-    });
-    
-    // 保存条目
-    storage
-        .save_entry(&entry, password.as_deref())
-        .await
-        .map_err(|e| format!("Failed to save entry: {}", e))?;
-    
-    Ok(ApiResponse::success(entry))
+    entry.refresh_word_stats(settings);
+
+    // 设置标签（规范化并去重）
+    if let Some(tags) = tags {
+        entry.set_tags(tags);
+    }
+
+    entry
+}
+
+/// 记录自动采集的位置与天气，仅在设置开启 `capture_location` 时生效
+///
+/// 用户手动填写的位置与天气不受此设置限制，直接通过 `set_context` 保存。
+fn set_captured_context(entry: &mut MemoryEntry, location: Option<String>, weather: Option<String>, settings: &UserSettings) {
+    if settings.capture_location {
+        entry.set_context(location, weather);
+    }
 }
 
 /// 更新记忆条目
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn update_memory_entry(
     app: AppHandle,
+    undo: State<'_, UndoState>,
     entry_id: String,
     title: Option<String>,
     content: Option<String>,
     emotion_tags: Option<Vec<String>>,
+    emotion_intensities: Option<HashMap<String, u8>>,
+    tags: Option<Vec<String>>,
+    mood: Option<String>,
+    location: Option<String>,
+    weather: Option<String>,
     password: Option<String>,
 ) -> Result<ApiResponse<MemoryEntry>, String> {
-    let storage = get_storage_manager(&app).await?;
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?.with_password(password.clone()).with_journal();
+    if let Some(response) = reject_read_only(&storage) {
+        return Ok(response);
+    }
     
     // 获取现有条目
     let mut entry = storage
@@ -112,17 +463,46 @@ pub async fn update_memory_entry(
         .map_err(|e| format!("Failed to get entry: {}", e))?
         .ok_or("Entry not found")?;
     
+    // 已加锁条目的正文需先解锁才能修改
+    if entry.encrypted_content.is_some() && content.is_some() {
+        return Ok(ApiResponse::error("Entry is locked".to_string())
+            .with_error_code(ErrorCode::EntryLocked));
+    }
+
     // 更新条目
-    entry.update(title, content);
+    let settings = storage
+        .load_settings(password.as_deref())
+        .await
+        .map_err(|e| format!("Failed to load settings: {}", e))?;
+    if let Some(content) = &content {
+        settings.check_content_length(content)?;
+    }
+    entry.update(title, content, &settings);
     
-    // 更新情感标签
-    if let Some(tags) = emotion_tags {
-        entry.emotion_tags.clear();
-        for tag_str in tags {
-            if let Ok(tag) = serde_json::from_str(&format!("\"{}\"", tag_str)) {
-                entry.add_emotion_tag(tag);
-            }
-        }
+    // 更新情感标签与强度
+    if let Some(emotion_tags) = emotion_tags {
+        entry.set_emotion_tags(parse_emotion_tags(emotion_tags));
+    }
+    if let Some(emotion_intensities) = emotion_intensities {
+        entry.set_emotion_intensities(emotion_intensities)?;
+    }
+
+    // 更新标签
+    if let Some(tags) = tags {
+        entry.set_tags(tags);
+    }
+
+    // 更新心情（传入空字符串可清除）
+    if mood.is_some() {
+        entry.set_mood(mood);
+    }
+
+    // 更新位置与天气（传入空字符串可清除）
+    if location.is_some() {
+        entry.set_location(location);
+    }
+    if weather.is_some() {
+        entry.set_weather(weather);
     }
     
     // 保存更新后的条目
@@ -130,141 +510,2241 @@ pub async fn update_memory_entry(
         .save_entry(&entry, password.as_deref())
         .await
         .map_err(|e| format!("Failed to update entry: {}", e))?;
+    undo.record("编辑条目", &storage);
     
     Ok(ApiResponse::success(entry))
 }
 
+/// 增量修改记忆条目（追加正文、增删标签等，见 [`EntryPatch`]）
+#[tauri::command]
+pub async fn patch_memory_entry(
+    app: AppHandle,
+    undo: State<'_, UndoState>,
+    entry_id: String,
+    patch: EntryPatch,
+    password: Option<String>,
+) -> Result<ApiResponse<MemoryEntry>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?.with_password(password.clone()).with_journal();
+    if let Some(response) = reject_read_only(&storage) {
+        return Ok(response);
+    }
+
+    let mut entry = storage
+        .get_entry(&entry_id)
+        .await
+        .map_err(|e| format!("Failed to get entry: {}", e))?
+        .ok_or("Entry not found")?;
+
+    if entry.encrypted_content.is_some() && patch.append_content.is_some() {
+        return Ok(ApiResponse::error("Entry is locked".to_string())
+            .with_error_code(ErrorCode::EntryLocked));
+    }
+
+    let settings = storage
+        .load_settings(password.as_deref())
+        .await
+        .map_err(|e| format!("Failed to load settings: {}", e))?;
+    let appends_content = patch.append_content.is_some();
+    entry.apply_patch(patch, &settings)
+        .map_err(|e| format!("Failed to patch entry: {}", e))?;
+    if appends_content {
+        settings.check_content_length(&entry.content)?;
+    }
+
+    storage
+        .save_entry(&entry, password.as_deref())
+        .await
+        .map_err(|e| format!("Failed to update entry: {}", e))?;
+    undo.record("编辑条目", &storage);
+
+    Ok(ApiResponse::success(entry))
+}
+
 /// 删除记忆条目
 #[tauri::command]
 pub async fn delete_memory_entry(
     app: AppHandle,
+    undo: State<'_, UndoState>,
     entry_id: String,
+    dry_run: Option<bool>,
+    password: Option<String>,
 ) -> Result<ApiResponse<bool>, String> {
-    let storage = get_storage_manager(&app).await?;
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?.with_password(password).with_journal();
+    if let Some(response) = reject_read_only(&storage) {
+        return Ok(response);
+    }
+    let dry_run = dry_run.unwrap_or(false);
     
     let deleted = storage
-        .delete_entry(&entry_id)
+        .delete_entry(&entry_id, dry_run)
         .await
         .map_err(|e| format!("Failed to delete entry: {}", e))?;
+    undo.record("删除条目", &storage);
     
-    if deleted {
+    if deleted && dry_run {
+        Ok(ApiResponse::success(true).with_message("预览：将删除 1 个记忆条目".to_string()))
+    } else if deleted {
         Ok(ApiResponse::success(true).with_message("记忆条目已删除".to_string()))
     } else {
         Ok(ApiResponse::error("记忆条目未找到".to_string()))
     }
 }
 
-/// 获取单个记忆条目
+/// 批量删除记忆条目
 #[tauri::command]
-pub async fn get_memory_entry(
+pub async fn bulk_delete_entries(
     app: AppHandle,
-    entry_id: String,
-) -> Result<ApiResponse<Option<MemoryEntry>>, String> {
-    let storage = get_storage_manager(&app).await?;
-    
-    let entry = storage
-        .get_entry(&entry_id)
+    undo: State<'_, UndoState>,
+    entry_ids: Vec<String>,
+    dry_run: Option<bool>,
+    password: Option<String>,
+) -> Result<ApiResponse<BulkResult>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?.with_password(password).with_journal();
+    if let Some(response) = reject_read_only(&storage) {
+        return Ok(response);
+    }
+
+    let result = storage
+        .bulk_delete_entries(&entry_ids, dry_run.unwrap_or(false))
         .await
-        .map_err(|e| format!("Failed to get entry: {}", e))?;
-    
-    Ok(ApiResponse::success(entry))
+        .map_err(|e| format!("Failed to delete entries: {}", e))?;
+    undo.record("批量删除", &storage);
+
+    Ok(ApiResponse::success(result))
 }
 
-/// 获取所有记忆条目
+/// 批量为条目添加标签
 #[tauri::command]
-pub async fn get_all_memory_entries(
+pub async fn bulk_add_tag(
     app: AppHandle,
+    undo: State<'_, UndoState>,
+    entry_ids: Vec<String>,
+    tag: String,
     password: Option<String>,
-) -> Result<ApiResponse<Vec<MemoryEntry>>, String> {
-    let storage = get_storage_manager(&app).await?;
-    
-    let entries = if let Some(password) = password {
-        storage
-            .load_entries_with_password(&password)
-            .await
-            .map_err(|e| format!("Failed to load encrypted entries: {}", e))?
-    } else {
-        storage
-            .get_all_entries()
-            .await
-            .map_err(|e| format!("Failed to get entries: {}", e))?
-    };
-    
-    Ok(ApiResponse::success(entries))
+) -> Result<ApiResponse<BulkResult>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?.with_password(password).with_journal();
+    if let Some(response) = reject_read_only(&storage) {
+        return Ok(response);
+    }
+
+    let result = storage
+        .bulk_add_tag(&entry_ids, &tag)
+        .await
+        .map_err(|e| format!("Failed to tag entries: {}", e))?;
+    undo.record("批量添加标签", &storage);
+
+    Ok(ApiResponse::success(result))
 }
 
-/// 搜索记忆条目
+/// 按 ID 批量获取记忆条目，按请求顺序返回，忽略不存在的 ID
 #[tauri::command]
-pub async fn search_memory_entries(
+pub async fn get_memory_entries_by_ids(
     app: AppHandle,
-    filter: SearchFilter,
+    ids: Vec<String>,
+    password: Option<String>,
+    read_only: Option<bool>,
 ) -> Result<ApiResponse<Vec<MemoryEntry>>, String> {
-    let storage = get_storage_manager(&app).await?;
-    
+    let password = resolve_password(&app, password)?;
+    let storage = get_reader_storage(&app, read_only).await?.with_password(password);
+
     let entries = storage
-        .search_entries(&filter)
+        .get_entries_by_ids(&ids)
         .await
-        .map_err(|e| format!("Failed to search entries: {}", e))?;
-    
+        .map_err(|e| format!("Failed to get entries: {}", e))?;
+
     Ok(ApiResponse::success(entries))
 }
 
-/// 加密数据
+/// 获取单个记忆条目
 #[tauri::command]
-pub async fn encrypt_data(
-    data: String,
-    password: String,
-) -> Result<ApiResponse<crate::crypto::EncryptionResult>, String> {
-    let encrypted = BackendEncryption::encrypt(&data, &password)
-        .map_err(|e| format!("Encryption failed: {}", e))?;
+pub async fn get_memory_entry(
+    app: AppHandle,
+    throttle: State<'_, DecryptThrottle>,
+    entry_id: String,
+    entry_password: Option<String>,
+    password: Option<String>,
+    read_only: Option<bool>,
+) -> Result<ApiResponse<Option<MemoryEntry>>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_reader_storage(&app, read_only).await?.with_password(password);
     
-    Ok(ApiResponse::success(encrypted))
-}
+    let entry = storage
+        .get_entry(&entry_id)
+        .await
+        .map_err(|e| format!("Failed to get entry: {}", e))?;
 
-/// 解密数据
-#[tauri::command]
-pub async fn decrypt_data(
-    params: DecryptionParams,
-) -> Result<ApiResponse<String>, String> {
-    let decrypted = BackendEncryption::decrypt(&params)
-        .map_err(|e| format!("Decryption failed: {}", e))?;
-    
-    Ok(ApiResponse::success(decrypted))
+    // 已加锁的条目：只有提供正确的条目密码才返回正文
+    match entry {
+        Some(mut entry) if entry.encrypted_content.is_some() => match entry_password {
+            Some(entry_password) => {
+                match throttle.run(|| reveal_entry_content(&entry, &entry_password)) {
+                    Ok(content) => {
+                        entry.content = content;
+                        Ok(ApiResponse::success(Some(entry)))
+                    }
+                    Err(failure) => Ok(failure.into_response(Some(Some(entry)))),
+                }
+            }
+            None => Ok(ApiResponse::error_with_data(Some(entry), "Entry is locked".to_string())
+                .with_error_code(ErrorCode::EntryLocked)),
+        },
+        entry => Ok(ApiResponse::success(entry)),
+    }
 }
 
-/// 验证密码强度
+/// 按 ID 前缀获取唯一条目（前缀有歧义或无匹配时返回错误）
 #[tauri::command]
-pub async fn validate_password_strength(password: String) -> Result<ApiResponse<u8>, String> {
-    let strength = BackendEncryption::validate_password_strength(&password);
-    Ok(ApiResponse::success(strength))
-}
-
+pub async fn get_entry_by_prefix(
+    app: AppHandle,
+    prefix: String,
+    password: Option<String>,
+) -> Result<ApiResponse<MemoryEntry>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?.with_password(password);
 
-/// 获取随机记忆（用于拾梦回响）
-#[tauri::command]
-pub async fn get_random_memory(app: AppHandle) -> Result<ApiResponse<Option<MemoryEntry>>, String> {
-    let storage = get_storage_manager(&app).await?;
-    
     let entry = storage
-        .get_random_entry()
+        .get_entry_by_prefix(&prefix)
         .await
-        .map_err(|e| format!("Failed to get random entry: {}", e))?;
+        .map_err(|e| format!("Failed to get entry: {}", e))?;
+
+    Ok(ApiResponse::success(entry))
+}
+
+/// 使用条目密码为条目加锁
+#[tauri::command]
+pub async fn lock_entry(
+    app: AppHandle,
+    undo: State<'_, UndoState>,
+    entry_id: String,
+    entry_password: String,
+    password: Option<String>,
+) -> Result<ApiResponse<MemoryEntry>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?.with_password(password).with_journal();
+    if let Some(response) = reject_read_only(&storage) {
+        return Ok(response);
+    }
+
+    let entry = storage
+        .lock_entry(&entry_id, &entry_password)
+        .await
+        .map_err(|e| format!("Failed to lock entry: {}", e))?;
+    undo.record("锁定条目", &storage);
+
+    Ok(ApiResponse::success(entry))
+}
+
+/// 使用条目密码解除条目锁定
+#[tauri::command]
+pub async fn unlock_entry(
+    app: AppHandle,
+    throttle: State<'_, DecryptThrottle>,
+    undo: State<'_, UndoState>,
+    entry_id: String,
+    entry_password: String,
+    password: Option<String>,
+) -> Result<ApiResponse<MemoryEntry>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?.with_password(password.clone()).with_journal();
+    if let Some(response) = reject_read_only(&storage) {
+        return Ok(response);
+    }
+
+    let mut entry = storage
+        .get_entry(&entry_id)
+        .await
+        .map_err(|e| format!("Failed to get entry: {}", e))?
+        .ok_or("Entry not found")?;
+
+    if let Err(failure) = throttle.run(|| unlock_entry_content(&mut entry, &entry_password)) {
+        return Ok(failure.into_response(None));
+    }
+
+    storage
+        .save_entry(&entry, password.as_deref())
+        .await
+        .map_err(|e| format!("Failed to save entry: {}", e))?;
+    undo.record("解锁条目", &storage);
+
+    Ok(ApiResponse::success(entry))
+}
+
+/// 为条目添加附件
+#[tauri::command]
+pub async fn add_attachment(
+    app: AppHandle,
+    undo: State<'_, UndoState>,
+    entry_id: String,
+    source_path: String,
+    password: Option<String>,
+) -> Result<ApiResponse<Attachment>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?.with_password(password).with_journal();
+    if let Some(response) = reject_read_only(&storage) {
+        return Ok(response);
+    }
+
+    let attachment = storage
+        .add_attachment(&entry_id, &source_path)
+        .await
+        .map_err(|e| format!("Failed to add attachment: {}", e))?;
+    undo.record("添加附件", &storage);
+
+    Ok(ApiResponse::success(attachment))
+}
+
+/// 校验所有附件的完整性
+#[tauri::command]
+pub async fn verify_attachments(
+    app: AppHandle,
+    password: Option<String>,
+) -> Result<ApiResponse<AttachmentReport>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?.with_password(password);
+
+    let report = storage
+        .verify_attachments()
+        .await
+        .map_err(|e| format!("Failed to verify attachments: {}", e))?;
+
+    Ok(ApiResponse::success(report))
+}
+
+/// 列出未被任何条目引用的附件文件
+#[tauri::command]
+pub async fn find_orphaned_attachments(
+    app: AppHandle,
+    password: Option<String>,
+) -> Result<ApiResponse<Vec<OrphanedAttachment>>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?.with_password(password);
+
+    let orphans = storage
+        .find_orphaned_attachments()
+        .await
+        .map_err(|e| format!("Failed to find orphaned attachments: {}", e))?;
+
+    Ok(ApiResponse::success(orphans))
+}
+
+/// 删除孤立附件文件（支持预览）
+#[tauri::command]
+pub async fn prune_orphaned_attachments(
+    app: AppHandle,
+    dry_run: Option<bool>,
+    password: Option<String>,
+) -> Result<ApiResponse<OrphanReport>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?.with_password(password);
+    let dry_run = dry_run.unwrap_or(false);
+    if !dry_run {
+        if let Some(response) = reject_read_only(&storage) {
+            return Ok(response);
+        }
+    }
+
+    let report = storage
+        .prune_orphaned_attachments(dry_run)
+        .await
+        .map_err(|e| format!("Failed to prune orphaned attachments: {}", e))?;
+
+    let message = if report.dry_run {
+        format!("预览：将删除 {} 个孤立附件，释放 {} 字节", report.files.len(), report.reclaimed_bytes)
+    } else {
+        format!("已删除 {} 个孤立附件，释放 {} 字节", report.files.len(), report.reclaimed_bytes)
+    };
+    Ok(ApiResponse::success(report).with_message(message))
+}
+
+/// 按需获取条目正文（正文外置存储时，列表中的 `content` 为空）
+#[tauri::command]
+pub async fn get_entry_content(
+    app: AppHandle,
+    entry_id: String,
+    password: Option<String>,
+) -> Result<ApiResponse<String>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?.with_password(password);
+
+    let content = storage
+        .get_entry_content(&entry_id)
+        .await
+        .map_err(|e| format!("Failed to get entry content: {}", e))?;
+
+    Ok(ApiResponse::success(content))
+}
+
+/// 获取所有未归档的记忆条目，指定 `sort` 时按该方式排序（`manual` 为手动排序）
+#[tauri::command]
+pub async fn get_all_memory_entries(
+    app: AppHandle,
+    throttle: State<'_, DecryptThrottle>,
+    password: Option<String>,
+    entry_password: Option<String>,
+    read_only: Option<bool>,
+    sort: Option<SearchSort>,
+) -> Result<ApiResponse<Vec<MemoryEntry>>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_reader_storage(&app, read_only).await?.with_password(password);
+    
+    let mut entries = storage
+        .get_visible_entries()
+        .await
+        .map_err(|e| format!("Failed to get entries: {}", e))?;
+    if let Some(sort) = sort {
+        sort_entries(&mut entries, sort);
+    }
+
+    // 提供条目密码时一并显示能用它解开的加锁条目（条目仍保持加锁）
+    if let Some(entry_password) = entry_password {
+        if let Err(failure) = throttle.run(|| reveal_locked_entries(&mut entries, &entry_password)) {
+            return Ok(failure.into_response(Some(entries)));
+        }
+    }
+    
+    Ok(ApiResponse::success(entries))
+}
+
+/// 获取已归档的记忆条目
+#[tauri::command]
+pub async fn list_archived(
+    app: AppHandle,
+    password: Option<String>,
+) -> Result<ApiResponse<Vec<MemoryEntry>>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?.with_password(password);
+
+    let entries = storage
+        .list_archived()
+        .await
+        .map_err(|e| format!("Failed to get archived entries: {}", e))?;
+
+    Ok(ApiResponse::success(entries))
+}
+
+/// 归档记忆条目（从默认列表隐藏，不删除）
+#[tauri::command]
+pub async fn archive_entry(
+    app: AppHandle,
+    undo: State<'_, UndoState>,
+    entry_id: String,
+    password: Option<String>,
+) -> Result<ApiResponse<MemoryEntry>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?.with_password(password).with_journal();
+    if let Some(response) = reject_read_only(&storage) {
+        return Ok(response);
+    }
+
+    let entry = storage
+        .set_archived(&entry_id, true)
+        .await
+        .map_err(|e| format!("Failed to archive entry: {}", e))?;
+    undo.record("归档条目", &storage);
+
+    Ok(ApiResponse::success(entry).with_message("记忆已归档".to_string()))
+}
+
+/// 切换条目中任务的完成状态（`task_index` 从 0 开始）
+#[tauri::command]
+pub async fn toggle_task(
+    app: AppHandle,
+    undo: State<'_, UndoState>,
+    entry_id: String,
+    task_index: usize,
+    password: Option<String>,
+) -> Result<ApiResponse<MemoryEntry>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?.with_password(password).with_journal();
+    if let Some(response) = reject_read_only(&storage) {
+        return Ok(response);
+    }
+
+    let entry = storage
+        .toggle_task(&entry_id, task_index)
+        .await
+        .map_err(|e| format!("Failed to toggle task: {}", e))?;
+    undo.record("切换任务", &storage);
+
+    Ok(ApiResponse::success(entry))
+}
+
+/// 按给出的 ID 顺序手动排列条目，未列出的条目排在其后
+#[tauri::command]
+pub async fn reorder_entries(
+    app: AppHandle,
+    undo: State<'_, UndoState>,
+    ordered_ids: Vec<String>,
+    password: Option<String>,
+) -> Result<ApiResponse<usize>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?.with_password(password).with_journal();
+    if let Some(response) = reject_read_only(&storage) {
+        return Ok(response);
+    }
+
+    let count = storage
+        .reorder_entries(&ordered_ids)
+        .await
+        .map_err(|e| format!("Failed to reorder entries: {}", e))?;
+    undo.record("调整顺序", &storage);
+
+    Ok(ApiResponse::success(count))
+}
+
+/// 取消归档记忆条目
+#[tauri::command]
+pub async fn unarchive_entry(
+    app: AppHandle,
+    undo: State<'_, UndoState>,
+    entry_id: String,
+    password: Option<String>,
+) -> Result<ApiResponse<MemoryEntry>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?.with_password(password).with_journal();
+    if let Some(response) = reject_read_only(&storage) {
+        return Ok(response);
+    }
+
+    let entry = storage
+        .set_archived(&entry_id, false)
+        .await
+        .map_err(|e| format!("Failed to unarchive entry: {}", e))?;
+    undo.record("取消归档", &storage);
+
+    Ok(ApiResponse::success(entry).with_message("已取消归档".to_string()))
+}
+
+/// 按当前阅读速度设置重新计算所有条目的字数与阅读时间
+#[tauri::command]
+pub async fn recompute_word_stats(
+    app: AppHandle,
+    undo: State<'_, UndoState>,
+    password: Option<String>,
+) -> Result<ApiResponse<u32>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?.with_password(password.clone()).with_journal();
+    if let Some(response) = reject_read_only(&storage) {
+        return Ok(response);
+    }
+
+    let settings = storage
+        .load_settings(password.as_deref())
+        .await
+        .unwrap_or_default();
+
+    let changed = storage
+        .recompute_word_stats(&settings)
+        .await
+        .map_err(|e| format!("Failed to recompute word stats: {}", e))?;
+    undo.record("更新阅读时间", &storage);
+
+    Ok(ApiResponse::success(changed).with_message(format!("已更新 {} 条记忆的阅读时间", changed)))
+}
+
+/// 解锁保险库：校验密码并缓存，之后未传密码的条目列表读取使用缓存的密码，返回条目数
+#[tauri::command]
+pub async fn unlock_vault(
+    app: AppHandle,
+    session: State<'_, SessionState>,
+    password: String,
+) -> Result<ApiResponse<usize>, String> {
+    let storage = get_storage_manager(&app).await?;
+
+    let entries = match storage.load_entries_throttled(&password).await {
+        Ok(entries) => entries,
+        Err(e) => match decrypt_error_code(&e) {
+            Some(code) => return Ok(ApiResponse::error(format!("Failed to unlock vault: {}", e)).with_error_code(code)),
+            None => return Err(format!("Failed to unlock vault: {}", e)),
+        },
+    };
+    let count = entries.len();
+    let settings = storage
+        .load_settings(Some(&password))
+        .await
+        .unwrap_or_default();
+
+    let mut session = session.0.lock().map_err(|e| e.to_string())?;
+    session.set_auto_lock_minutes(settings.auto_lock_minutes);
+    session.unlock(password, chrono::Utc::now());
+
+    Ok(ApiResponse::success(count))
+}
+
+/// 锁定保险库，清除缓存的密码
+#[tauri::command]
+pub async fn lock_vault(
+    app: AppHandle,
+    session: State<'_, SessionState>,
+) -> Result<ApiResponse<()>, String> {
+    session.lock(&app)?;
+    Ok(ApiResponse::success_empty().with_message("已锁定".to_string()))
+}
+
+/// 立即锁定（如离开电脑时），清除缓存的密码与撤销记录，之后需重新输入密码
+#[tauri::command]
+pub async fn lock_now(
+    app: AppHandle,
+    session: State<'_, SessionState>,
+) -> Result<ApiResponse<()>, String> {
+    lock_vault(app, session).await
+}
+
+/// 记录一次用户活动（由前端在用户操作时调用），推迟自动锁定，返回会话是否仍处于解锁状态
+#[tauri::command]
+pub async fn record_activity(
+    app: AppHandle,
+    session: State<'_, SessionState>,
+) -> Result<ApiResponse<bool>, String> {
+    let unlocked = session.access(&app)?.is_some();
+    Ok(ApiResponse::success(unlocked))
+}
+
+/// 检查是否超过自动锁定时间（由前端定时调用），返回本次是否锁定
+#[tauri::command]
+pub async fn check_auto_lock(
+    app: AppHandle,
+    session: State<'_, SessionState>,
+) -> Result<ApiResponse<bool>, String> {
+    let password = session.0
+        .lock()
+        .map_err(|e| e.to_string())?
+        .password()
+        .map(str::to_string);
+    if password.is_none() {
+        return Ok(ApiResponse::success(false));
+    }
+
+    let storage = get_storage_manager(&app).await?;
+    let settings = storage
+        .load_settings(password.as_deref())
+        .await
+        .unwrap_or_default();
+
+    let locked = {
+        let mut session = session.0.lock().map_err(|e| e.to_string())?;
+        session.set_auto_lock_minutes(settings.auto_lock_minutes);
+        session.check_auto_lock(chrono::Utc::now(), settings.auto_lock_minutes)
+    };
+    if locked {
+        forget_decrypted(&app);
+    }
+    Ok(ApiResponse::success(locked))
+}
+
+/// 更换存储密码，重新加密过程中通过 `reencrypt_progress` 事件报告进度
+#[tauri::command]
+pub async fn change_password(
+    app: AppHandle,
+    session: State<'_, SessionState>,
+    old_password: String,
+    new_password: String,
+) -> Result<ApiResponse<usize>, String> {
+    let storage = get_storage_manager(&app).await?;
+    if let Some(response) = reject_read_only(&storage) {
+        return Ok(response);
+    }
+
+    let emitter = app.clone();
+    let count = storage
+        .change_password(&old_password, &new_password, move |processed, total| {
+            let _ = emitter.emit("reencrypt_progress", ReencryptProgress { processed, total });
+        })
+        .await
+        .map_err(|e| format!("Failed to change password: {}", e))?;
+
+    // 已解锁的会话改用新密码
+    let mut session = session.0.lock().map_err(|e| e.to_string())?;
+    if session.password() == Some(old_password.as_str()) {
+        session.unlock(new_password, chrono::Utc::now());
+    }
+
+    Ok(ApiResponse::success(count).with_message(format!("已重新加密 {} 个文件", count)))
+}
+
+/// 轮换加密盐值与 nonce（密码不变），进度同样通过 `reencrypt_progress` 事件报告
+#[tauri::command]
+pub async fn rotate_encryption(
+    app: AppHandle,
+    password: String,
+) -> Result<ApiResponse<usize>, String> {
+    let storage = get_storage_manager(&app).await?;
+    if let Some(response) = reject_read_only(&storage) {
+        return Ok(response);
+    }
+
+    let emitter = app.clone();
+    let count = storage
+        .rotate_encryption(&password, move |processed, total| {
+            let _ = emitter.emit("reencrypt_progress", ReencryptProgress { processed, total });
+        })
+        .await
+        .map_err(|e| format!("Failed to rotate encryption: {}", e))?;
+
+    Ok(ApiResponse::success(count).with_message(format!("已重新加密 {} 个文件", count)))
+}
+
+/// 获取存储状态（是否加密、密码尝试锁定情况）
+#[tauri::command]
+pub async fn get_store_status(app: AppHandle) -> Result<ApiResponse<StoreStatus>, String> {
+    let storage = get_storage_manager(&app).await?;
+
+    let status = storage
+        .get_store_status()
+        .await
+        .map_err(|e| format!("Failed to get store status: {}", e))?;
+
+    Ok(ApiResponse::success(status))
+}
+
+/// 将界面锁定为只读，修改条目的命令返回 `ReadOnly` 错误，读取不受影响
+#[tauri::command]
+pub async fn lock_ui(ui_lock: State<'_, UiLockState>) -> Result<ApiResponse<bool>, String> {
+    ui_lock.0.lock().map_err(|e| e.to_string())?.lock();
+    Ok(ApiResponse::success(true).with_message("界面已锁定为只读".to_string()))
+}
+
+/// 解除界面只读锁定
+#[tauri::command]
+pub async fn unlock_ui(ui_lock: State<'_, UiLockState>) -> Result<ApiResponse<bool>, String> {
+    ui_lock.0.lock().map_err(|e| e.to_string())?.unlock();
+    Ok(ApiResponse::success(false).with_message("界面已解除只读锁定".to_string()))
+}
+
+/// 获取保险库的加密算法、格式版本与密钥派生参数（无需密码）
+#[tauri::command]
+pub async fn get_vault_security_info(app: AppHandle) -> Result<ApiResponse<VaultSecurityInfo>, String> {
+    let storage = get_storage_manager(&app).await?;
+
+    let info = storage
+        .get_vault_security_info()
+        .await
+        .map_err(|e| format!("Failed to get vault security info: {}", e))?;
+
+    if info.below_recommended {
+        Ok(ApiResponse::success(info).with_message("密钥派生参数低于当前推荐值，建议轮换加密".to_string()))
+    } else {
+        Ok(ApiResponse::success(info))
+    }
+}
+
+/// 校验条目完整性，列出标题或正文与保存时哈希不一致的条目
+#[tauri::command]
+pub async fn verify_entries_integrity(
+    app: AppHandle,
+    password: Option<String>,
+) -> Result<ApiResponse<IntegrityReport>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?.with_password(password);
+
+    let report = storage
+        .verify_entries_integrity()
+        .await
+        .map_err(|e| format!("Failed to verify entries: {}", e))?;
+
+    let message = if report.mismatched.is_empty() {
+        format!("已校验 {} 条记忆，未发现损坏", report.checked)
+    } else {
+        format!("发现 {} 条记忆内容与校验值不符，可能已损坏", report.mismatched.len())
+    };
+    Ok(ApiResponse::success(report).with_message(message))
+}
+
+/// 获取存储空间报告，记忆文件过大时附带压缩提示
+#[tauri::command]
+pub async fn get_storage_report(
+    app: AppHandle,
+    password: Option<String>,
+) -> Result<ApiResponse<StorageReport>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?.with_password(password);
+
+    let report = storage
+        .get_storage_report(chrono::Utc::now())
+        .await
+        .map_err(|e| format!("Failed to get storage report: {}", e))?;
+
+    if report.large_entries_file {
+        Ok(ApiResponse::success(report)
+            .with_message("记忆文件较大，建议压缩存储（如关闭 pretty_json 缩进排版）".to_string()))
+    } else {
+        Ok(ApiResponse::success(report))
+    }
+}
+
+/// 获取保险库占用的磁盘空间（按数据文件、外置正文与附件分项）
+#[tauri::command]
+pub async fn get_storage_usage(
+    app: AppHandle,
+) -> Result<ApiResponse<StorageUsage>, String> {
+    let storage = get_storage_manager(&app).await?;
+
+    let usage = storage
+        .get_storage_usage()
+        .await
+        .map_err(|e| format!("Failed to get storage usage: {}", e))?;
+
+    Ok(ApiResponse::success(usage))
+}
+
+/// 关联两个记忆条目
+#[tauri::command]
+pub async fn link_entries(
+    app: AppHandle,
+    undo: State<'_, UndoState>,
+    a: String,
+    b: String,
+    password: Option<String>,
+) -> Result<ApiResponse<()>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?.with_password(password).with_journal();
+    if let Some(response) = reject_read_only(&storage) {
+        return Ok(response);
+    }
+
+    storage
+        .link_entries(&a, &b)
+        .await
+        .map_err(|e| format!("Failed to link entries: {}", e))?;
+    undo.record("关联条目", &storage);
+
+    Ok(ApiResponse::success_empty().with_message("记忆已关联".to_string()))
+}
+
+/// 解除两个记忆条目的关联
+#[tauri::command]
+pub async fn unlink_entries(
+    app: AppHandle,
+    undo: State<'_, UndoState>,
+    a: String,
+    b: String,
+    password: Option<String>,
+) -> Result<ApiResponse<()>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?.with_password(password).with_journal();
+    if let Some(response) = reject_read_only(&storage) {
+        return Ok(response);
+    }
+
+    storage
+        .unlink_entries(&a, &b)
+        .await
+        .map_err(|e| format!("Failed to unlink entries: {}", e))?;
+    undo.record("解除关联", &storage);
+
+    Ok(ApiResponse::success_empty().with_message("已解除关联".to_string()))
+}
+
+/// 撤销最近一次修改，返回被撤销修改的描述
+///
+/// 相关条目在记录之后又被改动时拒绝撤销，并清空撤销记录。
+#[tauri::command]
+pub async fn undo_last(
+    app: AppHandle,
+    undo: State<'_, UndoState>,
+    password: Option<String>,
+) -> Result<ApiResponse<String>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?.with_password(password);
+    if let Some(response) = reject_read_only(&storage) {
+        return Ok(response);
+    }
+    // 先取出副本，恢复成功后才从栈中移除，读写失败时记录仍然保留
+    let record = undo.0.lock().map_err(|e| e.to_string())?.peek_undo().cloned();
+    let Some(record) = record else {
+        return Ok(ApiResponse::error("没有可撤销的操作".to_string()));
+    };
+
+    let mut entries = storage
+        .load_entries()
+        .await
+        .map_err(|e| format!("Failed to load entries: {}", e))?;
+
+    if let Err(e) = record.revert(&mut entries) {
+        undo.invalidate();
+        return Ok(ApiResponse::error(format!("Failed to undo: {}", e)));
+    }
+    storage
+        .save_entries(&entries)
+        .await
+        .map_err(|e| format!("Failed to save entries: {}", e))?;
+
+    let label = record.label.clone();
+    let mut stack = undo.0.lock().map_err(|e| e.to_string())?;
+    stack.pop_undo();
+    stack.push_redo(record);
+    Ok(ApiResponse::success(label.clone()).with_message(format!("已撤销：{}", label)))
+}
+
+/// 重做最近一次撤销的修改，返回被重做修改的描述
+#[tauri::command]
+pub async fn redo_last(
+    app: AppHandle,
+    undo: State<'_, UndoState>,
+    password: Option<String>,
+) -> Result<ApiResponse<String>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?.with_password(password);
+    if let Some(response) = reject_read_only(&storage) {
+        return Ok(response);
+    }
+    let record = undo.0.lock().map_err(|e| e.to_string())?.peek_redo().cloned();
+    let Some(record) = record else {
+        return Ok(ApiResponse::error("没有可重做的操作".to_string()));
+    };
+
+    let mut entries = storage
+        .load_entries()
+        .await
+        .map_err(|e| format!("Failed to load entries: {}", e))?;
+
+    if let Err(e) = record.reapply(&mut entries) {
+        undo.invalidate();
+        return Ok(ApiResponse::error(format!("Failed to redo: {}", e)));
+    }
+    storage
+        .save_entries(&entries)
+        .await
+        .map_err(|e| format!("Failed to save entries: {}", e))?;
+
+    let label = record.label.clone();
+    let mut stack = undo.0.lock().map_err(|e| e.to_string())?;
+    stack.pop_redo();
+    stack.push_undone(record);
+    Ok(ApiResponse::success(label.clone()).with_message(format!("已重做：{}", label)))
+}
+
+/// 获取关联的记忆条目
+#[tauri::command]
+pub async fn get_related_entries(
+    app: AppHandle,
+    entry_id: String,
+    password: Option<String>,
+) -> Result<ApiResponse<Vec<MemoryEntry>>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?.with_password(password);
+
+    let entries = storage
+        .get_related_entries(&entry_id)
+        .await
+        .map_err(|e| format!("Failed to get related entries: {}", e))?;
+
+    Ok(ApiResponse::success(entries))
+}
+
+/// 获取指向指定条目的反向链接
+#[tauri::command]
+pub async fn get_backlinks(
+    app: AppHandle,
+    entry_id: String,
+    password: Option<String>,
+) -> Result<ApiResponse<Vec<MemoryEntry>>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?.with_password(password);
+
+    let entries = storage
+        .get_backlinks(&entry_id)
+        .await
+        .map_err(|e| format!("Failed to get backlinks: {}", e))?;
+
+    Ok(ApiResponse::success(entries))
+}
+
+/// 获取最近更新的记忆条目
+#[tauri::command]
+pub async fn get_recent_entries(
+    app: AppHandle,
+    count: usize,
+    password: Option<String>,
+    read_only: Option<bool>,
+) -> Result<ApiResponse<Vec<MemoryEntry>>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_reader_storage(&app, read_only).await?.with_password(password);
+
+    let entries = storage
+        .get_recent_entries(count)
+        .await
+        .map_err(|e| format!("Failed to get recent entries: {}", e))?;
+
+    Ok(ApiResponse::success(entries))
+}
+
+/// 获取带有指定情感标签的条目，可选按记忆类型过滤
+#[tauri::command]
+pub async fn get_entries_by_emotion(
+    app: AppHandle,
+    emotion: EmotionTag,
+    memory_type: Option<MemoryType>,
+    password: Option<String>,
+    include_archived: Option<bool>,
+) -> Result<ApiResponse<Vec<MemoryEntry>>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?.with_password(password);
+
+    let entries = storage
+        .get_entries_by_emotion(&emotion, memory_type.as_ref(), include_archived.unwrap_or(false))
+        .await
+        .map_err(|e| format!("Failed to get entries: {}", e))?;
+
+    Ok(ApiResponse::success(entries))
+}
+
+/// 搜索记忆条目
+#[tauri::command]
+pub async fn search_memory_entries(
+    app: AppHandle,
+    filter: SearchFilter,
+    password: Option<String>,
+    read_only: Option<bool>,
+) -> Result<ApiResponse<Vec<MemoryEntry>>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_reader_storage(&app, read_only).await?.with_password(password);
+    
+    let entries = storage
+        .search_entries(&filter)
+        .await
+        .map_err(|e| format!("Failed to search entries: {}", e))?;
+    
+    Ok(ApiResponse::success(entries))
+}
+
+/// 分页搜索记忆条目，返回当前页和匹配总数
+#[tauri::command]
+pub async fn search_memories_page(
+    app: AppHandle,
+    filter: SearchFilter,
+    offset: Option<usize>,
+    limit: Option<usize>,
+    password: Option<String>,
+    read_only: Option<bool>,
+) -> Result<ApiResponse<SearchPage>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_reader_storage(&app, read_only).await?.with_password(password);
+
+    let page = storage
+        .search_entries_paginated(&filter, offset.unwrap_or(0), limit.unwrap_or(DEFAULT_PAGE_SIZE))
+        .await
+        .map_err(|e| format!("Failed to search entries: {}", e))?;
+
+    Ok(ApiResponse::success(page))
+}
+
+/// 统计匹配过滤条件的条目数（不返回条目，用于角标与分页）
+#[tauri::command]
+pub async fn count_entries(
+    app: AppHandle,
+    filter: SearchFilter,
+    password: Option<String>,
+    read_only: Option<bool>,
+) -> Result<ApiResponse<usize>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_reader_storage(&app, read_only).await?.with_password(password);
+
+    let count = storage
+        .count_entries(&filter)
+        .await
+        .map_err(|e| format!("Failed to count entries: {}", e))?;
+
+    Ok(ApiResponse::success(count))
+}
+
+/// 获取位置包含指定文本的记忆条目
+#[tauri::command]
+pub async fn get_entries_by_location(
+    app: AppHandle,
+    location: String,
+    password: Option<String>,
+) -> Result<ApiResponse<Vec<MemoryEntry>>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?.with_password(password);
+
+    let entries = storage
+        .get_entries_by_location(&location)
+        .await
+        .map_err(|e| format!("Failed to get entries: {}", e))?;
+
+    Ok(ApiResponse::success(entries))
+}
+
+/// 使用布尔表达式搜索记忆条目
+#[tauri::command]
+pub async fn query_memories(
+    app: AppHandle,
+    expression: String,
+    password: Option<String>,
+) -> Result<ApiResponse<Vec<MemoryEntry>>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?.with_password(password);
+
+    let entries = storage
+        .query_entries(&expression)
+        .await
+        .map_err(|e| format!("Failed to query entries: {}", e))?;
+
+    Ok(ApiResponse::success(entries))
+}
+
+/// 搜索并返回匹配高亮位置
+#[tauri::command]
+pub async fn search_with_highlights(
+    app: AppHandle,
+    keyword: String,
+    password: Option<String>,
+) -> Result<ApiResponse<Vec<EntryHighlights>>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?.with_password(password);
+
+    let results = storage
+        .search_with_highlights(&keyword)
+        .await
+        .map_err(|e| format!("Failed to search entries: {}", e))?;
+
+    Ok(ApiResponse::success(results))
+}
+
+/// 查找关键词在条目正文中的匹配位置（字节偏移）
+#[tauri::command]
+pub async fn find_matches(
+    app: AppHandle,
+    entry_id: String,
+    keyword: String,
+    password: Option<String>,
+) -> Result<ApiResponse<Vec<(usize, usize)>>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?.with_password(password);
+
+    let matches = storage
+        .find_matches(&entry_id, &keyword)
+        .await
+        .map_err(|e| format!("Failed to find matches: {}", e))?;
+
+    Ok(ApiResponse::success(matches))
+}
+
+/// 获取标签云
+#[tauri::command]
+pub async fn get_tag_cloud(
+    app: AppHandle,
+    limit: Option<usize>,
+    password: Option<String>,
+) -> Result<ApiResponse<Vec<(String, u32)>>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?.with_password(password);
+
+    let cloud = storage
+        .get_tag_cloud(limit)
+        .await
+        .map_err(|e| format!("Failed to get tag cloud: {}", e))?;
+
+    Ok(ApiResponse::success(cloud))
+}
+
+/// 获取已使用的全部标签（去重并按字母序，用于输入自动补全）
+#[tauri::command]
+pub async fn get_all_tags(
+    app: AppHandle,
+    cache: State<'_, TagIndexState>,
+    password: Option<String>,
+) -> Result<ApiResponse<Vec<String>>, String> {
+    let password = resolve_password(&app, password)?;
+    let token = app.state::<SessionState>().token_for(password.as_deref())?;
+    let storage = get_storage_manager(&app).await?.with_password(password);
+
+    let tags = storage
+        .all_tags(&cache.0, token)
+        .await
+        .map_err(|e| format!("Failed to get tags: {}", e))?;
+    Ok(ApiResponse::success(tags))
+}
+
+/// 获取已使用的全部心情（去重并按字母序，用于输入自动补全）
+#[tauri::command]
+pub async fn get_all_moods(
+    app: AppHandle,
+    cache: State<'_, TagIndexState>,
+    password: Option<String>,
+) -> Result<ApiResponse<Vec<String>>, String> {
+    let password = resolve_password(&app, password)?;
+    let token = app.state::<SessionState>().token_for(password.as_deref())?;
+    let storage = get_storage_manager(&app).await?.with_password(password);
+
+    let moods = storage
+        .all_moods(&cache.0, token)
+        .await
+        .map_err(|e| format!("Failed to get moods: {}", e))?;
+    Ok(ApiResponse::success(moods))
+}
+
+/// 获取层级标签树（用于可折叠的标签侧栏）
+#[tauri::command]
+pub async fn list_tag_tree(
+    app: AppHandle,
+    password: Option<String>,
+) -> Result<ApiResponse<Vec<TagNode>>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?.with_password(password);
+
+    let tree = storage
+        .list_tag_tree()
+        .await
+        .map_err(|e| format!("Failed to get tag tree: {}", e))?;
+
+    Ok(ApiResponse::success(tree))
+}
+
+/// 分析记忆条目的用词
+#[tauri::command]
+pub async fn analyze_entry(
+    app: AppHandle,
+    entry_id: String,
+    top_n: Option<usize>,
+    password: Option<String>,
+) -> Result<ApiResponse<EntryAnalysis>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?.with_password(password.clone());
+
+    let entry = storage
+        .get_entry(&entry_id)
+        .await
+        .map_err(|e| format!("Failed to get entry: {}", e))?
+        .ok_or("Entry not found")?;
+
+    let settings = storage
+        .load_settings(password.as_deref())
+        .await
+        .unwrap_or_default();
+
+    let analysis = crate::analysis::analyze_text(&entry.content, top_n.unwrap_or(10), &settings.custom_stopwords);
+    Ok(ApiResponse::success(analysis))
+}
+
+/// 获取整个日记的全局词云（结果按条目更新时间缓存）
+#[tauri::command]
+pub async fn get_global_word_cloud(
+    app: AppHandle,
+    cache: State<'_, WordCloudState>,
+    top_n: Option<usize>,
+    password: Option<String>,
+) -> Result<ApiResponse<Vec<(String, u32)>>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?.with_password(password.clone());
+
+    let entries = storage
+        .get_all_entries()
+        .await
+        .map_err(|e| format!("Failed to get entries: {}", e))?;
+
+    let settings = storage
+        .load_settings(password.as_deref())
+        .await
+        .unwrap_or_default();
+
+    let words = cache.0
+        .lock()
+        .map_err(|e| e.to_string())?
+        .top_words(&entries, top_n.unwrap_or(50), &settings.custom_stopwords);
+    Ok(ApiResponse::success(words))
+}
+
+/// 获取周回顾汇总（`week_offset` 为 0 表示本周，-1 表示上周）
+#[tauri::command]
+pub async fn get_week_summary(
+    app: AppHandle,
+    week_offset: i32,
+    password: Option<String>,
+) -> Result<ApiResponse<WeekSummary>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?.with_password(password.clone());
+
+    let entries = storage
+        .get_all_entries()
+        .await
+        .map_err(|e| format!("Failed to get entries: {}", e))?;
+
+    let settings = storage
+        .load_settings(password.as_deref())
+        .await
+        .unwrap_or_default();
+
+    let summary = crate::stats::week_summary(&entries, chrono::Utc::now(), &settings, week_offset);
+    Ok(ApiResponse::success(summary))
+}
+
+/// 按月份分组获取条目（用于时间线视图）
+#[tauri::command]
+pub async fn get_entries_grouped_by_month(
+    app: AppHandle,
+    password: Option<String>,
+    include_archived: Option<bool>,
+) -> Result<ApiResponse<Vec<MonthGroup>>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?.with_password(password.clone());
+
+    let mut entries = storage
+        .get_all_entries()
+        .await
+        .map_err(|e| format!("Failed to get entries: {}", e))?;
+    retain_unarchived(&mut entries, include_archived);
+
+    let settings = storage
+        .load_settings(password.as_deref())
+        .await
+        .unwrap_or_default();
+
+    let groups = crate::stats::group_by_month(&entries, settings.utc_offset());
+    Ok(ApiResponse::success(groups))
+}
+
+/// 历史上的今天：获取往年同一天的条目（`date` 格式为 `YYYY-MM-DD`，默认为用户时区的今天）
+#[tauri::command]
+pub async fn get_on_this_day(
+    app: AppHandle,
+    date: Option<String>,
+    match_leap_day: Option<bool>,
+    password: Option<String>,
+    read_only: Option<bool>,
+    include_archived: Option<bool>,
+) -> Result<ApiResponse<Vec<MemoryEntry>>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_reader_storage(&app, read_only).await?.with_password(password.clone());
+
+    let mut entries = storage
+        .get_all_entries()
+        .await
+        .map_err(|e| format!("Failed to get entries: {}", e))?;
+    retain_unarchived(&mut entries, include_archived);
+
+    let settings = storage
+        .load_settings(password.as_deref())
+        .await
+        .unwrap_or_default();
+    let offset = settings.utc_offset();
+
+    let today = match date {
+        Some(date) => chrono::NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")
+            .map_err(|e| format!("Invalid date: {}", e))?,
+        None => chrono::Utc::now().with_timezone(&offset).date_naive(),
+    };
+
+    let matched = crate::stats::on_this_day(&entries, today, offset, match_leap_day.unwrap_or(true));
+    Ok(ApiResponse::success(matched))
+}
+
+/// 获取整体统计数据
+#[tauri::command]
+pub async fn get_memory_stats(
+    app: AppHandle,
+    password: Option<String>,
+    read_only: Option<bool>,
+) -> Result<ApiResponse<MemoryStats>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_reader_storage(&app, read_only).await?.with_password(password.clone());
+
+    let entries = storage
+        .get_all_entries()
+        .await
+        .map_err(|e| format!("Failed to get entries: {}", e))?;
+
+    let settings = storage
+        .load_settings(password.as_deref())
+        .await
+        .unwrap_or_default();
+
+    let stats = crate::stats::compute_stats(&entries, chrono::Utc::now(), settings.utc_offset(), settings.locale);
+    Ok(ApiResponse::success(stats))
+}
+
+/// 导出统计数据 JSON 文档
+#[tauri::command]
+pub async fn export_stats_json(
+    app: AppHandle,
+    dest: String,
+    password: Option<String>,
+) -> Result<ApiResponse<String>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?.with_password(password);
+
+    let path = storage
+        .export_stats_json(&dest)
+        .await
+        .map_err(|e| format!("Failed to export stats: {}", e))?;
+
+    Ok(ApiResponse::success(path.to_string_lossy().to_string()))
+}
+
+/// 获取情感标签共现统计（哪些情感常一起出现）
+#[tauri::command]
+pub async fn get_emotion_cooccurrence(
+    app: AppHandle,
+    password: Option<String>,
+) -> Result<ApiResponse<Vec<EmotionPair>>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?.with_password(password);
+
+    let entries = storage
+        .get_all_entries()
+        .await
+        .map_err(|e| format!("Failed to get entries: {}", e))?;
+
+    Ok(ApiResponse::success(crate::stats::emotion_cooccurrence(&entries)))
+}
+
+/// 获取时间范围内最常出现的情感标签及条目数（默认前 3 个）
+#[tauri::command]
+pub async fn top_emotions(
+    app: AppHandle,
+    period: Option<DateRange>,
+    limit: Option<usize>,
+    password: Option<String>,
+) -> Result<ApiResponse<Vec<EmotionCount>>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?.with_password(password);
+
+    let entries = storage
+        .get_all_entries()
+        .await
+        .map_err(|e| format!("Failed to get entries: {}", e))?;
+
+    Ok(ApiResponse::success(crate::stats::top_emotions(&entries, period.as_ref(), limit.unwrap_or(3))))
+}
+
+/// 获取连续写作天数
+#[tauri::command]
+pub async fn get_writing_streak(
+    app: AppHandle,
+    password: Option<String>,
+) -> Result<ApiResponse<WritingStreak>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?.with_password(password.clone());
+
+    let entries = storage
+        .get_all_entries()
+        .await
+        .map_err(|e| format!("Failed to get entries: {}", e))?;
+
+    let settings = storage
+        .load_settings(password.as_deref())
+        .await
+        .unwrap_or_default();
+
+    let streak = crate::stats::writing_streak(&entries, chrono::Utc::now(), settings.utc_offset());
+    Ok(ApiResponse::success(streak))
+}
+
+/// 获取内置条目模板
+#[tauri::command]
+pub async fn get_entry_templates() -> Result<ApiResponse<Vec<EntryTemplate>>, String> {
+    Ok(ApiResponse::success(templates::builtin_templates()))
+}
+
+/// 按情感标签推荐条目模板（标记情感时提示合适的写作模板）
+#[tauri::command]
+pub async fn suggest_templates_for_emotions(
+    emotion_tags: Vec<String>,
+) -> Result<ApiResponse<Vec<EntryTemplate>>, String> {
+    let tags = parse_emotion_tags(emotion_tags);
+    Ok(ApiResponse::success(templates::suggest_templates_for_emotions(&templates::builtin_templates(), &tags)))
+}
+
+/// 加密数据
+#[tauri::command]
+pub async fn encrypt_data(
+    data: String,
+    password: String,
+    config: Option<EncryptionConfig>,
+) -> Result<ApiResponse<crate::crypto::EncryptionResult>, String> {
+    let encrypted = BackendEncryption::encrypt_with_config(&data, &password, &config.unwrap_or_default())
+        .map_err(|e| format!("Encryption failed: {}", e))?;
+    
+    Ok(ApiResponse::success(encrypted))
+}
+
+/// 解密数据
+#[tauri::command]
+pub async fn decrypt_data(
+    throttle: State<'_, DecryptThrottle>,
+    params: DecryptionParams,
+) -> Result<ApiResponse<String>, String> {
+    match throttle.run(|| BackendEncryption::decrypt(&params)) {
+        Ok(decrypted) => Ok(ApiResponse::success(decrypted)),
+        Err(failure) => Ok(failure.into_response(None)),
+    }
+}
+
+/// 宽松解密数据（用于诊断部分损坏的密文）
+#[tauri::command]
+pub async fn decrypt_data_lossy(
+    throttle: State<'_, DecryptThrottle>,
+    params: DecryptionParams,
+) -> Result<ApiResponse<LossyDecryption>, String> {
+    match throttle.run(|| BackendEncryption::decrypt_lossy(&params)) {
+        Ok(decrypted) => Ok(ApiResponse::success(decrypted)),
+        Err(failure) => Ok(failure.into_response(None)),
+    }
+}
+
+/// 加密文件（在后端读写文件，只返回加密文件路径，内容不经过命令边界）
+#[tauri::command]
+pub async fn encrypt_file_command(
+    file_path: String,
+    password: String,
+) -> Result<ApiResponse<String>, String> {
+    let encrypted_path = FileEncryption::encrypt_file(&file_path, &password)
+        .await
+        .map_err(|e| format!("Failed to encrypt file: {}", e))?;
+
+    Ok(ApiResponse::success(encrypted_path))
+}
+
+/// 解密文件（只返回解密文件路径，已有文件不会被覆盖）
+#[tauri::command]
+pub async fn decrypt_file_command(
+    throttle: State<'_, DecryptThrottle>,
+    file_path: String,
+    password: String,
+) -> Result<ApiResponse<String>, String> {
+    let params = FileEncryption::read_encrypted_file(&file_path, &password)
+        .await
+        .map_err(|e| format!("Failed to read encrypted file: {}", e))?;
+    let decrypted = match throttle.run(|| BackendEncryption::decrypt_bytes(&params)) {
+        Ok(decrypted) => decrypted,
+        Err(failure) => return Ok(failure.into_response(None)),
+    };
+
+    let decrypted_path = FileEncryption::write_new_file(&FileEncryption::decrypted_path_for(&file_path), &decrypted)
+        .await
+        .map_err(|e| format!("Failed to write decrypted file: {}", e))?;
+
+    Ok(ApiResponse::success(decrypted_path))
+}
+
+/// 验证密码强度
+#[tauri::command]
+pub async fn validate_password_strength(password: String) -> Result<ApiResponse<u8>, String> {
+    let strength = BackendEncryption::validate_password_strength(&password);
+    Ok(ApiResponse::success(strength))
+}
+
+/// 测量当前密钥派生参数在本机上的耗时（毫秒）
+#[tauri::command]
+pub async fn benchmark_kdf() -> Result<ApiResponse<u64>, String> {
+    let elapsed_ms = tokio::task::spawn_blocking(BackendEncryption::benchmark_kdf)
+        .await
+        .map_err(|e| format!("Failed to run KDF benchmark: {}", e))?
+        .map_err(|e| format!("Failed to run KDF benchmark: {}", e))?;
+
+    Ok(ApiResponse::success(elapsed_ms))
+}
+
+/// 获取用户设置
+#[tauri::command]
+pub async fn get_settings(
+    app: AppHandle,
+    password: Option<String>,
+) -> Result<ApiResponse<UserSettings>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?;
+
+    let settings = storage
+        .load_settings(password.as_deref())
+        .await
+        .map_err(|e| format!("Failed to load settings: {}", e))?;
+
+    Ok(ApiResponse::success(settings))
+}
+
+/// 获取设置字段的结构与默认值
+#[tauri::command]
+pub async fn get_settings_schema() -> Result<ApiResponse<Vec<SettingField>>, String> {
+    Ok(ApiResponse::success(UserSettings::schema()))
+}
+
+/// 保存用户设置
+#[tauri::command]
+pub async fn save_settings(
+    app: AppHandle,
+    session: State<'_, SessionState>,
+    settings: UserSettings,
+    password: Option<String>,
+) -> Result<ApiResponse<()>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?;
+    if let Some(response) = reject_read_only(&storage) {
+        return Ok(response);
+    }
+
+    storage
+        .save_settings(&settings, password.as_deref())
+        .await
+        .map_err(|e| format!("Failed to save settings: {}", e))?;
+    // 设置中的数据目录覆盖可能已改变，下次使用时按新设置重新打开
+    app.state::<StorageState>().0.invalidate().await;
+
+    // 新的自动锁定时间立即生效
+    session.0
+        .lock()
+        .map_err(|e| e.to_string())?
+        .set_auto_lock_minutes(settings.auto_lock_minutes);
+
+    Ok(ApiResponse::success_empty().with_message("设置已保存".to_string()))
+}
+
+/// 设置自定义数据目录（为空时恢复默认目录），可选择把现有数据复制过去
+#[tauri::command]
+pub async fn set_data_dir(
+    app: AppHandle,
+    data_dir: Option<String>,
+    migrate: Option<bool>,
+    password: Option<String>,
+) -> Result<ApiResponse<String>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = StorageManager::new(&default_data_dir(&app)?)
+        .await
+        .map_err(|e| format!("Failed to create storage manager: {}", e))?;
+    let storage = app.state::<UiLockState>().apply(storage)?;
+    if let Some(response) = reject_read_only(&storage) {
+        return Ok(response);
+    }
+
+    let active_dir = storage
+        .set_data_dir(data_dir.as_deref(), migrate.unwrap_or(false), password.as_deref())
+        .await
+        .map_err(|e| format!("Failed to set data directory: {}", e))?;
+
+    app.state::<StorageState>().0.invalidate().await;
+    forget_decrypted(&app);
+    Ok(ApiResponse::success(active_dir.to_string_lossy().to_string()))
+}
+
+/// 列出所有保险库
+#[tauri::command]
+pub async fn list_vaults(app: AppHandle) -> Result<ApiResponse<Vec<VaultInfo>>, String> {
+    let registry = VaultRegistry::new(&default_data_dir(&app)?);
+
+    let vaults = registry
+        .list()
+        .await
+        .map_err(|e| format!("Failed to list vaults: {}", e))?;
+
+    Ok(ApiResponse::success(vaults))
+}
+
+/// 创建新的保险库（不会自动切换）
+#[tauri::command]
+pub async fn create_vault(app: AppHandle, name: String) -> Result<ApiResponse<String>, String> {
+    let registry = VaultRegistry::new(&default_data_dir(&app)?);
+
+    let name = registry
+        .create(&name)
+        .await
+        .map_err(|e| format!("Failed to create vault: {}", e))?;
+
+    Ok(ApiResponse::success(name))
+}
+
+/// 切换当前保险库，并锁定已解锁的会话
+#[tauri::command]
+pub async fn switch_vault(
+    app: AppHandle,
+    session: State<'_, SessionState>,
+    name: String,
+) -> Result<ApiResponse<()>, String> {
+    let registry = VaultRegistry::new(&default_data_dir(&app)?);
+
+    registry
+        .switch(&name)
+        .await
+        .map_err(|e| format!("Failed to switch vault: {}", e))?;
+    app.state::<StorageState>().0.invalidate().await;
+
+    // 缓存的密码属于原保险库
+    session.lock(&app)?;
+    Ok(ApiResponse::success_empty().with_message(format!("已切换到保险库 {}", name.trim())))
+}
+
+/// 删除保险库及其全部数据（不能删除默认保险库和当前保险库）
+#[tauri::command]
+pub async fn delete_vault(app: AppHandle, name: String) -> Result<ApiResponse<()>, String> {
+    // 界面锁定时当前存储为只读，同样不允许删除其他保险库
+    let storage = get_storage_manager(&app).await?;
+    if let Some(response) = reject_read_only(&storage) {
+        return Ok(response);
+    }
+    let registry = VaultRegistry::new(&default_data_dir(&app)?);
+
+    registry
+        .delete(&name)
+        .await
+        .map_err(|e| format!("Failed to delete vault: {}", e))?;
+    app.state::<StorageState>().0.invalidate().await;
+
+    Ok(ApiResponse::success_empty().with_message("保险库已删除".to_string()))
+}
+
+/// 获取拾梦回响配置
+#[tauri::command]
+pub async fn get_dream_config(
+    app: AppHandle,
+    password: Option<String>,
+) -> Result<ApiResponse<DreamConfig>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?;
+
+    let config = storage
+        .load_dream_config(password.as_deref())
+        .await
+        .map_err(|e| format!("Failed to load dream config: {}", e))?;
+
+    Ok(ApiResponse::success(config))
+}
+
+/// 保存拾梦回响配置
+#[tauri::command]
+pub async fn save_dream_config(
+    app: AppHandle,
+    config: DreamConfig,
+    password: Option<String>,
+) -> Result<ApiResponse<()>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?;
+    if let Some(response) = reject_read_only(&storage) {
+        return Ok(response);
+    }
+
+    storage
+        .save_dream_config(&config, password.as_deref())
+        .await
+        .map_err(|e| format!("Failed to save dream config: {}", e))?;
+
+    Ok(ApiResponse::success_empty().with_message("拾梦配置已保存".to_string()))
+}
+
+/// 设置密码提示（提示不能包含密码本身，传入空字符串清除提示）
+#[tauri::command]
+pub async fn set_password_hint(
+    app: AppHandle,
+    hint: String,
+    password: String,
+) -> Result<ApiResponse<()>, String> {
+    let storage = get_storage_manager(&app).await?;
+    if let Some(response) = reject_read_only(&storage) {
+        return Ok(response);
+    }
+
+    storage
+        .set_password_hint(&hint, &password)
+        .await
+        .map_err(|e| format!("Failed to set password hint: {}", e))?;
+
+    Ok(ApiResponse::success_empty().with_message("密码提示已保存".to_string()))
+}
+
+/// 获取密码提示（在输入密码之前显示）
+#[tauri::command]
+pub async fn get_password_hint(app: AppHandle) -> Result<ApiResponse<Option<String>>, String> {
+    let storage = get_storage_manager(&app).await?;
+
+    let hint = storage
+        .get_password_hint()
+        .await
+        .map_err(|e| format!("Failed to get password hint: {}", e))?;
+
+    Ok(ApiResponse::success(hint))
+}
+
+/// 获取随机记忆（用于拾梦回响）
+#[tauri::command]
+pub async fn get_random_memory(
+    app: AppHandle,
+    password: Option<String>,
+    read_only: Option<bool>,
+) -> Result<ApiResponse<Option<MemoryEntry>>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_reader_storage(&app, read_only).await?.with_password(password);
+    
+    let entry = storage
+        .get_random_entry()
+        .await
+        .map_err(|e| format!("Failed to get random entry: {}", e))?;
     
     Ok(ApiResponse::success(entry))
 }
 
+/// 按设置中的提醒配置安排写作提醒，返回下一次提醒时间
+///
+/// 每次调用都会取消之前安排的提醒；提醒关闭时只取消。提醒发出后自动安排下一次。
+#[tauri::command]
+pub async fn schedule_reminder(
+    app: AppHandle,
+    reminder_state: State<'_, ReminderState>,
+    password: Option<String>,
+) -> Result<ApiResponse<Option<chrono::DateTime<chrono::Utc>>>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?;
+    let settings = storage
+        .load_settings(password.as_deref())
+        .await
+        .map_err(|e| format!("Failed to load settings: {}", e))?;
+
+    let mut scheduled = reminder_state.0.lock().map_err(|e| e.to_string())?;
+    if let Some(task) = scheduled.take() {
+        task.abort();
+    }
+
+    let config = settings.reminder.clone();
+    let offset = settings.utc_offset();
+    let Some(next) = reminder::next_due(&config, chrono::Utc::now(), offset) else {
+        return Ok(ApiResponse::success(None).with_message("写作提醒已关闭".to_string()));
+    };
+
+    let handle = app.clone();
+    *scheduled = Some(tauri::async_runtime::spawn(async move {
+        while let Some(due) = reminder::next_due(&config, chrono::Utc::now(), offset) {
+            let wait = (due - chrono::Utc::now()).to_std().unwrap_or_default();
+            tokio::time::sleep(wait).await;
+            let _ = handle
+                .notification()
+                .builder()
+                .title("桃花笺")
+                .body("该写日记了，记下今天的点滴吧")
+                .show();
+        }
+    }));
+
+    Ok(ApiResponse::success(Some(next)))
+}
+
 /// 备份数据
 #[tauri::command]
 pub async fn backup_data(
     app: AppHandle,
     backup_path: String,
-) -> Result<ApiResponse<()>, String> {
+) -> Result<ApiResponse<String>, String> {
     let storage = get_storage_manager(&app).await?;
     
-    storage
+    let backup_dir = storage
         .backup_data(&backup_path)
         .await
         .map_err(|e| format!("Failed to backup data: {}", e))?;
     
-    Ok(ApiResponse::success_empty().with_message("数据备份成功".to_string()))
+    Ok(ApiResponse::success(backup_dir.to_string_lossy().to_string()).with_message("数据备份成功".to_string()))
+}
+
+/// 从备份恢复数据（校验失败时拒绝恢复）
+#[tauri::command]
+pub async fn restore_data(
+    app: AppHandle,
+    backup_dir: String,
+) -> Result<ApiResponse<RestoreReport>, String> {
+    let storage = get_storage_manager(&app).await?;
+    if let Some(response) = reject_read_only(&storage) {
+        return Ok(response);
+    }
+
+    let report = storage
+        .restore_data(&backup_dir)
+        .await
+        .map_err(|e| format!("Failed to restore data: {}", e))?;
+
+    if report.restored {
+        // 恢复的设置可能带有不同的数据目录覆盖
+        app.state::<StorageState>().0.invalidate().await;
+        forget_decrypted(&app);
+        Ok(ApiResponse::success(report).with_message("数据恢复成功".to_string()))
+    } else {
+        Ok(ApiResponse::error_with_data(report, "备份校验失败，已拒绝恢复".to_string()))
+    }
+}
+
+/// 校验备份目录（不恢复、不修改任何文件），提供密码时试解密加密的备份
+#[tauri::command]
+pub async fn validate_backup(
+    app: AppHandle,
+    backup_dir: String,
+    password: Option<String>,
+) -> Result<ApiResponse<BackupValidation>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?;
+
+    let validation = storage
+        .validate_backup(&backup_dir, password.as_deref())
+        .await
+        .map_err(|e| format!("Failed to validate backup: {}", e))?;
+
+    Ok(ApiResponse::success(validation))
+}
+
+/// 导出单个条目为加密分享字符串
+///
+/// `password` 为分享密码，`store_password` 为当前存储的密码。
+#[tauri::command]
+pub async fn export_entry_sealed(
+    app: AppHandle,
+    entry_id: String,
+    password: String,
+    store_password: Option<String>,
+) -> Result<ApiResponse<String>, String> {
+    let store_password = resolve_password(&app, store_password)?;
+    let storage = get_storage_manager(&app).await?.with_password(store_password);
+
+    let payload = storage
+        .export_entry_sealed(&entry_id, &password)
+        .await
+        .map_err(|e| format!("Failed to export entry: {}", e))?;
+
+    Ok(ApiResponse::success(payload))
+}
+
+/// 从加密分享字符串导入单个条目
+///
+/// `password` 为分享密码，`store_password` 为当前存储的密码。
+#[tauri::command]
+pub async fn import_entry_sealed(
+    app: AppHandle,
+    undo: State<'_, UndoState>,
+    payload: String,
+    password: String,
+    store_password: Option<String>,
+) -> Result<ApiResponse<MemoryEntry>, String> {
+    let store_password = resolve_password(&app, store_password)?;
+    let storage = get_storage_manager(&app).await?.with_password(store_password).with_journal();
+    if let Some(response) = reject_read_only(&storage) {
+        return Ok(response);
+    }
+
+    let entry = storage
+        .import_entry_sealed(&payload, &password)
+        .await
+        .map_err(|e| format!("Failed to import entry: {}", e))?;
+    undo.record("导入条目", &storage);
+
+    Ok(ApiResponse::success(entry).with_message("记忆已导入".to_string()))
+}
+
+/// 导出单个条目为加密分享文件
+///
+/// `password` 为分享密码，`store_password` 为当前存储的密码。
+#[tauri::command]
+pub async fn export_entry_encrypted(
+    app: AppHandle,
+    entry_id: String,
+    password: String,
+    file_path: String,
+    store_password: Option<String>,
+) -> Result<ApiResponse<String>, String> {
+    let store_password = resolve_password(&app, store_password)?;
+    let storage = get_storage_manager(&app).await?.with_password(store_password);
+
+    let path = storage
+        .export_entry_encrypted(&entry_id, &password, &file_path)
+        .await
+        .map_err(|e| format!("Failed to export entry: {}", e))?;
+
+    Ok(ApiResponse::success(path.to_string_lossy().to_string()))
+}
+
+/// 从加密分享文件导入单个条目
+///
+/// `password` 为分享密码，`store_password` 为当前存储的密码。
+#[tauri::command]
+pub async fn import_entry_encrypted(
+    app: AppHandle,
+    undo: State<'_, UndoState>,
+    file_path: String,
+    password: String,
+    store_password: Option<String>,
+) -> Result<ApiResponse<MemoryEntry>, String> {
+    let store_password = resolve_password(&app, store_password)?;
+    let storage = get_storage_manager(&app).await?.with_password(store_password).with_journal();
+    if let Some(response) = reject_read_only(&storage) {
+        return Ok(response);
+    }
+
+    let entry = storage
+        .import_entry_encrypted(&file_path, &password)
+        .await
+        .map_err(|e| format!("Failed to import entry: {}", e))?;
+    undo.record("导入条目", &storage);
+
+    Ok(ApiResponse::success(entry).with_message("记忆已导入".to_string()))
+}
+
+/// 导出所有条目并打包附件
+#[tauri::command]
+pub async fn export_with_attachments(
+    app: AppHandle,
+    export_path: String,
+    password: Option<String>,
+) -> Result<ApiResponse<ExportReport>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?.with_password(password);
+
+    let report = storage
+        .export_with_attachments(&export_path)
+        .await
+        .map_err(|e| format!("Failed to export entries: {}", e))?;
+
+    Ok(ApiResponse::success(report))
+}
+
+/// 以流式方式导出所有条目为 JSON 文档，适合条目很多的保险库
+#[tauri::command]
+pub async fn export_json_stream(
+    app: AppHandle,
+    dest: String,
+    password: Option<String>,
+) -> Result<ApiResponse<u32>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?;
+
+    let count = storage
+        .export_json_stream(&dest, password.as_deref())
+        .await
+        .map_err(|e| format!("Failed to export entries: {}", e))?;
+
+    Ok(ApiResponse::success(count).with_message(format!("已导出 {} 条记忆", count)))
+}
+
+/// 将条目导出为静态 HTML 页面，`from`/`to` 为 `YYYY-MM-DD` 格式的可选日期范围
+#[tauri::command]
+pub async fn export_html(
+    app: AppHandle,
+    dest: String,
+    password: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<ApiResponse<String>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?.with_password(password);
+    let parse_date = |date: Option<String>| {
+        date.map(|d| chrono::NaiveDate::parse_from_str(d.trim(), "%Y-%m-%d"))
+            .transpose()
+            .map_err(|e| format!("Invalid date: {}", e))
+    };
+
+    let path = storage
+        .export_html(&dest, parse_date(from)?, parse_date(to)?)
+        .await
+        .map_err(|e| format!("Failed to export HTML: {}", e))?;
+
+    Ok(ApiResponse::success(path.to_string_lossy().to_string()))
+}
+
+/// 导入带附件的导出包
+#[tauri::command]
+pub async fn import_with_attachments(
+    app: AppHandle,
+    undo: State<'_, UndoState>,
+    export_dir: String,
+    password: Option<String>,
+) -> Result<ApiResponse<ImportReport>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?.with_password(password).with_journal();
+    if let Some(response) = reject_read_only(&storage) {
+        return Ok(response);
+    }
+
+    let report = storage
+        .import_with_attachments(&export_dir)
+        .await
+        .map_err(|e| format!("Failed to import entries: {}", e))?;
+    undo.record("导入条目", &storage);
+
+    Ok(ApiResponse::success(report))
+}
+
+/// 从其他日记应用的 JSON 导出导入条目
+#[tauri::command]
+pub async fn import_external_json(
+    app: AppHandle,
+    undo: State<'_, UndoState>,
+    src: String,
+    format: ExternalFormat,
+    field_map: Option<GenericFieldMap>,
+    password: Option<String>,
+) -> Result<ApiResponse<ImportReport>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?.with_password(password).with_journal();
+    if let Some(response) = reject_read_only(&storage) {
+        return Ok(response);
+    }
+
+    let report = storage
+        .import_external_json(&src, format, field_map)
+        .await
+        .map_err(|e| format!("Failed to import entries: {}", e))?;
+    undo.record("导入条目", &storage);
+
+    let message = format!("已导入 {} 条记忆，跳过 {} 条", report.imported, report.skipped.len());
+    Ok(ApiResponse::success(report).with_message(message))
+}
+
+/// 合并另一个保险库目录中的条目
+///
+/// `password` 为当前存储的密码，`other_password` 为另一保险库的密码，未提供时与 `password` 相同。
+#[tauri::command]
+pub async fn merge_vault(
+    app: AppHandle,
+    undo: State<'_, UndoState>,
+    other_path: String,
+    password: Option<String>,
+    other_password: Option<String>,
+    strategy: Option<MergeStrategy>,
+) -> Result<ApiResponse<MergeReport>, String> {
+    let password = resolve_password(&app, password)?;
+    let other_password = other_password.or_else(|| password.clone());
+    let storage = get_storage_manager(&app).await?.with_password(password).with_journal();
+    if let Some(response) = reject_read_only(&storage) {
+        return Ok(response);
+    }
+
+    let report = storage
+        .merge_vault(&other_path, other_password.as_deref(), strategy.unwrap_or_default())
+        .await
+        .map_err(|e| format!("Failed to merge vault: {}", e))?;
+    undo.record("合并保险库", &storage);
+
+    let message = format!("新增 {} 条，更新 {} 条，跳过 {} 条", report.added, report.updated, report.skipped);
+    Ok(ApiResponse::success(report).with_message(message))
+}
+
+/// 安全清空保险库（需要确认短语），清空后锁定会话并清除撤销记录，避免通过撤销或缓存的密码找回数据
+#[tauri::command]
+pub async fn wipe_vault(
+    app: AppHandle,
+    session: State<'_, SessionState>,
+    confirm_phrase: String,
+    dry_run: Option<bool>,
+) -> Result<ApiResponse<Vec<String>>, String> {
+    let storage = get_storage_manager(&app).await?;
+    if let Some(response) = reject_read_only(&storage) {
+        return Ok(response);
+    }
+    let dry_run = dry_run.unwrap_or(false);
+
+    let removed = storage
+        .wipe_vault(&confirm_phrase, dry_run)
+        .await
+        .map_err(|e| format!("Failed to wipe vault: {}", e))?;
+
+    let message = if dry_run {
+        format!("预览：将删除 {} 个文件或目录", removed.len())
+    } else {
+        app.state::<StorageState>().0.invalidate().await;
+        session.lock(&app)?;
+        "保险库已清空".to_string()
+    };
+    Ok(ApiResponse::success(removed).with_message(message))
+}
+
+/// 修复损坏的记忆文件
+#[tauri::command]
+pub async fn repair_vault(
+    app: AppHandle,
+    undo: State<'_, UndoState>,
+) -> Result<ApiResponse<RepairReport>, String> {
+    let storage = get_storage_manager(&app).await?;
+    if let Some(response) = reject_read_only(&storage) {
+        return Ok(response);
+    }
+
+    let report = storage
+        .repair_vault()
+        .await
+        .map_err(|e| format!("Failed to repair vault: {}", e))?;
+    // 修复按文件内容重写条目，之前的撤销记录不再对应当前条目
+    undo.invalidate();
+
+    let message = format!("已恢复 {} 条记忆，丢弃 {} 条", report.recovered, report.dropped);
+    Ok(ApiResponse::success(report).with_message(message))
+}
+
+/// 清理手工编辑过的记忆文件（去重、补全时间戳与字数），修复前自动备份
+#[tauri::command]
+pub async fn repair_store(
+    app: AppHandle,
+    undo: State<'_, UndoState>,
+    password: Option<String>,
+) -> Result<ApiResponse<SanitizeReport>, String> {
+    let password = resolve_password(&app, password)?;
+    let storage = get_storage_manager(&app).await?.with_password(password);
+    if let Some(response) = reject_read_only(&storage) {
+        return Ok(response);
+    }
+
+    let report = storage
+        .repair_store()
+        .await
+        .map_err(|e| format!("Failed to repair store: {}", e))?;
+    // 修复按文件内容重写条目，之前的撤销记录不再对应当前条目
+    undo.invalidate();
+
+    let message = format!("已修复 {} 处问题", report.changes.len());
+    Ok(ApiResponse::success(report).with_message(message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_ui_lock_rejects_mutation_until_unlocked() {
+        let dir = std::env::temp_dir().join(format!("peach-blossom-test-{}", uuid::Uuid::new_v4()));
+        let storage = StorageManager::new(dir.to_str().unwrap()).await.unwrap();
+        let ui_lock = UiLockState::default();
+        let entry = MemoryEntry::new("标题".to_string(), "内容".to_string(), MemoryType::Text);
+
+        ui_lock.0.lock().unwrap().lock();
+        let locked = ui_lock.apply(storage.clone()).unwrap();
+        let response = reject_read_only::<()>(&locked).expect("locked storage must be rejected");
+        assert!(!response.success);
+        assert_eq!(response.error_code, Some(ErrorCode::ReadOnly));
+        assert!(locked.save_entry(&entry, None).await.is_err());
+        assert!(storage.load_entries_for(None).await.unwrap().is_empty());
+
+        ui_lock.0.lock().unwrap().unlock();
+        let unlocked = ui_lock.apply(storage.clone()).unwrap();
+        assert!(reject_read_only::<()>(&unlocked).is_none());
+        unlocked.save_entry(&entry, None).await.unwrap();
+        assert_eq!(storage.load_entries_for(None).await.unwrap().len(), 1);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_retain_unarchived_honours_flag() {
+        let visible = MemoryEntry::new("标题".to_string(), "内容".to_string(), MemoryType::Text);
+        let mut archived = visible.clone();
+        archived.is_archived = true;
+        let entries = vec![visible.clone(), archived];
+
+        let mut listed = entries.clone();
+        retain_unarchived(&mut listed, None);
+        assert_eq!(listed.iter().map(|e| e.id.as_str()).collect::<Vec<_>>(), vec![visible.id.as_str()]);
+
+        let mut all = entries.clone();
+        retain_unarchived(&mut all, Some(true));
+        assert_eq!(all.len(), 2);
+    }
+
+    #[test]
+    fn test_captured_context_recorded_only_when_enabled() {
+        let build = |settings: &UserSettings| {
+            let mut entry = build_new_entry("标题".to_string(), "内容".to_string(), "text", Vec::new(), None, settings);
+            set_captured_context(&mut entry, Some("西湖".to_string()), Some("晴".to_string()), settings);
+            entry
+        };
+
+        let entry = build(&UserSettings::default());
+        assert!(entry.metadata.as_ref().is_none_or(|m| m.location.is_none() && m.weather.is_none()));
+
+        let settings = UserSettings { capture_location: true, ..UserSettings::default() };
+        let metadata = build(&settings).metadata.unwrap();
+        assert_eq!(metadata.location.as_deref(), Some("西湖"));
+        assert_eq!(metadata.weather.as_deref(), Some("晴"));
+    }
 }
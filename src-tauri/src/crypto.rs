@@ -11,6 +11,66 @@ use base64::{Engine as _, engine::general_purpose};
 use rand::RngCore;
 use anyhow::{Result, anyhow};
 use serde::{Deserialize, Serialize};
+use std::ops::RangeInclusive;
+
+/// 当前加密格式版本，版本决定盐值与 nonce 的长度（见 [`BackendEncryption::format_lengths`]）
+pub const ENCRYPTION_FORMAT_VERSION: u32 = 1;
+
+/// 自定义盐值长度的加密格式版本，盐值长度由解码后的字节数决定
+pub const CUSTOM_SALT_FORMAT_VERSION: u32 = 2;
+
+/// 未记录版本的旧数据按版本 1（盐值 32 字节、nonce 12 字节）解析
+pub(crate) fn legacy_format_version() -> u32 {
+    1
+}
+
+/// 加密配置（盐值与密钥长度）
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct EncryptionConfig {
+    /// 盐值长度（字节），不少于 16、不超过 48
+    pub salt_length: usize,
+    /// 密钥长度（字节），AES-256 固定为 32
+    pub key_length: usize,
+}
+
+impl Default for EncryptionConfig {
+    fn default() -> Self {
+        Self {
+            salt_length: BackendEncryption::SALT_LENGTH,
+            key_length: BackendEncryption::KEY_LENGTH,
+        }
+    }
+}
+
+impl EncryptionConfig {
+    /// 盐值最短长度
+    pub const MIN_SALT_LENGTH: usize = 16;
+    /// 盐值最长长度（Argon2 盐值编码后最多 64 个字符）
+    pub const MAX_SALT_LENGTH: usize = 48;
+
+    /// 校验配置，拒绝不安全或不支持的长度
+    pub fn validate(&self) -> Result<()> {
+        if self.salt_length < Self::MIN_SALT_LENGTH {
+            return Err(anyhow!(
+                "Salt length must be at least {} bytes, got {}",
+                Self::MIN_SALT_LENGTH, self.salt_length
+            ));
+        }
+        if self.salt_length > Self::MAX_SALT_LENGTH {
+            return Err(anyhow!(
+                "Salt length must be at most {} bytes, got {}",
+                Self::MAX_SALT_LENGTH, self.salt_length
+            ));
+        }
+        if self.key_length != BackendEncryption::KEY_LENGTH {
+            return Err(anyhow!(
+                "Key length must be {} bytes for AES-256-GCM, got {}",
+                BackendEncryption::KEY_LENGTH, self.key_length
+            ));
+        }
+        Ok(())
+    }
+}
 
 /// 加密结果结构
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -18,6 +78,8 @@ pub struct EncryptionResult {
     pub encrypted_data: String,
     pub nonce: String,
     pub salt: String,
+    #[serde(default = "legacy_format_version")]
+    pub format_version: u32,
 }
 
 /// 解密参数结构
@@ -26,9 +88,86 @@ pub struct DecryptionParams {
     pub encrypted_data: String,
     pub nonce: String,
     pub salt: String,
+    #[serde(default = "legacy_format_version")]
+    pub format_version: u32,
     pub password: String,
 }
 
+/// 加密算法名称
+pub const CIPHER_ALGORITHM: &str = "AES-256-GCM";
+
+/// 密钥派生算法名称
+pub const KDF_ALGORITHM: &str = "argon2id";
+
+/// Argon2 密钥派生参数
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Argon2Params {
+    /// 内存开销（KiB）
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+    /// Argon2 算法版本（19 即 0x13）
+    pub version: u32,
+}
+
+/// 当前推荐的 Argon2id 最低参数（OWASP 建议：19 MiB 内存、2 次迭代、1 路并行）
+pub const RECOMMENDED_ARGON2_PARAMS: Argon2Params = Argon2Params {
+    memory_kib: 19 * 1024,
+    iterations: 2,
+    parallelism: 1,
+    version: 0x13,
+};
+
+impl Argon2Params {
+    /// 是否有任一参数低于 `minimum`
+    pub fn is_below(&self, minimum: &Argon2Params) -> bool {
+        self.memory_kib < minimum.memory_kib
+            || self.iterations < minimum.iterations
+            || self.parallelism < minimum.parallelism
+            || self.version < minimum.version
+    }
+}
+
+/// 宽松解密结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LossyDecryption {
+    pub text: String,
+    /// 解密后的字节不是合法 UTF-8，已用替换字符填充
+    pub had_invalid_utf8: bool,
+}
+
+/// 解密失败的原因
+///
+/// 以 `anyhow::Error` 返回，调用方可用 `downcast_ref::<DecryptError>()` 区分。
+/// AES-GCM 无法区分密码错误与密文被篡改，认证标签校验失败一律视为密码错误；
+/// 认证通过但明文头不符时视为数据损坏。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecryptError {
+    /// 认证标签校验失败（通常是密码错误）
+    WrongPassword,
+    /// 密文格式损坏（Base64、长度或解密后的文本无效）
+    CorruptData(String),
+}
+
+impl std::fmt::Display for DecryptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecryptError::WrongPassword => write!(f, "Wrong password"),
+            DecryptError::CorruptData(reason) => write!(f, "Encrypted data is corrupt: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for DecryptError {}
+
+/// 明文头：加密前写在明文开头，解密后校验并去除
+///
+/// 首字节 0xFF 不会出现在 UTF-8 文本开头，因此没有明文头的旧版文本密文不会被误判。
+const PLAINTEXT_MAGIC: [u8; 4] = [0xFF, b'P', b'B', b'P'];
+
+/// 当前明文头版本
+const PLAINTEXT_VERSION: u8 = 1;
+
 /// 后端加密管理器
 pub struct BackendEncryption;
 
@@ -36,10 +175,11 @@ impl BackendEncryption {
     const SALT_LENGTH: usize = 32;
     const NONCE_LENGTH: usize = 12;
     const KEY_LENGTH: usize = 32;
+    const TAG_LENGTH: usize = 16;
 
-    /// 生成随机盐值
-    fn generate_salt() -> [u8; Self::SALT_LENGTH] {
-        let mut salt = [0u8; Self::SALT_LENGTH];
+    /// 生成指定长度的随机盐值
+    fn generate_salt(length: usize) -> Vec<u8> {
+        let mut salt = vec![0u8; length];
         OsRng.fill_bytes(&mut salt);
         salt
     }
@@ -51,6 +191,43 @@ impl BackendEncryption {
         nonce
     }
 
+    /// 加密格式版本允许的盐值长度范围与 nonce 长度，未知版本返回 None
+    pub fn format_lengths(version: u32) -> Option<(RangeInclusive<usize>, usize)> {
+        match version {
+            1 => Some((Self::SALT_LENGTH..=Self::SALT_LENGTH, Self::NONCE_LENGTH)),
+            CUSTOM_SALT_FORMAT_VERSION => Some((
+                EncryptionConfig::MIN_SALT_LENGTH..=EncryptionConfig::MAX_SALT_LENGTH,
+                Self::NONCE_LENGTH,
+            )),
+            _ => None,
+        }
+    }
+
+    /// 盐值长度对应的格式版本：默认长度沿用版本 1，其余长度使用自定义盐值格式
+    fn format_version_for_salt(salt_length: usize) -> u32 {
+        if salt_length == Self::SALT_LENGTH {
+            ENCRYPTION_FORMAT_VERSION
+        } else {
+            CUSTOM_SALT_FORMAT_VERSION
+        }
+    }
+
+    /// 加密格式版本使用的 Argon2id 参数，未知版本返回 None
+    pub fn kdf_params(version: u32) -> Option<Argon2Params> {
+        match version {
+            1 | CUSTOM_SALT_FORMAT_VERSION => {
+                let params = argon2::Params::default();
+                Some(Argon2Params {
+                    memory_kib: params.m_cost(),
+                    iterations: params.t_cost(),
+                    parallelism: params.p_cost(),
+                    version: argon2::Version::default() as u32,
+                })
+            }
+            _ => None,
+        }
+    }
+
     /// 从密码和盐值派生密钥
     fn derive_key(password: &str, salt: &[u8]) -> Result<[u8; Self::KEY_LENGTH]> {
         use argon2::{Argon2, PasswordHasher};
@@ -77,43 +254,111 @@ impl BackendEncryption {
         Ok(key)
     }
 
+    /// 用当前 Argon2 参数对一次性密码派生密钥，返回耗时毫秒数
+    ///
+    /// 解锁时每个加密文件都要派生一次密钥，前端据此预估解锁耗时。
+    pub fn benchmark_kdf() -> Result<u64> {
+        let start = std::time::Instant::now();
+        Self::derive_key("peach-blossom-benchmark", &Self::generate_salt(Self::SALT_LENGTH))?;
+        Ok(start.elapsed().as_millis() as u64)
+    }
+
     /// 加密数据
     pub fn encrypt(data: &str, password: &str) -> Result<EncryptionResult> {
-        if data.is_empty() || password.is_empty() {
-            return Err(anyhow!("Data and password cannot be empty"));
+        Self::encrypt_bytes(data.as_bytes(), password)
+    }
+
+    /// 按给定配置加密数据，配置不安全时返回错误
+    pub fn encrypt_with_config(data: &str, password: &str, config: &EncryptionConfig) -> Result<EncryptionResult> {
+        Self::encrypt_bytes_with_config(data.as_bytes(), password, config)
+    }
+
+    /// 加密任意字节（明文前加上明文头）
+    pub fn encrypt_bytes(data: &[u8], password: &str) -> Result<EncryptionResult> {
+        Self::encrypt_bytes_with_config(data, password, &EncryptionConfig::default())
+    }
+
+    /// 按给定配置加密任意字节
+    fn encrypt_bytes_with_config(data: &[u8], password: &str, config: &EncryptionConfig) -> Result<EncryptionResult> {
+        config.validate()?;
+        if data.is_empty() {
+            return Err(anyhow!("Data cannot be empty"));
         }
 
         // 生成盐值和 nonce
-        let salt = Self::generate_salt();
+        let salt = Self::generate_salt(config.salt_length);
         let nonce_bytes = Self::generate_nonce();
 
+        let mut framed = Vec::with_capacity(PLAINTEXT_MAGIC.len() + 1 + data.len());
+        framed.extend_from_slice(&PLAINTEXT_MAGIC);
+        framed.push(PLAINTEXT_VERSION);
+        framed.extend_from_slice(data);
+
+        Self::encrypt_bytes_with(&framed, password, &salt, &nonce_bytes)
+    }
+
+    /// 使用给定的盐值和 nonce 加密（生产路径只传入随机值，固定值仅用于已知答案测试）
+    fn encrypt_bytes_with(
+        data: &[u8],
+        password: &str,
+        salt: &[u8],
+        nonce_bytes: &[u8; Self::NONCE_LENGTH],
+    ) -> Result<EncryptionResult> {
+        if data.is_empty() || password.is_empty() {
+            return Err(anyhow!("Data and password cannot be empty"));
+        }
+
         // 派生密钥
-        let key_bytes = Self::derive_key(password, &salt)?;
+        let key_bytes = Self::derive_key(password, salt)?;
         let key = Key::<Aes256Gcm>::from_slice(&key_bytes);
-        let nonce = Nonce::from_slice(&nonce_bytes);
+        let nonce = Nonce::from_slice(nonce_bytes);
 
         // 创建加密器
         let cipher = Aes256Gcm::new(key);
 
         // 加密数据
         let encrypted_bytes = cipher
-            .encrypt(nonce, data.as_bytes())
+            .encrypt(nonce, data)
             .map_err(|e| anyhow!("Encryption failed: {}", e))?;
 
         // 编码为 Base64
         let encrypted_data = general_purpose::STANDARD.encode(&encrypted_bytes);
-        let nonce_b64 = general_purpose::STANDARD.encode(&nonce_bytes);
-        let salt_b64 = general_purpose::STANDARD.encode(&salt);
+        let nonce_b64 = general_purpose::STANDARD.encode(nonce_bytes);
+        let salt_b64 = general_purpose::STANDARD.encode(salt);
 
         Ok(EncryptionResult {
             encrypted_data,
             nonce: nonce_b64,
             salt: salt_b64,
+            format_version: Self::format_version_for_salt(salt.len()),
         })
     }
 
     /// 解密数据
     pub fn decrypt(params: &DecryptionParams) -> Result<String> {
+        let decrypted_bytes = Self::decrypt_bytes(params)?;
+
+        // 转换为字符串
+        let decrypted_text = String::from_utf8(decrypted_bytes)
+            .map_err(|e| DecryptError::CorruptData(format!("decrypted data is not valid UTF-8: {}", e)))?;
+
+        Ok(decrypted_text)
+    }
+
+    /// 宽松解密：非法 UTF-8 字节以替换字符代替，并标记是否发生替换
+    pub fn decrypt_lossy(params: &DecryptionParams) -> Result<LossyDecryption> {
+        let decrypted_bytes = Self::decrypt_bytes(params)?;
+
+        let (text, had_invalid_utf8) = match String::from_utf8(decrypted_bytes) {
+            Ok(text) => (text, false),
+            Err(e) => (String::from_utf8_lossy(e.as_bytes()).into_owned(), true),
+        };
+
+        Ok(LossyDecryption { text, had_invalid_utf8 })
+    }
+
+    /// 解密为原始字节
+    pub fn decrypt_bytes(params: &DecryptionParams) -> Result<Vec<u8>> {
         if params.encrypted_data.is_empty() 
             || params.nonce.is_empty() 
             || params.salt.is_empty() 
@@ -124,19 +369,38 @@ impl BackendEncryption {
         // 解码 Base64
         let encrypted_bytes = general_purpose::STANDARD
             .decode(&params.encrypted_data)
-            .map_err(|e| anyhow!("Failed to decode encrypted data: {}", e))?;
+            .map_err(|e| DecryptError::CorruptData(format!("failed to decode encrypted data: {}", e)))?;
         
         let nonce_bytes = general_purpose::STANDARD
             .decode(&params.nonce)
-            .map_err(|e| anyhow!("Failed to decode nonce: {}", e))?;
+            .map_err(|e| DecryptError::CorruptData(format!("failed to decode nonce: {}", e)))?;
         
         let salt_bytes = general_purpose::STANDARD
             .decode(&params.salt)
-            .map_err(|e| anyhow!("Failed to decode salt: {}", e))?;
-
-        // 验证长度
-        if nonce_bytes.len() != Self::NONCE_LENGTH {
-            return Err(anyhow!("Invalid nonce length"));
+            .map_err(|e| DecryptError::CorruptData(format!("failed to decode salt: {}", e)))?;
+
+        // 按格式版本验证长度
+        let (salt_length, nonce_length) = Self::format_lengths(params.format_version)
+            .ok_or_else(|| DecryptError::CorruptData(format!("unsupported encryption format version {}", params.format_version)))?;
+        if !salt_length.contains(&salt_bytes.len()) {
+            let expected = if salt_length.start() == salt_length.end() {
+                salt_length.start().to_string()
+            } else {
+                format!("{} to {}", salt_length.start(), salt_length.end())
+            };
+            return Err(DecryptError::CorruptData(format!(
+                "invalid salt length: expected {} bytes for format version {}, got {}",
+                expected, params.format_version, salt_bytes.len()
+            )).into());
+        }
+        if nonce_bytes.len() != nonce_length {
+            return Err(DecryptError::CorruptData(format!(
+                "invalid nonce length: expected {} bytes for format version {}, got {}",
+                nonce_length, params.format_version, nonce_bytes.len()
+            )).into());
+        }
+        if encrypted_bytes.len() < Self::TAG_LENGTH {
+            return Err(DecryptError::CorruptData("encrypted data is shorter than the authentication tag".to_string()).into());
         }
 
         // 派生密钥
@@ -148,15 +412,102 @@ impl BackendEncryption {
         let cipher = Aes256Gcm::new(key);
 
         // 解密数据
+        // 格式已校验，剩下的失败只可能是认证标签不匹配
         let decrypted_bytes = cipher
             .decrypt(nonce, encrypted_bytes.as_ref())
-            .map_err(|e| anyhow!("Decryption failed (possibly wrong password): {}", e))?;
+            .map_err(|_| DecryptError::WrongPassword)?;
 
-        // 转换为字符串
-        let decrypted_text = String::from_utf8(decrypted_bytes)
-            .map_err(|e| anyhow!("Failed to convert decrypted data to string: {}", e))?;
+        Ok(Self::strip_plaintext_header(decrypted_bytes)?)
+    }
 
-        Ok(decrypted_text)
+    /// 校验并去除明文头；没有明文头的旧版密文原样返回
+    fn strip_plaintext_header(mut plaintext: Vec<u8>) -> std::result::Result<Vec<u8>, DecryptError> {
+        if !plaintext.starts_with(&PLAINTEXT_MAGIC) {
+            return Ok(plaintext);
+        }
+
+        match plaintext.get(PLAINTEXT_MAGIC.len()) {
+            Some(&PLAINTEXT_VERSION) => {
+                plaintext.drain(..PLAINTEXT_MAGIC.len() + 1);
+                Ok(plaintext)
+            }
+            Some(version) => Err(DecryptError::CorruptData(format!("unknown plaintext header version {}", version))),
+            None => Err(DecryptError::CorruptData("plaintext header is truncated".to_string())),
+        }
+    }
+
+    /// 用新密码重新加密一批密文，每完成一项回调一次 `progress(processed, total)`
+    ///
+    /// 任一项解密失败即返回错误，不会返回部分结果。每项都要做两次 Argon2 派生，
+    /// 异步调用方应放到阻塞线程中执行。
+    pub fn reencrypt_all<F>(
+        items: Vec<EncryptionResult>,
+        old_password: &str,
+        new_password: &str,
+        mut progress: F,
+    ) -> Result<Vec<EncryptionResult>>
+    where
+        F: FnMut(usize, usize),
+    {
+        if new_password.is_empty() {
+            return Err(anyhow!("New password cannot be empty"));
+        }
+
+        let total = items.len();
+        let mut reencrypted = Vec::with_capacity(total);
+        for (index, item) in items.into_iter().enumerate() {
+            let plaintext = Self::decrypt_bytes(&DecryptionParams {
+                encrypted_data: item.encrypted_data,
+                nonce: item.nonce,
+                salt: item.salt,
+                format_version: item.format_version,
+                password: old_password.to_string(),
+            })?;
+            reencrypted.push(Self::encrypt_bytes(&plaintext, new_password)?);
+            progress(index + 1, total);
+        }
+
+        Ok(reencrypted)
+    }
+
+    /// 将加密结果打包为紧凑的 URL 安全 Base64 字符串（盐值 + nonce + 密文）
+    pub fn pack_sealed(result: &EncryptionResult) -> Result<String> {
+        // 紧凑格式不记录版本，按当前版本的长度拆分
+        if result.format_version != ENCRYPTION_FORMAT_VERSION {
+            return Err(anyhow!("Cannot pack encryption format version {}", result.format_version));
+        }
+        let mut packed = general_purpose::STANDARD
+            .decode(&result.salt)
+            .map_err(|e| anyhow!("Failed to decode salt: {}", e))?;
+        packed.extend(general_purpose::STANDARD
+            .decode(&result.nonce)
+            .map_err(|e| anyhow!("Failed to decode nonce: {}", e))?);
+        packed.extend(general_purpose::STANDARD
+            .decode(&result.encrypted_data)
+            .map_err(|e| anyhow!("Failed to decode encrypted data: {}", e))?);
+
+        Ok(general_purpose::URL_SAFE_NO_PAD.encode(packed))
+    }
+
+    /// 将紧凑字符串解包为加密结果
+    pub fn unpack_sealed(payload: &str) -> Result<EncryptionResult> {
+        let packed = general_purpose::URL_SAFE_NO_PAD
+            .decode(payload.trim())
+            .map_err(|e| anyhow!("Failed to decode sealed payload: {}", e))?;
+
+        if packed.len() <= Self::SALT_LENGTH + Self::NONCE_LENGTH {
+            return Err(anyhow!("Sealed payload is too short"));
+        }
+
+        let (salt, rest) = packed.split_at(Self::SALT_LENGTH);
+        let (nonce, encrypted_data) = rest.split_at(Self::NONCE_LENGTH);
+
+        Ok(EncryptionResult {
+            encrypted_data: general_purpose::STANDARD.encode(encrypted_data),
+            nonce: general_purpose::STANDARD.encode(nonce),
+            salt: general_purpose::STANDARD.encode(salt),
+            format_version: ENCRYPTION_FORMAT_VERSION,
+        })
     }
 
     /// 验证密码强度
@@ -211,10 +562,15 @@ impl BackendEncryption {
 
     /// 计算数据的 SHA-256 哈希值
     pub fn hash_sha256(data: &str) -> String {
+        Self::hash_sha256_bytes(data.as_bytes())
+    }
+
+    /// 计算字节数据的 SHA-256 哈希值
+    pub fn hash_sha256_bytes(data: &[u8]) -> String {
         use sha2::{Sha256, Digest};
         
         let mut hasher = Sha256::new();
-        hasher.update(data.as_bytes());
+        hasher.update(data);
         let result = hasher.finalize();
         
         hex::encode(result)
@@ -231,60 +587,103 @@ impl BackendEncryption {
 pub struct FileEncryption;
 
 impl FileEncryption {
-    /// 加密文件
+    const ENCRYPTED_EXTENSION: &'static str = ".encrypted";
+    const DECRYPTED_EXTENSION: &'static str = ".decrypted";
+
+    /// 加密文件（按字节读取，支持二进制附件），返回加密文件路径
     pub async fn encrypt_file(file_path: &str, password: &str) -> Result<String> {
         use tokio::fs;
-        
+
         // 读取文件内容
-        let content = fs::read_to_string(file_path).await
+        let content = fs::read(file_path).await
             .map_err(|e| anyhow!("Failed to read file: {}", e))?;
 
         // 加密内容
-        let encrypted = BackendEncryption::encrypt(&content, password)?;
-
-        // 生成加密文件路径
-        let encrypted_path = format!("{}.encrypted", file_path);
+        let encrypted = BackendEncryption::encrypt_bytes(&content, password)?;
+        drop(content);
 
         // 保存加密数据
-        let encrypted_json = serde_json::to_string(&encrypted)
+        let encrypted_json = serde_json::to_vec(&encrypted)
             .map_err(|e| anyhow!("Failed to serialize encrypted data: {}", e))?;
 
-        fs::write(&encrypted_path, encrypted_json).await
-            .map_err(|e| anyhow!("Failed to write encrypted file: {}", e))?;
-
-        Ok(encrypted_path)
+        let encrypted_path = format!("{}{}", file_path, Self::ENCRYPTED_EXTENSION);
+        Self::write_new_file(&encrypted_path, &encrypted_json).await
     }
 
-    /// 解密文件
+    /// 解密文件，返回解密文件路径
     pub async fn decrypt_file(encrypted_file_path: &str, password: &str) -> Result<String> {
+        let params = Self::read_encrypted_file(encrypted_file_path, password).await?;
+        let decrypted_content = BackendEncryption::decrypt_bytes(&params)?;
+        Self::write_new_file(&Self::decrypted_path_for(encrypted_file_path), &decrypted_content).await
+    }
+
+    /// 读取加密文件并组装解密参数
+    pub async fn read_encrypted_file(encrypted_file_path: &str, password: &str) -> Result<DecryptionParams> {
         use tokio::fs;
 
         // 读取加密文件
-        let encrypted_json = fs::read_to_string(encrypted_file_path).await
+        let encrypted_json = fs::read(encrypted_file_path).await
             .map_err(|e| anyhow!("Failed to read encrypted file: {}", e))?;
 
         // 解析加密数据
-        let encrypted_result: EncryptionResult = serde_json::from_str(&encrypted_json)
+        let encrypted_result: EncryptionResult = serde_json::from_slice(&encrypted_json)
             .map_err(|e| anyhow!("Failed to parse encrypted data: {}", e))?;
 
-        // 解密内容
-        let decrypt_params = DecryptionParams {
+        Ok(DecryptionParams {
             encrypted_data: encrypted_result.encrypted_data,
             nonce: encrypted_result.nonce,
             salt: encrypted_result.salt,
+            format_version: encrypted_result.format_version,
             password: password.to_string(),
-        };
-
-        let decrypted_content = BackendEncryption::decrypt(&decrypt_params)?;
-
-        // 生成解密文件路径
-        let decrypted_path = encrypted_file_path.replace(".encrypted", ".decrypted");
+        })
+    }
 
-        // 保存解密内容
-        fs::write(&decrypted_path, &decrypted_content).await
-            .map_err(|e| anyhow!("Failed to write decrypted file: {}", e))?;
+    /// 生成解密文件路径：去掉 .encrypted 后缀并追加 .decrypted
+    pub fn decrypted_path_for(encrypted_file_path: &str) -> String {
+        let base = encrypted_file_path
+            .strip_suffix(Self::ENCRYPTED_EXTENSION)
+            .unwrap_or(encrypted_file_path);
+        format!("{}{}", base, Self::DECRYPTED_EXTENSION)
+    }
 
-        Ok(decrypted_path)
+    /// 写入新文件，目标已存在时在扩展名前追加计数（name-1.ext），绝不覆盖已有文件
+    pub async fn write_new_file(path: &str, data: &[u8]) -> Result<String> {
+        use std::path::Path;
+        use tokio::fs::OpenOptions;
+        use tokio::io::AsyncWriteExt;
+
+        let original = Path::new(path);
+        let stem = original.file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+        let extension = original.extension().and_then(|s| s.to_str());
+
+        let mut counter = 0u32;
+        loop {
+            let candidate = if counter == 0 {
+                original.to_path_buf()
+            } else {
+                let name = match extension {
+                    Some(ext) => format!("{}-{}.{}", stem, counter, ext),
+                    None => format!("{}-{}", stem, counter),
+                };
+                original.with_file_name(name)
+            };
+
+            // create_new 保证检查与创建是原子的，不会与并发写入互相覆盖
+            match OpenOptions::new().write(true).create_new(true).open(&candidate).await {
+                Ok(mut file) => {
+                    file.write_all(data).await
+                        .map_err(|e| anyhow!("Failed to write file: {}", e))?;
+                    file.flush().await
+                        .map_err(|e| anyhow!("Failed to write file: {}", e))?;
+                    return Ok(candidate.to_string_lossy().into_owned());
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    counter = counter.checked_add(1)
+                        .ok_or_else(|| anyhow!("No available file name for {}", path))?;
+                }
+                Err(e) => return Err(anyhow!("Failed to create file: {}", e)),
+            }
+        }
     }
 }
 
@@ -305,6 +704,7 @@ mod tests {
             encrypted_data: encrypted.encrypted_data,
             nonce: encrypted.nonce,
             salt: encrypted.salt,
+            format_version: encrypted.format_version,
             password: password.to_string(),
         };
         
@@ -313,6 +713,37 @@ mod tests {
         assert_eq!(data, decrypted);
     }
 
+    #[test]
+    fn test_decrypt_lossy_flags_invalid_utf8() {
+        let password = "test_password_123";
+        let encrypted = BackendEncryption::encrypt_bytes(&[0x66, 0xff, 0x6f], password).unwrap();
+        let decrypt_params = DecryptionParams {
+            encrypted_data: encrypted.encrypted_data,
+            nonce: encrypted.nonce,
+            salt: encrypted.salt,
+            format_version: encrypted.format_version,
+            password: password.to_string(),
+        };
+
+        assert!(BackendEncryption::decrypt(&decrypt_params).is_err());
+
+        let lossy = BackendEncryption::decrypt_lossy(&decrypt_params).unwrap();
+        assert!(lossy.had_invalid_utf8);
+        assert_eq!(lossy.text, "f\u{FFFD}o");
+    }
+
+    #[test]
+    fn test_sealed_payload_roundtrip() {
+        let encrypted = BackendEncryption::encrypt("分享的记忆", "share_password").unwrap();
+        let payload = BackendEncryption::pack_sealed(&encrypted).unwrap();
+        let unpacked = BackendEncryption::unpack_sealed(&payload).unwrap();
+
+        assert_eq!(unpacked.salt, encrypted.salt);
+        assert_eq!(unpacked.nonce, encrypted.nonce);
+        assert_eq!(unpacked.encrypted_data, encrypted.encrypted_data);
+        assert!(BackendEncryption::unpack_sealed("c2hvcnQ").is_err());
+    }
+
     #[test]
     fn test_password_strength() {
         assert_eq!(BackendEncryption::validate_password_strength(""), 0);
@@ -327,4 +758,213 @@ mod tests {
         assert!(BackendEncryption::verify_integrity(data, &hash));
         assert!(!BackendEncryption::verify_integrity("modified data", &hash));
     }
+
+    #[tokio::test]
+    async fn test_file_roundtrip_large_binary_without_clobbering() {
+        let dir = std::env::temp_dir().join(format!("peach-blossom-crypto-{}", uuid::Uuid::new_v4()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        let source = dir.join("photo.bin");
+        let content: Vec<u8> = (0..3 * 1024 * 1024u32).map(|i| (i.wrapping_mul(31) % 251) as u8).collect();
+        tokio::fs::write(&source, &content).await.unwrap();
+        let source = source.to_str().unwrap();
+
+        let encrypted = FileEncryption::encrypt_file(source, "file_password").await.unwrap();
+        assert_eq!(encrypted, format!("{}.encrypted", source));
+
+        // 再次加密不会覆盖已有的加密文件
+        let second = FileEncryption::encrypt_file(source, "file_password").await.unwrap();
+        assert_ne!(second, encrypted);
+        assert!(second.ends_with("photo.bin-1.encrypted"));
+
+        let decrypted = FileEncryption::decrypt_file(&encrypted, "file_password").await.unwrap();
+        assert!(decrypted.ends_with("photo.bin.decrypted"));
+        assert_eq!(tokio::fs::read(&decrypted).await.unwrap(), content);
+
+        let decrypted_again = FileEncryption::decrypt_file(&encrypted, "file_password").await.unwrap();
+        assert!(decrypted_again.ends_with("photo.bin-1.decrypted"));
+        assert_eq!(tokio::fs::read(&decrypted).await.unwrap(), content);
+
+        assert!(FileEncryption::decrypt_file(&encrypted, "wrong_password").await.is_err());
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[test]
+    fn test_reencrypt_all_reports_progress() {
+        let items: Vec<EncryptionResult> = ["第一条", "第二条", "第三条"]
+            .iter()
+            .map(|text| BackendEncryption::encrypt(text, "old_password").unwrap())
+            .collect();
+
+        let mut calls = Vec::new();
+        let reencrypted = BackendEncryption::reencrypt_all(items.clone(), "old_password", "new_password", |processed, total| {
+            calls.push((processed, total));
+        }).unwrap();
+        assert_eq!(calls, vec![(1, 3), (2, 3), (3, 3)]);
+
+        let first = &reencrypted[0];
+        let decrypted = BackendEncryption::decrypt(&DecryptionParams {
+            encrypted_data: first.encrypted_data.clone(),
+            nonce: first.nonce.clone(),
+            salt: first.salt.clone(),
+            format_version: first.format_version,
+            password: "new_password".to_string(),
+        }).unwrap();
+        assert_eq!(decrypted, "第一条");
+
+        // 旧密码错误时在第一项就失败，不报告任何进度
+        let mut calls = Vec::new();
+        assert!(BackendEncryption::reencrypt_all(items, "wrong_password", "new_password", |processed, total| {
+            calls.push((processed, total));
+        }).is_err());
+        assert!(calls.is_empty());
+    }
+
+    #[test]
+    fn test_format_version_validates_salt_and_nonce_lengths() {
+        let encrypted = BackendEncryption::encrypt("桃花", "password").unwrap();
+        assert_eq!(encrypted.format_version, ENCRYPTION_FORMAT_VERSION);
+        let params = |result: &EncryptionResult| DecryptionParams {
+            encrypted_data: result.encrypted_data.clone(),
+            nonce: result.nonce.clone(),
+            salt: result.salt.clone(),
+            format_version: result.format_version,
+            password: "password".to_string(),
+        };
+        let error = |result: &EncryptionResult| BackendEncryption::decrypt(&params(result)).unwrap_err();
+
+        // 旧格式没有版本字段，按版本 1 解析
+        let legacy = serde_json::json!({
+            "encrypted_data": encrypted.encrypted_data,
+            "nonce": encrypted.nonce,
+            "salt": encrypted.salt,
+        });
+        let legacy: EncryptionResult = serde_json::from_value(legacy).unwrap();
+        assert_eq!(legacy.format_version, 1);
+        assert_eq!(BackendEncryption::decrypt(&params(&legacy)).unwrap(), "桃花");
+
+        let short_salt = EncryptionResult { salt: general_purpose::STANDARD.encode([0u8; 16]), ..encrypted.clone() };
+        let e = error(&short_salt);
+        assert!(matches!(e.downcast_ref::<DecryptError>(), Some(DecryptError::CorruptData(_))));
+        assert!(e.to_string().contains("invalid salt length: expected 32 bytes for format version 1, got 16"));
+
+        let long_nonce = EncryptionResult { nonce: general_purpose::STANDARD.encode([0u8; 24]), ..encrypted.clone() };
+        assert!(error(&long_nonce).to_string().contains("invalid nonce length: expected 12 bytes"));
+
+        let future = EncryptionResult { format_version: 3, ..encrypted.clone() };
+        assert!(error(&future).to_string().contains("unsupported encryption format version 3"));
+        assert!(BackendEncryption::pack_sealed(&future).is_err());
+    }
+
+    #[test]
+    fn test_encryption_config_salt_length() {
+        let config = EncryptionConfig { salt_length: 48, ..EncryptionConfig::default() };
+        let encrypted = BackendEncryption::encrypt_with_config("桃花", "password", &config).unwrap();
+        assert_eq!(encrypted.format_version, CUSTOM_SALT_FORMAT_VERSION);
+        assert_eq!(general_purpose::STANDARD.decode(&encrypted.salt).unwrap().len(), 48);
+        let params = DecryptionParams {
+            encrypted_data: encrypted.encrypted_data,
+            nonce: encrypted.nonce,
+            salt: encrypted.salt,
+            format_version: encrypted.format_version,
+            password: "password".to_string(),
+        };
+        assert_eq!(BackendEncryption::decrypt(&params).unwrap(), "桃花");
+
+        // 默认配置仍写出版本 1
+        let default = BackendEncryption::encrypt_with_config("桃花", "password", &EncryptionConfig::default()).unwrap();
+        assert_eq!(default.format_version, ENCRYPTION_FORMAT_VERSION);
+
+        let short = EncryptionConfig { salt_length: 8, ..EncryptionConfig::default() };
+        let e = BackendEncryption::encrypt_with_config("桃花", "password", &short).unwrap_err();
+        assert!(e.to_string().contains("Salt length must be at least 16 bytes, got 8"));
+        let weak_key = EncryptionConfig { key_length: 16, ..EncryptionConfig::default() };
+        assert!(BackendEncryption::encrypt_with_config("桃花", "password", &weak_key).is_err());
+    }
+
+    #[test]
+    fn test_benchmark_kdf_measures_derivation() {
+        // 默认 Argon2 参数需要填充约 19 MiB 内存，一次真实派生不可能在 1 毫秒内完成
+        let elapsed_ms = BackendEncryption::benchmark_kdf().unwrap();
+        assert!(elapsed_ms > 0);
+    }
+
+    #[test]
+    fn test_decrypt_distinguishes_wrong_password_from_corrupt_data() {
+        let encrypted = BackendEncryption::encrypt("桃花", "right_password").unwrap();
+        let params = |encrypted_data: &str, nonce: &str, password: &str| DecryptionParams {
+            encrypted_data: encrypted_data.to_string(),
+            nonce: nonce.to_string(),
+            salt: encrypted.salt.clone(),
+            format_version: encrypted.format_version,
+            password: password.to_string(),
+        };
+        let reason = |params: DecryptionParams| BackendEncryption::decrypt(&params)
+            .unwrap_err()
+            .downcast::<DecryptError>()
+            .unwrap();
+
+        assert_eq!(reason(params(&encrypted.encrypted_data, &encrypted.nonce, "wrong_password")), DecryptError::WrongPassword);
+        assert!(matches!(reason(params("not base64!", &encrypted.nonce, "right_password")), DecryptError::CorruptData(_)));
+        assert!(matches!(reason(params(&encrypted.encrypted_data, "CQkJ", "right_password")), DecryptError::CorruptData(_)));
+        assert!(matches!(reason(params("AAAA", &encrypted.nonce, "right_password")), DecryptError::CorruptData(_)));
+    }
+
+    #[test]
+    fn test_plaintext_header_detects_corruption_after_authentication() {
+        let decrypt = |result: &EncryptionResult, password: &str| BackendEncryption::decrypt_bytes(&DecryptionParams {
+            encrypted_data: result.encrypted_data.clone(),
+            nonce: result.nonce.clone(),
+            salt: result.salt.clone(),
+            format_version: result.format_version,
+            password: password.to_string(),
+        });
+
+        // 认证通过但明文头版本不符：数据损坏，而不是密码错误
+        let mut bad_header = PLAINTEXT_MAGIC.to_vec();
+        bad_header.extend_from_slice(&[9, b'x']);
+        let damaged = BackendEncryption::encrypt_bytes_with(&bad_header, "right_password", &KAT_SALT, &KAT_NONCE).unwrap();
+        let err = decrypt(&damaged, "right_password").unwrap_err();
+        assert!(matches!(err.downcast_ref::<DecryptError>(), Some(DecryptError::CorruptData(_))));
+        let err = decrypt(&damaged, "wrong_password").unwrap_err();
+        assert_eq!(err.downcast_ref::<DecryptError>(), Some(&DecryptError::WrongPassword));
+
+        // 新密文带明文头，解密后去除；无明文头的旧版密文仍能解密
+        let current = BackendEncryption::encrypt_bytes(b"peach", "right_password").unwrap();
+        assert_eq!(decrypt(&current, "right_password").unwrap(), b"peach");
+        let legacy = BackendEncryption::encrypt_bytes_with(b"peach", "right_password", &KAT_SALT, &KAT_NONCE).unwrap();
+        assert_eq!(decrypt(&legacy, "right_password").unwrap(), b"peach");
+    }
+
+    /// 已知答案测试使用的固定输入
+    const KAT_PASSWORD: &str = "correct horse battery staple";
+    const KAT_PLAINTEXT: &str = "桃花笺 known answer";
+    const KAT_SALT: [u8; 32] = [7u8; 32];
+    const KAT_NONCE: [u8; 12] = [9u8; 12];
+
+    #[test]
+    fn test_derive_key_known_answer() {
+        // 改动 KDF 参数会让已有保险库无法解密，此值不应随重构变化
+        let key = BackendEncryption::derive_key(KAT_PASSWORD, &KAT_SALT).unwrap();
+        let hex: String = key.iter().map(|b| format!("{:02x}", b)).collect();
+        assert_eq!(hex, "c9a2cf1f81a33994e4c9adbce9891c6d9c8cf3708a3f5d754780d5bf0bb6dfe7");
+    }
+
+    #[test]
+    fn test_encrypt_known_answer() {
+        let result = BackendEncryption::encrypt_bytes_with(KAT_PLAINTEXT.as_bytes(), KAT_PASSWORD, &KAT_SALT, &KAT_NONCE).unwrap();
+        assert_eq!(result.encrypted_data, "JNdBTC1Zz19bYswf3Yf9oT+yKj3BZrsaXhUuZ5jRRxSidunUR1g=");
+        assert_eq!(result.nonce, "CQkJCQkJCQkJCQkJ");
+        assert_eq!(result.salt, "BwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwcHBwc=");
+
+        // 固定密文必须能被生产解密路径解开
+        let params = DecryptionParams {
+            encrypted_data: result.encrypted_data,
+            nonce: result.nonce,
+            salt: result.salt,
+            format_version: result.format_version,
+            password: KAT_PASSWORD.to_string(),
+        };
+        assert_eq!(BackendEncryption::decrypt(&params).unwrap(), KAT_PLAINTEXT);
+    }
 }
@@ -0,0 +1,166 @@
+/**
+ * HTML 导出模块
+ * 将条目渲染为单个自包含的静态 HTML 页面（内联样式，正文按 Markdown 渲染）
+ */
+
+use crate::models::MemoryEntry;
+use chrono::{DateTime, FixedOffset, NaiveDate, Utc};
+use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag};
+
+/// 页面内联样式
+const PAGE_STYLE: &str = "
+body { margin: 0; padding: 2rem 1rem; background: #fdf6f4; color: #4a3b38; font-family: -apple-system, 'PingFang SC', 'Microsoft YaHei', sans-serif; line-height: 1.7; }
+main { max-width: 760px; margin: 0 auto; }
+header h1 { margin: 0; color: #c2587a; font-weight: 600; }
+header p { margin: 0.25rem 0 2rem; color: #9a8581; font-size: 0.9rem; }
+article { background: #fff; border-radius: 12px; padding: 1.25rem 1.5rem; margin-bottom: 1.25rem; box-shadow: 0 2px 8px rgba(194, 88, 122, 0.08); }
+article h2 { margin: 0 0 0.25rem; font-size: 1.25rem; }
+article time { color: #9a8581; font-size: 0.85rem; }
+ul.tags { list-style: none; padding: 0; margin: 0.5rem 0 0; display: flex; flex-wrap: wrap; gap: 0.4rem; }
+ul.tags li { background: #fbe3ea; color: #b04a6c; border-radius: 999px; padding: 0.1rem 0.6rem; font-size: 0.8rem; }
+.content { margin-top: 0.75rem; overflow-wrap: anywhere; }
+.content img { max-width: 100%; }
+.content pre { background: #f7eeec; padding: 0.75rem; border-radius: 8px; overflow-x: auto; }
+.locked { color: #9a8581; font-style: italic; }
+";
+
+/// 转义 HTML 特殊字符
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// 条目创建日期（用户时区）是否在闭区间 `[from, to]` 内，未指定的一端不限制
+pub fn in_date_range(entry: &MemoryEntry, offset: FixedOffset, from: Option<NaiveDate>, to: Option<NaiveDate>) -> bool {
+    let date = entry.created_at.with_timezone(&offset).date_naive();
+    from.is_none_or(|from| date >= from) && to.is_none_or(|to| date <= to)
+}
+
+/// 把 Markdown 渲染为 HTML
+///
+/// 正文中的原始 HTML 按文本转义输出，`javascript:` 等可执行脚本的链接改为 `#`。
+pub fn render_markdown(markdown: &str) -> String {
+    let options = Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TABLES | Options::ENABLE_TASKLISTS;
+    let events = Parser::new_ext(markdown, options).map(|event| match event {
+        Event::Html(raw) | Event::InlineHtml(raw) => Event::Text(raw),
+        Event::Start(Tag::Link { link_type, dest_url, title, id }) => Event::Start(Tag::Link {
+            link_type,
+            dest_url: safe_url(dest_url),
+            title,
+            id,
+        }),
+        Event::Start(Tag::Image { link_type, dest_url, title, id }) => Event::Start(Tag::Image {
+            link_type,
+            dest_url: safe_url(dest_url),
+            title,
+            id,
+        }),
+        event => event,
+    });
+
+    let mut rendered = String::new();
+    html::push_html(&mut rendered, events);
+    rendered
+}
+
+/// 屏蔽可执行脚本的链接协议
+fn safe_url(url: CowStr<'_>) -> CowStr<'_> {
+    let scheme: String = url
+        .chars()
+        .filter(|c| !c.is_ascii_whitespace() && !c.is_ascii_control())
+        .take(11)
+        .collect::<String>()
+        .to_ascii_lowercase();
+    if ["javascript:", "vbscript:", "data:"].iter().any(|s| scheme.starts_with(s)) {
+        CowStr::Borrowed("#")
+    } else {
+        url
+    }
+}
+
+/// 渲染单个条目卡片
+fn render_entry(entry: &MemoryEntry, offset: FixedOffset) -> String {
+    let created = entry.created_at.with_timezone(&offset);
+    let tags: String = entry
+        .emotion_tags
+        .iter()
+        .map(|tag| format!("<li>{}</li>", escape_html(tag.as_str())))
+        .collect();
+    let tags = if tags.is_empty() { String::new() } else { format!("\n<ul class=\"tags\">{}</ul>", tags) };
+    let content = if entry.encrypted_content.is_some() {
+        "<p class=\"locked\">（此条目已加密，未导出正文）</p>".to_string()
+    } else {
+        render_markdown(&entry.content)
+    };
+
+    format!(
+        "<article class=\"entry\">\n<h2>{}</h2>\n<time datetime=\"{}\">{}</time>{}\n<div class=\"content\">\n{}</div>\n</article>\n",
+        escape_html(&entry.title),
+        created.to_rfc3339(),
+        created.format("%Y-%m-%d %H:%M"),
+        tags,
+        content,
+    )
+}
+
+/// 渲染完整页面，条目按创建时间从旧到新排列
+pub fn render_html(entries: &[MemoryEntry], offset: FixedOffset, generated_at: DateTime<Utc>) -> String {
+    let mut sorted: Vec<&MemoryEntry> = entries.iter().collect();
+    sorted.sort_by_key(|e| e.created_at);
+    let articles: String = sorted.iter().map(|e| render_entry(e, offset)).collect();
+
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"zh-CN\">\n<head>\n<meta charset=\"utf-8\">\n<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n<title>桃花笺</title>\n<style>{}</style>\n</head>\n<body>\n<main>\n<header>\n<h1>桃花笺</h1>\n<p>共 {} 篇 · 导出于 {}</p>\n</header>\n{}</main>\n</body>\n</html>\n",
+        PAGE_STYLE,
+        entries.len(),
+        generated_at.with_timezone(&offset).format("%Y-%m-%d %H:%M"),
+        articles,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{EmotionTag, MemoryType};
+
+    #[test]
+    fn test_render_html_escapes_content_and_renders_each_entry() {
+        let offset = FixedOffset::east_opt(8 * 3600).unwrap();
+        let mut first = MemoryEntry::new("<script>alert(1)</script>".to_string(), "**桃花**开了 <img src=x onerror=alert(1)>".to_string(), MemoryType::Text);
+        first.emotion_tags = vec![EmotionTag::Joy, EmotionTag::Custom("\"引号\"".to_string())];
+        let second = MemoryEntry::new("第二篇".to_string(), "[点我](javascript:alert(1))".to_string(), MemoryType::Text);
+
+        let page = render_html(&[first, second], offset, Utc::now());
+
+        assert_eq!(page.matches("<article class=\"entry\">").count(), 2);
+        assert!(page.contains("<h2>&lt;script&gt;alert(1)&lt;/script&gt;</h2>"));
+        assert!(page.contains("<strong>桃花</strong>开了 &lt;img src=x onerror=alert(1)&gt;"));
+        assert!(page.contains("<li>joy</li><li>&quot;引号&quot;</li>"));
+        assert!(page.contains("<a href=\"#\">点我</a>"));
+        assert!(!page.contains("<script>") && !page.contains("<img") && !page.contains("javascript:"));
+    }
+
+    #[test]
+    fn test_in_date_range_uses_local_dates() {
+        let offset = FixedOffset::east_opt(8 * 3600).unwrap();
+        let mut entry = MemoryEntry::new("深夜".to_string(), "内容".to_string(), MemoryType::Text);
+        // 本地时间 2024-03-02 01:00
+        entry.created_at = "2024-03-01T17:00:00Z".parse().unwrap();
+        let date = |s: &str| Some(NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap());
+
+        assert!(in_date_range(&entry, offset, None, None));
+        assert!(in_date_range(&entry, offset, date("2024-03-02"), date("2024-03-02")));
+        assert!(!in_date_range(&entry, offset, None, date("2024-03-01")));
+        assert!(!in_date_range(&entry, offset, date("2024-03-03"), None));
+    }
+}
@@ -0,0 +1,301 @@
+/**
+ * 外部日记导入模块
+ * 将 Day One、Journey 及通用 JSON 日记映射为记忆条目
+ */
+
+use crate::models::{MemoryEntry, MemoryType, RecordError, UserSettings};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// 外部日记格式
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExternalFormat {
+    DayOne,
+    Journey,
+    Generic,
+}
+
+/// 通用 JSON 的字段映射
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GenericFieldMap {
+    pub text_field: String,
+    pub date_field: String,
+    pub tags_field: String,
+    pub title_field: Option<String>,
+}
+
+impl Default for GenericFieldMap {
+    fn default() -> Self {
+        Self {
+            text_field: "text".to_string(),
+            date_field: "date".to_string(),
+            tags_field: "tags".to_string(),
+            title_field: Some("title".to_string()),
+        }
+    }
+}
+
+impl ExternalFormat {
+    /// 获取该格式对应的字段映射
+    fn field_map(self, generic: Option<GenericFieldMap>) -> GenericFieldMap {
+        match self {
+            ExternalFormat::DayOne => GenericFieldMap {
+                text_field: "text".to_string(),
+                date_field: "creationDate".to_string(),
+                tags_field: "tags".to_string(),
+                title_field: None,
+            },
+            ExternalFormat::Journey => GenericFieldMap {
+                text_field: "text".to_string(),
+                date_field: "date_journal".to_string(),
+                tags_field: "tags".to_string(),
+                title_field: None,
+            },
+            ExternalFormat::Generic => generic.unwrap_or_default(),
+        }
+    }
+}
+
+/// 标题的最大字符数（从正文首行截取时）
+const MAX_DERIVED_TITLE_CHARS: usize = 50;
+
+/// 解析外部 JSON 为记忆条目，返回成功映射的条目及被跳过记录的原因
+pub fn parse_external(
+    value: &Value,
+    format: ExternalFormat,
+    generic: Option<GenericFieldMap>,
+    settings: &UserSettings,
+) -> (Vec<MemoryEntry>, Vec<String>) {
+    let map = format.field_map(generic);
+
+    let records: Vec<&Value> = match value {
+        Value::Array(items) => items.iter().collect(),
+        Value::Object(obj) => match obj.get("entries") {
+            Some(Value::Array(items)) => items.iter().collect(),
+            _ => vec![value],
+        },
+        _ => Vec::new(),
+    };
+
+    let mut entries = Vec::new();
+    let mut skipped = Vec::new();
+    for (index, record) in records.into_iter().enumerate() {
+        match map_record(record, &map, settings) {
+            Ok(entry) => entries.push(entry),
+            Err(reason) => skipped.push(format!("#{}: {}", index, reason)),
+        }
+    }
+
+    (entries, skipped)
+}
+
+/// 校验本应用导出的条目数组，返回通过校验的条目及逐条的校验错误
+///
+/// 先检查必填字段与取值（非空 ID、合法的记忆类型、可解析的时间戳），全部通过后再反序列化，
+/// 这样一条坏记录不会让整个导入失败。
+pub fn validate_native_records(value: &Value) -> Result<(Vec<MemoryEntry>, Vec<RecordError>), String> {
+    let records = value.as_array().ok_or("expected an array of entries")?;
+
+    let mut entries = Vec::new();
+    let mut invalid = Vec::new();
+    for (index, record) in records.iter().enumerate() {
+        let id = record.get("id").and_then(Value::as_str).map(str::to_string);
+        let errors = validate_native_record(record);
+        let result = if errors.is_empty() {
+            serde_json::from_value::<MemoryEntry>(record.clone()).map_err(|e| vec![e.to_string()])
+        } else {
+            Err(errors)
+        };
+
+        match result {
+            Ok(entry) => entries.push(entry),
+            Err(errors) => invalid.push(RecordError { index, id, errors }),
+        }
+    }
+
+    Ok((entries, invalid))
+}
+
+/// 检查单条记录的必填字段与取值，返回全部问题
+fn validate_native_record(record: &Value) -> Vec<String> {
+    let Some(obj) = record.as_object() else {
+        return vec!["record is not an object".to_string()];
+    };
+
+    let mut errors = Vec::new();
+    match obj.get("id").and_then(Value::as_str) {
+        Some(id) if !id.trim().is_empty() => {}
+        Some(_) => errors.push("`id` must not be empty".to_string()),
+        None => errors.push("missing `id`".to_string()),
+    }
+
+    for field in ["title", "content"] {
+        if !obj.get(field).is_some_and(Value::is_string) {
+            errors.push(format!("missing `{}`", field));
+        }
+    }
+
+    match obj.get("memory_type") {
+        Some(memory_type) => {
+            if serde_json::from_value::<MemoryType>(memory_type.clone()).is_err() {
+                errors.push(format!("invalid `memory_type`: {}", memory_type));
+            }
+        }
+        None => errors.push("missing `memory_type`".to_string()),
+    }
+
+    for field in ["created_at", "updated_at"] {
+        match obj.get(field).and_then(Value::as_str) {
+            Some(ts) if DateTime::parse_from_rfc3339(ts).is_ok() => {}
+            Some(ts) => errors.push(format!("invalid `{}`: {}", field, ts)),
+            None => errors.push(format!("missing `{}`", field)),
+        }
+    }
+
+    errors
+}
+
+/// 将单条外部记录映射为记忆条目
+fn map_record(record: &Value, map: &GenericFieldMap, settings: &UserSettings) -> Result<MemoryEntry, String> {
+    let text = record.get(&map.text_field)
+        .and_then(Value::as_str)
+        .filter(|t| !t.trim().is_empty())
+        .ok_or_else(|| format!("missing text field `{}`", map.text_field))?;
+
+    let created_at = record.get(&map.date_field)
+        .and_then(parse_date)
+        .ok_or_else(|| format!("missing or invalid date field `{}`", map.date_field))?;
+
+    let title = map.title_field.as_ref()
+        .and_then(|field| record.get(field))
+        .and_then(Value::as_str)
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+        .unwrap_or_else(|| derive_title(text));
+
+    let mut entry = MemoryEntry::new(title, text.to_string(), MemoryType::Text);
+    entry.created_at = created_at;
+    entry.updated_at = created_at;
+    entry.refresh_word_stats(settings);
+
+    if let Some(Value::Array(tags)) = record.get(&map.tags_field) {
+        entry.set_tags(tags.iter().filter_map(Value::as_str).map(str::to_string).collect());
+    }
+
+    Ok(entry)
+}
+
+/// 从正文首行生成标题
+fn derive_title(text: &str) -> String {
+    let first_line = text.lines()
+        .map(|line| line.trim().trim_start_matches('#').trim())
+        .find(|line| !line.is_empty())
+        .unwrap_or_default();
+    first_line.chars().take(MAX_DERIVED_TITLE_CHARS).collect()
+}
+
+/// 解析日期：支持 RFC 3339、常见日期时间字符串以及毫秒/秒级时间戳
+fn parse_date(value: &Value) -> Option<DateTime<Utc>> {
+    match value {
+        Value::String(s) => {
+            let s = s.trim();
+            DateTime::parse_from_rfc3339(s).map(|d| d.with_timezone(&Utc)).ok()
+                .or_else(|| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").ok().map(|d| d.and_utc()))
+                .or_else(|| NaiveDate::parse_from_str(s, "%Y-%m-%d").ok()
+                    .and_then(|d| d.and_hms_opt(0, 0, 0))
+                    .map(|d| d.and_utc()))
+        }
+        Value::Number(n) => {
+            let timestamp = n.as_i64()?;
+            // 大于 1e11 视为毫秒级时间戳
+            if timestamp.abs() > 100_000_000_000 {
+                Utc.timestamp_millis_opt(timestamp).single()
+            } else {
+                Utc.timestamp_opt(timestamp, 0).single()
+            }
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_day_one_export() {
+        let value = json!({
+            "entries": [
+                { "text": "# Kyoto\nTemple visit", "creationDate": "2023-04-01T08:30:00Z", "tags": ["Travel", "travel "] },
+                { "creationDate": "2023-04-02T08:30:00Z" }
+            ]
+        });
+
+        let (entries, skipped) = parse_external(&value, ExternalFormat::DayOne, None, &UserSettings::default());
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].title, "Kyoto");
+        assert_eq!(entries[0].created_at.to_rfc3339(), "2023-04-01T08:30:00+00:00");
+        assert_eq!(entries[0].metadata.as_ref().unwrap().tags, Some(vec!["travel".to_string()]));
+        assert_eq!(skipped.len(), 1);
+        assert!(skipped[0].contains("text"));
+    }
+
+    #[test]
+    fn test_parse_journey_entry_with_millis() {
+        let value = json!({ "text": "海边散步", "date_journal": 1_680_000_000_000_i64, "tags": [] });
+
+        let (entries, skipped) = parse_external(&value, ExternalFormat::Journey, None, &UserSettings::default());
+        assert!(skipped.is_empty());
+        assert_eq!(entries[0].created_at.timestamp(), 1_680_000_000);
+    }
+
+    #[test]
+    fn test_parse_generic_with_custom_fields() {
+        let value = json!([
+            { "body": "Snow day", "when": "2022-12-24", "heading": "Christmas Eve", "labels": ["Winter"] },
+            { "body": "No date" }
+        ]);
+        let map = GenericFieldMap {
+            text_field: "body".to_string(),
+            date_field: "when".to_string(),
+            tags_field: "labels".to_string(),
+            title_field: Some("heading".to_string()),
+        };
+
+        let (entries, skipped) = parse_external(&value, ExternalFormat::Generic, Some(map), &UserSettings::default());
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].title, "Christmas Eve");
+        assert_eq!(skipped.len(), 1);
+        assert!(skipped[0].contains("date"));
+    }
+
+    #[test]
+    fn test_validate_native_records_reports_each_bad_record() {
+        let valid = MemoryEntry::new("桃花".to_string(), "春日".to_string(), MemoryType::Text);
+        let mut bad_type = serde_json::to_value(&valid).unwrap();
+        bad_type["id"] = json!("");
+        bad_type["memory_type"] = json!("hologram");
+        let mut bad_date = serde_json::to_value(&valid).unwrap();
+        bad_date["id"] = json!("bad-date");
+        bad_date["created_at"] = json!("yesterday");
+        let value = json!([serde_json::to_value(&valid).unwrap(), bad_type, bad_date, 42]);
+
+        let (entries, invalid) = validate_native_records(&value).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].id, valid.id);
+
+        assert_eq!(invalid.len(), 3);
+        assert_eq!(invalid[0].index, 1);
+        assert_eq!(invalid[0].errors.len(), 2);
+        assert!(invalid[0].errors[1].contains("memory_type"));
+        assert_eq!(invalid[1].id.as_deref(), Some("bad-date"));
+        assert_eq!(invalid[1].errors, vec!["invalid `created_at`: yesterday".to_string()]);
+        assert_eq!(invalid[2].errors, vec!["record is not an object".to_string()]);
+
+        assert!(validate_native_records(&json!({ "id": "x" })).is_err());
+    }
+}
@@ -1,7 +1,18 @@
 mod models;
+mod analysis;
 mod crypto;
 mod storage;
+mod search;
+mod stats;
 mod commands;
+mod importers;
+mod throttle;
+mod session;
+mod vaults;
+mod undo;
+mod reminder;
+mod html_export;
+mod templates;
 
 use commands::*;
 
@@ -12,26 +23,135 @@ pub fn run() {
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_notification::init())
         .plugin(tauri_plugin_dialog::init())
+        .manage(StorageState::default())
+        .manage(DecryptThrottle::default())
+        .manage(WordCloudState::default())
+        .manage(TagIndexState::default())
+        .manage(SessionState::default())
+        .manage(UiLockState::default())
+        .manage(UndoState::default())
+        .manage(ReminderState::default())
+        .setup(|app| {
+            let handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move { preload_storage_manager(&handle).await });
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             // 记忆条目相关命令
             create_memory_entry,
+            create_memory_entry_with_context,
             update_memory_entry,
+            patch_memory_entry,
             delete_memory_entry,
+            bulk_delete_entries,
+            bulk_add_tag,
             get_memory_entry,
+            get_memory_entries_by_ids,
+            get_entry_content,
+            get_entry_by_prefix,
             get_all_memory_entries,
+            list_archived,
+            archive_entry,
+            unarchive_entry,
+            reorder_entries,
+            toggle_task,
+            recompute_word_stats,
+            unlock_vault,
+            lock_vault,
+            check_auto_lock,
+            lock_now,
+            lock_ui,
+            unlock_ui,
+            record_activity,
+            change_password,
+            rotate_encryption,
+            get_store_status,
+            get_vault_security_info,
+            get_storage_report,
+            get_storage_usage,
+            verify_entries_integrity,
+            get_recent_entries,
+            get_entries_by_emotion,
+            get_entries_by_location,
+            link_entries,
+            unlink_entries,
+            undo_last,
+            redo_last,
+            get_related_entries,
+            get_backlinks,
+            add_attachment,
+            verify_attachments,
+            find_orphaned_attachments,
+            prune_orphaned_attachments,
             search_memory_entries,
+            search_memories_page,
+            count_entries,
+            query_memories,
+            search_with_highlights,
+            find_matches,
+            get_tag_cloud,
+            get_all_tags,
+            get_all_moods,
+            list_tag_tree,
+            analyze_entry,
+            get_global_word_cloud,
+            get_week_summary,
+            get_writing_streak,
+            get_emotion_cooccurrence,
+            top_emotions,
+            get_memory_stats,
+            export_stats_json,
+            get_on_this_day,
+            get_entries_grouped_by_month,
 
             // 加密相关命令
             encrypt_data,
+            lock_entry,
+            unlock_entry,
             decrypt_data,
+            decrypt_data_lossy,
+            encrypt_file_command,
+            decrypt_file_command,
             validate_password_strength,
+            benchmark_kdf,
 
+            // 设置相关命令
+            get_settings,
+            get_settings_schema,
+            save_settings,
+            set_data_dir,
+            list_vaults,
+            create_vault,
+            switch_vault,
+            delete_vault,
+            get_dream_config,
+            save_dream_config,
+            set_password_hint,
+            get_password_hint,
 
             // 拾梦回响相关命令
             get_random_memory,
+            schedule_reminder,
+            get_entry_templates,
+            suggest_templates_for_emotions,
 
             // 文件操作命令
             backup_data,
+            restore_data,
+            validate_backup,
+            export_entry_sealed,
+            import_entry_sealed,
+            export_entry_encrypted,
+            import_entry_encrypted,
+            export_with_attachments,
+            export_html,
+            export_json_stream,
+            import_with_attachments,
+            import_external_json,
+            merge_vault,
+            repair_vault,
+            repair_store,
+            wipe_vault,
 
             // 初始化命令
             initialize_app
@@ -3,9 +3,10 @@
  */
 
 use serde::{Deserialize, Serialize};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, FixedOffset, NaiveDate, Utc};
 use uuid::Uuid;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use crate::crypto::{Argon2Params, BackendEncryption, DecryptionParams, EncryptionResult};
 
 /// 记忆类型枚举
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
@@ -17,9 +18,18 @@ pub enum MemoryType {
     Mixed,
 }
 
+/// 情感强度范围（1-5）
+pub const MIN_EMOTION_INTENSITY: u8 = 1;
+pub const MAX_EMOTION_INTENSITY: u8 = 5;
+
+/// 未设置强度的情感标签视为中等强度（兼容引入强度之前的条目）
+pub const NEUTRAL_EMOTION_INTENSITY: u8 = 3;
+
 /// 情感标签枚举
+///
+/// 序列化为小写字符串；无法识别的标签作为用户自定义情感保留（已规范化）。
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
-#[serde(rename_all = "lowercase")]
+#[serde(try_from = "String", into = "String")]
 pub enum EmotionTag {
     Joy,           // 喜悦
     Sadness,       // 悲伤
@@ -28,6 +38,51 @@ pub enum EmotionTag {
     Regret,        // 悔过
     Attachment,    // 难舍
     Persistence,   // 执着
+    Custom(String), // 自定义情感
+}
+
+impl EmotionTag {
+    /// 标签的字符串形式
+    pub fn as_str(&self) -> &str {
+        match self {
+            EmotionTag::Joy => "joy",
+            EmotionTag::Sadness => "sadness",
+            EmotionTag::Nostalgia => "nostalgia",
+            EmotionTag::Hope => "hope",
+            EmotionTag::Regret => "regret",
+            EmotionTag::Attachment => "attachment",
+            EmotionTag::Persistence => "persistence",
+            EmotionTag::Custom(label) => label,
+        }
+    }
+}
+
+impl TryFrom<String> for EmotionTag {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let label = normalize_tag(&value);
+        Ok(match label.as_str() {
+            "" => return Err("Emotion label cannot be empty".to_string()),
+            "joy" => EmotionTag::Joy,
+            "sadness" => EmotionTag::Sadness,
+            "nostalgia" => EmotionTag::Nostalgia,
+            "hope" => EmotionTag::Hope,
+            "regret" => EmotionTag::Regret,
+            "attachment" => EmotionTag::Attachment,
+            "persistence" => EmotionTag::Persistence,
+            _ => EmotionTag::Custom(label),
+        })
+    }
+}
+
+impl From<EmotionTag> for String {
+    fn from(tag: EmotionTag) -> Self {
+        match tag {
+            EmotionTag::Custom(label) => label,
+            builtin => builtin.as_str().to_string(),
+        }
+    }
 }
 
 /// 附件结构
@@ -35,15 +90,19 @@ pub enum EmotionTag {
 pub struct Attachment {
     pub id: String,
     pub file_name: String,
+    /// 相对数据目录的附件路径（旧版本保存的是绝对路径）
     pub file_path: String,
     pub file_type: String,
     pub file_size: u64,
     pub is_encrypted: bool,
     pub created_at: DateTime<Utc>,
+    /// 添加附件时记录的 SHA-256，用于完整性校验
+    #[serde(default)]
+    pub attachment_hash: Option<String>,
 }
 
 /// 记忆元数据
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct MemoryMetadata {
     pub word_count: Option<u32>,
     pub reading_time: Option<u32>, // 预估阅读时间（分钟）
@@ -53,6 +112,55 @@ pub struct MemoryMetadata {
     pub tags: Option<Vec<String>>,
 }
 
+/// 条目内的待办任务
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Task {
+    pub text: String,
+    #[serde(default)]
+    pub done: bool,
+}
+
+/// 任务清单的完成数与总数（用于条目列表展示）
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TaskProgress {
+    pub done: u32,
+    pub total: u32,
+}
+
+/// 前端采集的位置（经纬度和/或地名）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CaptureLocation {
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub place_name: Option<String>,
+}
+
+impl CaptureLocation {
+    /// 校验坐标范围并格式化为元数据中的位置字符串，如 `西湖 (30.250000,120.150000)`
+    pub fn to_metadata(&self) -> Result<Option<String>, String> {
+        let place = self.place_name.as_deref().map(str::trim).filter(|p| !p.is_empty());
+        let coordinates = match (self.latitude, self.longitude) {
+            (Some(lat), Some(lon)) => {
+                if !(-90.0..=90.0).contains(&lat) {
+                    return Err(format!("Latitude must be between -90 and 90, got {}", lat));
+                }
+                if !(-180.0..=180.0).contains(&lon) {
+                    return Err(format!("Longitude must be between -180 and 180, got {}", lon));
+                }
+                Some(format!("{:.6},{:.6}", lat, lon))
+            }
+            (None, None) => None,
+            _ => return Err("Latitude and longitude must be provided together".to_string()),
+        };
+
+        Ok(match (place, coordinates) {
+            (Some(place), Some(coordinates)) => Some(format!("{} ({})", place, coordinates)),
+            (Some(place), None) => Some(place.to_string()),
+            (None, coordinates) => coordinates,
+        })
+    }
+}
+
 /// 记忆条目结构
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MemoryEntry {
@@ -61,11 +169,63 @@ pub struct MemoryEntry {
     pub content: String,
     pub memory_type: MemoryType,
     pub emotion_tags: Vec<EmotionTag>,
+    /// 情感标签的强度（1-5），以标签字符串为键；未列出的标签为中等强度
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub emotion_intensities: BTreeMap<String, u8>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub is_encrypted: bool,
     pub attachments: Option<Vec<Attachment>>,
     pub metadata: Option<MemoryMetadata>,
+    #[serde(default)]
+    pub linked_entry_ids: Vec<String>,
+    /// 条目级加密后的正文（使用独立于存储密码的条目密码）
+    #[serde(default)]
+    pub encrypted_content: Option<EncryptedData>,
+    /// 已归档：不出现在默认列表和拾梦回响中，但不是删除
+    #[serde(default)]
+    pub is_archived: bool,
+    /// 正文外置时的文件名（位于数据目录的 `contents` 下）；列表读取时 `content` 为空，打开条目时再加载
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_ref: Option<String>,
+    /// 保存时计算的标题与正文 SHA-256，用于发现磁盘上的静默损坏
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
+    /// 手动排序的位置（越小越靠前），未手动排序时为空
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sort_order: Option<i64>,
+    /// 任务清单（可选，使条目兼作简单的计划）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tasks: Vec<Task>,
+    /// 任务清单完成情况，由 `tasks` 计算后返回给前端；不写入存储，读取时忽略
+    #[serde(default, skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub task_progress: Option<TaskProgress>,
+}
+
+/// 条目的增量修改
+///
+/// 只修改给出的字段，列表类操作是追加/移除而不是整体替换；未知字段在反序列化时报错。
+/// 字符串字段传入空字符串表示清除（标题除外）。
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EntryPatch {
+    /// 替换标题
+    pub title: Option<String>,
+    /// 在正文末尾追加一段（以空行分隔）
+    pub append_content: Option<String>,
+    /// 追加情感标签
+    pub add_emotion_tags: Option<Vec<String>>,
+    /// 移除情感标签
+    pub remove_emotion_tags: Option<Vec<String>>,
+    /// 追加元数据标签
+    pub add_tags: Option<Vec<String>>,
+    /// 移除元数据标签
+    pub remove_tags: Option<Vec<String>>,
+    pub mood: Option<String>,
+    pub location: Option<String>,
+    pub weather: Option<String>,
+    /// 在任务清单末尾追加未完成的任务
+    pub add_tasks: Option<Vec<String>>,
 }
 
 /// 加密数据结构
@@ -75,17 +235,93 @@ pub struct EncryptedData {
     pub salt: String,
     pub nonce: String,
     pub algorithm: String,
+    #[serde(default = "crate::crypto::legacy_format_version")]
+    pub format_version: u32,
+}
+
+impl EncryptedData {
+    /// 由加密结果构建
+    pub fn from_result(result: EncryptionResult) -> Self {
+        Self {
+            data: result.encrypted_data,
+            salt: result.salt,
+            nonce: result.nonce,
+            algorithm: crate::crypto::CIPHER_ALGORITHM.to_string(),
+            format_version: result.format_version,
+        }
+    }
+
+    /// 转换为解密参数
+    pub fn to_params(&self, password: &str) -> DecryptionParams {
+        DecryptionParams {
+            encrypted_data: self.data.clone(),
+            nonce: self.nonce.clone(),
+            salt: self.salt.clone(),
+            format_version: self.format_version,
+            password: password.to_string(),
+        }
+    }
 }
 
 
 /// 搜索过滤器
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct SearchFilter {
     pub keyword: Option<String>,
     pub memory_type: Option<MemoryType>,
     pub emotion_tags: Option<Vec<EmotionTag>>,
     pub date_range: Option<DateRange>,
     pub tags: Option<Vec<String>>,
+    /// 附件文件名（大小写不敏感的子串匹配）
+    pub attachment_name: Option<String>,
+    /// 心情（规范化后精确匹配）
+    #[serde(default)]
+    pub mood: Option<String>,
+    /// 位置（大小写不敏感的子串匹配）
+    #[serde(default)]
+    pub location: Option<String>,
+    /// 天气（大小写不敏感的子串匹配）
+    #[serde(default)]
+    pub weather: Option<String>,
+    /// 是否包含已归档条目（默认不包含）
+    #[serde(default)]
+    pub include_archived: Option<bool>,
+    /// 最低情感强度：条目需有强度不低于此值的情感标签（指定 `emotion_tags` 时限于其中的标签）
+    #[serde(default)]
+    pub min_emotion_intensity: Option<u8>,
+    /// 结果排序方式，未设置时保持存储顺序
+    #[serde(default)]
+    pub sort: Option<SearchSort>,
+    /// 按简单查询语法解析 `keyword`（空格为 AND、`OR`、前缀 `-` 排除），否则整体作为子串匹配
+    #[serde(default)]
+    pub structured_query: bool,
+}
+
+/// 搜索结果排序方式
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchSort {
+    /// 创建时间从新到旧
+    #[default]
+    NewestFirst,
+    /// 创建时间从旧到新
+    OldestFirst,
+    /// 最近更新优先
+    RecentlyUpdated,
+    /// 标题字母序
+    Title,
+    /// 手动排序：有位置的条目按位置在前，其余按创建时间从新到旧在后
+    Manual,
+}
+
+/// 分页搜索结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchPage {
+    pub entries: Vec<MemoryEntry>,
+    /// 匹配条目总数（不受分页影响）
+    pub total: usize,
+    pub offset: usize,
+    pub limit: usize,
 }
 
 /// 日期范围
@@ -103,17 +339,663 @@ pub struct MemoryStats {
     pub average_words_per_entry: f32,
     pub entries_by_type: HashMap<MemoryType, u32>,
     pub entries_by_emotion: HashMap<EmotionTag, u32>,
+    /// 各情感标签按强度加权的合计（未设置强度按中等强度计）
+    #[serde(default)]
+    pub emotion_intensity_totals: HashMap<EmotionTag, u32>,
     pub entries_by_month: HashMap<String, u32>,
+    /// 各月份的条目数，按 `key` 从早到晚排列，附带按用户语言格式化的显示名
+    #[serde(default)]
+    pub months: Vec<MonthBucket>,
+    /// 各心情的条目数（心情已规范化）
+    #[serde(default)]
+    pub entries_by_mood: HashMap<String, u32>,
     pub longest_streak: u32,
     pub current_streak: u32,
 }
 
+/// 统计中的一个月份
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MonthBucket {
+    /// 稳定的排序键，格式为 `YYYY-MM`
+    pub key: String,
+    /// 显示名，如 `2023年5月`
+    pub label: String,
+    pub count: u32,
+}
+
+/// 导出给外部图表使用的统计文档
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatsExport {
+    pub generated_at: DateTime<Utc>,
+    /// 保险库中的条目总数（含归档）
+    pub entry_count: u32,
+    pub stats: MemoryStats,
+    /// 最近一次写作的本地日期
+    pub last_entry_date: Option<NaiveDate>,
+}
+
+/// 每周起始日
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum WeekStart {
+    #[default]
+    Monday,
+    Sunday,
+}
+
+/// 界面语言，用于格式化统计中的显示文本
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Locale {
+    #[default]
+    #[serde(rename = "zh-CN")]
+    ZhCn,
+    #[serde(rename = "en")]
+    En,
+}
+
+impl Locale {
+    /// 格式化月份显示名，`month` 为 1-12
+    pub fn month_label(self, year: i32, month: u32) -> String {
+        const EN_MONTHS: [&str; 12] = [
+            "January", "February", "March", "April", "May", "June",
+            "July", "August", "September", "October", "November", "December",
+        ];
+        match self {
+            Locale::ZhCn => format!("{}年{}月", year, month),
+            Locale::En => format!("{} {}", EN_MONTHS[(month as usize - 1) % 12], year),
+        }
+    }
+}
+
+/// 条目模板（写作提示），可关联若干情感标签
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EntryTemplate {
+    pub id: String,
+    pub name: String,
+    pub title: String,
+    /// 预填的正文提示
+    pub content: String,
+    pub memory_type: MemoryType,
+    /// 为条目标记这些情感时推荐此模板
+    #[serde(default)]
+    pub emotion_tags: Vec<EmotionTag>,
+}
+
+/// 写作提醒配置
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct ReminderConfig {
+    pub enabled: bool,
+    /// 提醒时间（用户时区），格式为 `HH:MM`
+    pub time_of_day: String,
+    /// 提醒的星期（1 为周一，7 为周日），为空时每天提醒
+    pub days_of_week: Vec<u32>,
+}
+
+impl Default for ReminderConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            time_of_day: "21:00".to_string(),
+            days_of_week: Vec::new(),
+        }
+    }
+}
+
+/// 阅读速度下限，避免除零或异常的阅读时间
+pub const MIN_READING_SPEED: u32 = 10;
+
+/// 用户设置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UserSettings {
+    /// 是否使用主密码加密设置与拾梦配置（首次保存开启后，下一次保存起生效）
+    pub encrypt_metadata: bool,
+    /// 词频分析时额外忽略的停用词
+    pub custom_stopwords: Vec<String>,
+    /// 首次使用密码读取明文数据时，是否自动改为加密保存
+    pub migrate_to_encrypted: bool,
+    /// 用户时区，格式为相对 UTC 的偏移，如 `+08:00`
+    pub timezone: String,
+    /// 每周起始日
+    pub week_start: WeekStart,
+    /// 拉丁文字阅读速度（词/分钟）
+    pub reading_speed_wpm: u32,
+    /// 中日韩文字阅读速度（字/分钟）
+    pub reading_speed_cpm: u32,
+    /// 保存条目时必须提供密码，拒绝写入明文
+    pub require_password: bool,
+    /// 自定义数据目录（绝对路径），为空时使用应用数据目录
+    pub data_dir_override: Option<String>,
+    /// 是否自动记录创建条目时的位置与天气（需用户主动开启；手动填写的位置与天气不受影响）
+    pub capture_location: bool,
+    /// 解锁后无操作多少分钟自动锁定，0 表示不自动锁定
+    pub auto_lock_minutes: u32,
+    /// 明文 JSON 文件是否缩进排版（关闭可减小大文件体积，加密存储始终使用紧凑格式）
+    ///
+    /// 5000 条、每条 100 字中文正文的保险库：缩进约 4.57 MB，紧凑约 3.70 MB（小约 19%），
+    /// 由 `storage` 中的 `test_compact_json_size_on_large_vault` 复现。正文越短，缩进占比越大，
+    /// 关闭后节省越多。导出文件始终缩进排版。
+    pub pretty_json: bool,
+    /// 记忆文件超过多少 MB 时在存储报告中提示压缩
+    pub large_file_warning_mb: u32,
+    /// 正文超过多少字节时单独存放在 `contents` 目录中并按需加载，0 表示不外置（仅对未加密存储生效）
+    pub external_content_threshold: u32,
+    /// 写作提醒
+    pub reminder: ReminderConfig,
+    /// 正文最多字符数（与字数统计一致按字符计），未设置时不限制
+    pub max_content_chars: Option<u32>,
+    /// 界面语言
+    pub locale: Locale,
+}
+
+impl Default for UserSettings {
+    fn default() -> Self {
+        Self {
+            encrypt_metadata: false,
+            custom_stopwords: Vec::new(),
+            migrate_to_encrypted: false,
+            timezone: "+00:00".to_string(),
+            week_start: WeekStart::Monday,
+            reading_speed_wpm: 200,
+            reading_speed_cpm: 300,
+            require_password: false,
+            data_dir_override: None,
+            capture_location: false,
+            auto_lock_minutes: 10,
+            pretty_json: true,
+            large_file_warning_mb: 50,
+            external_content_threshold: 0,
+            reminder: ReminderConfig::default(),
+            max_content_chars: None,
+            locale: Locale::default(),
+        }
+    }
+}
+
+impl UserSettings {
+    /// 估算阅读时间（分钟，至少 1 分钟）
+    ///
+    /// 中日韩字符按 `reading_speed_cpm` 计算，其余按词数和 `reading_speed_wpm` 计算。
+    pub fn reading_time(&self, content: &str) -> u32 {
+        let tokens = crate::analysis::tokenize(content);
+        let cjk_chars = tokens.iter()
+            .filter(|t| t.chars().all(crate::analysis::is_cjk))
+            .count() as f64;
+        let latin_words = tokens.len() as f64 - cjk_chars;
+
+        let wpm = self.reading_speed_wpm.max(MIN_READING_SPEED) as f64;
+        let cpm = self.reading_speed_cpm.max(MIN_READING_SPEED) as f64;
+        ((latin_words / wpm + cjk_chars / cpm).ceil() as u32).max(1)
+    }
+
+    /// 检查正文是否超过 `max_content_chars`，超过时返回包含上限与实际长度的错误
+    pub fn check_content_length(&self, content: &str) -> Result<(), String> {
+        let Some(max) = self.max_content_chars else { return Ok(()) };
+        let actual = content.chars().count();
+        if actual > max as usize {
+            return Err(format!("Content is too long: {} characters, the limit is {}", actual, max));
+        }
+        Ok(())
+    }
+
+    /// 解析时区偏移，无效或未设置时使用 UTC
+    pub fn utc_offset(&self) -> FixedOffset {
+        self.timezone
+            .parse::<FixedOffset>()
+            .unwrap_or_else(|_| FixedOffset::east_opt(0).expect("zero offset is valid"))
+    }
+
+    /// 由默认设置推导出的字段结构，供前端动态生成设置界面
+    ///
+    /// 字段类型取自默认值的 JSON 类型；默认为空的可选字段视为可为空的字符串，
+    /// 枚举字段列出全部可选值。
+    pub fn schema() -> Vec<SettingField> {
+        let defaults = match serde_json::to_value(Self::default()) {
+            Ok(serde_json::Value::Object(defaults)) => defaults,
+            _ => return Vec::new(),
+        };
+
+        defaults
+            .into_iter()
+            .map(|(name, default)| {
+                let options = match name.as_str() {
+                    "week_start" => Some(
+                        [WeekStart::Monday, WeekStart::Sunday]
+                            .iter()
+                            .filter_map(|w| serde_json::to_value(w).ok())
+                            .filter_map(|w| w.as_str().map(str::to_string))
+                            .collect(),
+                    ),
+                    _ => None,
+                };
+                let field_type = match (&default, &options) {
+                    (_, Some(_)) => "enum",
+                    (serde_json::Value::Bool(_), _) => "boolean",
+                    (serde_json::Value::Number(n), _) if n.is_f64() => "number",
+                    (serde_json::Value::Number(_), _) => "integer",
+                    (serde_json::Value::Array(_), _) => "array",
+                    (serde_json::Value::Object(_), _) => "object",
+                    (serde_json::Value::String(_) | serde_json::Value::Null, _) => "string",
+                };
+
+                SettingField {
+                    nullable: default.is_null(),
+                    name,
+                    field_type: field_type.to_string(),
+                    default,
+                    options,
+                }
+            })
+            .collect()
+    }
+}
+
+/// 设置字段的结构描述
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettingField {
+    pub name: String,
+    /// `boolean`、`integer`、`number`、`string`、`array`、`object` 或 `enum`
+    pub field_type: String,
+    pub default: serde_json::Value,
+    pub nullable: bool,
+    /// 枚举字段的可选值
+    pub options: Option<Vec<String>>,
+}
+
+/// 应用级配置（保存在数据目录顶层的 `app.json`，不随保险库切换）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppConfig {
+    /// 当前使用的保险库，未设置时为默认保险库
+    pub active_vault: Option<String>,
+}
+
+/// 保险库的加密状态与密钥派生参数（从记忆文件头读取，无需密码）
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct VaultSecurityInfo {
+    pub encrypted: bool,
+    /// 加密算法，未加密时为空
+    pub algorithm: Option<String>,
+    /// 密钥派生算法，未加密或格式版本未知时为空
+    pub kdf: Option<String>,
+    pub argon2_params: Option<Argon2Params>,
+    pub format_version: Option<u32>,
+    /// 密钥派生参数低于当前推荐值
+    pub below_recommended: bool,
+}
+
+/// 保险库信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VaultInfo {
+    pub name: String,
+    pub active: bool,
+}
+
+/// 拾梦回响配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DreamConfig {
+    pub enabled: bool,
+    pub interval_minutes: u32,
+    pub avoid_repeats: bool,
+}
+
+impl Default for DreamConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_minutes: 60,
+            avoid_repeats: true,
+        }
+    }
+}
+
+/// 高亮所在字段
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HighlightField {
+    Title,
+    Content,
+}
+
+/// 单个高亮位置（字符偏移，左闭右开）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Highlight {
+    pub field: HighlightField,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// 条目的搜索高亮结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntryHighlights {
+    pub entry_id: String,
+    pub highlights: Vec<Highlight>,
+}
+
+/// 周回顾汇总
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WeekSummary {
+    pub week_start: DateTime<Utc>,
+    pub week_end: DateTime<Utc>,
+    pub entry_count: u32,
+    pub total_words: u32,
+    pub dominant_emotion: Option<EmotionTag>,
+    pub entries: Vec<MemoryEntry>,
+}
+
+/// 标签树中的一个节点（层级标签按 `/` 拆分）
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TagNode {
+    /// 本级名称，如 `japan`
+    pub name: String,
+    /// 完整路径，如 `travel/japan`，可直接用于标签筛选
+    pub path: String,
+    /// 带有该标签或其任一下级标签的条目数
+    pub count: u32,
+    pub children: Vec<TagNode>,
+}
+
+/// 时间线中的一个月份分组
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MonthGroup {
+    /// 月份，格式为 `YYYY-MM`
+    pub month: String,
+    pub entries: Vec<MemoryEntry>,
+}
+
+/// 情感标签及其出现的条目数
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct EmotionCount {
+    pub emotion: EmotionTag,
+    pub count: u32,
+}
+
+/// 两个情感标签在同一条目中共同出现的次数（`first` 与 `second` 按字符串排序，不含自身配对）
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct EmotionPair {
+    pub first: EmotionTag,
+    pub second: EmotionTag,
+    pub count: u32,
+}
+
+/// 连续写作天数
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct WritingStreak {
+    pub current_streak: u32,
+    pub longest_streak: u32,
+    /// 最近一次写作的本地日期
+    pub last_entry_date: Option<NaiveDate>,
+}
+
+/// 条目文本分析结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntryAnalysis {
+    pub top_words: Vec<(String, u32)>,
+    pub total_words: u32,
+    pub unique_words: u32,
+    pub average_sentence_length: f32,
+}
+
+/// 导入报告
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportReport {
+    pub imported: u32,
+    pub skipped: Vec<String>,
+    /// 未通过校验而未导入的记录
+    #[serde(default)]
+    pub invalid: Vec<RecordError>,
+}
+
+/// 单条导入记录的校验错误
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RecordError {
+    /// 记录在导入文件中的位置（从 0 开始）
+    pub index: usize,
+    /// 记录的 ID（可读取时）
+    pub id: Option<String>,
+    pub errors: Vec<String>,
+}
+
+/// 合并保险库时 ID 冲突的处理方式
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeStrategy {
+    /// 保留 `updated_at` 较新的一条
+    #[default]
+    KeepNewer,
+    /// 两条都保留，另一保险库的条目分配新 ID
+    KeepBoth,
+    /// 保留本地条目，跳过另一保险库的条目
+    Skip,
+}
+
+/// 保险库合并报告
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MergeReport {
+    pub added: u32,
+    pub updated: u32,
+    /// 因冲突策略或内容完全相同而未合并的条目数
+    pub skipped: u32,
+}
+
+/// 批量操作结果，逐个列出每个 ID 的处理结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BulkResult {
+    pub succeeded: Vec<String>,
+    /// 失败的 ID 及原因
+    pub failed: Vec<(String, String)>,
+}
+
+/// 附件完整性校验报告（均为附件 ID）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AttachmentReport {
+    pub ok: Vec<String>,
+    pub missing: Vec<String>,
+    pub modified: Vec<String>,
+}
+
+/// 未被任何条目引用的附件文件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrphanedAttachment {
+    pub path: String,
+    pub size: u64,
+}
+
+/// 孤立附件清理报告
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OrphanReport {
+    pub files: Vec<OrphanedAttachment>,
+    /// 删除这些文件可释放（或已释放）的字节数
+    pub reclaimed_bytes: u64,
+    pub dry_run: bool,
+}
+
+/// 存储状态
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoreStatus {
+    /// 记忆文件是否为加密存储
+    pub encrypted: bool,
+    /// 连续密码错误次数
+    pub failed_attempts: u32,
+    /// 下一次密码尝试前需要等待的秒数
+    pub next_attempt_delay_secs: i64,
+}
+
+/// 单个数据文件的大小
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FileSize {
+    pub name: String,
+    pub size: u64,
+}
+
+/// 存储空间报告
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageReport {
+    /// 数据目录中各数据文件的大小（不存在的文件不列出）
+    pub files: Vec<FileSize>,
+    pub attachment_count: u32,
+    pub attachments_size: u64,
+    pub entry_count: u32,
+    /// 数据文件与附件的总字节数
+    pub total_size: u64,
+    /// 统计增长的天数
+    pub growth_window_days: u32,
+    /// 最近 `growth_window_days` 天新增的字节数（新建条目的估算大小加新附件大小）
+    pub recent_growth_bytes: u64,
+    /// 按最近增长速度推算一年后的总字节数
+    pub projected_size_in_year: u64,
+    /// 记忆文件超过设置中的 `large_file_warning_mb`
+    pub large_entries_file: bool,
+}
+
+/// 保险库占用的磁盘空间（字节，不存在的部分计为 0）
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct StorageUsage {
+    /// 记忆文件 `memories.json`
+    pub entries_size: u64,
+    pub settings_size: u64,
+    pub dream_config_size: u64,
+    /// 外置正文目录
+    pub contents_size: u64,
+    /// 附件目录（递归统计）
+    pub attachments_size: u64,
+    pub total_size: u64,
+}
+
+/// 重新加密进度（`reencrypt_progress` 事件负载）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReencryptProgress {
+    pub processed: usize,
+    pub total: usize,
+}
+
+/// 带附件导出的结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportReport {
+    pub path: String,
+    pub entries: u32,
+    pub attachments: u32,
+    /// 源文件不存在、未能打包的附件 ID
+    pub missing: Vec<String>,
+}
+
+/// 备份清单（记录每个备份文件的 SHA-256）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupManifest {
+    pub created_at: DateTime<Utc>,
+    pub files: BTreeMap<String, String>,
+}
+
+/// 完整性校验中哈希不匹配的条目
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IntegrityIssue {
+    pub entry_id: String,
+    pub title: String,
+}
+
+/// 条目完整性校验报告
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IntegrityReport {
+    /// 带有哈希并完成校验的条目数
+    pub checked: u32,
+    /// 尚无哈希的条目数（在引入哈希之前保存，下次保存时补上）
+    pub unhashed: u32,
+    /// 哈希不再匹配的条目
+    pub mismatched: Vec<IntegrityIssue>,
+}
+
+/// 恢复报告
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestoreReport {
+    pub restored: bool,
+    pub verified: Vec<String>,
+    pub corrupted: Vec<String>,
+    pub missing: Vec<String>,
+}
+
+/// 备份校验报告（只读取备份，不做任何修改）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupValidation {
+    /// 清单可读，清单中的文件齐全、哈希匹配，且记忆文件可以解析
+    pub valid: bool,
+    pub verified: Vec<String>,
+    pub corrupted: Vec<String>,
+    pub missing: Vec<String>,
+    /// 备份的记忆文件是否加密
+    pub encrypted: bool,
+    /// 备份中的条目数（已加密且未提供密码或密码错误时为空）
+    pub entry_count: Option<u32>,
+    /// 提供的密码能否解密备份（未尝试时为空）
+    pub password_ok: Option<bool>,
+}
+
+/// 存储修复报告
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RepairReport {
+    pub recovered: u32,
+    pub dropped: u32,
+    pub corrupt_file: Option<String>,
+}
+
+/// 条目清理时做出的一类修改
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RepairKind {
+    /// 无法解析而被丢弃的记录
+    Dropped,
+    /// 重复 ID 中较旧的一条被移除
+    DuplicateRemoved,
+    /// 补全缺失的 `created_at`
+    CreatedAtFilled,
+    /// 补全缺失的 `updated_at`
+    UpdatedAtFilled,
+    /// 重新计算缺失的字数
+    WordCountRecomputed,
+}
+
+/// 条目清理中的单项修改
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RepairChange {
+    /// 记录的 ID（无法读取 ID 时为 None）
+    pub entry_id: Option<String>,
+    pub kind: RepairKind,
+    pub detail: String,
+}
+
+/// 条目清理报告
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SanitizeReport {
+    /// 清理后的条目数
+    pub entry_count: u32,
+    pub changes: Vec<RepairChange>,
+    /// 修复前写入的备份文件（无需修改时为 None）
+    pub backup_file: Option<String>,
+}
+
+/// 错误码，便于前端区分错误类型
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// 条目已加锁，需要条目密码
+    EntryLocked,
+    /// 密码错误
+    InvalidPassword,
+    /// 密文已损坏，重试密码无济于事
+    CorruptData,
+    /// 界面已锁定为只读，拒绝修改
+    ReadOnly,
+}
+
 /// API 响应结构
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ApiResponse<T> {
     pub success: bool,
     pub data: Option<T>,
     pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<ErrorCode>,
     pub message: Option<String>,
 }
 
@@ -124,6 +1006,7 @@ impl<T> ApiResponse<T> {
             success: true,
             data: Some(data),
             error: None,
+            error_code: None,
             message: None,
         }
     }
@@ -134,6 +1017,7 @@ impl<T> ApiResponse<T> {
             success: true,
             data: None,
             error: None,
+            error_code: None,
             message: None,
         }
     }
@@ -144,10 +1028,28 @@ impl<T> ApiResponse<T> {
             success: false,
             data: None,
             error: Some(error),
+            error_code: None,
             message: None,
         }
     }
 
+    /// 创建带数据的错误响应
+    pub fn error_with_data(data: T, error: String) -> Self {
+        Self {
+            success: false,
+            data: Some(data),
+            error: Some(error),
+            error_code: None,
+            message: None,
+        }
+    }
+
+    /// 附加错误码
+    pub fn with_error_code(mut self, code: ErrorCode) -> Self {
+        self.error_code = Some(code);
+        self
+    }
+
     /// 创建带消息的响应
     pub fn with_message(mut self, message: String) -> Self {
         self.message = Some(message);
@@ -166,25 +1068,41 @@ impl MemoryEntry {
             content,
             memory_type,
             emotion_tags: Vec::new(),
+            emotion_intensities: BTreeMap::new(),
             created_at: now,
             updated_at: now,
             is_encrypted: false,
             attachments: None,
             metadata: None,
+            linked_entry_ids: Vec::new(),
+            encrypted_content: None,
+            is_archived: false,
+            content_ref: None,
+            content_hash: None,
+            sort_order: None,
+            tasks: Vec::new(),
+            task_progress: None,
         }
     }
 
+    /// 计算标题与正文的 SHA-256（用 `\0` 分隔，避免标题与正文边界移动后哈希相同）
+    pub fn compute_content_hash(&self) -> String {
+        BackendEncryption::hash_sha256(&format!("{}\0{}", self.title, self.content))
+    }
+
     /// 更新记忆条目
-    pub fn update(&mut self, title: Option<String>, content: Option<String>) {
+    pub fn update(&mut self, title: Option<String>, content: Option<String>, settings: &UserSettings) {
         if let Some(title) = title {
             self.title = title;
         }
         if let Some(content) = content {
             self.content = content;
+            // 正文已被替换，不再沿用外置文件
+            self.content_ref = None;
             // 重新计算字数
             if let Some(ref mut metadata) = self.metadata {
                 metadata.word_count = Some(self.content.chars().count() as u32);
-                metadata.reading_time = Some((self.content.chars().count() as u32 / 200).max(1));
+                metadata.reading_time = Some(settings.reading_time(&self.content));
             }
         }
         self.updated_at = Utc::now();
@@ -197,8 +1115,425 @@ impl MemoryEntry {
         }
     }
 
-    /// 移除情感标签
+    /// 移除情感标签及其强度
     pub fn remove_emotion_tag(&mut self, tag: &EmotionTag) {
         self.emotion_tags.retain(|t| t != tag);
+        self.emotion_intensities.remove(tag.as_str());
+    }
+
+    /// 替换情感标签，保留的标签沿用原有强度
+    pub fn set_emotion_tags(&mut self, tags: Vec<EmotionTag>) {
+        self.emotion_tags.clear();
+        for tag in tags {
+            self.add_emotion_tag(tag);
+        }
+        self.emotion_intensities.retain(|label, _| self.emotion_tags.iter().any(|t| t.as_str() == label));
+    }
+
+    /// 情感标签的强度，未设置时为中等强度
+    pub fn emotion_intensity(&self, tag: &EmotionTag) -> u8 {
+        self.emotion_intensities
+            .get(tag.as_str())
+            .copied()
+            .unwrap_or(NEUTRAL_EMOTION_INTENSITY)
+    }
+
+    /// 设置情感强度（键为情感标签），条目还没有的标签会一并添加
+    ///
+    /// 任一强度不在 1-5 之间或标签无效时返回错误，且不修改条目。
+    pub fn set_emotion_intensities(&mut self, intensities: HashMap<String, u8>) -> Result<(), String> {
+        let mut parsed = Vec::with_capacity(intensities.len());
+        for (label, intensity) in intensities {
+            if !(MIN_EMOTION_INTENSITY..=MAX_EMOTION_INTENSITY).contains(&intensity) {
+                return Err(format!(
+                    "Emotion intensity for '{}' must be between {} and {}, got {}",
+                    label, MIN_EMOTION_INTENSITY, MAX_EMOTION_INTENSITY, intensity
+                ));
+            }
+            parsed.push((EmotionTag::try_from(label)?, intensity));
+        }
+
+        for (tag, intensity) in parsed {
+            self.emotion_intensities.insert(tag.as_str().to_string(), intensity);
+            self.add_emotion_tag(tag);
+        }
+        Ok(())
+    }
+
+    /// 重新计算字数与阅读时间（必要时创建元数据）
+    pub fn refresh_word_stats(&mut self, settings: &UserSettings) {
+        let word_count = self.content.chars().count() as u32;
+        let reading_time = settings.reading_time(&self.content);
+        let metadata = self.metadata.get_or_insert_with(MemoryMetadata::default);
+        metadata.word_count = Some(word_count);
+        metadata.reading_time = Some(reading_time);
+    }
+
+    /// 设置元数据标签（规范化并去重）
+    pub fn set_tags(&mut self, tags: Vec<String>) {
+        let metadata = self.metadata.get_or_insert_with(MemoryMetadata::default);
+        metadata.tags = Some(normalize_tags(tags));
+    }
+
+    /// 设置心情（与标签相同的规范化方式，空白时清除）
+    pub fn set_mood(&mut self, mood: Option<String>) {
+        let mood = mood.map(|m| normalize_tag(&m)).filter(|m| !m.is_empty());
+        if mood.is_none() && self.metadata.is_none() {
+            return;
+        }
+        self.metadata.get_or_insert_with(MemoryMetadata::default).mood = mood;
+    }
+
+    /// 获取心情
+    pub fn mood(&self) -> Option<&str> {
+        self.metadata.as_ref().and_then(|m| m.mood.as_deref())
+    }
+
+    /// 应用增量修改
+    ///
+    /// 已加锁条目不能追加正文；标题不能为空。
+    pub fn apply_patch(&mut self, patch: EntryPatch, settings: &UserSettings) -> Result<(), String> {
+        if let Some(title) = &patch.title {
+            if title.trim().is_empty() {
+                return Err("Title cannot be empty".to_string());
+            }
+        }
+        if patch.append_content.is_some() && self.encrypted_content.is_some() {
+            return Err("Entry is locked".to_string());
+        }
+
+        if let Some(title) = patch.title {
+            self.title = title;
+        }
+        if let Some(paragraph) = patch.append_content.filter(|p| !p.trim().is_empty()) {
+            if !self.content.trim().is_empty() {
+                self.content = format!("{}\n\n{}", self.content.trim_end(), paragraph);
+            } else {
+                self.content = paragraph;
+            }
+            self.refresh_word_stats(settings);
+        }
+
+        for tag in patch.add_emotion_tags.unwrap_or_default() {
+            if let Ok(tag) = EmotionTag::try_from(tag) {
+                self.add_emotion_tag(tag);
+            }
+        }
+        for tag in patch.remove_emotion_tags.unwrap_or_default() {
+            if let Ok(tag) = EmotionTag::try_from(tag) {
+                self.remove_emotion_tag(&tag);
+            }
+        }
+
+        if patch.add_tags.is_some() || patch.remove_tags.is_some() {
+            let removed = normalize_tags(patch.remove_tags.unwrap_or_default());
+            let mut tags = self.metadata.as_ref().and_then(|m| m.tags.clone()).unwrap_or_default();
+            tags.extend(patch.add_tags.unwrap_or_default());
+            self.set_tags(tags.into_iter().filter(|t| !removed.contains(&normalize_tag(t))).collect());
+        }
+
+        if patch.mood.is_some() {
+            self.set_mood(patch.mood);
+        }
+        if patch.location.is_some() {
+            self.set_location(patch.location);
+        }
+        if patch.weather.is_some() {
+            self.set_weather(patch.weather);
+        }
+        if let Some(texts) = patch.add_tasks {
+            let mut tasks = std::mem::take(&mut self.tasks);
+            tasks.extend(texts.into_iter().map(|text| Task { text, done: false }));
+            self.set_tasks(tasks);
+        }
+
+        self.updated_at = Utc::now();
+        Ok(())
+    }
+
+    /// 设置任务清单（去除首尾空白，忽略空任务），同时更新完成情况
+    pub fn set_tasks(&mut self, tasks: Vec<Task>) {
+        self.tasks = tasks
+            .into_iter()
+            .map(|task| Task { text: task.text.trim().to_string(), done: task.done })
+            .filter(|task| !task.text.is_empty())
+            .collect();
+        self.refresh_task_progress();
+    }
+
+    /// 切换第 `index` 个任务的完成状态，返回切换后的状态
+    pub fn toggle_task(&mut self, index: usize) -> Result<bool, String> {
+        let task = self.tasks.get_mut(index)
+            .ok_or_else(|| format!("Task index {} out of range", index))?;
+        task.done = !task.done;
+        let done = task.done;
+        self.refresh_task_progress();
+        self.updated_at = Utc::now();
+        Ok(done)
+    }
+
+    /// 按任务清单重新计算完成情况，没有任务时为空
+    pub fn refresh_task_progress(&mut self) {
+        self.task_progress = (!self.tasks.is_empty()).then(|| TaskProgress {
+            done: self.tasks.iter().filter(|t| t.done).count() as u32,
+            total: self.tasks.len() as u32,
+        });
+    }
+
+    /// 记录创建时的位置与天气（空白值视为未提供）
+    pub fn set_context(&mut self, location: Option<String>, weather: Option<String>) {
+        self.set_location(location);
+        self.set_weather(weather);
+    }
+
+    /// 设置位置（空白时清除）
+    pub fn set_location(&mut self, location: Option<String>) {
+        let location = location.map(|l| l.trim().to_string()).filter(|l| !l.is_empty());
+        if location.is_some() || self.metadata.is_some() {
+            self.metadata.get_or_insert_with(MemoryMetadata::default).location = location;
+        }
+    }
+
+    /// 设置天气（空白时清除）
+    pub fn set_weather(&mut self, weather: Option<String>) {
+        let weather = weather.map(|w| w.trim().to_string()).filter(|w| !w.is_empty());
+        if weather.is_some() || self.metadata.is_some() {
+            self.metadata.get_or_insert_with(MemoryMetadata::default).weather = weather;
+        }
+    }
+}
+
+/// 层级标签的分隔符，如 `travel/japan/2023`
+pub const TAG_SEPARATOR: char = '/';
+
+/// 规范化单个标签：去除首尾空白、合并内部连续空白并转为小写
+///
+/// 层级标签逐级规范化，并丢弃空的层级（`Travel / Japan/` → `travel/japan`）。
+pub fn normalize_tag(tag: &str) -> String {
+    tag.split(TAG_SEPARATOR)
+        .map(|segment| segment.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>()
+        .join(&TAG_SEPARATOR.to_string())
+        .to_lowercase()
+}
+
+/// 标签是否匹配筛选标签：完全相同，或是其下级标签（`travel` 匹配 `travel/japan`）
+pub fn tag_matches(tag: &str, wanted: &str) -> bool {
+    tag.strip_prefix(wanted)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(TAG_SEPARATOR))
+}
+
+/// 规范化标签列表：逐个规范化，丢弃空标签，并按首次出现的顺序去重
+pub fn normalize_tags<I, S>(tags: I) -> Vec<String>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = normalize_tag(tag.as_ref());
+        if !tag.is_empty() && !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings_schema_lists_fields_with_defaults() {
+        let schema = UserSettings::schema();
+        let field = |name: &str| schema.iter().find(|f| f.name == name).unwrap_or_else(|| panic!("missing {}", name));
+
+        assert_eq!(field("timezone").field_type, "string");
+        assert_eq!(field("timezone").default, serde_json::json!("+00:00"));
+        assert_eq!(field("reading_speed_wpm").field_type, "integer");
+        assert_eq!(field("reading_speed_wpm").default, serde_json::json!(200));
+        assert_eq!(field("custom_stopwords").field_type, "array");
+        assert_eq!(field("week_start").options, Some(vec!["monday".to_string(), "sunday".to_string()]));
+        assert!(field("data_dir_override").nullable);
+        assert_eq!(schema.len(), serde_json::to_value(UserSettings::default()).unwrap().as_object().unwrap().len());
+    }
+
+    #[test]
+    fn test_emotion_tag_custom_labels() {
+        let tags: Vec<EmotionTag> = serde_json::from_str(r#"["Joy", "  Quiet   Awe ", "期待"]"#).unwrap();
+        assert_eq!(tags, vec![
+            EmotionTag::Joy,
+            EmotionTag::Custom("quiet awe".to_string()),
+            EmotionTag::Custom("期待".to_string()),
+        ]);
+        assert_eq!(serde_json::to_string(&tags).unwrap(), r#"["joy","quiet awe","期待"]"#);
+        assert!(serde_json::from_str::<EmotionTag>(r#""   ""#).is_err());
+    }
+
+    #[test]
+    fn test_normalize_tag() {
+        assert_eq!(normalize_tag(" Friendship "), "friendship");
+        assert_eq!(normalize_tag("Road   Trip\t 2023"), "road trip 2023");
+        assert_eq!(normalize_tag(" Travel / Japan//2023 / "), "travel/japan/2023");
+    }
+
+    #[test]
+    fn test_tag_matches_descendants() {
+        assert!(tag_matches("travel", "travel"));
+        assert!(tag_matches("travel/japan/2023", "travel"));
+        assert!(tag_matches("travel/japan/2023", "travel/japan"));
+        assert!(!tag_matches("travelling", "travel"));
+        assert!(!tag_matches("travel", "travel/japan"));
+    }
+
+    #[test]
+    fn test_normalize_tags_dedup_keeps_first_order() {
+        let tags = normalize_tags(vec![" Friendship ", "summer", "friendship", "", "SUMMER", "beach"]);
+        assert_eq!(tags, vec!["friendship", "summer", "beach"]);
+    }
+
+    #[test]
+    fn test_settings_utc_offset() {
+        let settings = UserSettings { timezone: "+08:00".to_string(), ..UserSettings::default() };
+        assert_eq!(settings.utc_offset().local_minus_utc(), 8 * 3600);
+        assert_eq!(UserSettings::default().utc_offset().local_minus_utc(), 0);
+    }
+
+    #[test]
+    fn test_check_content_length_counts_chars() {
+        assert!(UserSettings::default().check_content_length(&"桃".repeat(100_000)).is_ok());
+
+        let settings = UserSettings { max_content_chars: Some(5), ..UserSettings::default() };
+        assert!(settings.check_content_length("桃花笺日").is_ok());
+        // 5 个汉字为 15 字节，按字符计恰好在上限
+        assert!(settings.check_content_length("桃花笺日记").is_ok());
+        let err = settings.check_content_length("桃花笺日记本").unwrap_err();
+        assert!(err.contains("6 characters") && err.contains("limit is 5"), "{}", err);
+    }
+
+    #[test]
+    fn test_reading_time_follows_settings() {
+        let content = "word ".repeat(400);
+        let mut entry = MemoryEntry::new("标题".to_string(), content, MemoryType::Text);
+
+        entry.refresh_word_stats(&UserSettings::default());
+        assert_eq!(entry.metadata.as_ref().unwrap().reading_time, Some(2));
+
+        let faster = UserSettings { reading_speed_wpm: 400, ..UserSettings::default() };
+        entry.update(None, Some("word ".repeat(400)), &faster);
+        assert_eq!(entry.metadata.as_ref().unwrap().reading_time, Some(1));
+
+        let slower = UserSettings { reading_speed_wpm: 0, ..UserSettings::default() };
+        entry.refresh_word_stats(&slower);
+        assert_eq!(entry.metadata.as_ref().unwrap().reading_time, Some(400 / MIN_READING_SPEED));
+    }
+
+    #[test]
+    fn test_set_tags_creates_metadata() {
+        let mut entry = MemoryEntry::new("标题".to_string(), "内容".to_string(), MemoryType::Text);
+        entry.set_tags(vec!["  Travel ".to_string(), "travel".to_string()]);
+        let tags = entry.metadata.unwrap().tags.unwrap();
+        assert_eq!(tags, vec!["travel"]);
+    }
+
+    #[test]
+    fn test_emotion_intensities_default_to_neutral() {
+        let legacy: MemoryEntry = serde_json::from_value(serde_json::json!({
+            "id": "1", "title": "旧条目", "content": "", "memory_type": "text", "emotion_tags": ["joy"],
+            "created_at": "2024-01-01T00:00:00Z", "updated_at": "2024-01-01T00:00:00Z",
+            "is_encrypted": false, "attachments": null, "metadata": null
+        })).unwrap();
+        assert_eq!(legacy.emotion_intensity(&EmotionTag::Joy), NEUTRAL_EMOTION_INTENSITY);
+        assert!(serde_json::to_value(&legacy).unwrap().get("emotion_intensities").is_none());
+
+        let mut entry = legacy.clone();
+        entry.set_emotion_intensities(HashMap::from([(" Hope ".to_string(), 5)])).unwrap();
+        assert_eq!(entry.emotion_tags, vec![EmotionTag::Joy, EmotionTag::Hope]);
+        assert_eq!(entry.emotion_intensity(&EmotionTag::Hope), 5);
+
+        assert!(entry.set_emotion_intensities(HashMap::from([("joy".to_string(), 6)])).is_err());
+        assert!(entry.set_emotion_intensities(HashMap::from([("joy".to_string(), 0)])).is_err());
+        assert_eq!(entry.emotion_intensity(&EmotionTag::Joy), NEUTRAL_EMOTION_INTENSITY);
+
+        entry.set_emotion_tags(vec![EmotionTag::Hope, EmotionTag::Regret]);
+        assert_eq!(entry.emotion_intensity(&EmotionTag::Hope), 5);
+        entry.remove_emotion_tag(&EmotionTag::Hope);
+        assert!(entry.emotion_intensities.is_empty());
+    }
+
+    #[test]
+    fn test_apply_patch_is_additive() {
+        let mut entry = MemoryEntry::new("标题".to_string(), "第一段".to_string(), MemoryType::Text);
+        entry.add_emotion_tag(EmotionTag::Joy);
+        entry.set_tags(vec!["travel".to_string(), "spring".to_string()]);
+        let patch: EntryPatch = serde_json::from_value(serde_json::json!({
+            "append_content": "第二段",
+            "add_emotion_tags": ["Hope"],
+            "add_tags": ["Family"],
+            "remove_tags": ["SPRING"],
+            "mood": "Calm"
+        })).unwrap();
+
+        entry.apply_patch(patch, &UserSettings::default()).unwrap();
+        assert_eq!(entry.title, "标题");
+        assert_eq!(entry.content, "第一段\n\n第二段");
+        assert_eq!(entry.metadata.as_ref().unwrap().word_count, Some(8));
+        assert_eq!(entry.emotion_tags, vec![EmotionTag::Joy, EmotionTag::Hope]);
+        assert_eq!(entry.metadata.as_ref().unwrap().tags, Some(vec!["travel".to_string(), "family".to_string()]));
+        assert_eq!(entry.mood(), Some("calm"));
+
+        let empty_title = EntryPatch { title: Some(" ".to_string()), ..EntryPatch::default() };
+        assert!(entry.apply_patch(empty_title, &UserSettings::default()).is_err());
+        assert!(serde_json::from_value::<EntryPatch>(serde_json::json!({ "content": "整体替换" })).is_err());
+    }
+
+    #[test]
+    fn test_tasks_toggle_and_progress() {
+        let mut entry = MemoryEntry::new("周末".to_string(), "计划".to_string(), MemoryType::Text);
+        let patch: EntryPatch = serde_json::from_value(serde_json::json!({
+            "add_tasks": ["买花", "  ", " 写信 "]
+        })).unwrap();
+        entry.apply_patch(patch, &UserSettings::default()).unwrap();
+        assert_eq!(entry.tasks.iter().map(|t| t.text.as_str()).collect::<Vec<_>>(), vec!["买花", "写信"]);
+        let progress = |entry: &MemoryEntry| entry.task_progress;
+        assert_eq!(progress(&entry), Some(TaskProgress { done: 0, total: 2 }));
+
+        assert_eq!(entry.toggle_task(1), Ok(true));
+        assert_eq!(progress(&entry), Some(TaskProgress { done: 1, total: 2 }));
+        assert_eq!(entry.toggle_task(1), Ok(false));
+        assert!(entry.toggle_task(2).is_err());
+
+        entry.set_tasks(Vec::new());
+        assert_eq!(progress(&entry), None);
+        let json = serde_json::to_value(&entry).unwrap();
+        assert!(json.get("tasks").is_none());
+    }
+
+    #[test]
+    fn test_set_mood_normalizes_and_clears() {
+        let mut entry = MemoryEntry::new("标题".to_string(), "内容".to_string(), MemoryType::Text);
+        entry.set_mood(Some("  Calm   Morning ".to_string()));
+        assert_eq!(entry.mood(), Some("calm morning"));
+
+        entry.set_mood(Some("  ".to_string()));
+        assert_eq!(entry.mood(), None);
+    }
+
+    #[test]
+    fn test_capture_location_validates_coordinates() {
+        let both = CaptureLocation { latitude: Some(30.25), longitude: Some(120.15), place_name: Some(" 西湖 ".to_string()) };
+        assert_eq!(both.to_metadata().unwrap().as_deref(), Some("西湖 (30.250000,120.150000)"));
+
+        let place_only = CaptureLocation { place_name: Some("Kyoto".to_string()), ..CaptureLocation::default() };
+        assert_eq!(place_only.to_metadata().unwrap().as_deref(), Some("Kyoto"));
+        assert_eq!(CaptureLocation::default().to_metadata().unwrap(), None);
+
+        let out_of_range = CaptureLocation { latitude: Some(91.0), longitude: Some(0.0), place_name: None };
+        assert!(out_of_range.to_metadata().is_err());
+        let not_a_number = CaptureLocation { latitude: Some(f64::NAN), longitude: Some(0.0), place_name: None };
+        assert!(not_a_number.to_metadata().is_err());
+        let half = CaptureLocation { latitude: Some(10.0), ..CaptureLocation::default() };
+        assert!(half.to_metadata().is_err());
     }
 }
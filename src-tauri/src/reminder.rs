@@ -0,0 +1,76 @@
+/**
+ * 写作提醒模块
+ * 根据提醒配置计算下一次提醒时间，与通知插件无关，便于测试
+ */
+
+use crate::models::ReminderConfig;
+use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveTime, Utc};
+
+/// 解析 `HH:MM` 格式的提醒时间
+pub fn parse_time_of_day(time_of_day: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(time_of_day.trim(), "%H:%M").ok()
+}
+
+/// 计算 `now` 之后（不含）的下一次提醒时间
+///
+/// 提醒时间按 `offset` 时区解释。未启用、时间格式无效或所选星期全部无效时返回 None。
+pub fn next_due(config: &ReminderConfig, now: DateTime<Utc>, offset: FixedOffset) -> Option<DateTime<Utc>> {
+    if !config.enabled {
+        return None;
+    }
+    let time = parse_time_of_day(&config.time_of_day)?;
+    let today = now.with_timezone(&offset).date_naive();
+
+    // 今天的提醒时间已过时，下一次最晚在 7 天后的同一星期
+    (0..=7)
+        .map(|days| today + Duration::days(days))
+        .filter(|date| config.days_of_week.is_empty() || config.days_of_week.contains(&date.weekday().number_from_monday()))
+        .filter_map(|date| date.and_time(time).and_local_timezone(offset).single())
+        .map(|due| due.with_timezone(&Utc))
+        .find(|due| *due > now)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(time_of_day: &str, days_of_week: &[u32]) -> ReminderConfig {
+        ReminderConfig { enabled: true, time_of_day: time_of_day.to_string(), days_of_week: days_of_week.to_vec() }
+    }
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_next_due_respects_selected_weekdays() {
+        let offset = FixedOffset::east_opt(0).unwrap();
+        // 2024-06-05 是周三
+        let now = utc("2024-06-05T10:00:00Z");
+
+        assert_eq!(next_due(&config("21:00", &[]), now, offset), Some(utc("2024-06-05T21:00:00Z")));
+        assert_eq!(next_due(&config("09:00", &[]), now, offset), Some(utc("2024-06-06T09:00:00Z")));
+        assert_eq!(next_due(&config("21:00", &[1, 5]), now, offset), Some(utc("2024-06-07T21:00:00Z")));
+        // 只选了周三且今天已过，顺延到下周三
+        assert_eq!(next_due(&config("09:00", &[3]), now, offset), Some(utc("2024-06-12T09:00:00Z")));
+        // 恰好到点时计算的是下一次
+        assert_eq!(next_due(&config("10:00", &[3]), now, offset), Some(utc("2024-06-12T10:00:00Z")));
+
+        assert_eq!(next_due(&config("21:00", &[0, 8]), now, offset), None);
+        assert_eq!(next_due(&config("9pm", &[]), now, offset), None);
+        assert_eq!(next_due(&ReminderConfig { enabled: false, ..config("21:00", &[]) }, now, offset), None);
+    }
+
+    #[test]
+    fn test_next_due_rolls_over_midnight_in_local_time() {
+        let offset = FixedOffset::east_opt(8 * 3600).unwrap();
+        // 本地时间 2024-06-08 周六 23:30
+        let now = utc("2024-06-08T15:30:00Z");
+
+        assert_eq!(next_due(&config("00:15", &[]), now, offset), Some(utc("2024-06-08T16:15:00Z")));
+        assert_eq!(next_due(&config("21:00", &[]), now, offset), Some(utc("2024-06-09T13:00:00Z")));
+        // 周日的提醒：本地已是周六深夜，下一次为本地周日
+        assert_eq!(next_due(&config("07:00", &[7]), now, offset), Some(utc("2024-06-08T23:00:00Z")));
+        assert_eq!(next_due(&config("23:00", &[6]), now, offset), Some(utc("2024-06-15T15:00:00Z")));
+    }
+}
@@ -0,0 +1,413 @@
+/**
+ * 搜索辅助模块
+ * 提供大小写不敏感的文本匹配定位和布尔查询表达式
+ */
+
+use anyhow::{Result, anyhow};
+
+/// 单个匹配位置，同时记录字节偏移和字符偏移
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextMatch {
+    pub byte_start: usize,
+    pub byte_end: usize,
+    pub char_start: usize,
+    pub char_end: usize,
+}
+
+/// 大小写折叠单个字符
+fn fold_char(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+/// 大小写不敏感地查找所有不重叠的匹配（相邻匹配会分别报告）
+pub fn find_all_matches(haystack: &str, needle: &str) -> Vec<TextMatch> {
+    let needle: Vec<char> = needle.chars().map(fold_char).collect();
+    if needle.is_empty() {
+        return Vec::new();
+    }
+
+    let chars: Vec<(usize, char)> = haystack.char_indices().collect();
+    let mut matches = Vec::new();
+    let mut i = 0;
+
+    while i + needle.len() <= chars.len() {
+        let is_match = chars[i..i + needle.len()]
+            .iter()
+            .zip(&needle)
+            .all(|((_, c), n)| fold_char(*c) == *n);
+
+        if is_match {
+            let end = i + needle.len();
+            matches.push(TextMatch {
+                byte_start: chars[i].0,
+                byte_end: chars.get(end).map(|(b, _)| *b).unwrap_or(haystack.len()),
+                char_start: i,
+                char_end: end,
+            });
+            i = end;
+        } else {
+            i += 1;
+        }
+    }
+
+    matches
+}
+
+/// 大小写不敏感地查找所有匹配的字节偏移范围（左闭右开）
+pub fn find_byte_ranges(haystack: &str, needle: &str) -> Vec<(usize, usize)> {
+    find_all_matches(haystack, needle)
+        .into_iter()
+        .map(|m| (m.byte_start, m.byte_end))
+        .collect()
+}
+
+/// 布尔查询表达式
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryExpr {
+    Term(String),
+    And(Box<QueryExpr>, Box<QueryExpr>),
+    Or(Box<QueryExpr>, Box<QueryExpr>),
+    Not(Box<QueryExpr>),
+}
+
+impl QueryExpr {
+    /// 判断文本是否满足表达式（关键词大小写不敏感）
+    pub fn matches(&self, text: &str) -> bool {
+        match self {
+            QueryExpr::Term(term) => !find_all_matches(text, term).is_empty(),
+            QueryExpr::And(left, right) => left.matches(text) && right.matches(text),
+            QueryExpr::Or(left, right) => left.matches(text) || right.matches(text),
+            QueryExpr::Not(inner) => !inner.matches(text),
+        }
+    }
+}
+
+/// 查询词法单元
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum QueryToken {
+    Term(String),
+    And,
+    Or,
+    Not,
+    LParen,
+    RParen,
+}
+
+/// 将查询字符串切分为词法单元
+///
+/// 关键词可以用双引号包裹以包含空格；大写的 `AND`/`OR`/`NOT` 为运算符。
+fn tokenize_query(input: &str) -> Result<Vec<QueryToken>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '(' {
+            chars.next();
+            tokens.push(QueryToken::LParen);
+        } else if c == ')' {
+            chars.next();
+            tokens.push(QueryToken::RParen);
+        } else if c == '"' {
+            chars.next();
+            let mut term = String::new();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some(c) => term.push(c),
+                    None => return Err(anyhow!("Unterminated quote")),
+                }
+            }
+            if term.is_empty() {
+                return Err(anyhow!("Empty quoted term"));
+            }
+            tokens.push(QueryToken::Term(term));
+        } else {
+            let mut word = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() || c == '(' || c == ')' || c == '"' {
+                    break;
+                }
+                word.push(c);
+                chars.next();
+            }
+            tokens.push(match word.as_str() {
+                "AND" => QueryToken::And,
+                "OR" => QueryToken::Or,
+                "NOT" => QueryToken::Not,
+                _ => QueryToken::Term(word),
+            });
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// 递归下降解析器
+///
+/// 优先级从低到高：`OR`、`AND`/二元 `NOT`（即 AND NOT）、一元 `NOT`、括号。
+struct QueryParser {
+    tokens: Vec<QueryToken>,
+    pos: usize,
+}
+
+impl QueryParser {
+    fn peek(&self) -> Option<&QueryToken> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<QueryToken> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn parse_or(&mut self) -> Result<QueryExpr> {
+        let mut expr = self.parse_and()?;
+        while self.peek() == Some(&QueryToken::Or) {
+            self.next();
+            expr = QueryExpr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<QueryExpr> {
+        let mut expr = self.parse_unary()?;
+        loop {
+            match self.peek() {
+                Some(QueryToken::And) => {
+                    self.next();
+                    expr = QueryExpr::And(Box::new(expr), Box::new(self.parse_unary()?));
+                }
+                Some(QueryToken::Not) => {
+                    self.next();
+                    let negated = QueryExpr::Not(Box::new(self.parse_unary()?));
+                    expr = QueryExpr::And(Box::new(expr), Box::new(negated));
+                }
+                _ => return Ok(expr),
+            }
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<QueryExpr> {
+        match self.next() {
+            Some(QueryToken::Not) => Ok(QueryExpr::Not(Box::new(self.parse_unary()?))),
+            Some(QueryToken::Term(term)) => Ok(QueryExpr::Term(term)),
+            Some(QueryToken::LParen) => {
+                let expr = self.parse_or()?;
+                match self.next() {
+                    Some(QueryToken::RParen) => Ok(expr),
+                    _ => Err(anyhow!("Missing closing parenthesis")),
+                }
+            }
+            Some(token) => Err(anyhow!("Unexpected {:?} at position {}", token, self.pos)),
+            None => Err(anyhow!("Unexpected end of query")),
+        }
+    }
+}
+
+/// 解析布尔查询表达式，例如 `"dream" AND "rain" NOT "work"`
+pub fn parse_query(input: &str) -> Result<QueryExpr> {
+    let tokens = tokenize_query(input)?;
+    if tokens.is_empty() {
+        return Err(anyhow!("Empty query"));
+    }
+
+    let mut parser = QueryParser { tokens, pos: 0 };
+    let expr = parser.parse_or()?;
+    if let Some(token) = parser.peek() {
+        return Err(anyhow!("Unexpected {:?} at position {}", token, parser.pos + 1));
+    }
+
+    Ok(expr)
+}
+
+/// 简单关键词查询的词法单元
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum KeywordToken {
+    Term { text: String, negated: bool },
+    Or,
+}
+
+/// 按空白切分关键词查询，支持双引号和前缀 `-`
+fn tokenize_keywords(input: &str) -> Result<Vec<KeywordToken>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        let negated = c == '-';
+        if negated {
+            chars.next();
+        }
+
+        let quoted = chars.peek() == Some(&'"');
+        let mut text = String::new();
+        if quoted {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some(c) => text.push(c),
+                    None => return Err(anyhow!("Unterminated quote")),
+                }
+            }
+        } else {
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() {
+                    break;
+                }
+                text.push(c);
+                chars.next();
+            }
+        }
+
+        if text.is_empty() {
+            return Err(anyhow!("Empty search term"));
+        }
+        if text == "OR" && !quoted && !negated {
+            tokens.push(KeywordToken::Or);
+        } else {
+            tokens.push(KeywordToken::Term { text, negated });
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// 解析简单关键词查询：空格分隔的词须同时出现，`OR` 取并集，前缀 `-` 表示排除
+///
+/// `OR` 的优先级低于隐式的 AND，例如 `beach sun OR snow -winter` 等价于
+/// `(beach AND sun) OR (snow AND NOT winter)`。关键词可用双引号包裹以包含空格。
+pub fn parse_keyword_query(input: &str) -> Result<QueryExpr> {
+    let tokens = tokenize_keywords(input)?;
+    if tokens.is_empty() {
+        return Err(anyhow!("Empty query"));
+    }
+
+    let mut groups = Vec::new();
+    for group in tokens.split(|token| *token == KeywordToken::Or) {
+        let expr = group
+            .iter()
+            .map(|token| match token {
+                KeywordToken::Term { text, negated: false } => QueryExpr::Term(text.clone()),
+                KeywordToken::Term { text, negated: true } => QueryExpr::Not(Box::new(QueryExpr::Term(text.clone()))),
+                KeywordToken::Or => unreachable!("split removes OR tokens"),
+            })
+            .reduce(|left, right| QueryExpr::And(Box::new(left), Box::new(right)))
+            .ok_or_else(|| anyhow!("OR must be between two search terms"))?;
+        groups.push(expr);
+    }
+
+    Ok(groups
+        .into_iter()
+        .reduce(|left, right| QueryExpr::Or(Box::new(left), Box::new(right)))
+        .expect("at least one group"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_all_matches_case_insensitive() {
+        let matches = find_all_matches("Rain, rain, go away", "RAIN");
+        assert_eq!(matches.len(), 2);
+        assert_eq!((matches[1].char_start, matches[1].char_end), (6, 10));
+    }
+
+    #[test]
+    fn test_find_byte_ranges_repeated_cjk_keyword() {
+        let text = "Spring 春雨，春雨春雨 spring";
+        let ranges = find_byte_ranges(text, "春雨");
+
+        assert_eq!(ranges, vec![(7, 13), (16, 22), (22, 28)]);
+        for (start, end) in ranges {
+            assert_eq!(&text[start..end], "春雨");
+        }
+        assert_eq!(find_byte_ranges(text, "SPRING"), vec![(0, 6), (29, 35)]);
+    }
+
+    #[test]
+    fn test_find_all_matches_cjk_offsets() {
+        let matches = find_all_matches("桃花开了，桃花桃花", "桃花");
+        let chars: Vec<(usize, usize)> = matches.iter().map(|m| (m.char_start, m.char_end)).collect();
+        let bytes: Vec<(usize, usize)> = matches.iter().map(|m| (m.byte_start, m.byte_end)).collect();
+
+        assert_eq!(chars, vec![(0, 2), (5, 7), (7, 9)]);
+        assert_eq!(bytes, vec![(0, 6), (15, 21), (21, 27)]);
+    }
+
+    #[test]
+    fn test_parse_query_and_not() {
+        let expr = parse_query(r#""dream" AND "rain" NOT "work""#).unwrap();
+        assert_eq!(expr, QueryExpr::And(
+            Box::new(QueryExpr::And(
+                Box::new(QueryExpr::Term("dream".to_string())),
+                Box::new(QueryExpr::Term("rain".to_string())),
+            )),
+            Box::new(QueryExpr::Not(Box::new(QueryExpr::Term("work".to_string())))),
+        ));
+
+        assert!(expr.matches("A Dream about the rain"));
+        assert!(!expr.matches("dream of rain at work"));
+        assert!(!expr.matches("just a dream"));
+    }
+
+    #[test]
+    fn test_parse_query_or_and_parentheses() {
+        let expr = parse_query(r#"(桃花 OR "spring rain") AND NOT 工作"#).unwrap();
+        assert!(expr.matches("桃花盛开"));
+        assert!(expr.matches("Spring Rain falls"));
+        assert!(!expr.matches("桃花与工作"));
+        assert!(!expr.matches("spring"));
+    }
+
+    #[test]
+    fn test_keyword_query_or() {
+        let expr = parse_keyword_query("joy OR sadness").unwrap();
+        assert_eq!(expr, QueryExpr::Or(
+            Box::new(QueryExpr::Term("joy".to_string())),
+            Box::new(QueryExpr::Term("sadness".to_string())),
+        ));
+        assert!(expr.matches("Pure JOY today"));
+        assert!(expr.matches("a quiet sadness"));
+        assert!(!expr.matches("calm"));
+    }
+
+    #[test]
+    fn test_keyword_query_negation() {
+        let expr = parse_keyword_query("beach -winter").unwrap();
+        assert!(expr.matches("summer beach"));
+        assert!(!expr.matches("beach in winter"));
+        assert!(!expr.matches("winter"));
+        assert!(!expr.matches("mountains"));
+    }
+
+    #[test]
+    fn test_keyword_query_mixed() {
+        let expr = parse_keyword_query(r#"beach sun OR "spring rain" -桃花"#).unwrap();
+        assert!(expr.matches("beach and sun"));
+        assert!(!expr.matches("beach at night"));
+        assert!(expr.matches("Spring Rain in the evening"));
+        assert!(!expr.matches("spring rain on 桃花"));
+        // 减号只在词首表示排除
+        assert!(parse_keyword_query("well-being").unwrap().matches("my well-being"));
+
+        for input in ["", "  ", "OR joy", "joy OR", "joy OR OR sadness", "-", r#"-"""#, r#""beach"#] {
+            assert!(parse_keyword_query(input).is_err(), "expected error for {:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_parse_query_rejects_malformed() {
+        for input in ["", "   ", r#""dream"#, "dream AND", "OR rain", "(dream", "dream)", r#""""#, "dream rain"] {
+            assert!(parse_query(input).is_err(), "expected error for {:?}", input);
+        }
+    }
+}
@@ -0,0 +1,176 @@
+/**
+ * 解锁会话模块
+ * 在内存中缓存解锁后的密码，超过自动锁定时间无活动则清除
+ */
+
+use chrono::{DateTime, Duration, Utc};
+
+/// 已解锁的保险库会话
+#[derive(Debug, Default)]
+pub struct VaultSession {
+    password: Option<String>,
+    /// 每次解锁随机生成的令牌，用作解密结果缓存的键，锁定后作废
+    token: Option<u128>,
+    last_access: Option<DateTime<Utc>>,
+    /// 自动锁定时间（分钟），0 表示不自动锁定
+    auto_lock_minutes: u32,
+}
+
+impl VaultSession {
+    /// 缓存已验证的密码
+    pub fn unlock(&mut self, password: String, now: DateTime<Utc>) {
+        self.password = Some(password);
+        self.token = Some(uuid::Uuid::new_v4().as_u128());
+        self.last_access = Some(now);
+    }
+
+    /// 设置访问时检查的自动锁定时间（分钟），0 表示不自动锁定
+    pub fn set_auto_lock_minutes(&mut self, minutes: u32) {
+        self.auto_lock_minutes = minutes;
+    }
+
+    /// 清除缓存的密码
+    pub fn lock(&mut self) {
+        self.password = None;
+        self.token = None;
+        self.last_access = None;
+    }
+
+    /// 是否已解锁
+    pub fn is_unlocked(&self) -> bool {
+        self.password.is_some()
+    }
+
+    /// 缓存的密码（不刷新访问时间）
+    pub fn password(&self) -> Option<&str> {
+        self.password.as_deref()
+    }
+
+    /// 本次解锁的会话令牌，仅当 `password` 与缓存的密码一致时返回
+    pub fn token_for(&self, password: &str) -> Option<u128> {
+        self.token.filter(|_| self.password.as_deref() == Some(password))
+    }
+
+    /// 记录一次用户活动，推迟自动锁定（已超时则先锁定）
+    pub fn touch(&mut self, now: DateTime<Utc>) {
+        self.check_auto_lock(now, self.auto_lock_minutes);
+        if self.is_unlocked() {
+            self.last_access = Some(now);
+        }
+    }
+
+    /// 使用缓存的密码，并刷新最后访问时间
+    ///
+    /// 距上次活动已超过自动锁定时间时先锁定，返回 None。
+    pub fn access(&mut self, now: DateTime<Utc>) -> Option<String> {
+        self.touch(now);
+        self.password.clone()
+    }
+
+    /// 超过 `auto_lock_minutes` 未访问时锁定，返回本次是否发生锁定
+    ///
+    /// `auto_lock_minutes` 为 0 表示不自动锁定。
+    pub fn check_auto_lock(&mut self, now: DateTime<Utc>, auto_lock_minutes: u32) -> bool {
+        if auto_lock_minutes == 0 || !self.is_unlocked() {
+            return false;
+        }
+
+        let expired = self.last_access
+            .is_none_or(|last| now - last >= Duration::minutes(auto_lock_minutes as i64));
+        if expired {
+            self.lock();
+        }
+        expired
+    }
+}
+
+/// 界面只读锁：审阅或在共享设备上使用时禁止修改条目，读取不受影响
+#[derive(Debug, Default)]
+pub struct UiLock {
+    locked: bool,
+}
+
+impl UiLock {
+    /// 锁定为只读
+    pub fn lock(&mut self) {
+        self.locked = true;
+    }
+
+    /// 解除只读锁定
+    pub fn unlock(&mut self) {
+        self.locked = false;
+    }
+
+    /// 是否处于只读状态
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_auto_lock_after_timeout() {
+        let start = Utc::now();
+        let mut session = VaultSession::default();
+        assert!(!session.check_auto_lock(start, 5));
+
+        session.unlock("secret".to_string(), start);
+        assert!(!session.check_auto_lock(start + Duration::minutes(4), 5));
+
+        // 访问会推迟锁定
+        assert_eq!(session.access(start + Duration::minutes(4)).as_deref(), Some("secret"));
+        assert!(!session.check_auto_lock(start + Duration::minutes(8), 5));
+
+        assert!(session.check_auto_lock(start + Duration::minutes(9), 5));
+        assert!(!session.is_unlocked());
+        assert!(session.access(start + Duration::minutes(9)).is_none());
+        assert!(!session.check_auto_lock(start + Duration::minutes(20), 5));
+    }
+
+    #[test]
+    fn test_token_changes_per_unlock() {
+        let now = Utc::now();
+        let mut session = VaultSession::default();
+        assert!(session.token_for("secret").is_none());
+
+        session.unlock("secret".to_string(), now);
+        let first = session.token_for("secret").unwrap();
+        assert!(session.token_for("wrong").is_none());
+
+        session.lock();
+        assert!(session.token_for("secret").is_none());
+        session.unlock("secret".to_string(), now);
+        assert_ne!(session.token_for("secret"), Some(first));
+    }
+
+    #[test]
+    fn test_zero_minutes_disables_auto_lock() {
+        let start = Utc::now();
+        let mut session = VaultSession::default();
+        session.unlock("secret".to_string(), start);
+
+        assert!(!session.check_auto_lock(start + Duration::days(30), 0));
+        assert!(session.is_unlocked());
+    }
+
+    #[test]
+    fn test_access_locks_after_inactivity() {
+        let start = Utc::now();
+        let mut session = VaultSession::default();
+        session.set_auto_lock_minutes(5);
+        session.unlock("secret".to_string(), start);
+
+        // 活动会推迟锁定
+        session.touch(start + Duration::minutes(3));
+        assert_eq!(session.access(start + Duration::minutes(7)).as_deref(), Some("secret"));
+
+        // 超时后访问不再返回密码，需重新解锁
+        assert!(session.access(start + Duration::minutes(13)).is_none());
+        assert!(!session.is_unlocked());
+        session.touch(start + Duration::minutes(14));
+        assert!(!session.is_unlocked());
+    }
+}
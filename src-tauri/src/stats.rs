@@ -0,0 +1,546 @@
+/**
+ * 统计模块
+ * 基于用户时区计算周期汇总等统计数据
+ */
+
+use crate::models::{DateRange, EmotionCount, EmotionPair, EmotionTag, Locale, MemoryEntry, MemoryStats, MonthBucket, MonthGroup, StatsExport, UserSettings, WeekStart, WeekSummary, WritingStreak};
+use std::collections::{BTreeMap, HashMap};
+use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveDate, TimeZone, Utc};
+
+/// 计算包含 `now` 的那一周（偏移 `week_offset` 周）的起止时间，左闭右开
+pub fn week_range(
+    now: DateTime<Utc>,
+    offset: FixedOffset,
+    week_start: WeekStart,
+    week_offset: i32,
+) -> (DateTime<Utc>, DateTime<Utc>) {
+    let today = now.with_timezone(&offset).date_naive();
+    let days_since_start = match week_start {
+        WeekStart::Monday => today.weekday().num_days_from_monday(),
+        WeekStart::Sunday => today.weekday().num_days_from_sunday(),
+    };
+
+    let start_date = today - Duration::days(days_since_start as i64) + Duration::weeks(week_offset as i64);
+    let start = local_midnight(start_date, offset);
+    (start, start + Duration::weeks(1))
+}
+
+/// 将本地日期的零点转换为 UTC 时间
+fn local_midnight(date: NaiveDate, offset: FixedOffset) -> DateTime<Utc> {
+    let midnight = date.and_hms_opt(0, 0, 0).expect("midnight is always valid");
+    offset
+        .from_local_datetime(&midnight)
+        .single()
+        .expect("fixed offsets are unambiguous")
+        .with_timezone(&Utc)
+}
+
+/// 统计出现次数最多的情感标签（次数相同时取最早出现的）
+pub fn dominant_emotion<'a, I>(entries: I) -> Option<EmotionTag>
+where
+    I: IntoIterator<Item = &'a MemoryEntry>,
+{
+    let mut counts: Vec<(EmotionTag, u32)> = Vec::new();
+    for tag in entries.into_iter().flat_map(|e| e.emotion_tags.iter()) {
+        match counts.iter_mut().find(|(t, _)| t == tag) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((tag.clone(), 1)),
+        }
+    }
+
+    counts.into_iter()
+        .fold(None, |best: Option<(EmotionTag, u32)>, (tag, count)| match best {
+            Some((_, best_count)) if best_count >= count => best,
+            _ => Some((tag, count)),
+        })
+        .map(|(tag, _)| tag)
+}
+
+/// 统计情感标签两两共同出现的次数，按次数降序、标签字母序排列
+///
+/// (Joy, Hope) 与 (Hope, Joy) 计入同一组，同一标签与自身不配对。
+pub fn emotion_cooccurrence(entries: &[MemoryEntry]) -> Vec<EmotionPair> {
+    let mut counts: HashMap<(EmotionTag, EmotionTag), u32> = HashMap::new();
+    for entry in entries {
+        let mut tags: Vec<&EmotionTag> = entry.emotion_tags.iter().collect();
+        tags.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+        tags.dedup();
+
+        for (i, first) in tags.iter().enumerate() {
+            for second in &tags[i + 1..] {
+                *counts.entry(((*first).clone(), (*second).clone())).or_insert(0) += 1;
+            }
+        }
+    }
+
+    let mut pairs: Vec<EmotionPair> = counts
+        .into_iter()
+        .map(|((first, second), count)| EmotionPair { first, second, count })
+        .collect();
+    pairs.sort_by(|a, b| {
+        b.count.cmp(&a.count)
+            .then_with(|| a.first.as_str().cmp(b.first.as_str()))
+            .then_with(|| a.second.as_str().cmp(b.second.as_str()))
+    });
+    pairs
+}
+
+/// 统计时间范围内（含两端）出现最多的情感标签，按条目数降序、标签字母序排列，最多 `limit` 个
+pub fn top_emotions(entries: &[MemoryEntry], period: Option<&DateRange>, limit: usize) -> Vec<EmotionCount> {
+    let mut counts: HashMap<&EmotionTag, u32> = HashMap::new();
+    for entry in entries {
+        if period.is_some_and(|p| entry.created_at < p.start || entry.created_at > p.end) {
+            continue;
+        }
+        for tag in &entry.emotion_tags {
+            *counts.entry(tag).or_insert(0) += 1;
+        }
+    }
+
+    let mut top: Vec<EmotionCount> = counts
+        .into_iter()
+        .map(|(emotion, count)| EmotionCount { emotion: emotion.clone(), count })
+        .collect();
+    top.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.emotion.as_str().cmp(b.emotion.as_str())));
+    top.truncate(limit);
+    top
+}
+
+/// 条目字数（优先使用元数据中的字数）
+pub fn entry_word_count(entry: &MemoryEntry) -> u32 {
+    entry.metadata
+        .as_ref()
+        .and_then(|m| m.word_count)
+        .unwrap_or_else(|| entry.content.chars().count() as u32)
+}
+
+/// 生成指定周的回顾汇总
+pub fn week_summary(
+    entries: &[MemoryEntry],
+    now: DateTime<Utc>,
+    settings: &UserSettings,
+    week_offset: i32,
+) -> WeekSummary {
+    let (start, end) = week_range(now, settings.utc_offset(), settings.week_start, week_offset);
+
+    let mut week_entries: Vec<MemoryEntry> = entries
+        .iter()
+        .filter(|e| e.created_at >= start && e.created_at < end)
+        .cloned()
+        .collect();
+    week_entries.sort_by_key(|e| e.created_at);
+
+    WeekSummary {
+        week_start: start,
+        week_end: end,
+        entry_count: week_entries.len() as u32,
+        total_words: week_entries.iter().map(entry_word_count).sum(),
+        dominant_emotion: dominant_emotion(&week_entries),
+        entries: week_entries,
+    }
+}
+
+/// 计算连续写作天数
+///
+/// 按用户时区划分日期，当天有任意条目即视为活跃。最近活跃日为今天或昨天时，
+/// 当前连续天数才计入，否则为 0。
+pub fn writing_streak(entries: &[MemoryEntry], now: DateTime<Utc>, offset: FixedOffset) -> WritingStreak {
+    let mut days: Vec<NaiveDate> = entries
+        .iter()
+        .map(|e| e.created_at.with_timezone(&offset).date_naive())
+        .collect();
+    days.sort();
+    days.dedup();
+
+    let mut longest = 0;
+    let mut run = 0;
+    let mut previous: Option<NaiveDate> = None;
+    for &day in &days {
+        run = match previous {
+            Some(prev) if day - prev == Duration::days(1) => run + 1,
+            _ => 1,
+        };
+        longest = longest.max(run);
+        previous = Some(day);
+    }
+
+    let today = now.with_timezone(&offset).date_naive();
+    let current = match previous {
+        Some(last) if today - last <= Duration::days(1) => run,
+        _ => 0,
+    };
+
+    WritingStreak {
+        current_streak: current,
+        longest_streak: longest,
+        last_entry_date: previous,
+    }
+}
+
+/// 计算整体统计数据（月份和连续天数按用户时区计算，月份显示名按 `locale` 格式化）
+pub fn compute_stats(entries: &[MemoryEntry], now: DateTime<Utc>, offset: FixedOffset, locale: Locale) -> MemoryStats {
+    let mut entries_by_type = HashMap::new();
+    let mut entries_by_emotion = HashMap::new();
+    let mut emotion_intensity_totals = HashMap::new();
+    let mut entries_by_month = HashMap::new();
+    let mut months: BTreeMap<(i32, u32), u32> = BTreeMap::new();
+    let mut entries_by_mood = HashMap::new();
+
+    for entry in entries {
+        *entries_by_type.entry(entry.memory_type.clone()).or_insert(0) += 1;
+        for tag in &entry.emotion_tags {
+            *entries_by_emotion.entry(tag.clone()).or_insert(0) += 1;
+            *emotion_intensity_totals.entry(tag.clone()).or_insert(0) += entry.emotion_intensity(tag) as u32;
+        }
+        let created = entry.created_at.with_timezone(&offset);
+        *entries_by_month.entry(created.format("%Y-%m").to_string()).or_insert(0) += 1;
+        *months.entry((created.year(), created.month())).or_insert(0) += 1;
+        if let Some(mood) = entry.mood() {
+            *entries_by_mood.entry(mood.to_string()).or_insert(0) += 1;
+        }
+    }
+
+    let total_entries = entries.len() as u32;
+    let total_words: u32 = entries.iter().map(entry_word_count).sum();
+    let streak = writing_streak(entries, now, offset);
+
+    MemoryStats {
+        total_entries,
+        total_words,
+        average_words_per_entry: if total_entries == 0 { 0.0 } else { total_words as f32 / total_entries as f32 },
+        entries_by_type,
+        entries_by_emotion,
+        emotion_intensity_totals,
+        entries_by_month,
+        months: months
+            .into_iter()
+            .map(|((year, month), count)| MonthBucket {
+                key: format!("{:04}-{:02}", year, month),
+                label: locale.month_label(year, month),
+                count,
+            })
+            .collect(),
+        entries_by_mood,
+        longest_streak: streak.longest_streak,
+        current_streak: streak.current_streak,
+    }
+}
+
+/// 生成导出用的统计文档
+pub fn stats_export(entries: &[MemoryEntry], now: DateTime<Utc>, offset: FixedOffset, locale: Locale) -> StatsExport {
+    StatsExport {
+        generated_at: now,
+        entry_count: entries.len() as u32,
+        stats: compute_stats(entries, now, offset, locale),
+        last_entry_date: writing_streak(entries, now, offset).last_entry_date,
+    }
+}
+
+/// 按本地月份分组条目：月份从新到旧，月内按创建时间从早到晚
+pub fn group_by_month(entries: &[MemoryEntry], offset: FixedOffset) -> Vec<MonthGroup> {
+    let mut sorted: Vec<&MemoryEntry> = entries.iter().collect();
+    sorted.sort_by_key(|e| e.created_at);
+
+    let mut groups: Vec<MonthGroup> = Vec::new();
+    for entry in sorted {
+        let month = entry.created_at.with_timezone(&offset).format("%Y-%m").to_string();
+        match groups.last_mut() {
+            Some(group) if group.month == month => group.entries.push(entry.clone()),
+            _ => groups.push(MonthGroup { month, entries: vec![entry.clone()] }),
+        }
+    }
+
+    groups.reverse();
+    groups
+}
+
+/// 历史上的今天：返回往年同一月日（按用户时区）创建的条目，年份从近到远
+///
+/// `match_leap_day` 为 true 且 `today` 是平年的 2 月 28 日时，同时返回往年 2 月 29 日的条目。
+/// 已归档条目不参与。
+pub fn on_this_day(entries: &[MemoryEntry], today: NaiveDate, offset: FixedOffset, match_leap_day: bool) -> Vec<MemoryEntry> {
+    let include_leap_day = match_leap_day
+        && (today.month(), today.day()) == (2, 28)
+        && NaiveDate::from_ymd_opt(today.year(), 2, 29).is_none();
+
+    let mut matched: Vec<MemoryEntry> = entries.iter()
+        .filter(|e| !e.is_archived)
+        .filter(|e| {
+            let date = e.created_at.with_timezone(&offset).date_naive();
+            let same_day = (date.month(), date.day()) == (today.month(), today.day());
+            let leap_day = include_leap_day && (date.month(), date.day()) == (2, 29);
+            date.year() < today.year() && (same_day || leap_day)
+        })
+        .cloned()
+        .collect();
+
+    matched.sort_by_key(|e| std::cmp::Reverse(e.created_at));
+    matched
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::MemoryType;
+
+    fn entry_at(created_at: &str, tags: Vec<EmotionTag>) -> MemoryEntry {
+        let mut entry = MemoryEntry::new("标题".to_string(), "春日桃花".to_string(), MemoryType::Text);
+        entry.created_at = created_at.parse().unwrap();
+        entry.emotion_tags = tags;
+        entry
+    }
+
+    #[test]
+    fn test_week_range_respects_timezone_and_start_day() {
+        // 2024-05-12 为周日；UTC 16:30 在东八区已是周一
+        let now: DateTime<Utc> = "2024-05-12T16:30:00Z".parse().unwrap();
+        let offset = FixedOffset::east_opt(8 * 3600).unwrap();
+
+        let (start, end) = week_range(now, offset, WeekStart::Monday, 0);
+        assert_eq!(start.to_rfc3339(), "2024-05-12T16:00:00+00:00");
+        assert_eq!(end - start, Duration::weeks(1));
+
+        let (start, _) = week_range(now, offset, WeekStart::Sunday, -1);
+        assert_eq!(start.to_rfc3339(), "2024-05-04T16:00:00+00:00");
+    }
+
+    #[test]
+    fn test_emotion_cooccurrence_normalizes_pairs() {
+        let entries = vec![
+            entry_at("2024-05-01T00:00:00Z", vec![EmotionTag::Joy, EmotionTag::Hope]),
+            entry_at("2024-05-02T00:00:00Z", vec![EmotionTag::Hope, EmotionTag::Joy, EmotionTag::Nostalgia]),
+            entry_at("2024-05-03T00:00:00Z", vec![EmotionTag::Joy, EmotionTag::Joy]),
+            entry_at("2024-05-04T00:00:00Z", vec![EmotionTag::Sadness]),
+        ];
+
+        let pairs = emotion_cooccurrence(&entries);
+        let summary: Vec<(&str, &str, u32)> = pairs
+            .iter()
+            .map(|p| (p.first.as_str(), p.second.as_str(), p.count))
+            .collect();
+        assert_eq!(summary, vec![
+            ("hope", "joy", 2),
+            ("hope", "nostalgia", 1),
+            ("joy", "nostalgia", 1),
+        ]);
+        assert!(emotion_cooccurrence(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_top_emotions_within_period() {
+        let entries = vec![
+            entry_at("2024-04-30T23:00:00Z", vec![EmotionTag::Sadness, EmotionTag::Regret]),
+            entry_at("2024-05-01T00:00:00Z", vec![EmotionTag::Joy, EmotionTag::Hope]),
+            entry_at("2024-05-10T00:00:00Z", vec![EmotionTag::Hope]),
+            entry_at("2024-05-20T00:00:00Z", vec![EmotionTag::Hope, EmotionTag::Nostalgia]),
+        ];
+        let may = DateRange {
+            start: "2024-05-01T00:00:00Z".parse().unwrap(),
+            end: "2024-05-31T23:59:59Z".parse().unwrap(),
+        };
+
+        let summary = |counts: &[EmotionCount]| counts.iter().map(|c| (c.emotion.as_str().to_string(), c.count)).collect::<Vec<_>>();
+        assert_eq!(summary(&top_emotions(&entries, Some(&may), 2)), vec![("hope".to_string(), 3), ("joy".to_string(), 1)]);
+        assert_eq!(top_emotions(&entries, Some(&may), 10).len(), 3);
+        assert_eq!(top_emotions(&entries, None, 10).len(), 5);
+        assert!(top_emotions(&entries, Some(&may), 0).is_empty());
+    }
+
+    #[test]
+    fn test_week_summary_counts_and_dominant_emotion() {
+        let entries = vec![
+            entry_at("2024-05-06T09:00:00Z", vec![EmotionTag::Joy, EmotionTag::Hope]),
+            entry_at("2024-05-08T09:00:00Z", vec![EmotionTag::Hope]),
+            entry_at("2024-05-10T09:00:00Z", vec![EmotionTag::Joy, EmotionTag::Hope]),
+            entry_at("2024-04-30T09:00:00Z", vec![EmotionTag::Sadness]),
+        ];
+        let now: DateTime<Utc> = "2024-05-15T12:00:00Z".parse().unwrap();
+
+        let summary = week_summary(&entries, now, &UserSettings::default(), -1);
+        assert_eq!(summary.entry_count, 3);
+        assert_eq!(summary.total_words, 12);
+        assert_eq!(summary.dominant_emotion, Some(EmotionTag::Hope));
+
+        let empty = week_summary(&entries, now, &UserSettings::default(), 0);
+        assert_eq!(empty.entry_count, 0);
+        assert_eq!(empty.dominant_emotion, None);
+    }
+
+    #[test]
+    fn test_writing_streak_handles_gaps_and_timezone() {
+        let offset = FixedOffset::east_opt(8 * 3600).unwrap();
+        let entries = vec![
+            entry_at("2024-05-01T09:00:00Z", vec![]),
+            entry_at("2024-05-02T09:00:00Z", vec![]),
+            entry_at("2024-05-02T10:00:00Z", vec![]),
+            entry_at("2024-05-03T09:00:00Z", vec![]),
+            // 间隔一天
+            entry_at("2024-05-05T09:00:00Z", vec![]),
+            // UTC 5 日 18:00 在东八区已是 6 日
+            entry_at("2024-05-05T18:00:00Z", vec![]),
+        ];
+
+        let now: DateTime<Utc> = "2024-05-06T12:00:00Z".parse().unwrap();
+        let streak = writing_streak(&entries, now, offset);
+        assert_eq!(streak.longest_streak, 3);
+        assert_eq!(streak.current_streak, 2);
+        assert_eq!(streak.last_entry_date, NaiveDate::from_ymd_opt(2024, 5, 6));
+
+        // 昨天之后没有写作，当前连续中断
+        let later: DateTime<Utc> = "2024-05-08T12:00:00Z".parse().unwrap();
+        assert_eq!(writing_streak(&entries, later, offset).current_streak, 0);
+
+        let utc_streak = writing_streak(&entries, now, FixedOffset::east_opt(0).unwrap());
+        assert_eq!(utc_streak.current_streak, 1);
+        assert_eq!(utc_streak.longest_streak, 3);
+    }
+
+    #[test]
+    fn test_writing_streak_single_day_and_empty() {
+        let offset = FixedOffset::east_opt(0).unwrap();
+        let now: DateTime<Utc> = "2024-05-01T20:00:00Z".parse().unwrap();
+
+        let single = vec![entry_at("2024-05-01T09:00:00Z", vec![])];
+        let streak = writing_streak(&single, now, offset);
+        assert_eq!((streak.current_streak, streak.longest_streak), (1, 1));
+
+        let empty = writing_streak(&[], now, offset);
+        assert_eq!(empty, WritingStreak { current_streak: 0, longest_streak: 0, last_entry_date: None });
+    }
+
+    #[test]
+    fn test_compute_stats_counts_custom_emotions() {
+        let awe = EmotionTag::Custom("awe".to_string());
+        let entries = vec![
+            entry_at("2024-04-30T20:00:00Z", vec![EmotionTag::Joy, awe.clone()]),
+            entry_at("2024-05-02T09:00:00Z", vec![awe.clone()]),
+            entry_at("2024-05-03T09:00:00Z", vec![]),
+        ];
+        let now: DateTime<Utc> = "2024-05-03T12:00:00Z".parse().unwrap();
+        let offset = FixedOffset::east_opt(8 * 3600).unwrap();
+
+        let stats = compute_stats(&entries, now, offset, Locale::ZhCn);
+        assert_eq!(stats.total_entries, 3);
+        assert_eq!(stats.total_words, 12);
+        assert_eq!(stats.average_words_per_entry, 4.0);
+        assert_eq!(stats.entries_by_emotion.get(&awe), Some(&2));
+        assert_eq!(stats.entries_by_emotion.get(&EmotionTag::Joy), Some(&1));
+        // 东八区下第一条已属于五月
+        assert_eq!(stats.entries_by_month.get("2024-05"), Some(&3));
+        assert_eq!(stats.months, vec![MonthBucket { key: "2024-05".to_string(), label: "2024年5月".to_string(), count: 3 }]);
+        assert_eq!((stats.current_streak, stats.longest_streak), (3, 3));
+
+        let json = serde_json::to_value(&stats).unwrap();
+        assert_eq!(json["entries_by_emotion"]["awe"], 2);
+    }
+
+    #[test]
+    fn test_compute_stats_weights_emotions_by_intensity() {
+        let mut strong = entry_at("2024-05-01T09:00:00Z", vec![]);
+        strong.set_emotion_intensities(HashMap::from([("joy".to_string(), 5)])).unwrap();
+        let mild = entry_at("2024-05-02T09:00:00Z", vec![EmotionTag::Joy, EmotionTag::Hope]);
+        let now: DateTime<Utc> = "2024-05-02T12:00:00Z".parse().unwrap();
+
+        let stats = compute_stats(&[strong, mild], now, FixedOffset::east_opt(0).unwrap(), Locale::default());
+        assert_eq!(stats.entries_by_emotion.get(&EmotionTag::Joy), Some(&2));
+        assert_eq!(stats.emotion_intensity_totals.get(&EmotionTag::Joy), Some(&8));
+        assert_eq!(stats.emotion_intensity_totals.get(&EmotionTag::Hope), Some(&3));
+    }
+
+    #[test]
+    fn test_compute_stats_mood_distribution() {
+        let mut calm = entry_at("2024-05-01T09:00:00Z", vec![]);
+        calm.set_mood(Some("Calm".to_string()));
+        let mut calm_again = entry_at("2024-05-02T09:00:00Z", vec![]);
+        calm_again.set_mood(Some(" calm ".to_string()));
+        let mut restless = entry_at("2024-05-03T09:00:00Z", vec![]);
+        restless.set_mood(Some("Restless".to_string()));
+        let no_mood = entry_at("2024-05-04T09:00:00Z", vec![]);
+        let now: DateTime<Utc> = "2024-05-04T12:00:00Z".parse().unwrap();
+
+        let stats = compute_stats(&[calm, calm_again, restless, no_mood], now, FixedOffset::east_opt(0).unwrap(), Locale::default());
+        assert_eq!(stats.entries_by_mood.len(), 2);
+        assert_eq!(stats.entries_by_mood.get("calm"), Some(&2));
+        assert_eq!(stats.entries_by_mood.get("restless"), Some(&1));
+    }
+
+    #[test]
+    fn test_compute_stats_month_buckets_sorted_with_labels() {
+        let entries = vec![
+            entry_at("2024-01-15T09:00:00Z", vec![]),
+            entry_at("2023-12-31T09:00:00Z", vec![]),
+            entry_at("2024-01-02T09:00:00Z", vec![]),
+            entry_at("2023-05-01T09:00:00Z", vec![]),
+        ];
+        let now: DateTime<Utc> = "2024-01-15T12:00:00Z".parse().unwrap();
+
+        let stats = compute_stats(&entries, now, FixedOffset::east_opt(0).unwrap(), Locale::En);
+        let buckets: Vec<(&str, &str, u32)> = stats.months.iter().map(|m| (m.key.as_str(), m.label.as_str(), m.count)).collect();
+        assert_eq!(buckets, vec![
+            ("2023-05", "May 2023", 1),
+            ("2023-12", "December 2023", 1),
+            ("2024-01", "January 2024", 2),
+        ]);
+        assert_eq!(Locale::ZhCn.month_label(2023, 12), "2023年12月");
+    }
+
+    #[test]
+    fn test_group_by_month_orders_months_and_days() {
+        let entries = vec![
+            entry_at("2024-05-20T09:00:00Z", vec![]),
+            entry_at("2024-03-02T09:00:00Z", vec![]),
+            entry_at("2024-05-03T09:00:00Z", vec![]),
+            // 东八区已是 6 月 1 日
+            entry_at("2024-05-31T18:00:00Z", vec![]),
+        ];
+        let offset = FixedOffset::east_opt(8 * 3600).unwrap();
+
+        let groups = group_by_month(&entries, offset);
+        let months: Vec<&str> = groups.iter().map(|g| g.month.as_str()).collect();
+        assert_eq!(months, vec!["2024-06", "2024-05", "2024-03"]);
+        assert_eq!(groups[1].entries[0].id, entries[2].id);
+        assert_eq!(groups[1].entries[1].id, entries[0].id);
+
+        assert!(group_by_month(&[], offset).is_empty());
+    }
+
+    #[test]
+    fn test_on_this_day_spans_years_and_timezone() {
+        let offset = FixedOffset::east_opt(8 * 3600).unwrap();
+        let entries = vec![
+            entry_at("2021-05-12T03:00:00Z", vec![]),
+            entry_at("2023-05-12T03:00:00Z", vec![]),
+            // UTC 11 日 20:00 在东八区已是 12 日
+            entry_at("2022-05-11T20:00:00Z", vec![]),
+            entry_at("2023-05-13T03:00:00Z", vec![]),
+            // 今年的不算
+            entry_at("2024-05-12T03:00:00Z", vec![]),
+        ];
+        let today = NaiveDate::from_ymd_opt(2024, 5, 12).unwrap();
+
+        let years: Vec<i32> = on_this_day(&entries, today, offset, true)
+            .iter()
+            .map(|e| e.created_at.with_timezone(&offset).year())
+            .collect();
+        assert_eq!(years, vec![2023, 2022, 2021]);
+    }
+
+    #[test]
+    fn test_on_this_day_leap_day() {
+        let utc = FixedOffset::east_opt(0).unwrap();
+        let entries = vec![
+            entry_at("2020-02-29T09:00:00Z", vec![]),
+            entry_at("2022-02-28T09:00:00Z", vec![]),
+        ];
+
+        let non_leap = NaiveDate::from_ymd_opt(2023, 2, 28).unwrap();
+        assert_eq!(on_this_day(&entries, non_leap, utc, true).len(), 2);
+        assert_eq!(on_this_day(&entries, non_leap, utc, false).len(), 1);
+
+        // 闰年的 2 月 28 日只匹配 28 日，29 日另有自己的回顾
+        let leap = NaiveDate::from_ymd_opt(2024, 2, 28).unwrap();
+        assert_eq!(on_this_day(&entries, leap, utc, true).len(), 1);
+        let leap_day = NaiveDate::from_ymd_opt(2024, 2, 29).unwrap();
+        assert_eq!(on_this_day(&entries, leap_day, utc, true)[0].created_at.year(), 2020);
+    }
+}
@@ -3,260 +3,5704 @@
  * 负责管理本地数据的存储和检索
  */
 
-use crate::models::{MemoryEntry, SearchFilter};
-use crate::crypto::{BackendEncryption, EncryptionResult, DecryptionParams};
+use crate::importers::{self, ExternalFormat, GenericFieldMap};
+use crate::html_export;
+use crate::models::{MemoryEntry, MemoryType, EmotionTag, Attachment, AttachmentReport, BulkResult, OrphanReport, OrphanedAttachment, ExportReport, FileSize, StorageReport, StorageUsage, StoreStatus, EncryptedData, SearchFilter, SearchPage, SearchSort, ImportReport, MergeReport, MergeStrategy, RepairChange, RepairKind, RepairReport, SanitizeReport, TagNode, BackupManifest, RestoreReport, BackupValidation, VaultSecurityInfo, IntegrityIssue, IntegrityReport, UserSettings, DreamConfig, EntryHighlights, Highlight, HighlightField, normalize_tag, tag_matches, TAG_SEPARATOR};
+use crate::search::{find_all_matches, find_byte_ranges, parse_keyword_query, parse_query, QueryExpr};
+use crate::crypto::{BackendEncryption, DecryptError, EncryptionResult, DecryptionParams, CIPHER_ALGORITHM, KDF_ALGORITHM, RECOMMENDED_ARGON2_PARAMS};
+use crate::throttle::AttemptThrottle;
 use anyhow::{Result, anyhow};
-use serde_json;
+use serde::{de::DeserializeOwned, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::future::Future;
 use std::path::{Path, PathBuf};
 use tokio::fs;
 
 
-/// 存储管理器
-pub struct StorageManager {
-    data_dir: PathBuf,
-    entries_file: PathBuf,
-}
+/// 清空保险库时必须输入的确认短语
+pub const WIPE_CONFIRM_PHRASE: &str = "DELETE ALL MEMORIES";
 
-impl StorageManager {
-    /// 创建新的存储管理器实例
-    pub async fn new(app_data_dir: &str) -> Result<Self> {
-        let data_dir = PathBuf::from(app_data_dir);
-        
-        // 确保数据目录存在
-        if !data_dir.exists() {
-            fs::create_dir_all(&data_dir).await
-                .map_err(|e| anyhow!("Failed to create data directory: {}", e))?;
-        }
+/// 分页搜索的默认每页条目数
+pub const DEFAULT_PAGE_SIZE: usize = 20;
 
-        let entries_file = data_dir.join("memories.json");
+/// 存储报告统计增长的天数
+pub const GROWTH_WINDOW_DAYS: u32 = 30;
 
-        Ok(Self {
-            data_dir,
-            entries_file,
-        })
+/// 外置正文目录名（位于数据目录下）
+const CONTENTS_DIR: &str = "contents";
+
+/// 附件目录名（位于数据目录下）
+const ATTACHMENTS_DIR: &str = "attachments";
+
+/// 备份清单文件名
+const BACKUP_MANIFEST: &str = "manifest.json";
+
+/// 单条目加密分享文件的扩展名
+pub const SHARE_FILE_EXTENSION: &str = "pbpshare";
+
+/// 密码提示的加密密钥
+///
+/// 提示需要在输入主密码之前显示，因此不能用主密码加密；此密钥只防止提示以明文
+/// 落盘，不能防止有意读取。
+const PASSWORD_HINT_KEY: &str = "peach-blossom-paper:password-hint";
+
+/// 密码提示的最大长度（字符）
+const MAX_PASSWORD_HINT_CHARS: usize = 200;
+
+/// 导出包中的条目文件名
+const EXPORT_ENTRIES_FILE: &str = "entries.json";
+
+/// 导出包中的附件目录名
+const EXPORT_ATTACHMENTS_DIR: &str = "attachments";
+
+/// 每个条目最多返回的高亮数量
+const MAX_HIGHLIGHTS_PER_ENTRY: usize = 50;
+
+/// 条目存储接口
+///
+/// 实现者只需提供整体读写，增删查与搜索逻辑由默认方法提供。文件存储由
+/// [`StorageManager`] 实现，测试可以使用内存实现 `MemoryStorage`。
+pub trait Storage {
+    /// 读取所有条目
+    async fn load_entries(&self) -> Result<Vec<MemoryEntry>>;
+
+    /// 覆盖写入所有条目
+    async fn save_entries(&self, entries: &[MemoryEntry]) -> Result<()>;
+
+    /// 获取所有记忆条目
+    async fn get_all_entries(&self) -> Result<Vec<MemoryEntry>> {
+        self.load_entries().await
     }
 
-    /// 获取数据目录路径
-    pub fn get_data_dir(&self) -> &Path {
-        &self.data_dir
+    /// 读取用于列表展示的条目，实现可以跳过按需加载的外置正文
+    async fn load_entry_listing(&self) -> Result<Vec<MemoryEntry>> {
+        self.load_entries().await
     }
 
-    /// 保存记忆条目
-    pub async fn save_entry(&self, entry: &MemoryEntry, password: Option<&str>) -> Result<()> {
-        let mut entries = self.load_all_entries().await.unwrap_or_default();
-        
-        // 检查是否是更新现有条目
-        if let Some(index) = entries.iter().position(|e| e.id == entry.id) {
-            entries[index] = entry.clone();
-        } else {
-            entries.push(entry.clone());
+    /// 获取未归档的记忆条目（默认列表）
+    async fn get_visible_entries(&self) -> Result<Vec<MemoryEntry>> {
+        let mut entries = self.load_entry_listing().await?;
+        entries.retain(|e| !e.is_archived);
+        Ok(entries)
+    }
+
+    /// 获取已归档的记忆条目（按创建时间倒序）
+    async fn list_archived(&self) -> Result<Vec<MemoryEntry>> {
+        let mut entries: Vec<MemoryEntry> = self.load_entry_listing().await?
+            .into_iter()
+            .filter(|e| e.is_archived)
+            .collect();
+        entries.sort_by_key(|e| std::cmp::Reverse(e.created_at));
+        Ok(entries)
+    }
+
+    /// 归档或取消归档条目，返回更新后的条目
+    async fn set_archived(&self, entry_id: &str, archived: bool) -> Result<MemoryEntry> {
+        let mut entries = self.load_entries().await?;
+        let entry = entries.iter_mut().find(|e| e.id == entry_id)
+            .ok_or_else(|| anyhow!("Entry not found"))?;
+        entry.is_archived = archived;
+
+        let updated = entry.clone();
+        self.save_entries(&entries).await?;
+        Ok(updated)
+    }
+
+    /// 切换条目中第 `task_index` 个任务的完成状态，返回更新后的条目
+    async fn toggle_task(&self, entry_id: &str, task_index: usize) -> Result<MemoryEntry> {
+        let mut entries = self.load_entries().await?;
+        let entry = entries.iter_mut().find(|e| e.id == entry_id)
+            .ok_or_else(|| anyhow!("Entry not found"))?;
+        entry.toggle_task(task_index).map_err(|e| anyhow!(e))?;
+
+        let updated = entry.clone();
+        self.save_entries(&entries).await?;
+        Ok(updated)
+    }
+
+    /// 按给出的 ID 顺序为条目分配手动排序位置（从 0 起），返回排序的条目数
+    ///
+    /// 未列出的条目清除位置，排在手动排序的条目之后。ID 重复或不存在时不做修改。
+    async fn reorder_entries(&self, ordered_ids: &[String]) -> Result<usize> {
+        let mut entries = self.load_entries().await?;
+        let mut positions = HashMap::new();
+        for (position, id) in ordered_ids.iter().enumerate() {
+            if positions.insert(id.as_str(), position as i64).is_some() {
+                return Err(anyhow!("Duplicate entry id: {}", id));
+            }
+        }
+        if let Some(missing) = ordered_ids.iter().find(|id| !entries.iter().any(|e| &e.id == *id)) {
+            return Err(anyhow!("Entry not found: {}", missing));
         }
 
-        self.save_all_entries(&entries, password).await
+        for entry in entries.iter_mut() {
+            entry.sort_order = positions.get(entry.id.as_str()).copied();
+        }
+        self.save_entries(&entries).await?;
+        Ok(positions.len())
     }
 
-    /// 删除记忆条目
-    pub async fn delete_entry(&self, entry_id: &str) -> Result<bool> {
-        let mut entries = self.load_all_entries().await.unwrap_or_default();
-        let initial_len = entries.len();
-        
-        entries.retain(|e| e.id != entry_id);
-        
-        if entries.len() < initial_len {
-            self.save_all_entries(&entries, None).await?;
-            Ok(true)
-        } else {
-            Ok(false)
+    /// 按当前设置重新计算所有条目的字数与阅读时间，返回发生变化的条目数
+    ///
+    /// 已加锁条目的正文不可读，保持原值。
+    async fn recompute_word_stats(&self, settings: &UserSettings) -> Result<u32> {
+        let mut entries = self.load_entries().await?;
+        let mut changed = 0;
+        for entry in entries.iter_mut().filter(|e| e.encrypted_content.is_none()) {
+            let before = entry.metadata.as_ref().map(|m| (m.word_count, m.reading_time));
+            entry.refresh_word_stats(settings);
+            let after = entry.metadata.as_ref().map(|m| (m.word_count, m.reading_time));
+            if before != after {
+                changed += 1;
+            }
+        }
+
+        if changed > 0 {
+            self.save_entries(&entries).await?;
         }
+        Ok(changed)
     }
 
     /// 根据ID获取记忆条目
-    pub async fn get_entry(&self, entry_id: &str) -> Result<Option<MemoryEntry>> {
-        let entries = self.load_all_entries().await?;
+    async fn get_entry(&self, entry_id: &str) -> Result<Option<MemoryEntry>> {
+        let entries = self.load_entries().await?;
         Ok(entries.into_iter().find(|e| e.id == entry_id))
     }
 
-    /// 获取所有记忆条目
-    pub async fn get_all_entries(&self) -> Result<Vec<MemoryEntry>> {
-        self.load_all_entries().await
+    /// 按给定 ID 的顺序批量获取条目，只读取一次存储
+    ///
+    /// 不存在的 ID 被忽略，重复的 ID 只返回一次。
+    async fn get_entries_by_ids(&self, entry_ids: &[String]) -> Result<Vec<MemoryEntry>> {
+        Ok(select_by_ids(self.load_entries().await?, entry_ids))
     }
 
-    /// 根据过滤器搜索记忆条目
-    pub async fn search_entries(&self, filter: &SearchFilter) -> Result<Vec<MemoryEntry>> {
-        let entries = self.load_all_entries().await?;
-        
-        let filtered_entries: Vec<MemoryEntry> = entries
+    /// 按 ID 前缀查找唯一条目（类似 git 短哈希）
+    ///
+    /// 前缀为空、没有匹配或匹配多个条目时返回错误。
+    async fn get_entry_by_prefix(&self, prefix: &str) -> Result<MemoryEntry> {
+        let prefix = prefix.trim();
+        if prefix.is_empty() {
+            return Err(anyhow!("ID prefix cannot be empty"));
+        }
+
+        let mut matches: Vec<MemoryEntry> = self.load_entries().await?
             .into_iter()
-            .filter(|entry| self.matches_filter(entry, filter))
+            .filter(|e| e.id.starts_with(prefix))
             .collect();
-
-        Ok(filtered_entries)
+        match matches.len() {
+            0 => Err(anyhow!("No entry matches prefix {}", prefix)),
+            1 => Ok(matches.remove(0)),
+            n => Err(anyhow!("Prefix {} is ambiguous ({} entries match)", prefix, n)),
+        }
     }
 
-    /// 检查条目是否匹配过滤器
-    fn matches_filter(&self, entry: &MemoryEntry, filter: &SearchFilter) -> bool {
-        // 关键词搜索
-        if let Some(keyword) = &filter.keyword {
-            let keyword_lower = keyword.to_lowercase();
-            if !entry.title.to_lowercase().contains(&keyword_lower) 
-                && !entry.content.to_lowercase().contains(&keyword_lower) {
-                return false;
+    /// 删除记忆条目
+    ///
+    /// `dry_run` 为 true 时只返回是否会删除，不写入文件。
+    async fn delete_entry(&self, entry_id: &str, dry_run: bool) -> Result<bool> {
+        let mut entries = self.load_entries().await.unwrap_or_default();
+        let initial_len = entries.len();
+        
+        entries.retain(|e| e.id != entry_id);
+        
+        if entries.len() < initial_len {
+            if dry_run {
+                return Ok(true);
             }
-        }
 
-        // 类型过滤
-        if let Some(memory_type) = &filter.memory_type {
-            if entry.memory_type != *memory_type {
-                return false;
+            // 清理其他条目中指向该条目的关联
+            for entry in entries.iter_mut() {
+                entry.linked_entry_ids.retain(|id| id != entry_id);
             }
+
+            self.save_entries(&entries).await?;
+            Ok(true)
+        } else {
+            Ok(false)
         }
+    }
 
-        // 情感标签过滤
-        if let Some(emotion_tags) = &filter.emotion_tags {
-            if !emotion_tags.iter().any(|tag| entry.emotion_tags.contains(tag)) {
-                return false;
-            }
+    /// 双向关联两个条目
+    async fn link_entries(&self, a: &str, b: &str) -> Result<()> {
+        if a == b {
+            return Err(anyhow!("Cannot link an entry to itself"));
         }
 
-        // 日期范围过滤
-        if let Some(date_range) = &filter.date_range {
-            if entry.created_at < date_range.start || entry.created_at > date_range.end {
-                return false;
+        let mut entries = self.load_entries().await?;
+        let index_a = entries.iter().position(|e| e.id == a)
+            .ok_or_else(|| anyhow!("Entry not found: {}", a))?;
+        let index_b = entries.iter().position(|e| e.id == b)
+            .ok_or_else(|| anyhow!("Entry not found: {}", b))?;
+
+        for (index, other) in [(index_a, b), (index_b, a)] {
+            let links = &mut entries[index].linked_entry_ids;
+            if !links.iter().any(|id| id == other) {
+                links.push(other.to_string());
             }
         }
 
-        // 标签过滤
-        if let Some(tags) = &filter.tags {
-            if let Some(entry_metadata) = &entry.metadata {
-                if let Some(entry_tags) = &entry_metadata.tags {
-                    if !tags.iter().any(|tag| entry_tags.contains(tag)) {
-                        return false;
-                    }
-                } else {
-                    return false;
-                }
-            } else {
-                return false;
+        self.save_entries(&entries).await
+    }
+
+    /// 解除两个条目之间的关联
+    async fn unlink_entries(&self, a: &str, b: &str) -> Result<()> {
+        let mut entries = self.load_entries().await?;
+
+        for entry in entries.iter_mut() {
+            if entry.id == a {
+                entry.linked_entry_ids.retain(|id| id != b);
+            } else if entry.id == b {
+                entry.linked_entry_ids.retain(|id| id != a);
             }
         }
 
-        true
+        self.save_entries(&entries).await
     }
 
-    /// 加载所有记忆条目
-    async fn load_all_entries(&self) -> Result<Vec<MemoryEntry>> {
-        if !self.entries_file.exists() {
-            return Ok(Vec::new());
-        }
+    /// 获取与指定条目关联的条目
+    async fn get_related_entries(&self, entry_id: &str) -> Result<Vec<MemoryEntry>> {
+        let entries = self.load_entries().await?;
+        let entry = entries.iter().find(|e| e.id == entry_id)
+            .ok_or_else(|| anyhow!("Entry not found"))?;
 
-        let content = fs::read_to_string(&self.entries_file).await
-            .map_err(|e| anyhow!("Failed to read entries file: {}", e))?;
+        Ok(entry.linked_entry_ids
+            .iter()
+            .filter_map(|id| entries.iter().find(|e| &e.id == id).cloned())
+            .collect())
+    }
 
-        if content.trim().is_empty() {
-            return Ok(Vec::new());
+    /// 获取反向链接：关联列表中包含指定条目的其他条目
+    ///
+    /// 关联是双向记录的，反向链接通常与 `get_related_entries` 相同；导入的旧数据可能只有单向关联。
+    async fn get_backlinks(&self, entry_id: &str) -> Result<Vec<MemoryEntry>> {
+        let entries = self.load_entries().await?;
+        if !entries.iter().any(|e| e.id == entry_id) {
+            return Err(anyhow!("Entry not found"));
         }
 
-        // 尝试解析为加密数据
-        if let Ok(_encrypted_data) = serde_json::from_str::<EncryptionResult>(&content) {
-            // 这是加密数据，需要密码解密
-            return Err(anyhow!("Entries are encrypted, password required"));
-        }
+        Ok(entries
+            .into_iter()
+            .filter(|e| e.id != entry_id && e.linked_entry_ids.iter().any(|id| id == entry_id))
+            .collect())
+    }
 
-        // 尝试解析为普通JSON
-        let entries: Vec<MemoryEntry> = serde_json::from_str(&content)
-            .map_err(|e| anyhow!("Failed to parse entries: {}", e))?;
+    /// 获取最近更新的若干未归档条目（按 `updated_at` 降序）
+    async fn get_recent_entries(&self, count: usize) -> Result<Vec<MemoryEntry>> {
+        let mut entries = self.get_visible_entries().await?;
+        entries.sort_by_key(|e| std::cmp::Reverse(e.updated_at));
+        entries.truncate(count);
+        Ok(entries)
+    }
 
+    /// 获取带有指定情感标签的条目（可选按记忆类型过滤），按创建时间倒序
+    ///
+    /// 已归档条目只在 `include_archived` 为 true 时返回。
+    async fn get_entries_by_emotion(&self, emotion: &EmotionTag, memory_type: Option<&MemoryType>, include_archived: bool) -> Result<Vec<MemoryEntry>> {
+        let mut entries: Vec<MemoryEntry> = self.load_entries().await?
+            .into_iter()
+            .filter(|e| include_archived || !e.is_archived)
+            .filter(|e| e.emotion_tags.contains(emotion))
+            .filter(|e| memory_type.is_none_or(|t| e.memory_type == *t))
+            .collect();
+        entries.sort_by_key(|e| std::cmp::Reverse(e.created_at));
         Ok(entries)
     }
 
-    /// 保存所有记忆条目
-    async fn save_all_entries(&self, entries: &[MemoryEntry], password: Option<&str>) -> Result<()> {
-        let json_content = serde_json::to_string_pretty(entries)
-            .map_err(|e| anyhow!("Failed to serialize entries: {}", e))?;
+    /// 根据过滤器搜索记忆条目
+    async fn search_entries(&self, filter: &SearchFilter) -> Result<Vec<MemoryEntry>> {
+        let query = keyword_query(filter)?;
+        let entries = self.load_entries().await?;
+        
+        let mut filtered_entries: Vec<MemoryEntry> = entries
+            .into_iter()
+            .filter(|entry| matches_filter(entry, filter, query.as_ref()))
+            .collect();
 
-        let content_to_save = if let Some(password) = password {
-            // 加密保存
-            let encrypted = BackendEncryption::encrypt(&json_content, password)?;
-            serde_json::to_string(&encrypted)
-                .map_err(|e| anyhow!("Failed to serialize encrypted data: {}", e))?
-        } else {
-            // 明文保存
-            json_content
+        if let Some(sort) = filter.sort {
+            sort_entries(&mut filtered_entries, sort);
+        }
+
+        Ok(filtered_entries)
+    }
+
+    /// 分页搜索：返回一页结果和匹配总数，未指定排序时按创建时间倒序
+    async fn search_entries_paginated(&self, filter: &SearchFilter, offset: usize, limit: usize) -> Result<SearchPage> {
+        let query = keyword_query(filter)?;
+        let mut matched: Vec<MemoryEntry> = self.load_entries().await?
+            .into_iter()
+            .filter(|entry| matches_filter(entry, filter, query.as_ref()))
+            .collect();
+        let total = matched.len();
+
+        sort_entries(&mut matched, filter.sort.unwrap_or_default());
+        let entries = matched.into_iter().skip(offset).take(limit).collect();
+
+        Ok(SearchPage { entries, total, offset, limit })
+    }
+
+    /// 统计匹配过滤条件的条目数，不返回条目
+    async fn count_entries(&self, filter: &SearchFilter) -> Result<usize> {
+        let query = keyword_query(filter)?;
+        Ok(self.load_entries().await?
+            .iter()
+            .filter(|entry| matches_filter(entry, filter, query.as_ref()))
+            .count())
+    }
+
+    /// 获取位置包含指定文本的条目（大小写不敏感），按创建时间倒序
+    async fn get_entries_by_location(&self, location: &str) -> Result<Vec<MemoryEntry>> {
+        let filter = SearchFilter {
+            location: Some(location.to_string()),
+            sort: Some(SearchSort::NewestFirst),
+            ..SearchFilter::default()
         };
+        self.search_entries(&filter).await
+    }
 
-        fs::write(&self.entries_file, content_to_save).await
-            .map_err(|e| anyhow!("Failed to write entries file: {}", e))?;
+    /// 使用布尔查询表达式搜索标题和正文
+    async fn query_entries(&self, expression: &str) -> Result<Vec<MemoryEntry>> {
+        let query = parse_query(expression)
+            .map_err(|e| anyhow!("Invalid query: {}", e))?;
+        let entries = self.load_entries().await?;
 
-        Ok(())
+        Ok(entries
+            .into_iter()
+            .filter(|entry| query.matches(&format!("{}\n{}", entry.title, entry.content)))
+            .collect())
     }
 
-    /// 使用密码解密并加载条目
-    pub async fn load_entries_with_password(&self, password: &str) -> Result<Vec<MemoryEntry>> {
-        if !self.entries_file.exists() {
+    /// 搜索关键词并返回每个条目中的匹配位置（字符偏移）
+    async fn search_with_highlights(&self, keyword: &str) -> Result<Vec<EntryHighlights>> {
+        if keyword.is_empty() {
             return Ok(Vec::new());
         }
 
-        let content = fs::read_to_string(&self.entries_file).await
-            .map_err(|e| anyhow!("Failed to read entries file: {}", e))?;
+        let entries = self.load_entries().await?;
 
-        if content.trim().is_empty() {
-            return Ok(Vec::new());
-        }
+        let results = entries
+            .iter()
+            .filter_map(|entry| {
+                let highlights: Vec<Highlight> = [
+                    (HighlightField::Title, &entry.title),
+                    (HighlightField::Content, &entry.content),
+                ]
+                .into_iter()
+                .flat_map(|(field, text)| {
+                    find_all_matches(text, keyword).into_iter().map(move |m| Highlight {
+                        field,
+                        start: m.char_start,
+                        end: m.char_end,
+                    })
+                })
+                .take(MAX_HIGHLIGHTS_PER_ENTRY)
+                .collect();
 
-        // 尝试解析为加密数据
-        let encrypted_data: EncryptionResult = serde_json::from_str(&content)
-            .map_err(|e| anyhow!("Failed to parse encrypted data: {}", e))?;
+                (!highlights.is_empty()).then(|| EntryHighlights {
+                    entry_id: entry.id.clone(),
+                    highlights,
+                })
+            })
+            .collect();
 
-        // 解密数据
-        let decrypt_params = DecryptionParams {
-            encrypted_data: encrypted_data.encrypted_data,
-            nonce: encrypted_data.nonce,
-            salt: encrypted_data.salt,
-            password: password.to_string(),
-        };
+        Ok(results)
+    }
 
-        let decrypted_content = BackendEncryption::decrypt(&decrypt_params)?;
+    /// 查找关键词在指定条目正文中的所有匹配（字节偏移）
+    async fn find_matches(&self, entry_id: &str, keyword: &str) -> Result<Vec<(usize, usize)>> {
+        let entry = self.get_entry(entry_id).await?
+            .ok_or_else(|| anyhow!("Entry not found"))?;
+        Ok(find_byte_ranges(&entry.content, keyword))
+    }
 
-        // 解析解密后的JSON
-        let entries: Vec<MemoryEntry> = serde_json::from_str(&decrypted_content)
-            .map_err(|e| anyhow!("Failed to parse decrypted entries: {}", e))?;
+    /// 获取标签云（按出现次数降序，次数相同按字母序）
+    async fn get_tag_cloud(&self, limit: Option<usize>) -> Result<Vec<(String, u32)>> {
+        let entries = self.load_entries().await?;
+        Ok(build_tag_cloud(&entries, limit))
+    }
 
-        Ok(entries)
+    /// 获取层级标签树（同级按名称排序），父级计数包含所有下级标签的条目
+    async fn list_tag_tree(&self) -> Result<Vec<TagNode>> {
+        let entries = self.load_entries().await?;
+        Ok(build_tag_tree(&entries))
     }
+}
 
+/// 共享的存储管理器缓存
+///
+/// 命令各自复制一份缓存的管理器（只含路径，复制开销很小），再设置本次调用的密码，互不影响。
+/// 读锁只在复制期间持有，并发读取不会互相阻塞；只有首次打开或失效后重建时才取写锁。
+#[derive(Default)]
+pub struct StorageCache(tokio::sync::RwLock<Option<StorageManager>>);
 
-    /// 获取随机记忆条目（用于拾梦回响）
-    pub async fn get_random_entry(&self) -> Result<Option<MemoryEntry>> {
-        let entries = self.load_all_entries().await?;
-        
-        if entries.is_empty() {
-            return Ok(None);
+impl StorageCache {
+    /// 返回缓存的存储管理器，尚未打开时用 `open` 打开并缓存（打开失败不缓存）
+    pub async fn get_or_open<F, Fut, E>(&self, open: F) -> std::result::Result<StorageManager, E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = std::result::Result<StorageManager, E>>,
+    {
+        if let Some(storage) = self.0.read().await.as_ref() {
+            return Ok(storage.clone());
         }
 
-        use rand::seq::SliceRandom;
-        let mut rng = rand::thread_rng();
-        let random_entry = entries.choose(&mut rng).cloned();
-        
-        Ok(random_entry)
+        let mut cached = self.0.write().await;
+        // 等待写锁期间其他调用可能已经打开
+        if let Some(storage) = cached.as_ref() {
+            return Ok(storage.clone());
+        }
+        let storage = open().await?;
+        *cached = Some(storage.clone());
+        Ok(storage)
     }
 
-    /// 备份数据
-    pub async fn backup_data(&self, backup_path: &str) -> Result<()> {
-        let backup_dir = PathBuf::from(backup_path);
+    /// 清除缓存，下次访问时重新打开（切换保险库或数据目录后调用）
+    pub async fn invalidate(&self) {
+        *self.0.write().await = None;
+    }
+}
+
+/// 一次调用中首次读取的条目与最后写入的条目，用于生成撤销记录而不必另外读取存储
+#[derive(Default)]
+struct EntryJournal {
+    loaded: Option<Vec<MemoryEntry>>,
+    saved: Option<Vec<MemoryEntry>>,
+}
+
+/// 存储管理器
+#[derive(Clone)]
+pub struct StorageManager {
+    data_dir: PathBuf,
+    entries_file: PathBuf,
+    settings_file: PathBuf,
+    dream_config_file: PathBuf,
+    attachments_dir: PathBuf,
+    contents_dir: PathBuf,
+    attempts_file: PathBuf,
+    hint_file: PathBuf,
+    /// 本次调用使用的存储密码，`Storage` 的读写都经由它解密/加密
+    password: Option<String>,
+    /// 只读模式下拒绝一切数据写入
+    read_only: bool,
+    /// 开启后记录本次调用读写的条目，见 [`Self::with_journal`]
+    journal: Option<std::sync::Arc<std::sync::Mutex<EntryJournal>>>,
+}
+
+impl StorageManager {
+    /// 创建新的存储管理器实例
+    pub async fn new(app_data_dir: &str) -> Result<Self> {
+        let data_dir = PathBuf::from(app_data_dir);
         
-        if !backup_dir.exists() {
-            fs::create_dir_all(&backup_dir).await
-                .map_err(|e| anyhow!("Failed to create backup directory: {}", e))?;
+        // 确保数据目录存在
+        if !data_dir.exists() {
+            fs::create_dir_all(&data_dir).await
+                .map_err(|e| anyhow!("Failed to create data directory: {}", e))?;
         }
 
-        // 备份记忆条目
-        if self.entries_file.exists() {
-            let backup_entries_file = backup_dir.join("memories_backup.json");
-            fs::copy(&self.entries_file, backup_entries_file).await
-                .map_err(|e| anyhow!("Failed to backup entries: {}", e))?;
+        Ok(Self::at(data_dir, false))
+    }
+
+    /// 以只读方式打开已有的数据目录
+    ///
+    /// 不创建目录、不写入默认值；通过它写入数据会返回错误。密码错误次数仍会记录，
+    /// 避免借只读入口绕过尝试限流。
+    pub async fn new_read_only(app_data_dir: &str) -> Result<Self> {
+        let data_dir = PathBuf::from(app_data_dir);
+        if !data_dir.is_dir() {
+            return Err(anyhow!("Data directory {} does not exist", data_dir.display()));
         }
 
+        Ok(Self::at(data_dir, true))
+    }
+
+    /// 按数据目录构造各文件路径
+    fn at(data_dir: PathBuf, read_only: bool) -> Self {
+        let entries_file = data_dir.join("memories.json");
+        let settings_file = data_dir.join("settings.json");
+        let dream_config_file = data_dir.join("dream_config.json");
+        let attachments_dir = data_dir.join(ATTACHMENTS_DIR);
+        let contents_dir = data_dir.join(CONTENTS_DIR);
+        let attempts_file = data_dir.join("password_attempts.json");
+        let hint_file = data_dir.join("password_hint.json");
+
+        Self {
+            data_dir,
+            entries_file,
+            settings_file,
+            dream_config_file,
+            attachments_dir,
+            contents_dir,
+            attempts_file,
+            hint_file,
+            password: None,
+            read_only,
+            journal: None,
+        }
+    }
 
+    /// 只读存储上拒绝写入
+    fn ensure_not_read_only(&self) -> Result<()> {
+        if self.read_only {
+            return Err(anyhow!("Storage is opened read-only"));
+        }
         Ok(())
     }
+
+    /// 转为只读：之后通过它的写入都会返回错误
+    pub fn into_read_only(mut self) -> Self {
+        self.read_only = true;
+        self
+    }
+
+    /// 是否为只读存储
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// 设置读写条目时使用的存储密码
+    pub fn with_password(mut self, password: Option<String>) -> Self {
+        self.password = password;
+        self
+    }
+
+    /// 记录之后读取与写入的条目，修改完成后用 [`Self::take_journal`] 取出修改前后的条目
+    pub fn with_journal(mut self) -> Self {
+        self.journal = Some(Default::default());
+        self
+    }
+
+    /// 取出首次读取与最后写入的条目；没有写入过条目时返回 None
+    pub fn take_journal(&self) -> Option<(Vec<MemoryEntry>, Vec<MemoryEntry>)> {
+        let mut journal = self.journal.as_ref()?.lock().ok()?;
+        let saved = journal.saved.take()?;
+        Some((journal.loaded.take()?, saved))
+    }
+
+    /// 记录读取到的条目（只保留首次读取的结果）
+    fn journal_loaded(&self, entries: &[MemoryEntry]) {
+        if let Some(mut journal) = self.journal.as_ref().and_then(|j| j.lock().ok()) {
+            if journal.loaded.is_none() {
+                journal.loaded = Some(entries.to_vec());
+            }
+        }
+    }
+
+    /// 记录写入的条目（保留最后一次写入的结果）
+    fn journal_saved(&self, entries: &[MemoryEntry]) {
+        if let Some(mut journal) = self.journal.as_ref().and_then(|j| j.lock().ok()) {
+            journal.saved = Some(entries.to_vec());
+        }
+    }
+
+    /// 获取数据目录路径
+    pub fn get_data_dir(&self) -> &Path {
+        &self.data_dir
+    }
+
+    /// 保存记忆条目
+    ///
+    /// 未提供密码时，如果存储已加密或设置要求密码，则拒绝保存，避免写入明文。
+    pub async fn save_entry(&self, entry: &MemoryEntry, password: Option<&str>) -> Result<()> {
+        self.check_password_requirement(password).await?;
+
+        let mut entries = self.load_entries_for(password).await?;
+        
+        // 检查是否是更新现有条目
+        if let Some(index) = entries.iter().position(|e| e.id == entry.id) {
+            entries[index] = entry.clone();
+        } else {
+            entries.push(entry.clone());
+        }
+
+        self.save_all_entries(&entries, password).await
+    }
+
+    /// 保存新条目；已有同 ID 的条目时不做修改，返回已有条目（用于客户端重试的幂等创建）
+    pub async fn save_new_entry(&self, entry: &MemoryEntry, password: Option<&str>) -> Result<MemoryEntry> {
+        self.check_password_requirement(password).await?;
+
+        let mut entries = self.load_entries_for(password).await?;
+        if let Some(existing) = entries.iter().find(|e| e.id == entry.id) {
+            return Ok(existing.clone());
+        }
+
+        entries.push(entry.clone());
+        self.save_all_entries(&entries, password).await?;
+        Ok(entry.clone())
+    }
+
+    /// 检查未提供密码时是否允许写入明文
+    async fn check_password_requirement(&self, password: Option<&str>) -> Result<()> {
+        if password.is_some() {
+            return Ok(());
+        }
+
+        if self.is_entries_encrypted().await {
+            return Err(anyhow!("Entries are encrypted, password required"));
+        }
+
+        // 设置文件已加密而无法读取时，同样视为需要密码
+        let required = self.load_settings(None).await
+            .map(|settings| settings.require_password)
+            .unwrap_or(true);
+        if required {
+            return Err(anyhow!("Password is required by settings, refusing to save plaintext"));
+        }
+
+        Ok(())
+    }
+
+    /// 记忆文件是否为加密存储
+    async fn is_entries_encrypted(&self) -> bool {
+        match fs::read_to_string(&self.entries_file).await {
+            Ok(content) => serde_json::from_str::<EncryptionResult>(&content).is_ok(),
+            Err(_) => false,
+        }
+    }
+
+    /// 批量删除记忆条目
+    ///
+    /// `dry_run` 为 true 时返回将要删除的结果，不写入文件。
+    pub async fn bulk_delete_entries(&self, entry_ids: &[String], dry_run: bool) -> Result<BulkResult> {
+        let mut entries = self.load_entries().await?;
+        let mut result = BulkResult::default();
+
+        for entry_id in entry_ids {
+            match entries.iter().position(|e| &e.id == entry_id) {
+                Some(index) => {
+                    entries.remove(index);
+                    result.succeeded.push(entry_id.clone());
+                }
+                None => result.failed.push((entry_id.clone(), "not found".to_string())),
+            }
+        }
+
+        if !dry_run && !result.succeeded.is_empty() {
+            // 清理其他条目中指向已删除条目的关联
+            for entry in entries.iter_mut() {
+                entry.linked_entry_ids.retain(|id| !result.succeeded.contains(id));
+            }
+            self.save_entries(&entries).await?;
+        }
+
+        Ok(result)
+    }
+
+    /// 批量为条目添加标签
+    pub async fn bulk_add_tag(&self, entry_ids: &[String], tag: &str) -> Result<BulkResult> {
+        let tag = normalize_tag(tag);
+        if tag.is_empty() {
+            return Err(anyhow!("Tag cannot be empty"));
+        }
+
+        let mut entries = self.load_entries().await?;
+        let mut result = BulkResult::default();
+
+        for entry_id in entry_ids {
+            let Some(entry) = entries.iter_mut().find(|e| &e.id == entry_id) else {
+                result.failed.push((entry_id.clone(), "not found".to_string()));
+                continue;
+            };
+
+            let mut tags = entry.metadata.as_ref().and_then(|m| m.tags.clone()).unwrap_or_default();
+            if tags.contains(&tag) {
+                result.failed.push((entry_id.clone(), "already tagged".to_string()));
+                continue;
+            }
+
+            tags.push(tag.clone());
+            entry.set_tags(tags);
+            result.succeeded.push(entry_id.clone());
+        }
+
+        if !result.succeeded.is_empty() {
+            self.save_entries(&entries).await?;
+        }
+
+        Ok(result)
+    }
+
+    /// 使用条目密码为单个条目加锁
+    ///
+    /// 正文加密后存入 `encrypted_content`，明文正文被清空，其余字段保持可读。
+    pub async fn lock_entry(&self, entry_id: &str, entry_password: &str) -> Result<MemoryEntry> {
+        let mut entries = self.load_entries().await?;
+        let entry = entries.iter_mut().find(|e| e.id == entry_id)
+            .ok_or_else(|| anyhow!("Entry not found"))?;
+
+        if entry.encrypted_content.is_some() {
+            return Err(anyhow!("Entry is already locked"));
+        }
+
+        self.load_content(entry).await?;
+        let encrypted = BackendEncryption::encrypt(&entry.content, entry_password)?;
+        entry.encrypted_content = Some(EncryptedData::from_result(encrypted));
+        entry.content = String::new();
+        entry.is_encrypted = true;
+        entry.updated_at = chrono::Utc::now();
+
+        let locked = entry.clone();
+        self.save_entries(&entries).await?;
+        Ok(locked)
+    }
+
+    /// 将文件复制到附件目录并添加到条目
+    pub async fn add_attachment(&self, entry_id: &str, source_path: &str) -> Result<Attachment> {
+        self.ensure_not_read_only()?;
+        let mut entries = self.load_entries().await?;
+        let entry = entries.iter_mut().find(|e| e.id == entry_id)
+            .ok_or_else(|| anyhow!("Entry not found"))?;
+
+        let source = Path::new(source_path);
+        let file_name = source.file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| anyhow!("Invalid attachment path: {}", source_path))?
+            .to_string();
+
+        fs::create_dir_all(&self.attachments_dir).await
+            .map_err(|e| anyhow!("Failed to create attachments directory: {}", e))?;
+
+        let id = uuid::Uuid::new_v4().to_string();
+        let stored_name = format!("{}-{}", id, file_name);
+        let dest = self.attachments_dir.join(&stored_name);
+        let file_size = fs::copy(source, &dest).await
+            .map_err(|e| anyhow!("Failed to copy attachment: {}", e))?;
+
+        let attachment = Attachment {
+            id,
+            file_type: guess_file_type(&file_name).to_string(),
+            file_name,
+            file_path: format!("{}/{}", ATTACHMENTS_DIR, stored_name),
+            file_size,
+            is_encrypted: false,
+            created_at: chrono::Utc::now(),
+            attachment_hash: Some(hash_file(&dest).await?),
+        };
+
+        entry.attachments.get_or_insert_with(Vec::new).push(attachment.clone());
+        self.save_entries(&entries).await?;
+        Ok(attachment)
+    }
+
+    /// 附件文件的实际路径
+    ///
+    /// `file_path` 保存为相对数据目录的路径，数据目录迁移后仍然有效；旧版本保存的绝对路径原样使用。
+    fn attachment_path(&self, attachment: &Attachment) -> PathBuf {
+        self.data_dir.join(&attachment.file_path)
+    }
+
+    /// 校验所有附件文件是否存在、大小和哈希是否与记录一致
+    pub async fn verify_attachments(&self) -> Result<AttachmentReport> {
+        let entries = self.load_entries().await?;
+        let mut report = AttachmentReport::default();
+
+        for attachment in entries.iter().flat_map(|e| e.attachments.iter().flatten()) {
+            let path = self.attachment_path(attachment);
+            let size = match fs::metadata(&path).await {
+                Ok(metadata) if metadata.is_file() => metadata.len(),
+                _ => {
+                    report.missing.push(attachment.id.clone());
+                    continue;
+                }
+            };
+
+            let hash_matches = match &attachment.attachment_hash {
+                Some(expected) => hash_file(&path).await? == *expected,
+                None => true,
+            };
+
+            if size == attachment.file_size && hash_matches {
+                report.ok.push(attachment.id.clone());
+            } else {
+                report.modified.push(attachment.id.clone());
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// 列出附件目录中未被任何条目引用的文件
+    pub async fn find_orphaned_attachments(&self) -> Result<Vec<OrphanedAttachment>> {
+        if !self.attachments_dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let entries = self.load_entries().await?;
+        let referenced: std::collections::HashSet<PathBuf> = entries
+            .iter()
+            .flat_map(|e| e.attachments.iter().flatten())
+            .map(|attachment| self.attachment_key(attachment))
+            .collect();
+
+        let mut orphans: Vec<OrphanedAttachment> = self.attachment_files().await?
+            .into_iter()
+            .filter(|(path, _)| {
+                let relative = path.strip_prefix(&self.attachments_dir).unwrap_or(path);
+                !referenced.contains(relative)
+            })
+            .map(|(path, metadata)| OrphanedAttachment {
+                path: path.to_string_lossy().to_string(),
+                size: metadata.len(),
+            })
+            .collect();
+
+        orphans.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(orphans)
+    }
+
+    /// 附件相对附件目录的路径，用于与磁盘上的文件比对
+    ///
+    /// 旧版本保存的绝对路径若不在当前附件目录下（数据目录迁移前写入），按文件名比对。
+    fn attachment_key(&self, attachment: &Attachment) -> PathBuf {
+        let path = self.attachment_path(attachment);
+        match path.strip_prefix(&self.attachments_dir) {
+            Ok(relative) => relative.to_path_buf(),
+            Err(_) => path.file_name().map(PathBuf::from).unwrap_or_default(),
+        }
+    }
+
+    /// 递归列出附件目录下的所有文件及其元数据
+    async fn attachment_files(&self) -> Result<Vec<(PathBuf, std::fs::Metadata)>> {
+        list_files(&self.attachments_dir).await
+    }
+
+    /// 删除孤立附件文件，返回删除的文件与释放的字节数
+    ///
+    /// `dry_run` 为 true 时只报告将被删除的文件，不改动磁盘。
+    pub async fn prune_orphaned_attachments(&self, dry_run: bool) -> Result<OrphanReport> {
+        if !dry_run {
+            self.ensure_not_read_only()?;
+        }
+
+        let files = self.find_orphaned_attachments().await?;
+        if !dry_run {
+            for file in &files {
+                fs::remove_file(&file.path).await
+                    .map_err(|e| anyhow!("Failed to remove {}: {}", file.path, e))?;
+            }
+        }
+
+        Ok(OrphanReport {
+            reclaimed_bytes: files.iter().map(|f| f.size).sum(),
+            files,
+            dry_run,
+        })
+    }
+
+    /// 加载所有记忆条目
+    async fn load_all_entries(&self) -> Result<Vec<MemoryEntry>> {
+        if !self.entries_file.exists() {
+            return Ok(Vec::new());
+        }
+
+        let content = fs::read_to_string(&self.entries_file).await
+            .map_err(|e| anyhow!("Failed to read entries file: {}", e))?;
+
+        if content.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // 尝试解析为加密数据
+        if let Ok(_encrypted_data) = serde_json::from_str::<EncryptionResult>(&content) {
+            // 这是加密数据，需要密码解密
+            return Err(anyhow!("Entries are encrypted, password required"));
+        }
+
+        // 尝试解析为普通JSON
+        let entries: Vec<MemoryEntry> = serde_json::from_str(&content)
+            .map_err(|e| anyhow!("Failed to parse entries: {}", e))?;
+
+        Ok(entries)
+    }
+
+    /// 保存所有记忆条目
+    ///
+    /// 明文按设置中的 `pretty_json` 排版，加密前的内容始终为紧凑 JSON。
+    async fn save_all_entries(&self, entries: &[MemoryEntry], password: Option<&str>) -> Result<()> {
+        self.ensure_not_read_only()?;
+
+        // 外置正文与缩进排版只用于明文存储；加密保存时正文内联、内容紧凑
+        let (threshold, pretty) = match password {
+            Some(_) => (0, false),
+            None => self.load_settings(None).await
+                .map_or((0, true), |s| (s.external_content_threshold as usize, s.pretty_json)),
+        };
+        let stored = self.prepare_entries_for_save(entries, threshold).await?;
+
+        let json_content = to_json(&stored, pretty)
+            .map_err(|e| anyhow!("Failed to serialize entries: {}", e))?;
+
+        let content_to_save = if let Some(password) = password {
+            // 加密保存
+            let encrypted = BackendEncryption::encrypt(&json_content, password)?;
+            serde_json::to_string(&encrypted)
+                .map_err(|e| anyhow!("Failed to serialize encrypted data: {}", e))?
+        } else {
+            // 明文保存
+            json_content
+        };
+
+        write_atomic(&self.entries_file, content_to_save).await
+            .map_err(|e| anyhow!("Failed to write entries file: {}", e))?;
+
+        self.journal_saved(entries);
+
+        // 记忆文件写入成功后才删除旧的外置正文，写入失败时旧文件的引用仍然有效
+        self.remove_unreferenced_contents(&stored).await
+    }
+
+    /// 加载条目：提供密码时解密（带暴力破解保护），否则按明文读取
+    ///
+    /// 所有读取路径都经由此方法，因此无论存储是否加密，读取行为一致。外置正文会一并加载。
+    pub async fn load_entries_for(&self, password: Option<&str>) -> Result<Vec<MemoryEntry>> {
+        let mut entries = self.load_entry_index_for(password).await?;
+        for entry in entries.iter_mut() {
+            self.load_content(entry).await?;
+        }
+        self.journal_loaded(&entries);
+        Ok(entries)
+    }
+
+    /// 加载条目但不读取外置正文（用于列表）
+    async fn load_entry_index_for(&self, password: Option<&str>) -> Result<Vec<MemoryEntry>> {
+        let mut entries = match password {
+            Some(password) => self.load_entries_throttled(password).await?,
+            None => self.load_all_entries().await?,
+        };
+        // 任务完成情况不写入存储，读取时按任务清单计算
+        for entry in entries.iter_mut() {
+            entry.refresh_task_progress();
+        }
+        Ok(entries)
+    }
+
+    /// 重新计算每个条目的标题与正文哈希，找出与保存时记录不一致的条目
+    pub async fn verify_entries_integrity(&self) -> Result<IntegrityReport> {
+        let entries = self.load_entries().await?;
+
+        let mut report = IntegrityReport { checked: 0, unhashed: 0, mismatched: Vec::new() };
+        for entry in &entries {
+            match &entry.content_hash {
+                Some(hash) => {
+                    report.checked += 1;
+                    if *hash != entry.compute_content_hash() {
+                        report.mismatched.push(IntegrityIssue { entry_id: entry.id.clone(), title: entry.title.clone() });
+                    }
+                }
+                None => report.unhashed += 1,
+            }
+        }
+        Ok(report)
+    }
+
+    /// 按需获取条目正文（正文外置时从 `contents` 目录读取）
+    pub async fn get_entry_content(&self, entry_id: &str) -> Result<String> {
+        let mut entry = self.load_entry_index_for(self.password.as_deref()).await?
+            .into_iter()
+            .find(|e| e.id == entry_id)
+            .ok_or_else(|| anyhow!("Entry not found"))?;
+        if entry.encrypted_content.is_some() {
+            return Err(anyhow!("Entry is locked"));
+        }
+
+        self.load_content(&mut entry).await?;
+        Ok(entry.content)
+    }
+
+    /// 获取已使用的全部标签（去重并按字母序），记忆文件未变化时使用缓存
+    pub async fn all_tags(&self, cache: &std::sync::Mutex<TagIndexCache>, session: Option<u128>) -> Result<Vec<String>> {
+        self.refresh_tag_index(cache, session).await?;
+        Ok(cache.lock().map_err(|e| anyhow!("{}", e))?.tags.clone())
+    }
+
+    /// 获取已使用的全部心情（去重并按字母序），记忆文件未变化时使用缓存
+    pub async fn all_moods(&self, cache: &std::sync::Mutex<TagIndexCache>, session: Option<u128>) -> Result<Vec<String>> {
+        self.refresh_tag_index(cache, session).await?;
+        Ok(cache.lock().map_err(|e| anyhow!("{}", e))?.moods.clone())
+    }
+
+    /// 记忆文件自上次统计后发生变化（或换了会话）时重新读取条目并统计
+    ///
+    /// `session` 为解锁会话的令牌，只在本次密码与会话密码一致时传入。带密码但没有令牌时
+    /// 无法证明密码已校验过，既不使用也不保留缓存，每次都重新解密读取。
+    async fn refresh_tag_index(&self, cache: &std::sync::Mutex<TagIndexCache>, session: Option<u128>) -> Result<()> {
+        let stamp = fs::metadata(&self.entries_file).await
+            .and_then(|meta| Ok((self.entries_file.clone(), meta.len(), meta.modified()?, session)))
+            .ok()
+            .filter(|_| self.password.is_none() || session.is_some());
+        if stamp.is_some() && cache.lock().map_err(|e| anyhow!("{}", e))?.stamp == stamp {
+            return Ok(());
+        }
+
+        let entries = self.load_entry_listing().await?;
+        let mut cache = cache.lock().map_err(|e| anyhow!("{}", e))?;
+        cache.tags = distinct_tags(&entries);
+        cache.moods = distinct_moods(&entries);
+        cache.stamp = stamp;
+        cache.computations += 1;
+        Ok(())
+    }
+
+    /// 外置正文的文件路径；拒绝包含路径分隔符的文件名，避免篡改后的引用指向数据目录之外
+    fn content_path(&self, content_ref: &str) -> Result<PathBuf> {
+        content_file(&self.contents_dir, content_ref)
+    }
+
+    /// 正文尚未加载时从外置文件读取（保留 `content_ref`）
+    async fn load_content(&self, entry: &mut MemoryEntry) -> Result<()> {
+        if let Some(content_ref) = entry.content_ref.as_deref().filter(|_| entry.content.is_empty()) {
+            let path = self.content_path(content_ref)?;
+            entry.content = fs::read_to_string(&path).await
+                .map_err(|e| anyhow!("Failed to read content of entry {}: {}", entry.id, e))?;
+        }
+        Ok(())
+    }
+
+    /// 准备写入记忆文件的条目：计算 `content_hash`，并把超过 `threshold` 字节的正文写入 `contents` 目录
+    ///
+    /// 正文为空但带有 `content_ref` 的条目视为未加载正文，保留原有外置文件与哈希。
+    /// `threshold` 为 0 时所有已加载的正文都内联保存。
+    async fn prepare_entries_for_save(&self, entries: &[MemoryEntry], threshold: usize) -> Result<Vec<MemoryEntry>> {
+        let mut stored = Vec::with_capacity(entries.len());
+
+        for entry in entries {
+            let mut entry = entry.clone();
+            entry.task_progress = None;
+            let unloaded = entry.content.is_empty() && entry.content_ref.is_some();
+            if !unloaded {
+                entry.content_hash = Some(entry.compute_content_hash());
+                if threshold > 0 && entry.encrypted_content.is_none() && entry.content.len() > threshold {
+                    let content_ref = entry.content_ref.take()
+                        .unwrap_or_else(|| format!("{}.txt", uuid::Uuid::new_v4()));
+                    let path = self.content_path(&content_ref)?;
+                    fs::create_dir_all(&self.contents_dir).await
+                        .map_err(|e| anyhow!("Failed to create contents directory: {}", e))?;
+                    fs::write(&path, &entry.content).await
+                        .map_err(|e| anyhow!("Failed to write content of entry {}: {}", entry.id, e))?;
+                    entry.content = String::new();
+                    entry.content_ref = Some(content_ref);
+                } else {
+                    entry.content_ref = None;
+                }
+            }
+
+            stored.push(entry);
+        }
+
+        Ok(stored)
+    }
+
+    /// 删除不再被任何条目引用的外置正文文件
+    async fn remove_unreferenced_contents(&self, entries: &[MemoryEntry]) -> Result<()> {
+        let referenced: HashSet<&str> = entries.iter().filter_map(|e| e.content_ref.as_deref()).collect();
+        for (path, _) in list_files(&self.contents_dir).await? {
+            let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+            if !referenced.contains(name.as_str()) {
+                fs::remove_file(&path).await
+                    .map_err(|e| anyhow!("Failed to remove {}: {}", path.display(), e))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 使用密码解密并加载条目
+    ///
+    /// 如果文件仍为旧版明文，则直接读取；当设置开启 `migrate_to_encrypted` 时，
+    /// 使用该密码重新加密保存。迁移后文件即为密文，因此只会发生一次。
+    pub async fn load_entries_with_password(&self, password: &str) -> Result<Vec<MemoryEntry>> {
+        if !self.entries_file.exists() {
+            return Ok(Vec::new());
+        }
+
+        let content = fs::read_to_string(&self.entries_file).await
+            .map_err(|e| anyhow!("Failed to read entries file: {}", e))?;
+
+        if content.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // 尝试解析为加密数据
+        let encrypted_data = match serde_json::from_str::<EncryptionResult>(&content) {
+            Ok(encrypted_data) => encrypted_data,
+            Err(_) => return self.migrate_plaintext_entries(&content, password).await,
+        };
+
+        // 解密数据
+        let decrypted_content = decrypt_content(encrypted_data, password)?;
+
+        // 解析解密后的JSON
+        let entries: Vec<MemoryEntry> = serde_json::from_str(&decrypted_content)
+            .map_err(|e| anyhow!("Failed to parse decrypted entries: {}", e))?;
+
+        Ok(entries)
+    }
+
+    /// 带暴力破解保护的密码加载
+    ///
+    /// 连续失败次数持久化在数据目录中，超过免费次数后每次尝试前按指数退避等待，
+    /// 成功后重置。
+    pub async fn load_entries_throttled(&self, password: &str) -> Result<Vec<MemoryEntry>> {
+        self.load_entries_throttled_with(password, tokio::time::sleep).await
+    }
+
+    /// 使用指定的等待函数执行限流加载
+    async fn load_entries_throttled_with<S, F>(&self, password: &str, sleep: S) -> Result<Vec<MemoryEntry>>
+    where
+        S: FnOnce(std::time::Duration) -> F,
+        F: Future<Output = ()>,
+    {
+        self.with_attempt_throttle(sleep, self.load_entries_with_password(password)).await
+    }
+
+    /// 按持久化的失败次数退避等待后执行一次密码尝试，并记录尝试结果
+    async fn with_attempt_throttle<T, S, F, A>(&self, sleep: S, attempt: A) -> Result<T>
+    where
+        S: FnOnce(std::time::Duration) -> F,
+        F: Future<Output = ()>,
+        A: Future<Output = Result<T>>,
+    {
+        let mut throttle: AttemptThrottle = self.read_json_file(&self.attempts_file, None).await.unwrap_or_default();
+
+        let delay = AttemptThrottle::delay_for(throttle.failures());
+        if delay > chrono::Duration::zero() {
+            sleep(delay.to_std().unwrap_or_default()).await;
+        }
+
+        // 只有密码错误计入失败；密文损坏或读取失败换密码重试也无法成功，不计数
+        let result = attempt.await;
+        let changed = match &result {
+            Ok(_) => {
+                let had_failures = throttle.failures() > 0;
+                throttle.record_success();
+                had_failures
+            }
+            Err(e) if matches!(e.downcast_ref::<DecryptError>(), Some(DecryptError::WrongPassword)) => {
+                throttle.record_failure(chrono::Utc::now());
+                true
+            }
+            Err(_) => false,
+        };
+        // 只在计数变化时写回；只读存储（如合并时打开的另一保险库）不写入
+        if changed && !self.read_only {
+            self.write_json_file(&self.attempts_file, &throttle, false, true, None).await?;
+        }
+
+        result
+    }
+
+    /// 获取存储状态（加密情况与密码尝试锁定状态）
+    pub async fn get_store_status(&self) -> Result<StoreStatus> {
+        let encrypted = self.is_entries_encrypted().await;
+        let throttle: AttemptThrottle = self.read_json_file(&self.attempts_file, None).await.unwrap_or_default();
+
+        Ok(StoreStatus {
+            encrypted,
+            failed_attempts: throttle.failures(),
+            next_attempt_delay_secs: AttemptThrottle::delay_for(throttle.failures()).num_seconds(),
+        })
+    }
+
+    /// 读取保险库的加密状态、格式版本与密钥派生参数（只读取记忆文件头，无需密码）
+    pub async fn get_vault_security_info(&self) -> Result<VaultSecurityInfo> {
+        if !self.entries_file.exists() {
+            return Ok(VaultSecurityInfo::default());
+        }
+        let content = fs::read_to_string(&self.entries_file).await
+            .map_err(|e| anyhow!("Failed to read entries file: {}", e))?;
+        let Ok(encrypted) = serde_json::from_str::<EncryptionResult>(&content) else {
+            return Ok(VaultSecurityInfo::default());
+        };
+
+        let argon2_params = BackendEncryption::kdf_params(encrypted.format_version);
+        Ok(VaultSecurityInfo {
+            encrypted: true,
+            algorithm: Some(CIPHER_ALGORITHM.to_string()),
+            kdf: argon2_params.map(|_| KDF_ALGORITHM.to_string()),
+            argon2_params,
+            format_version: Some(encrypted.format_version),
+            below_recommended: argon2_params.is_some_and(|p| p.is_below(&RECOMMENDED_ARGON2_PARAMS)),
+        })
+    }
+
+    /// 生成存储空间报告：各数据文件与附件大小、条目数及增长趋势
+    ///
+    /// 增长按最近 [`GROWTH_WINDOW_DAYS`] 天内新建条目的序列化大小与修改时间在此期间的附件估算。
+    pub async fn get_storage_report(&self, now: chrono::DateTime<chrono::Utc>) -> Result<StorageReport> {
+        let entries = self.load_entries().await?;
+        let settings = self.load_settings(self.password.as_deref()).await.unwrap_or_default();
+        let window_start = now - chrono::Duration::days(GROWTH_WINDOW_DAYS as i64);
+
+        let mut files = Vec::new();
+        for path in [&self.entries_file, &self.settings_file, &self.dream_config_file, &self.hint_file, &self.attempts_file] {
+            if let Ok(metadata) = fs::metadata(path).await {
+                let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+                files.push(FileSize { name, size: metadata.len() });
+            }
+        }
+
+        let contents_size: u64 = list_files(&self.contents_dir).await?.iter().map(|(_, m)| m.len()).sum();
+        if contents_size > 0 {
+            files.push(FileSize { name: CONTENTS_DIR.to_string(), size: contents_size });
+        }
+
+        let attachments = self.attachment_files().await?;
+        let attachments_size: u64 = attachments.iter().map(|(_, m)| m.len()).sum();
+        let recent_attachments: u64 = attachments
+            .iter()
+            .filter(|(_, m)| m.modified().is_ok_and(|t| chrono::DateTime::<chrono::Utc>::from(t) >= window_start))
+            .map(|(_, m)| m.len())
+            .sum();
+        let recent_entries: u64 = entries
+            .iter()
+            .filter(|e| e.created_at >= window_start)
+            .map(|e| serde_json::to_string(e).map(|json| json.len() as u64).unwrap_or(0))
+            .sum();
+
+        let entries_file_size = files.first().filter(|f| f.name == "memories.json").map_or(0, |f| f.size);
+        let total_size = files.iter().map(|f| f.size).sum::<u64>() + attachments_size;
+        let recent_growth_bytes = recent_entries + recent_attachments;
+
+        Ok(StorageReport {
+            files,
+            attachment_count: attachments.len() as u32,
+            attachments_size,
+            entry_count: entries.len() as u32,
+            total_size,
+            growth_window_days: GROWTH_WINDOW_DAYS,
+            recent_growth_bytes,
+            projected_size_in_year: total_size + recent_growth_bytes * 365 / GROWTH_WINDOW_DAYS as u64,
+            large_entries_file: entries_file_size > settings.large_file_warning_mb as u64 * 1024 * 1024,
+        })
+    }
+
+    /// 统计保险库各部分占用的磁盘空间
+    ///
+    /// 只读取文件大小，不加载条目，因此加密存储无需密码。
+    pub async fn get_storage_usage(&self) -> Result<StorageUsage> {
+        let dir_size = |files: Vec<(PathBuf, std::fs::Metadata)>| files.iter().map(|(_, m)| m.len()).sum::<u64>();
+
+        let mut usage = StorageUsage {
+            entries_size: file_size(&self.entries_file).await,
+            settings_size: file_size(&self.settings_file).await,
+            dream_config_size: file_size(&self.dream_config_file).await,
+            contents_size: dir_size(list_files(&self.contents_dir).await?),
+            attachments_size: dir_size(self.attachment_files().await?),
+            total_size: 0,
+        };
+        usage.total_size = usage.entries_size
+            + usage.settings_size
+            + usage.dream_config_size
+            + usage.contents_size
+            + usage.attachments_size;
+        Ok(usage)
+    }
+
+    /// 将单个条目导出为加密的紧凑分享字符串
+    pub async fn export_entry_sealed(&self, entry_id: &str, password: &str) -> Result<String> {
+        let entry = self.get_entry(entry_id).await?
+            .ok_or_else(|| anyhow!("Entry not found"))?;
+
+        let json_content = serde_json::to_string(&entry)
+            .map_err(|e| anyhow!("Failed to serialize entry: {}", e))?;
+        let encrypted = BackendEncryption::encrypt(&json_content, password)?;
+
+        BackendEncryption::pack_sealed(&encrypted)
+    }
+
+    /// 从加密的分享字符串导入单个条目
+    ///
+    /// 如果已存在相同 ID 的条目，则为导入的条目分配新 ID。
+    pub async fn import_entry_sealed(&self, payload: &str, password: &str) -> Result<MemoryEntry> {
+        let encrypted = BackendEncryption::unpack_sealed(payload)?;
+        let decrypted_content = decrypt_content(encrypted, password)?;
+
+        let mut entry: MemoryEntry = serde_json::from_str(&decrypted_content)
+            .map_err(|e| anyhow!("Failed to parse shared entry: {}", e))?;
+
+        if self.get_entry(&entry.id).await?.is_some() {
+            entry.id = uuid::Uuid::new_v4().to_string();
+        }
+
+        self.save_entry(&entry, self.password.as_deref()).await?;
+        Ok(entry)
+    }
+
+    /// 将单个条目导出为加密的 `.pbpshare` 分享文件
+    pub async fn export_entry_encrypted(&self, entry_id: &str, password: &str, file_path: &str) -> Result<PathBuf> {
+        let payload = self.export_entry_sealed(entry_id, password).await?;
+
+        let mut path = PathBuf::from(file_path);
+        if path.extension().and_then(|ext| ext.to_str()) != Some(SHARE_FILE_EXTENSION) {
+            path.set_extension(SHARE_FILE_EXTENSION);
+        }
+
+        fs::write(&path, payload).await
+            .map_err(|e| anyhow!("Failed to write share file: {}", e))?;
+
+        Ok(path)
+    }
+
+    /// 将统计数据导出为 JSON 文档，供外部看板绘制图表
+    pub async fn export_stats_json(&self, dest: &str) -> Result<PathBuf> {
+        let entries = self.load_entries().await?;
+        let settings = self.load_settings(self.password.as_deref()).await.unwrap_or_default();
+        let export = crate::stats::stats_export(&entries, chrono::Utc::now(), settings.utc_offset(), settings.locale);
+
+        let json_content = serde_json::to_string_pretty(&export)
+            .map_err(|e| anyhow!("Failed to serialize stats: {}", e))?;
+        let path = PathBuf::from(dest);
+        fs::write(&path, json_content).await
+            .map_err(|e| anyhow!("Failed to write stats file: {}", e))?;
+
+        Ok(path)
+    }
+
+    /// 将条目导出为单个自包含的 HTML 页面，可按创建日期（用户时区，闭区间）筛选
+    ///
+    /// 已加锁条目只导出标题与标签，不导出密文。
+    pub async fn export_html(&self, dest: &str, from: Option<chrono::NaiveDate>, to: Option<chrono::NaiveDate>) -> Result<PathBuf> {
+        let entries = self.load_entries().await?;
+        let settings = self.load_settings(self.password.as_deref()).await.unwrap_or_default();
+        let offset = settings.utc_offset();
+        let selected: Vec<MemoryEntry> = entries
+            .into_iter()
+            .filter(|e| html_export::in_date_range(e, offset, from, to))
+            .collect();
+
+        let page = html_export::render_html(&selected, offset, chrono::Utc::now());
+        let path = PathBuf::from(dest);
+        fs::write(&path, page).await
+            .map_err(|e| anyhow!("Failed to write HTML file: {}", e))?;
+
+        Ok(path)
+    }
+
+    /// 从 `.pbpshare` 分享文件导入单个条目
+    ///
+    /// 导入的条目总是分配新 ID，保留 `created_at`，`updated_at` 设为当前时间。
+    pub async fn import_entry_encrypted(&self, file_path: &str, password: &str) -> Result<MemoryEntry> {
+        let payload = fs::read_to_string(file_path).await
+            .map_err(|e| anyhow!("Failed to read share file: {}", e))?;
+        let encrypted = BackendEncryption::unpack_sealed(payload.trim())?;
+        let decrypted_content = decrypt_content(encrypted, password)?;
+
+        let mut entry: MemoryEntry = serde_json::from_str(&decrypted_content)
+            .map_err(|e| anyhow!("Failed to parse shared entry: {}", e))?;
+        entry.id = uuid::Uuid::new_v4().to_string();
+        entry.updated_at = chrono::Utc::now();
+        entry.linked_entry_ids.clear();
+
+        self.save_entry(&entry, self.password.as_deref()).await?;
+        Ok(entry)
+    }
+
+    /// 从其他日记应用的 JSON 导出导入条目
+    pub async fn import_external_json(
+        &self,
+        src: &str,
+        format: ExternalFormat,
+        field_map: Option<GenericFieldMap>,
+    ) -> Result<ImportReport> {
+        let content = fs::read_to_string(src).await
+            .map_err(|e| anyhow!("Failed to read import file: {}", e))?;
+        let value: serde_json::Value = serde_json::from_str(&content)
+            .map_err(|e| anyhow!("Failed to parse import file: {}", e))?;
+
+        let settings = self.load_settings(self.password.as_deref()).await.unwrap_or_default();
+        let (imported, skipped) = importers::parse_external(&value, format, field_map, &settings);
+
+        if !imported.is_empty() {
+            let mut entries = self.load_entries().await?;
+            entries.extend(imported.iter().cloned());
+            self.save_entries(&entries).await?;
+        }
+
+        Ok(ImportReport {
+            imported: imported.len() as u32,
+            skipped,
+            invalid: Vec::new(),
+        })
+    }
+
+    /// 以流式方式导出所有条目为 JSON 文档，返回导出的条目数
+    ///
+    /// 文档为 `{ "generated_at", "entries": [...], "entry_count" }` 信封，条目数在写完条目后写在末尾。
+    /// 明文存储时条目从记忆文件逐条读取并立即写出，外置正文在写出对应条目时才读取，内存占用与条目数无关；
+    /// 加密存储只能先把整个记忆文件解密为文本，内存占用与保险库大小成正比。
+    ///
+    /// 先写入同目录的临时文件，完成后才重命名为 `dest`，中途失败不会留下不完整的导出文件。
+    /// `dest` 不能位于数据目录内，避免覆盖保险库自身的文件。
+    pub async fn export_json_stream(&self, dest: &str, password: Option<&str>) -> Result<u32> {
+        let path = self.export_destination(dest).await?;
+        let source = self.entries_source(password).await?;
+        let contents_dir = self.contents_dir.clone();
+        let generated_at = chrono::Utc::now();
+        tokio::task::spawn_blocking(move || write_json_export(&path, source, &contents_dir, generated_at))
+            .await
+            .map_err(|e| anyhow!("Export task failed: {}", e))?
+    }
+
+    /// 解析导出文件路径（所在目录须已存在），拒绝数据目录内的路径
+    async fn export_destination(&self, dest: &str) -> Result<PathBuf> {
+        let dest = PathBuf::from(dest);
+        let file_name = dest.file_name()
+            .ok_or_else(|| anyhow!("Invalid export path: {}", dest.display()))?;
+        let parent = match dest.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        let parent = fs::canonicalize(parent).await
+            .map_err(|e| anyhow!("Failed to resolve export directory {}: {}", parent.display(), e))?;
+        let data_dir = fs::canonicalize(&self.data_dir).await
+            .map_err(|e| anyhow!("Failed to resolve data directory: {}", e))?;
+        if parent.starts_with(&data_dir) {
+            return Err(anyhow!("Export destination must be outside the data directory: {}", dest.display()));
+        }
+        Ok(parent.join(file_name))
+    }
+
+    /// 确定导出所读取的条目来源；加密时用密码解密（带暴力破解保护），但不解析为条目
+    async fn entries_source(&self, password: Option<&str>) -> Result<EntriesSource> {
+        match first_json_byte(&self.entries_file).await? {
+            None => Ok(EntriesSource::Empty),
+            Some(b'{') => {
+                let content = fs::read_to_string(&self.entries_file).await
+                    .map_err(|e| anyhow!("Failed to read entries file: {}", e))?;
+                let encrypted = serde_json::from_str::<EncryptionResult>(&content)
+                    .map_err(|e| anyhow!("Failed to parse entries file: {}", e))?;
+                let password = password.ok_or_else(|| anyhow!("Entries are encrypted, password required"))?;
+                let json = self.with_attempt_throttle(tokio::time::sleep, async { decrypt_content(encrypted, password) }).await?;
+                Ok(EntriesSource::Decrypted(json))
+            }
+            Some(_) => Ok(EntriesSource::File(self.entries_file.clone())),
+        }
+    }
+
+    /// 导出所有条目并打包引用的附件文件
+    ///
+    /// 附件复制到导出目录的 `attachments/` 下，`file_path` 改写为相对于导出目录的路径。
+    pub async fn export_with_attachments(&self, export_path: &str) -> Result<ExportReport> {
+        let mut entries = self.load_entries().await?;
+        // 导出包自包含：外置正文内联
+        for entry in entries.iter_mut() {
+            self.load_content(entry).await?;
+            entry.content_ref = None;
+        }
+
+        let export_root = PathBuf::from(export_path);
+        let export_name = format!("export-{}", chrono::Utc::now().format("%Y%m%d-%H%M%S"));
+        let mut export_dir = export_root.join(&export_name);
+        let mut suffix = 1;
+        while export_dir.exists() {
+            export_dir = export_root.join(format!("{}-{}", export_name, suffix));
+            suffix += 1;
+        }
+
+        let attachments_dir = export_dir.join(EXPORT_ATTACHMENTS_DIR);
+        fs::create_dir_all(&attachments_dir).await
+            .map_err(|e| anyhow!("Failed to create export directory: {}", e))?;
+
+        let mut copied = 0;
+        let mut missing = Vec::new();
+        for attachment in entries.iter_mut().flat_map(|e| e.attachments.iter_mut().flatten()) {
+            let relative = format!("{}/{}-{}", EXPORT_ATTACHMENTS_DIR, attachment.id, attachment.file_name);
+            let source = self.attachment_path(attachment);
+
+            if source.is_file() {
+                fs::copy(&source, export_dir.join(&relative)).await
+                    .map_err(|e| anyhow!("Failed to copy attachment {}: {}", attachment.file_name, e))?;
+                copied += 1;
+            } else {
+                missing.push(attachment.id.clone());
+            }
+            attachment.file_path = relative;
+        }
+
+        let json_content = serde_json::to_string_pretty(&entries)
+            .map_err(|e| anyhow!("Failed to serialize entries: {}", e))?;
+        fs::write(export_dir.join(EXPORT_ENTRIES_FILE), json_content).await
+            .map_err(|e| anyhow!("Failed to write export file: {}", e))?;
+
+        Ok(ExportReport {
+            path: export_dir.to_string_lossy().to_string(),
+            entries: entries.len() as u32,
+            attachments: copied,
+            missing,
+        })
+    }
+
+    /// 导入带附件的导出包
+    ///
+    /// 附件复制到本地附件目录并分配新的附件 ID；与现有条目 ID 冲突时分配新 ID。
+    /// 路径指向导出目录之外的附件会被跳过。未通过校验的记录不会中断导入，
+    /// 而是逐条列在报告的 `invalid` 中。
+    pub async fn import_with_attachments(&self, export_dir: &str) -> Result<ImportReport> {
+        self.ensure_not_read_only()?;
+        let export_dir = PathBuf::from(export_dir);
+        let content = fs::read_to_string(export_dir.join(EXPORT_ENTRIES_FILE)).await
+            .map_err(|e| anyhow!("Failed to read export file: {}", e))?;
+        let value: serde_json::Value = serde_json::from_str(&content)
+            .map_err(|e| anyhow!("Failed to parse export file: {}", e))?;
+        let (mut imported, invalid) = importers::validate_native_records(&value)
+            .map_err(|e| anyhow!("Failed to parse export file: {}", e))?;
+
+        fs::create_dir_all(&self.attachments_dir).await
+            .map_err(|e| anyhow!("Failed to create attachments directory: {}", e))?;
+
+        let mut entries = self.load_entries().await?;
+        let mut skipped = Vec::new();
+        for entry in imported.iter_mut() {
+            if entries.iter().any(|e| e.id == entry.id) {
+                entry.id = uuid::Uuid::new_v4().to_string();
+            }
+
+            let Some(attachments) = entry.attachments.as_mut() else { continue };
+            let mut kept = Vec::with_capacity(attachments.len());
+            for mut attachment in attachments.drain(..) {
+                // 导出包来自外部，路径与文件名都不可信
+                let source = match contained_file(&export_dir, Path::new(&attachment.file_path)).await {
+                    Ok(source) => source,
+                    Err(e) => {
+                        skipped.push(format!("attachment {}: {}", attachment.file_name, e));
+                        continue;
+                    }
+                };
+                let Some(file_name) = safe_file_name(&attachment.file_name) else {
+                    skipped.push(format!("attachment {}: invalid file name", attachment.file_name));
+                    continue;
+                };
+
+                attachment.id = uuid::Uuid::new_v4().to_string();
+                let stored_name = format!("{}-{}", attachment.id, file_name);
+                match source {
+                    Some(source) => {
+                        fs::copy(&source, self.attachments_dir.join(&stored_name)).await
+                            .map_err(|e| anyhow!("Failed to restore attachment {}: {}", file_name, e))?;
+                    }
+                    None => skipped.push(format!("attachment {}: file missing from export", file_name)),
+                }
+                attachment.file_name = file_name;
+                attachment.file_path = format!("{}/{}", ATTACHMENTS_DIR, stored_name);
+                kept.push(attachment);
+            }
+            *attachments = kept;
+        }
+        entries.extend(imported.iter().cloned());
+        self.save_entries(&entries).await?;
+
+        Ok(ImportReport {
+            imported: imported.len() as u32,
+            skipped,
+            invalid,
+        })
+    }
+
+    /// 把另一个保险库目录中的条目合并到当前存储，合并结果只保存一次
+    ///
+    /// 另一保险库以只读方式打开，用 `other_password` 解密（可与当前存储的密码不同）；
+    /// 合并进来的条目引用的附件会复制到本地附件目录，指向其数据目录之外的附件被丢弃。
+    pub async fn merge_vault(&self, other_path: &str, other_password: Option<&str>, strategy: MergeStrategy) -> Result<MergeReport> {
+        self.ensure_not_read_only()?;
+        let other = StorageManager::new_read_only(other_path).await?;
+        if other.data_dir.canonicalize().ok() == self.data_dir.canonicalize().ok() {
+            return Err(anyhow!("Cannot merge a vault into itself"));
+        }
+
+        let mut incoming = other.load_entries_for(other_password).await?;
+        let mut copies = Vec::new();
+        for entry in incoming.iter_mut() {
+            // 正文已加载，外置文件属于另一保险库
+            entry.content_ref = None;
+            let Some(attachments) = entry.attachments.as_mut() else { continue };
+            // 另一保险库的数据不可信：来源必须位于其数据目录内，ID 与文件名不能带路径
+            let mut kept = Vec::with_capacity(attachments.len());
+            for mut attachment in attachments.drain(..) {
+                let recorded = Path::new(&attachment.file_path);
+                let relative = recorded.strip_prefix(&other.data_dir).unwrap_or(recorded);
+                let (Ok(source), Some(file_name)) = (
+                    contained_file(&other.data_dir, relative).await,
+                    safe_file_name(&attachment.file_name),
+                ) else {
+                    continue;
+                };
+                if safe_file_name(&attachment.id).as_deref() != Some(attachment.id.as_str()) {
+                    attachment.id = uuid::Uuid::new_v4().to_string();
+                }
+
+                let stored_name = format!("{}-{}", attachment.id, file_name);
+                if let Some(source) = source {
+                    copies.push((attachment.id.clone(), source, self.attachments_dir.join(&stored_name)));
+                }
+                attachment.file_name = file_name;
+                attachment.file_path = format!("{}/{}", ATTACHMENTS_DIR, stored_name);
+                kept.push(attachment);
+            }
+            *attachments = kept;
+        }
+
+        let local = self.load_entries().await?;
+        let (merged, report) = merge_entries(local, incoming, strategy);
+        if report.added == 0 && report.updated == 0 {
+            return Ok(report);
+        }
+
+        let kept: HashSet<&str> = merged.iter()
+            .flat_map(|e| e.attachments.iter().flatten())
+            .map(|a| a.id.as_str())
+            .collect();
+        fs::create_dir_all(&self.attachments_dir).await
+            .map_err(|e| anyhow!("Failed to create attachments directory: {}", e))?;
+        for (id, source, dest) in &copies {
+            if kept.contains(id.as_str()) && !dest.exists() {
+                fs::copy(source, dest).await
+                    .map_err(|e| anyhow!("Failed to copy attachment {}: {}", source.display(), e))?;
+            }
+        }
+
+        self.save_entries(&merged).await?;
+        Ok(report)
+    }
+
+    /// 修复损坏的记忆文件
+    ///
+    /// 宽松解析条目数组，跳过无法解析的对象，原文件保留为 `.corrupt`，
+    /// 恢复出的条目写回记忆文件。
+    pub async fn repair_vault(&self) -> Result<RepairReport> {
+        self.ensure_not_read_only()?;
+        if !self.entries_file.exists() {
+            return Ok(RepairReport { recovered: 0, dropped: 0, corrupt_file: None });
+        }
+
+        let content = fs::read_to_string(&self.entries_file).await
+            .map_err(|e| anyhow!("Failed to read entries file: {}", e))?;
+
+        if serde_json::from_str::<EncryptionResult>(&content).is_ok() {
+            return Err(anyhow!("Entries are encrypted, cannot repair without decrypting"));
+        }
+
+        // 文件完好则无需修复
+        if serde_json::from_str::<Vec<MemoryEntry>>(&content).is_ok() {
+            return Ok(RepairReport { recovered: 0, dropped: 0, corrupt_file: None });
+        }
+
+        let (fragments, truncated) = extract_json_objects(&content);
+        let mut dropped = if truncated { 1 } else { 0 };
+        let mut recovered = Vec::new();
+        for fragment in fragments {
+            match serde_json::from_str::<MemoryEntry>(fragment) {
+                Ok(entry) => recovered.push(entry),
+                Err(_) => dropped += 1,
+            }
+        }
+
+        // 保留原始文件
+        let mut corrupt_name = self.entries_file.as_os_str().to_owned();
+        corrupt_name.push(".corrupt");
+        let corrupt_file = PathBuf::from(corrupt_name);
+        fs::rename(&self.entries_file, &corrupt_file).await
+            .map_err(|e| anyhow!("Failed to keep corrupt entries file: {}", e))?;
+
+        self.save_all_entries(&recovered, None).await?;
+
+        Ok(RepairReport {
+            recovered: recovered.len() as u32,
+            dropped,
+            corrupt_file: Some(corrupt_file.to_string_lossy().to_string()),
+        })
+    }
+
+    /// 清理手工编辑过的记忆文件
+    ///
+    /// 宽松读取每条记录：补全缺失的时间戳，重复 ID 只保留 `updated_at` 最新的一条，
+    /// 为缺少字数的条目重新计算字数。有修改时先把原文件备份为
+    /// `memories.json.<时间戳>.bak` 再写回，报告列出每一项修改。
+    pub async fn repair_store(&self) -> Result<SanitizeReport> {
+        self.ensure_not_read_only()?;
+        if !self.entries_file.exists() {
+            return Ok(SanitizeReport { entry_count: 0, changes: Vec::new(), backup_file: None });
+        }
+
+        let content = fs::read_to_string(&self.entries_file).await
+            .map_err(|e| anyhow!("Failed to read entries file: {}", e))?;
+        let content = match serde_json::from_str::<EncryptionResult>(&content) {
+            Ok(encrypted) => {
+                let password = self.password.as_deref()
+                    .ok_or_else(|| anyhow!("Entries are encrypted, password required"))?;
+                decrypt_content(encrypted, password)?
+            }
+            Err(_) => content,
+        };
+
+        let values = match serde_json::from_str::<Vec<serde_json::Value>>(&content) {
+            Ok(values) => values,
+            Err(_) => extract_json_objects(&content).0
+                .into_iter()
+                .map(|fragment| serde_json::from_str(fragment).unwrap_or(serde_json::Value::Null))
+                .collect(),
+        };
+
+        let settings = self.load_settings(self.password.as_deref()).await.unwrap_or_default();
+        let (entries, changes) = sanitize_entries(values, chrono::Utc::now(), &settings);
+        if changes.is_empty() {
+            return Ok(SanitizeReport { entry_count: entries.len() as u32, changes, backup_file: None });
+        }
+
+        let mut backup_name = self.entries_file.as_os_str().to_owned();
+        backup_name.push(format!(".{}.bak", chrono::Utc::now().format("%Y%m%d%H%M%S")));
+        let backup_file = PathBuf::from(backup_name);
+        fs::copy(&self.entries_file, &backup_file).await
+            .map_err(|e| anyhow!("Failed to back up entries file: {}", e))?;
+
+        self.save_all_entries(&entries, self.password.as_deref()).await?;
+
+        Ok(SanitizeReport {
+            entry_count: entries.len() as u32,
+            changes,
+            backup_file: Some(backup_file.to_string_lossy().to_string()),
+        })
+    }
+
+    /// 加载用户设置（文件不存在或已损坏时返回默认值，加密时需要密码）
+    pub async fn load_settings(&self, password: Option<&str>) -> Result<UserSettings> {
+        self.read_config_file(&self.settings_file, password).await
+    }
+
+    /// 保存用户设置
+    ///
+    /// 仅当已持久化的设置开启了 `encrypt_metadata` 时才加密保存，
+    /// 因此首次开启该选项的保存仍为明文，用于确立标志。
+    pub async fn save_settings(&self, settings: &UserSettings, password: Option<&str>) -> Result<()> {
+        self.ensure_not_read_only()?;
+        self.set_aside_corrupt_config::<UserSettings>(&self.settings_file, password).await?;
+        let encrypt = settings.encrypt_metadata && self.is_metadata_encryption_enabled().await?;
+        self.write_json_file(&self.settings_file, settings, encrypt, settings.pretty_json, password).await
+    }
+
+    /// 加载拾梦回响配置（文件不存在或已损坏时返回默认值）
+    pub async fn load_dream_config(&self, password: Option<&str>) -> Result<DreamConfig> {
+        self.read_config_file(&self.dream_config_file, password).await
+    }
+
+    /// 保存拾梦回响配置（已开启元数据加密时加密保存）
+    pub async fn save_dream_config(&self, config: &DreamConfig, password: Option<&str>) -> Result<()> {
+        self.ensure_not_read_only()?;
+        self.set_aside_corrupt_config::<DreamConfig>(&self.dream_config_file, password).await?;
+        let encrypt = self.is_metadata_encryption_enabled().await?;
+        let pretty = self.pretty_json(password).await;
+        self.write_json_file(&self.dream_config_file, config, encrypt, pretty, password).await
+    }
+
+    /// 更换存储密码，用新密码重新加密所有已加密的数据文件，返回重新加密的文件数
+    ///
+    /// 解密与重新加密在阻塞线程中完成，每处理完一个文件回调一次
+    /// `progress(processed, total)`。全部成功后才写回，旧密码错误时不改动任何文件。
+    pub async fn change_password<F>(&self, old_password: &str, new_password: &str, progress: F) -> Result<usize>
+    where
+        F: FnMut(usize, usize) + Send + 'static,
+    {
+        self.ensure_not_read_only()?;
+
+        let mut paths = Vec::new();
+        let mut originals = Vec::new();
+        let mut items = Vec::new();
+        for path in [&self.entries_file, &self.settings_file, &self.dream_config_file] {
+            if !path.exists() {
+                continue;
+            }
+            let content = fs::read_to_string(path).await
+                .map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+            if let Ok(encrypted) = serde_json::from_str::<EncryptionResult>(&content) {
+                paths.push(path.clone());
+                originals.push(content);
+                items.push(encrypted);
+            }
+        }
+
+        if items.is_empty() {
+            return Err(anyhow!("Store is not encrypted, nothing to re-encrypt"));
+        }
+
+        let old_password = old_password.to_string();
+        let new_password = new_password.to_string();
+        let reencrypted = tokio::task::spawn_blocking(move || {
+            BackendEncryption::reencrypt_all(items, &old_password, &new_password, progress)
+        })
+        .await
+        .map_err(|e| anyhow!("Re-encryption task failed: {}", e))??;
+
+        let mut contents = Vec::with_capacity(reencrypted.len());
+        for encrypted in &reencrypted {
+            contents.push(serde_json::to_string(encrypted)
+                .map_err(|e| anyhow!("Failed to serialize encrypted data: {}", e))?);
+        }
+
+        // 所有文件一起替换：不会出现部分文件换成新密码、部分仍是旧密码的情况
+        replace_all(&paths, &contents, &originals).await?;
+        Ok(reencrypted.len())
+    }
+
+    /// 轮换加密材料：密码不变，用新的盐值与 nonce 及当前 KDF 参数重新加密所有已加密的数据文件
+    ///
+    /// 每次加密都会重新生成盐值与 nonce，因此等同于以同一密码执行 [`Self::change_password`]，
+    /// 同样在所有文件重新加密成功后才一起替换，失败时保留原有密文。
+    pub async fn rotate_encryption<F>(&self, password: &str, progress: F) -> Result<usize>
+    where
+        F: FnMut(usize, usize) + Send + 'static,
+    {
+        self.change_password(password, password, progress).await
+    }
+
+    /// 设置密码提示，提示为空时清除
+    ///
+    /// 提示单独加密保存在设置文件之外，这样即使设置已用主密码加密，也能在输入密码前
+    /// 显示。提示不能包含密码本身；存储已加密时会先校验密码。
+    pub async fn set_password_hint(&self, hint: &str, password: &str) -> Result<()> {
+        self.ensure_not_read_only()?;
+        let hint = hint.trim();
+        if hint.is_empty() {
+            if self.hint_file.exists() {
+                fs::remove_file(&self.hint_file).await
+                    .map_err(|e| anyhow!("Failed to remove password hint: {}", e))?;
+            }
+            return Ok(());
+        }
+
+        if hint.chars().count() > MAX_PASSWORD_HINT_CHARS {
+            return Err(anyhow!("Password hint must be at most {} characters", MAX_PASSWORD_HINT_CHARS));
+        }
+        if !password.is_empty() && hint.to_lowercase().contains(&password.to_lowercase()) {
+            return Err(anyhow!("Password hint must not contain the password"));
+        }
+        if self.is_entries_encrypted().await {
+            self.load_entries_with_password(password).await
+                .map_err(|_| anyhow!("Password is incorrect"))?;
+        }
+
+        let encrypted = BackendEncryption::encrypt(hint, PASSWORD_HINT_KEY)?;
+        self.write_json_file(&self.hint_file, &encrypted, false, true, None).await
+    }
+
+    /// 获取密码提示（只返回提示文本）
+    pub async fn get_password_hint(&self) -> Result<Option<String>> {
+        if !self.hint_file.exists() {
+            return Ok(None);
+        }
+
+        let content = fs::read_to_string(&self.hint_file).await
+            .map_err(|e| anyhow!("Failed to read password hint: {}", e))?;
+        let encrypted: EncryptionResult = serde_json::from_str(&content)
+            .map_err(|e| anyhow!("Failed to parse password hint: {}", e))?;
+
+        Ok(Some(decrypt_content(encrypted, PASSWORD_HINT_KEY)?))
+    }
+
+    /// 明文 JSON 是否缩进排版（设置无法读取时保持默认的缩进格式）
+    async fn pretty_json(&self, password: Option<&str>) -> bool {
+        self.load_settings(password)
+            .await
+            .map(|settings| settings.pretty_json)
+            .unwrap_or(true)
+    }
+
+    /// 检查已持久化的设置是否开启了元数据加密
+    async fn is_metadata_encryption_enabled(&self) -> Result<bool> {
+        if !self.settings_file.exists() {
+            return Ok(false);
+        }
+
+        let content = fs::read_to_string(&self.settings_file).await
+            .map_err(|e| anyhow!("Failed to read settings file: {}", e))?;
+
+        if serde_json::from_str::<EncryptionResult>(&content).is_ok() {
+            return Ok(true);
+        }
+
+        let settings: UserSettings = serde_json::from_str(&content)
+            .map_err(|e| anyhow!("Failed to parse settings: {}", e))?;
+        Ok(settings.encrypt_metadata)
+    }
+
+    /// 读取 JSON 文件，自动识别并解密加密内容
+    async fn read_json_file<T: DeserializeOwned + Default>(&self, path: &Path, password: Option<&str>) -> Result<T> {
+        self.parse_json_file(path, password).await?
+            .map_err(|reason| anyhow!("Failed to parse {}: {}", path.display(), reason))
+    }
+
+    /// 读取配置文件，内容损坏时回退为默认值
+    ///
+    /// 非 UTF-8、无法解析的 JSON 或损坏的密文按默认值处理，文件保持不动，下次保存时才留存为
+    /// `.bad`（见 [`Self::set_aside_corrupt_config`]）；缺少密码或密码错误仍返回错误，避免把加密的
+    /// 配置误当作损坏。
+    async fn read_config_file<T: DeserializeOwned + Default>(&self, path: &Path, password: Option<&str>) -> Result<T> {
+        Ok(self.parse_config_file(path, password).await?.unwrap_or_default())
+    }
+
+    /// 解析配置文件，损坏的密文与无法解析的内容一样归为内层错误
+    async fn parse_config_file<T: DeserializeOwned + Default>(&self, path: &Path, password: Option<&str>) -> Result<std::result::Result<T, String>> {
+        match self.parse_json_file(path, password).await {
+            Ok(parsed) => Ok(parsed),
+            Err(e) if matches!(e.downcast_ref::<DecryptError>(), Some(DecryptError::CorruptData(_))) => Ok(Err(e.to_string())),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// 保存配置前把已损坏的旧文件改名为 `<文件名>.bad` 留存
+    ///
+    /// 改名只发生在写入路径上：读取方遇到损坏的文件只回退为默认值，不改动磁盘。
+    async fn set_aside_corrupt_config<T: DeserializeOwned + Default>(&self, path: &Path, password: Option<&str>) -> Result<()> {
+        if self.parse_config_file::<T>(path, password).await.is_ok_and(|parsed| parsed.is_err()) {
+            let bad_path = corrupt_config_path(path);
+            fs::rename(path, &bad_path).await
+                .map_err(|e| anyhow!("Failed to back up corrupt {}: {}", path.display(), e))?;
+        }
+        Ok(())
+    }
+
+    /// 读取并解析 JSON 文件，必要时解密
+    ///
+    /// 外层错误为读取失败、缺少密码或解密失败；内层错误为内容损坏（非 UTF-8 或无法解析）的原因。
+    async fn parse_json_file<T: DeserializeOwned + Default>(&self, path: &Path, password: Option<&str>) -> Result<std::result::Result<T, String>> {
+        if !path.exists() {
+            return Ok(Ok(T::default()));
+        }
+
+        let bytes = fs::read(path).await
+            .map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+        let content = match String::from_utf8(bytes) {
+            Ok(content) => content,
+            Err(e) => return Ok(Err(format!("invalid UTF-8: {}", e))),
+        };
+
+        if content.trim().is_empty() {
+            return Ok(Ok(T::default()));
+        }
+
+        let content = match serde_json::from_str::<EncryptionResult>(&content) {
+            Ok(encrypted) => {
+                let password = password
+                    .ok_or_else(|| anyhow!("{} is encrypted, password required", path.display()))?;
+                decrypt_content(encrypted, password)?
+            }
+            Err(_) => content,
+        };
+
+        Ok(serde_json::from_str(&content).map_err(|e| e.to_string()))
+    }
+
+    /// 写入 JSON 文件，按需加密；`pretty` 仅对明文生效
+    ///
+    /// 只读存储上仅允许写入密码尝试记录。
+    async fn write_json_file<T: Serialize>(&self, path: &Path, value: &T, encrypt: bool, pretty: bool, password: Option<&str>) -> Result<()> {
+        if path != self.attempts_file {
+            self.ensure_not_read_only()?;
+        }
+
+        let json_content = to_json(value, pretty && !encrypt)
+            .map_err(|e| anyhow!("Failed to serialize {}: {}", path.display(), e))?;
+
+        let content_to_save = if encrypt {
+            let password = password
+                .ok_or_else(|| anyhow!("Metadata encryption is enabled, password required"))?;
+            let encrypted = BackendEncryption::encrypt(&json_content, password)?;
+            serde_json::to_string(&encrypted)
+                .map_err(|e| anyhow!("Failed to serialize encrypted data: {}", e))?
+        } else {
+            json_content
+        };
+
+        write_atomic(path, content_to_save).await
+            .map_err(|e| anyhow!("Failed to write {}: {}", path.display(), e))?;
+
+        Ok(())
+    }
+
+    /// 读取旧版明文条目，并按设置迁移为加密存储
+    async fn migrate_plaintext_entries(&self, content: &str, password: &str) -> Result<Vec<MemoryEntry>> {
+        let entries: Vec<MemoryEntry> = serde_json::from_str(content)
+            .map_err(|e| anyhow!("Failed to parse entries: {}", e))?;
+
+        if self.read_only {
+            return Ok(entries);
+        }
+
+        let settings = self.load_settings(Some(password)).await.unwrap_or_default();
+        if settings.migrate_to_encrypted {
+            self.save_all_entries(&entries, Some(password)).await?;
+        }
+
+        Ok(entries)
+    }
+
+    /// 安全清空保险库
+    ///
+    /// 需要精确输入 [`WIPE_CONFIRM_PHRASE`]。记忆、设置、配置、密码提示与密码尝试记录文件及其备份副本，
+    /// 以及附件与外置正文目录中的文件会先用随机字节覆盖再删除，返回被删除的路径。`dry_run` 为 true 时只返回
+    /// 将被删除的路径，不需要确认短语，也不改动任何文件。
+    pub async fn wipe_vault(&self, confirm_phrase: &str, dry_run: bool) -> Result<Vec<String>> {
+        if !dry_run {
+            self.ensure_not_read_only()?;
+        }
+        if !dry_run && confirm_phrase != WIPE_CONFIRM_PHRASE {
+            return Err(anyhow!("Confirmation phrase does not match, vault was not wiped"));
+        }
+
+        let mut removed = Vec::new();
+        let data_files = [
+            self.entries_file.clone(),
+            self.settings_file.clone(),
+            self.dream_config_file.clone(),
+            self.hint_file.clone(),
+            self.attempts_file.clone(),
+        ];
+        for file in data_files.into_iter().chain(self.data_file_copies().await?) {
+            if file.exists() {
+                if !dry_run {
+                    shred_file(&file).await?;
+                }
+                removed.push(file.to_string_lossy().to_string());
+            }
+        }
+
+        for dir in [&self.attachments_dir, &self.contents_dir] {
+            if !dir.exists() {
+                continue;
+            }
+            for (path, _) in list_files(dir).await? {
+                if !dry_run {
+                    shred_file(&path).await?;
+                }
+                removed.push(path.to_string_lossy().to_string());
+            }
+
+            if !dry_run {
+                fs::remove_dir_all(dir).await
+                    .map_err(|e| anyhow!("Failed to remove {}: {}", dir.display(), e))?;
+            }
+            removed.push(dir.to_string_lossy().to_string());
+        }
+
+        Ok(removed)
+    }
+
+    /// 数据目录中数据文件留下的副本，按路径排序
+    ///
+    /// 包括修复前的 `.corrupt` 与 `<时间戳>.bak` 备份、损坏配置留存的 `.bad`，以及写入中断残留的临时文件。
+    async fn data_file_copies(&self) -> Result<Vec<PathBuf>> {
+        let names: Vec<String> = [&self.entries_file, &self.settings_file, &self.dream_config_file, &self.hint_file]
+            .iter()
+            .filter_map(|path| path.file_name().map(|n| n.to_string_lossy().to_string()))
+            .collect();
+
+        let mut copies = Vec::new();
+        let mut items = fs::read_dir(&self.data_dir).await
+            .map_err(|e| anyhow!("Failed to read {}: {}", self.data_dir.display(), e))?;
+        while let Some(item) = items.next_entry().await
+            .map_err(|e| anyhow!("Failed to read {}: {}", self.data_dir.display(), e))? {
+            let file_name = item.file_name().to_string_lossy().to_string();
+            let is_copy = names.iter().any(|name| {
+                let backup = file_name.strip_prefix(name.as_str()).is_some_and(|rest| {
+                    rest.starts_with('.') && [".bak", ".bad", ".corrupt"].iter().any(|ext| rest.ends_with(ext))
+                });
+                let temp = file_name.starts_with(&format!(".{}.", name)) && file_name.ends_with(".tmp");
+                backup || temp
+            });
+            if is_copy && item.path().is_file() {
+                copies.push(item.path());
+            }
+        }
+        copies.sort();
+        Ok(copies)
+    }
+
+    /// 获取随机记忆条目（用于拾梦回响，不包含已归档条目）
+    pub async fn get_random_entry(&self) -> Result<Option<MemoryEntry>> {
+        let entries = self.get_visible_entries().await?;
+        
+        if entries.is_empty() {
+            return Ok(None);
+        }
+
+        use rand::seq::SliceRandom;
+        let mut rng = rand::thread_rng();
+        let mut random_entry = entries.choose(&mut rng).cloned();
+        if let Some(entry) = random_entry.as_mut() {
+            self.load_content(entry).await?;
+        }
+        
+        Ok(random_entry)
+    }
+
+    /// 备份数据
+    ///
+    /// 先复制到临时目录，全部文件复制并校验大小后再重命名为带时间戳的备份目录，
+    /// 返回最终备份目录路径。
+    pub async fn backup_data(&self, backup_path: &str) -> Result<PathBuf> {
+        self.backup_data_with(backup_path, |from, to| std::fs::copy(from, to)).await
+    }
+
+    /// 使用指定的复制函数备份数据
+    async fn backup_data_with<F>(&self, backup_path: &str, copy_file: F) -> Result<PathBuf>
+    where
+        F: Fn(&Path, &Path) -> std::io::Result<u64>,
+    {
+        let backup_root = user_dir_path(backup_path)?;
+
+        if !backup_root.exists() {
+            fs::create_dir_all(&backup_root).await
+                .map_err(|e| anyhow!("Failed to create backup directory: {}", e))?;
+        }
+        let backup_root = fs::canonicalize(&backup_root).await
+            .map_err(|e| anyhow!("Failed to resolve backup directory: {}", e))?;
+        if !backup_root.is_dir() {
+            return Err(anyhow!("Backup path is not a directory: {}", backup_root.display()));
+        }
+
+        let backup_name = format!("backup-{}", chrono::Utc::now().format("%Y%m%d-%H%M%S"));
+        let mut final_dir = backup_root.join(&backup_name);
+        let mut suffix = 1;
+        while final_dir.exists() {
+            final_dir = backup_root.join(format!("{}-{}", backup_name, suffix));
+            suffix += 1;
+        }
+
+        let temp_dir = backup_root.join(format!(".tmp-{}", backup_name));
+        fs::create_dir_all(&temp_dir).await
+            .map_err(|e| anyhow!("Failed to create temporary backup directory: {}", e))?;
+
+        if let Err(e) = self.copy_backup_files(&temp_dir, &copy_file).await {
+            let _ = fs::remove_dir_all(&temp_dir).await;
+            return Err(e);
+        }
+
+        fs::rename(&temp_dir, &final_dir).await
+            .map_err(|e| anyhow!("Failed to finalize backup: {}", e))?;
+
+        Ok(final_dir)
+    }
+
+    /// 需要备份的文件及其在备份目录中的文件名
+    fn backup_files(&self) -> [(&Path, &'static str); 3] {
+        [
+            (&self.entries_file, "memories_backup.json"),
+            (&self.settings_file, "settings.json"),
+            (&self.dream_config_file, "dream_config.json"),
+        ]
+    }
+
+    /// 清单中的文件名是否为备份会写出的名称：固定的数据文件名或 `contents/<文件名>`
+    fn is_backup_file_name(&self, name: &str) -> bool {
+        match name.split_once('/') {
+            Some((dir, file)) => dir == CONTENTS_DIR && self.content_path(file).is_ok(),
+            None => self.backup_files().iter().any(|(_, known)| *known == name),
+        }
+    }
+
+    /// 按清单校验备份目录中的文件，返回齐全、损坏与缺失的文件名（不做恢复）
+    ///
+    /// 不是备份文件名的路径与符号链接计为损坏。
+    async fn check_backup_files(&self, backup_dir: &Path, manifest: &BackupManifest) -> Result<RestoreReport> {
+        let mut report = RestoreReport {
+            restored: false,
+            verified: Vec::new(),
+            corrupted: Vec::new(),
+            missing: Vec::new(),
+        };
+
+        for (name, expected_hash) in &manifest.files {
+            let path = backup_dir.join(name);
+            if !self.is_backup_file_name(name) || is_symlink(&path).await {
+                report.corrupted.push(name.clone());
+            } else if !path.exists() {
+                report.missing.push(name.clone());
+            } else if hash_file(&path).await? != *expected_hash {
+                report.corrupted.push(name.clone());
+            } else {
+                report.verified.push(name.clone());
+            }
+        }
+        Ok(report)
+    }
+
+    /// 外置正文文件及其在备份目录中的相对路径（`contents/<文件名>`）
+    async fn content_backup_files(&self) -> Result<Vec<(PathBuf, String)>> {
+        Ok(list_files(&self.contents_dir).await?
+            .into_iter()
+            .filter_map(|(path, _)| {
+                let name = format!("{}/{}", CONTENTS_DIR, path.file_name()?.to_string_lossy());
+                Some((path, name))
+            })
+            .collect())
+    }
+
+    /// 将需要备份的文件复制到目标目录，校验大小并写入哈希清单
+    async fn copy_backup_files<F>(&self, target_dir: &Path, copy_file: &F) -> Result<()>
+    where
+        F: Fn(&Path, &Path) -> std::io::Result<u64>,
+    {
+        let mut manifest = BackupManifest {
+            created_at: chrono::Utc::now(),
+            files: Default::default(),
+        };
+
+        let mut files: Vec<(PathBuf, String)> = self.backup_files()
+            .iter()
+            .map(|(path, name)| (path.to_path_buf(), name.to_string()))
+            .collect();
+        let content_files = self.content_backup_files().await?;
+        if !content_files.is_empty() {
+            fs::create_dir_all(target_dir.join(CONTENTS_DIR)).await
+                .map_err(|e| anyhow!("Failed to create backup contents directory: {}", e))?;
+        }
+        files.extend(content_files);
+
+        for (source, name) in &files {
+            let (source, name) = (source.as_path(), name.as_str());
+            if !source.exists() {
+                continue;
+            }
+
+            let target = target_dir.join(name);
+            copy_file(source, &target)
+                .map_err(|e| anyhow!("Failed to backup {}: {}", name, e))?;
+
+            let source_size = fs::metadata(source).await
+                .map_err(|e| anyhow!("Failed to read {} metadata: {}", name, e))?
+                .len();
+            let target_size = fs::metadata(&target).await
+                .map_err(|e| anyhow!("Failed to read backup {} metadata: {}", name, e))?
+                .len();
+            if source_size != target_size {
+                return Err(anyhow!("Backup of {} is incomplete ({} of {} bytes)", name, target_size, source_size));
+            }
+
+            let source_hash = hash_file(source).await?;
+            if hash_file(&target).await? != source_hash {
+                return Err(anyhow!("Backup of {} does not match the source checksum", name));
+            }
+            manifest.files.insert(name.to_string(), source_hash);
+        }
+
+        let manifest_json = serde_json::to_string_pretty(&manifest)
+            .map_err(|e| anyhow!("Failed to serialize backup manifest: {}", e))?;
+        fs::write(target_dir.join(BACKUP_MANIFEST), manifest_json).await
+            .map_err(|e| anyhow!("Failed to write backup manifest: {}", e))?;
+
+        Ok(())
+    }
+
+    /// 从备份恢复数据
+    ///
+    /// 先按清单校验每个备份文件的哈希，任一文件缺失或不匹配则拒绝恢复，
+    /// 不会覆盖现有数据。清单中不是备份文件名的路径（如含 `..`）与符号链接都视为损坏；
+    /// 恢复目标是符号链接时拒绝恢复，避免写到数据目录之外。
+    pub async fn restore_data(&self, backup_dir: &str) -> Result<RestoreReport> {
+        self.ensure_not_read_only()?;
+        let backup_dir = resolve_backup_dir(backup_dir).await?;
+        let manifest_content = fs::read_to_string(backup_dir.join(BACKUP_MANIFEST)).await
+            .map_err(|e| anyhow!("Failed to read backup manifest: {}", e))?;
+        let manifest: BackupManifest = serde_json::from_str(&manifest_content)
+            .map_err(|e| anyhow!("Failed to parse backup manifest: {}", e))?;
+
+        let mut report = self.check_backup_files(&backup_dir, &manifest).await?;
+        if !report.corrupted.is_empty() || !report.missing.is_empty() {
+            return Ok(report);
+        }
+
+        let mut copies = Vec::new();
+        for (target, name) in self.backup_files() {
+            if manifest.files.contains_key(name) {
+                copies.push((name.to_string(), target.to_path_buf()));
+            }
+        }
+        // 外置正文：清单中的 `contents/<文件名>`
+        for name in manifest.files.keys() {
+            let Some(content_ref) = name.strip_prefix(&format!("{}/", CONTENTS_DIR)) else { continue };
+            copies.push((name.clone(), self.content_path(content_ref)?));
+        }
+
+        for (name, target) in &copies {
+            if is_symlink(target).await {
+                return Err(anyhow!("Refusing to restore {} over a symbolic link: {}", name, target.display()));
+            }
+        }
+        if copies.iter().any(|(_, target)| target.starts_with(&self.contents_dir)) {
+            fs::create_dir_all(&self.contents_dir).await
+                .map_err(|e| anyhow!("Failed to create contents directory: {}", e))?;
+        }
+        for (name, target) in &copies {
+            fs::copy(backup_dir.join(name), target).await
+                .map_err(|e| anyhow!("Failed to restore {}: {}", name, e))?;
+        }
+
+        report.restored = true;
+        Ok(report)
+    }
+
+    /// 校验备份目录而不恢复：按清单检查文件是否齐全、哈希是否匹配，并解析备份的记忆文件
+    ///
+    /// 记忆文件为明文时统计条目数；已加密且提供了密码时试解密。
+    pub async fn validate_backup(&self, backup_dir: &str, password: Option<&str>) -> Result<BackupValidation> {
+        let backup_dir = resolve_backup_dir(backup_dir).await?;
+        let mut validation = BackupValidation {
+            valid: false,
+            verified: Vec::new(),
+            corrupted: Vec::new(),
+            missing: Vec::new(),
+            encrypted: false,
+            entry_count: None,
+            password_ok: None,
+        };
+
+        let manifest_path = backup_dir.join(BACKUP_MANIFEST);
+        if !manifest_path.exists() {
+            validation.missing.push(BACKUP_MANIFEST.to_string());
+            return Ok(validation);
+        }
+        let manifest_content = fs::read_to_string(&manifest_path).await
+            .map_err(|e| anyhow!("Failed to read backup manifest: {}", e))?;
+        let Ok(manifest) = serde_json::from_str::<BackupManifest>(&manifest_content) else {
+            validation.corrupted.push(BACKUP_MANIFEST.to_string());
+            return Ok(validation);
+        };
+
+        let report = self.check_backup_files(&backup_dir, &manifest).await?;
+        validation.verified = report.verified;
+        validation.corrupted = report.corrupted;
+        validation.missing = report.missing;
+
+        let entries_name = "memories_backup.json";
+        let entries_path = backup_dir.join(entries_name);
+        if entries_path.exists() {
+            let content = fs::read_to_string(&entries_path).await.unwrap_or_default();
+            let count = |json: &str| serde_json::from_str::<Vec<MemoryEntry>>(json)
+                .map(|entries| Some(entries.len() as u32))
+                .map_err(|_| ());
+            let parsed = match serde_json::from_str::<EncryptionResult>(&content) {
+                Ok(encrypted) => {
+                    validation.encrypted = true;
+                    match password.map(|password| decrypt_content(encrypted, password)) {
+                        None => Ok(None),
+                        Some(Ok(plaintext)) => {
+                            validation.password_ok = Some(true);
+                            count(&plaintext)
+                        }
+                        Some(Err(e)) if matches!(e.downcast_ref::<DecryptError>(), Some(DecryptError::WrongPassword)) => {
+                            validation.password_ok = Some(false);
+                            Ok(None)
+                        }
+                        Some(Err(_)) => Err(()),
+                    }
+                }
+                Err(_) => count(&content),
+            };
+            match parsed {
+                Ok(entry_count) => validation.entry_count = entry_count,
+                Err(()) if !validation.corrupted.iter().any(|n| n == entries_name) => {
+                    validation.corrupted.push(entries_name.to_string());
+                }
+                Err(()) => {}
+            }
+        }
+
+        validation.valid = validation.corrupted.is_empty() && validation.missing.is_empty();
+        Ok(validation)
+    }
+
+    /// 按默认数据目录中的设置打开存储
+    ///
+    /// 设置了 `data_dir_override` 时打开该目录（不存在则创建，并校验可写），
+    /// 否则使用默认目录。
+    pub async fn open_with_override(default_dir: &str) -> Result<Self> {
+        let default_storage = Self::new(default_dir).await?;
+        match default_storage.data_dir_override().await {
+            Some(dir) => {
+                let storage = Self::new(&dir).await?;
+                storage.ensure_writable().await?;
+                Ok(storage)
+            }
+            None => Ok(default_storage),
+        }
+    }
+
+    /// 以只读方式按默认数据目录中的设置打开存储，不创建任何目录
+    pub async fn open_read_only_with_override(default_dir: &str) -> Result<Self> {
+        let default_storage = Self::new_read_only(default_dir).await?;
+        match default_storage.data_dir_override().await {
+            Some(dir) => Self::new_read_only(&dir).await,
+            None => Ok(default_storage),
+        }
+    }
+
+    /// 读取数据目录覆盖设置
+    ///
+    /// 启动时还没有密码，因此只读取明文设置；设置文件不存在、已加密或未设置覆盖时返回 None。
+    pub async fn data_dir_override(&self) -> Option<String> {
+        let settings: UserSettings = self.read_json_file(&self.settings_file, None).await.ok()?;
+        settings.data_dir_override.filter(|dir| !dir.trim().is_empty())
+    }
+
+    /// 设置或清除数据目录覆盖，返回之后生效的数据目录
+    ///
+    /// 覆盖写入默认数据目录的设置中，因此必须在默认目录的存储上调用。`migrate` 为 true 时
+    /// 把当前数据目录的文件复制到新目录（原文件保留）；新目录已有日记数据时拒绝迁移，避免覆盖。
+    pub async fn set_data_dir(&self, new_dir: Option<&str>, migrate: bool, password: Option<&str>) -> Result<PathBuf> {
+        self.ensure_not_read_only()?;
+        if self.is_metadata_encryption_enabled().await? {
+            return Err(anyhow!("Cannot relocate the data directory while settings are encrypted"));
+        }
+
+        let new_dir = new_dir.map(str::trim).filter(|dir| !dir.is_empty());
+        let target = match new_dir {
+            Some(dir) => {
+                if !Path::new(dir).is_absolute() {
+                    return Err(anyhow!("Data directory must be an absolute path"));
+                }
+                let target = Self::new(dir).await?;
+                target.ensure_writable().await?;
+                Some(target)
+            }
+            None => None,
+        };
+
+        if migrate {
+            let current = match self.data_dir_override().await {
+                Some(dir) => Self::new(&dir).await?,
+                None => Self::new(&self.data_dir.to_string_lossy()).await?,
+            };
+            let destination = target.as_ref().unwrap_or(self);
+            if current.data_dir != destination.data_dir {
+                current.copy_data_to(destination, password).await?;
+            }
+        }
+
+        let mut settings = self.load_settings(password).await?;
+        settings.data_dir_override = target.as_ref().map(|t| t.data_dir.to_string_lossy().to_string());
+        self.save_settings(&settings, password).await?;
+
+        Ok(target.map_or_else(|| self.data_dir.clone(), |t| t.data_dir))
+    }
+
+    /// 校验数据目录可写（写入并删除一个探测文件）
+    async fn ensure_writable(&self) -> Result<()> {
+        let probe = self.data_dir.join(format!(".write-test-{}", uuid::Uuid::new_v4()));
+        fs::write(&probe, b"ok").await
+            .map_err(|e| anyhow!("Data directory {} is not writable: {}", self.data_dir.display(), e))?;
+        fs::remove_file(&probe).await
+            .map_err(|e| anyhow!("Failed to clean up {}: {}", probe.display(), e))?;
+        Ok(())
+    }
+
+    /// 把数据文件与附件复制到另一个存储的数据目录
+    ///
+    /// 旧版本保存的附件绝对路径指向本目录，复制后改写为相对路径，避免删除原目录后附件失效。
+    async fn copy_data_to(&self, target: &StorageManager, password: Option<&str>) -> Result<()> {
+        if target.entries_file.exists() {
+            return Err(anyhow!("{} already contains diary data", target.data_dir.display()));
+        }
+
+        let mut entries = self.load_entry_index_for(password).await?;
+        let mut rewritten = false;
+        for attachment in entries.iter_mut().flat_map(|e| e.attachments.iter_mut().flatten()) {
+            if let Ok(relative) = Path::new(&attachment.file_path).strip_prefix(&self.attachments_dir) {
+                attachment.file_path = format!("{}/{}", ATTACHMENTS_DIR, relative.to_string_lossy());
+                rewritten = true;
+            }
+        }
+
+        for (source, dest) in [
+            (&self.entries_file, &target.entries_file),
+            (&self.settings_file, &target.settings_file),
+            (&self.dream_config_file, &target.dream_config_file),
+            (&self.attempts_file, &target.attempts_file),
+            (&self.hint_file, &target.hint_file),
+        ] {
+            if source.exists() {
+                fs::copy(source, dest).await
+                    .map_err(|e| anyhow!("Failed to copy {}: {}", source.display(), e))?;
+            }
+        }
+
+        for (source_dir, target_dir) in [
+            (&self.attachments_dir, &target.attachments_dir),
+            (&self.contents_dir, &target.contents_dir),
+        ] {
+            if !source_dir.exists() {
+                continue;
+            }
+            let mut pending = vec![PathBuf::new()];
+            while let Some(relative) = pending.pop() {
+                let dir = source_dir.join(&relative);
+                fs::create_dir_all(target_dir.join(&relative)).await
+                    .map_err(|e| anyhow!("Failed to create {}: {}", target_dir.display(), e))?;
+
+                let mut read_dir = fs::read_dir(&dir).await
+                    .map_err(|e| anyhow!("Failed to read {}: {}", dir.display(), e))?;
+                while let Some(item) = read_dir.next_entry().await
+                    .map_err(|e| anyhow!("Failed to read {}: {}", dir.display(), e))? {
+                    let relative = relative.join(item.file_name());
+                    if item.path().is_dir() {
+                        pending.push(relative);
+                    } else {
+                        fs::copy(item.path(), target_dir.join(&relative)).await
+                            .map_err(|e| anyhow!("Failed to copy {}: {}", item.path().display(), e))?;
+                    }
+                }
+            }
+        }
+
+        if rewritten {
+            target.save_all_entries(&entries, password).await?;
+        }
+        Ok(())
+    }
+}
+
+impl Storage for StorageManager {
+    async fn load_entries(&self) -> Result<Vec<MemoryEntry>> {
+        self.load_entries_for(self.password.as_deref()).await
+    }
+
+    async fn save_entries(&self, entries: &[MemoryEntry]) -> Result<()> {
+        self.save_all_entries(entries, self.password.as_deref()).await
+    }
+
+    async fn load_entry_listing(&self) -> Result<Vec<MemoryEntry>> {
+        self.load_entry_index_for(self.password.as_deref()).await
+    }
+
+    /// 只加载选中条目的外置正文
+    async fn get_entries_by_ids(&self, entry_ids: &[String]) -> Result<Vec<MemoryEntry>> {
+        let mut entries = select_by_ids(self.load_entry_index_for(self.password.as_deref()).await?, entry_ids);
+        for entry in entries.iter_mut() {
+            self.load_content(entry).await?;
+        }
+        Ok(entries)
+    }
+
+    /// 只加载所取条目的外置正文
+    async fn get_entry(&self, entry_id: &str) -> Result<Option<MemoryEntry>> {
+        let entry = self.load_entry_index_for(self.password.as_deref()).await?
+            .into_iter()
+            .find(|e| e.id == entry_id);
+        match entry {
+            Some(mut entry) => {
+                self.load_content(&mut entry).await?;
+                Ok(Some(entry))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+/// 内存存储（仅用于测试，不触碰文件系统）
+#[cfg(test)]
+#[derive(Default)]
+pub struct MemoryStorage {
+    entries: std::sync::Mutex<Vec<MemoryEntry>>,
+}
+
+#[cfg(test)]
+impl MemoryStorage {
+    /// 使用给定条目创建内存存储
+    pub fn with_entries(entries: Vec<MemoryEntry>) -> Self {
+        Self { entries: std::sync::Mutex::new(entries) }
+    }
+}
+
+#[cfg(test)]
+impl Storage for MemoryStorage {
+    async fn load_entries(&self) -> Result<Vec<MemoryEntry>> {
+        Ok(self.entries.lock().map_err(|e| anyhow!("{}", e))?.clone())
+    }
+
+    async fn save_entries(&self, entries: &[MemoryEntry]) -> Result<()> {
+        *self.entries.lock().map_err(|e| anyhow!("{}", e))? = entries.to_vec();
+        Ok(())
+    }
+}
+
+/// 递归列出目录下的所有文件及其元数据，目录不存在时返回空列表
+async fn list_files(root: &Path) -> Result<Vec<(PathBuf, std::fs::Metadata)>> {
+    let mut files = Vec::new();
+    if !root.exists() {
+        return Ok(files);
+    }
+
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let mut items = fs::read_dir(&dir).await
+            .map_err(|e| anyhow!("Failed to read {}: {}", dir.display(), e))?;
+        while let Some(item) = items.next_entry().await
+            .map_err(|e| anyhow!("Failed to read {}: {}", dir.display(), e))? {
+            let path = item.path();
+            let metadata = item.metadata().await
+                .map_err(|e| anyhow!("Failed to read {} metadata: {}", path.display(), e))?;
+            if metadata.is_dir() {
+                pending.push(path);
+            } else {
+                files.push((path, metadata));
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// 用随机字节覆盖文件内容后删除
+async fn shred_file(path: &Path) -> Result<()> {
+    use rand::RngCore;
+    use tokio::io::AsyncWriteExt;
+
+    let len = fs::metadata(path).await
+        .map_err(|e| anyhow!("Failed to read {} metadata: {}", path.display(), e))?
+        .len() as usize;
+
+    let mut noise = vec![0u8; len];
+    rand::thread_rng().fill_bytes(&mut noise);
+
+    let mut file = fs::OpenOptions::new().write(true).open(path).await
+        .map_err(|e| anyhow!("Failed to open {}: {}", path.display(), e))?;
+    file.write_all(&noise).await
+        .map_err(|e| anyhow!("Failed to overwrite {}: {}", path.display(), e))?;
+    file.sync_all().await
+        .map_err(|e| anyhow!("Failed to sync {}: {}", path.display(), e))?;
+    drop(file);
+
+    fs::remove_file(path).await
+        .map_err(|e| anyhow!("Failed to remove {}: {}", path.display(), e))
+}
+
+/// 逐个解析 JSON 数组中的条目并交给回调处理，不把整个数组收集到内存中
+struct EachEntry<F>(F);
+
+impl<'de, F: FnMut(MemoryEntry) -> Result<()>> serde::de::Visitor<'de> for EachEntry<F> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("an array of entries")
+    }
+
+    fn visit_seq<A: serde::de::SeqAccess<'de>>(mut self, mut seq: A) -> std::result::Result<(), A::Error> {
+        use serde::de::Error;
+
+        while let Some(entry) = seq.next_element::<MemoryEntry>()? {
+            (self.0)(entry).map_err(|e| A::Error::custom(e.to_string()))?;
+        }
+        Ok(())
+    }
+}
+
+/// 导出读取的条目 JSON 来源
+enum EntriesSource {
+    /// 明文记忆文件，逐条流式读取
+    File(PathBuf),
+    /// 解密后的记忆文件全文
+    Decrypted(String),
+    /// 记忆文件不存在或为空
+    Empty,
+}
+
+/// 文件中第一个非空白字节，文件不存在或只含空白时返回 None（用于区分明文数组与加密对象）
+async fn first_json_byte(path: &Path) -> Result<Option<u8>> {
+    use tokio::io::AsyncReadExt;
+
+    let file = match fs::File::open(path).await {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(anyhow!("Failed to read entries file: {}", e)),
+    };
+    let mut reader = tokio::io::BufReader::new(file);
+    loop {
+        match reader.read_u8().await {
+            Ok(byte) if byte.is_ascii_whitespace() => continue,
+            Ok(byte) => return Ok(Some(byte)),
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(anyhow!("Failed to read entries file: {}", e)),
+        }
+    }
+}
+
+/// 把导出文档写入 `path` 同目录的临时文件，完成后重命名为 `path`，失败时删除临时文件
+fn write_json_export(path: &Path, source: EntriesSource, contents_dir: &Path, generated_at: chrono::DateTime<chrono::Utc>) -> Result<u32> {
+    let temp = temp_path_for(path);
+    let result = write_json_document(&temp, source, contents_dir, generated_at).and_then(|written| {
+        std::fs::rename(&temp, path)
+            .map_err(|e| anyhow!("Failed to write {}: {}", path.display(), e))?;
+        Ok(written)
+    });
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp);
+    }
+    result
+}
+
+/// 依次写出信封头、条目序列与条目数，返回写出的条目数
+///
+/// 条目从 `source` 中逐条解析后立即写出，外置正文在写出对应条目时从 `contents_dir` 读取并内联。
+fn write_json_document(path: &Path, source: EntriesSource, contents_dir: &Path, generated_at: chrono::DateTime<chrono::Utc>) -> Result<u32> {
+    use serde::Deserializer;
+    use std::io::Write;
+
+    let file = std::fs::File::create(path)
+        .map_err(|e| anyhow!("Failed to create {}: {}", path.display(), e))?;
+    let mut writer = std::io::BufWriter::new(file);
+    let generated_at = serde_json::to_string(&generated_at)
+        .map_err(|e| anyhow!("Failed to write export header: {}", e))?;
+    write!(writer, "{{\"generated_at\":{},\"entries\":[", generated_at)
+        .map_err(|e| anyhow!("Failed to write export header: {}", e))?;
+
+    let mut written = 0;
+    let write_entry = |mut entry: MemoryEntry| -> Result<()> {
+        if let Some(content_ref) = entry.content_ref.take() {
+            if entry.content.is_empty() {
+                entry.content = std::fs::read_to_string(content_file(contents_dir, &content_ref)?)
+                    .map_err(|e| anyhow!("Failed to read content of entry {}: {}", entry.id, e))?;
+            }
+        }
+        if written > 0 {
+            writer.write_all(b",")?;
+        }
+        serde_json::to_writer(&mut writer, &entry)?;
+        written += 1;
+        Ok(())
+    };
+    match source {
+        EntriesSource::File(entries_file) => {
+            let file = std::fs::File::open(&entries_file)
+                .map_err(|e| anyhow!("Failed to read entries file: {}", e))?;
+            serde_json::Deserializer::from_reader(std::io::BufReader::new(file))
+                .deserialize_seq(EachEntry(write_entry))
+        }
+        EntriesSource::Decrypted(json) => serde_json::Deserializer::from_str(&json)
+            .deserialize_seq(EachEntry(write_entry)),
+        EntriesSource::Empty => Ok(()),
+    }
+    .map_err(|e| anyhow!("Failed to write entries: {}", e))?;
+
+    write!(writer, "],\"entry_count\":{}}}", written)
+        .and_then(|_| writer.flush())
+        .map_err(|e| anyhow!("Failed to write {}: {}", path.display(), e))?;
+    Ok(written)
+}
+
+/// 外置正文在 `contents_dir` 下的文件路径；`content_ref` 必须是单纯的文件名
+fn content_file(contents_dir: &Path, content_ref: &str) -> Result<PathBuf> {
+    if Path::new(content_ref).file_name() != Some(std::ffi::OsStr::new(content_ref)) {
+        return Err(anyhow!("Invalid content reference: {}", content_ref));
+    }
+    Ok(contents_dir.join(content_ref))
+}
+
+/// 校验用户给出的目录路径：不能为空，也不能含有 `..`
+fn user_dir_path(path: &str) -> Result<PathBuf> {
+    let path = PathBuf::from(path);
+    if path.as_os_str().is_empty() {
+        return Err(anyhow!("Directory path is empty"));
+    }
+    if path.components().any(|c| matches!(c, std::path::Component::ParentDir)) {
+        return Err(anyhow!("Directory path must not contain '..': {}", path.display()));
+    }
+    Ok(path)
+}
+
+/// 校验并解析已存在的备份目录（解析符号链接后的真实路径）
+async fn resolve_backup_dir(backup_dir: &str) -> Result<PathBuf> {
+    let backup_dir = fs::canonicalize(user_dir_path(backup_dir)?).await
+        .map_err(|e| anyhow!("Failed to resolve backup directory: {}", e))?;
+    if !backup_dir.is_dir() {
+        return Err(anyhow!("Backup path is not a directory: {}", backup_dir.display()));
+    }
+    Ok(backup_dir)
+}
+
+/// 把多个文件一起替换为新内容：先全部写入临时文件，全部成功后才逐个重命名
+///
+/// 写入临时文件失败时删除已写的临时文件，目标文件都不变；重命名中途失败时把已替换的文件写回 `originals`。
+async fn replace_all(paths: &[PathBuf], contents: &[String], originals: &[String]) -> Result<()> {
+    let mut temps = Vec::with_capacity(paths.len());
+    for (path, content) in paths.iter().zip(contents) {
+        let temp = temp_path_for(path);
+        let written = fs::write(&temp, content).await;
+        temps.push(temp);
+        if let Err(e) = written {
+            for temp in &temps {
+                let _ = fs::remove_file(temp).await;
+            }
+            return Err(anyhow!("Failed to write {}: {}", path.display(), e));
+        }
+    }
+
+    for (index, (temp, path)) in temps.iter().zip(paths).enumerate() {
+        if let Err(e) = fs::rename(temp, path).await {
+            for temp in &temps[index..] {
+                let _ = fs::remove_file(temp).await;
+            }
+            for (path, original) in paths.iter().zip(originals).take(index) {
+                write_atomic(path, original).await
+                    .map_err(|e| anyhow!("Failed to restore {}: {}", path.display(), e))?;
+            }
+            return Err(anyhow!("Failed to write {}: {}", path.display(), e));
+        }
+    }
+    Ok(())
+}
+
+/// 先写入同目录下的临时文件再重命名替换目标文件
+///
+/// 写入中途失败时目标文件保持原样，读取方也不会读到写了一半的内容。
+async fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> std::io::Result<()> {
+    let temp = temp_path_for(path);
+    let result = match fs::write(&temp, contents).await {
+        Ok(()) => fs::rename(&temp, path).await,
+        Err(e) => Err(e),
+    };
+    if result.is_err() {
+        let _ = fs::remove_file(&temp).await;
+    }
+    result
+}
+
+/// 损坏配置文件的留存路径：`<文件名>.bad`
+fn corrupt_config_path(path: &Path) -> PathBuf {
+    let mut bad_name = path.as_os_str().to_owned();
+    bad_name.push(".bad");
+    PathBuf::from(bad_name)
+}
+
+/// 与目标文件同目录的临时文件路径（同一文件系统内重命名才是原子的）
+fn temp_path_for(path: &Path) -> PathBuf {
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    path.with_file_name(format!(".{}.{}.tmp", name, uuid::Uuid::new_v4()))
+}
+
+/// 解析不可信数据中记录的文件路径，返回 `root` 下对应文件的真实路径，文件不存在时返回 None
+///
+/// 拒绝绝对路径与 `..`，并在解析符号链接后确认文件仍位于 `root` 之内。
+async fn contained_file(root: &Path, relative: &Path) -> Result<Option<PathBuf>> {
+    if relative.as_os_str().is_empty()
+        || relative.components().any(|c| !matches!(c, std::path::Component::Normal(_) | std::path::Component::CurDir))
+    {
+        return Err(anyhow!("unsafe path {}", relative.display()));
+    }
+
+    let root = fs::canonicalize(root).await
+        .map_err(|e| anyhow!("failed to resolve {}: {}", root.display(), e))?;
+    let Ok(path) = fs::canonicalize(root.join(relative)).await else {
+        return Ok(None);
+    };
+    if !path.starts_with(&root) {
+        return Err(anyhow!("path {} points outside {}", relative.display(), root.display()));
+    }
+    Ok(path.is_file().then_some(path))
+}
+
+/// 不可信文件名的最后一段，不含路径时返回 None
+fn safe_file_name(file_name: &str) -> Option<String> {
+    Path::new(file_name)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .map(str::to_string)
+}
+
+/// 路径本身是否为符号链接（不跟随链接）
+async fn is_symlink(path: &Path) -> bool {
+    fs::symlink_metadata(path).await.is_ok_and(|m| m.file_type().is_symlink())
+}
+
+/// 文件大小，文件不存在时为 0
+async fn file_size(path: &Path) -> u64 {
+    fs::metadata(path).await.map_or(0, |m| m.len())
+}
+
+/// 计算文件内容的 SHA-256 哈希值
+async fn hash_file(path: &Path) -> Result<String> {
+    let bytes = fs::read(path).await
+        .map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+    Ok(BackendEncryption::hash_sha256_bytes(&bytes))
+}
+
+/// 根据扩展名推断附件 MIME 类型
+fn guess_file_type(file_name: &str) -> &'static str {
+    let extension = Path::new(file_name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_lowercase())
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "m4a" => "audio/mp4",
+        "txt" => "text/plain",
+        _ => "application/octet-stream",
+    }
+}
+
+/// 序列化为缩进或紧凑 JSON
+fn to_json<T: Serialize + ?Sized>(value: &T, pretty: bool) -> serde_json::Result<String> {
+    if pretty {
+        serde_json::to_string_pretty(value)
+    } else {
+        serde_json::to_string(value)
+    }
+}
+
+/// 使用密码解密加密结果
+fn decrypt_content(encrypted: EncryptionResult, password: &str) -> Result<String> {
+    let decrypt_params = DecryptionParams {
+        encrypted_data: encrypted.encrypted_data,
+        nonce: encrypted.nonce,
+        salt: encrypted.salt,
+        format_version: encrypted.format_version,
+        password: password.to_string(),
+    };
+
+    BackendEncryption::decrypt(&decrypt_params)
+}
+
+/// 使用条目密码解密已加锁条目的正文
+pub fn reveal_entry_content(entry: &MemoryEntry, entry_password: &str) -> Result<String> {
+    let encrypted = entry.encrypted_content.as_ref()
+        .ok_or_else(|| anyhow!("Entry is not locked"))?;
+    BackendEncryption::decrypt(&encrypted.to_params(entry_password))
+}
+
+/// 用同一个条目密码批量显示已加锁条目的正文（不解除锁定），返回显示成功的条目数
+///
+/// 存在加锁条目但没有一条能用该密码解开时返回密码错误。
+pub fn reveal_locked_entries(entries: &mut [MemoryEntry], entry_password: &str) -> Result<usize> {
+    let mut locked = 0;
+    let mut revealed = 0;
+    for entry in entries.iter_mut() {
+        let Some(encrypted) = &entry.encrypted_content else { continue };
+        locked += 1;
+        if let Ok(content) = BackendEncryption::decrypt(&encrypted.to_params(entry_password)) {
+            entry.content = content;
+            revealed += 1;
+        }
+    }
+
+    if locked > 0 && revealed == 0 {
+        return Err(DecryptError::WrongPassword.into());
+    }
+    Ok(revealed)
+}
+
+/// 解除条目锁定，恢复明文正文
+pub fn unlock_entry_content(entry: &mut MemoryEntry, entry_password: &str) -> Result<()> {
+    entry.content = reveal_entry_content(entry, entry_password)?;
+    entry.encrypted_content = None;
+    entry.is_encrypted = false;
+    entry.updated_at = chrono::Utc::now();
+    Ok(())
+}
+
+/// 按排序方式排列条目（排序稳定，键相同时保持原顺序）
+pub fn sort_entries(entries: &mut [MemoryEntry], sort: SearchSort) {
+    match sort {
+        SearchSort::NewestFirst => entries.sort_by_key(|e| std::cmp::Reverse(e.created_at)),
+        SearchSort::OldestFirst => entries.sort_by_key(|e| e.created_at),
+        SearchSort::RecentlyUpdated => entries.sort_by_key(|e| std::cmp::Reverse(e.updated_at)),
+        SearchSort::Title => entries.sort_by_cached_key(|e| e.title.to_lowercase()),
+        SearchSort::Manual => entries.sort_by_key(|e| match e.sort_order {
+            Some(position) => (0, position, std::cmp::Reverse(e.created_at)),
+            None => (1, 0, std::cmp::Reverse(e.created_at)),
+        }),
+    }
+}
+
+/// 开启 `structured_query` 时解析关键词查询（关键词为空时不解析）
+fn keyword_query(filter: &SearchFilter) -> Result<Option<QueryExpr>> {
+    match &filter.keyword {
+        Some(keyword) if filter.structured_query && !keyword.trim().is_empty() => parse_keyword_query(keyword)
+            .map(Some)
+            .map_err(|e| anyhow!("Invalid query: {}", e)),
+        _ => Ok(None),
+    }
+}
+
+/// 检查条目是否匹配过滤器，`keyword_query` 为已解析的结构化关键词查询
+fn matches_filter(entry: &MemoryEntry, filter: &SearchFilter, keyword_query: Option<&QueryExpr>) -> bool {
+    // 已归档条目只在显式要求时返回
+    if entry.is_archived && !filter.include_archived.unwrap_or(false) {
+        return false;
+    }
+
+    // 关键词搜索
+    if let Some(query) = keyword_query {
+        if !query.matches(&format!("{}\n{}", entry.title, entry.content)) {
+            return false;
+        }
+    } else if let Some(keyword) = &filter.keyword {
+        let keyword_lower = keyword.to_lowercase();
+        if !entry.title.to_lowercase().contains(&keyword_lower) 
+            && !entry.content.to_lowercase().contains(&keyword_lower) {
+            return false;
+        }
+    }
+
+    // 类型过滤
+    if let Some(memory_type) = &filter.memory_type {
+        if entry.memory_type != *memory_type {
+            return false;
+        }
+    }
+
+    // 情感标签过滤
+    if let Some(emotion_tags) = &filter.emotion_tags {
+        if !emotion_tags.iter().any(|tag| entry.emotion_tags.contains(tag)) {
+            return false;
+        }
+    }
+
+    // 情感强度过滤
+    if let Some(min_intensity) = filter.min_emotion_intensity {
+        let strong_enough = entry.emotion_tags.iter()
+            .filter(|tag| filter.emotion_tags.as_ref().is_none_or(|wanted| wanted.contains(tag)))
+            .any(|tag| entry.emotion_intensity(tag) >= min_intensity);
+        if !strong_enough {
+            return false;
+        }
+    }
+
+    // 日期范围过滤
+    if let Some(date_range) = &filter.date_range {
+        if entry.created_at < date_range.start || entry.created_at > date_range.end {
+            return false;
+        }
+    }
+
+    // 标签过滤
+    if let Some(tags) = &filter.tags {
+        if let Some(entry_metadata) = &entry.metadata {
+            if let Some(entry_tags) = &entry_metadata.tags {
+                // 父级标签匹配其所有下级标签
+                let matched = tags.iter().any(|tag| {
+                    let wanted = normalize_tag(tag);
+                    entry_tags.iter().any(|t| tag_matches(t, &wanted))
+                });
+                if !matched {
+                    return false;
+                }
+            } else {
+                return false;
+            }
+        } else {
+            return false;
+        }
+    }
+
+    // 心情过滤
+    if let Some(mood) = &filter.mood {
+        if entry.mood() != Some(normalize_tag(mood).as_str()) {
+            return false;
+        }
+    }
+
+    // 位置与天气过滤
+    for (wanted, value) in [
+        (&filter.location, entry.metadata.as_ref().and_then(|m| m.location.as_deref())),
+        (&filter.weather, entry.metadata.as_ref().and_then(|m| m.weather.as_deref())),
+    ] {
+        if let Some(wanted) = wanted {
+            if !value.is_some_and(|v| v.to_lowercase().contains(&wanted.trim().to_lowercase())) {
+                return false;
+            }
+        }
+    }
+
+    // 附件文件名过滤
+    if let Some(attachment_name) = &filter.attachment_name {
+        let name_lower = attachment_name.to_lowercase();
+        let has_match = entry.attachments.as_ref().is_some_and(|attachments| {
+            attachments.iter().any(|a| a.file_name.to_lowercase().contains(&name_lower))
+        });
+        if !has_match {
+            return false;
+        }
+    }
+
+    true
+}
+
+/// 统计所有条目的元数据标签频次
+fn build_tag_cloud(entries: &[MemoryEntry], limit: Option<usize>) -> Vec<(String, u32)> {
+    let mut counts: HashMap<String, u32> = HashMap::new();
+    for tags in entries.iter().filter_map(|e| e.metadata.as_ref()?.tags.as_ref()) {
+        for tag in tags {
+            *counts.entry(tag.clone()).or_insert(0) += 1;
+        }
+    }
+
+    let mut cloud: Vec<(String, u32)> = counts.into_iter().collect();
+    cloud.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    if let Some(limit) = limit {
+        cloud.truncate(limit);
+    }
+    cloud
+}
+
+/// 所有条目中出现过的标签（去重后按字母序）
+fn distinct_tags(entries: &[MemoryEntry]) -> Vec<String> {
+    entries
+        .iter()
+        .filter_map(|e| e.metadata.as_ref()?.tags.as_ref())
+        .flatten()
+        .cloned()
+        .collect::<BTreeSet<String>>()
+        .into_iter()
+        .collect()
+}
+
+/// 所有条目中出现过的心情（去重后按字母序）
+fn distinct_moods(entries: &[MemoryEntry]) -> Vec<String> {
+    entries
+        .iter()
+        .filter_map(|e| e.mood())
+        .map(str::to_string)
+        .collect::<BTreeSet<String>>()
+        .into_iter()
+        .collect()
+}
+
+/// 记忆文件的标记：文件路径、大小、修改时间与解锁会话令牌，任一变化即视为缓存过期
+type EntriesStamp = (PathBuf, u64, std::time::SystemTime, Option<u128>);
+
+/// 已用标签与心情的缓存（供输入自动补全）
+///
+/// 记忆文件未变化时直接返回缓存，不读取条目；任何写入都会改变文件的大小或修改时间，下次请求时重新统计。
+#[derive(Default)]
+pub struct TagIndexCache {
+    stamp: Option<EntriesStamp>,
+    tags: Vec<String>,
+    moods: Vec<String>,
+    computations: u32,
+}
+
+impl TagIndexCache {
+    /// 清空缓存的标签与心情（会话锁定时调用）
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// 统计层级标签树：每个条目对其标签的每一级祖先各计一次
+fn build_tag_tree(entries: &[MemoryEntry]) -> Vec<TagNode> {
+    let mut counts: BTreeMap<String, u32> = BTreeMap::new();
+    for tags in entries.iter().filter_map(|e| e.metadata.as_ref()?.tags.as_ref()) {
+        let mut paths = BTreeSet::new();
+        for tag in tags {
+            for (index, _) in tag.match_indices(TAG_SEPARATOR) {
+                paths.insert(tag[..index].to_string());
+            }
+            paths.insert(tag.clone());
+        }
+        for path in paths {
+            *counts.entry(path).or_insert(0) += 1;
+        }
+    }
+    tag_children(&counts, None)
+}
+
+/// 构建指定父级（None 为顶层）下的标签节点
+fn tag_children(counts: &BTreeMap<String, u32>, parent: Option<&str>) -> Vec<TagNode> {
+    counts
+        .iter()
+        .filter(|(path, _)| path.rsplit_once(TAG_SEPARATOR).map(|(p, _)| p) == parent)
+        .map(|(path, count)| TagNode {
+            name: path.rsplit(TAG_SEPARATOR).next().unwrap_or(path).to_string(),
+            path: path.clone(),
+            count: *count,
+            children: tag_children(counts, Some(path)),
+        })
+        .collect()
+}
+
+/// 按 `entry_ids` 的顺序取出条目，忽略不存在与重复的 ID
+fn select_by_ids(entries: Vec<MemoryEntry>, entry_ids: &[String]) -> Vec<MemoryEntry> {
+    let mut by_id: HashMap<String, MemoryEntry> = entries.into_iter().map(|e| (e.id.clone(), e)).collect();
+    entry_ids.iter().filter_map(|id| by_id.remove(id)).collect()
+}
+
+/// 按冲突策略把 `incoming` 合并进 `local`
+///
+/// 与本地条目完全相同的条目总是跳过，不会因 `KeepBoth` 产生重复。
+fn merge_entries(mut local: Vec<MemoryEntry>, incoming: Vec<MemoryEntry>, strategy: MergeStrategy) -> (Vec<MemoryEntry>, MergeReport) {
+    let mut report = MergeReport::default();
+
+    for mut entry in incoming {
+        let Some(index) = local.iter().position(|e| e.id == entry.id) else {
+            local.push(entry);
+            report.added += 1;
+            continue;
+        };
+
+        if serde_json::to_value(&local[index]).ok() == serde_json::to_value(&entry).ok() {
+            report.skipped += 1;
+            continue;
+        }
+
+        match strategy {
+            MergeStrategy::KeepNewer if entry.updated_at > local[index].updated_at => {
+                local[index] = entry;
+                report.updated += 1;
+            }
+            MergeStrategy::KeepBoth => {
+                entry.id = uuid::Uuid::new_v4().to_string();
+                local.push(entry);
+                report.added += 1;
+            }
+            MergeStrategy::KeepNewer | MergeStrategy::Skip => report.skipped += 1,
+        }
+    }
+
+    (local, report)
+}
+
+/// 宽松解析并清理条目记录，返回清理后的条目与所做的修改
+fn sanitize_entries(values: Vec<serde_json::Value>, now: chrono::DateTime<chrono::Utc>, settings: &UserSettings) -> (Vec<MemoryEntry>, Vec<RepairChange>) {
+    let mut changes = Vec::new();
+    let mut entries: Vec<MemoryEntry> = Vec::new();
+    let mut positions: HashMap<String, usize> = HashMap::new();
+
+    for (index, mut value) in values.into_iter().enumerate() {
+        let entry_id = value.get("id").and_then(|id| id.as_str()).map(str::to_string);
+        let Some(record) = value.as_object_mut() else {
+            changes.push(RepairChange { entry_id, kind: RepairKind::Dropped, detail: format!("Record {} is not an object", index) });
+            continue;
+        };
+
+        // 时间戳补全只在记录最终可解析时才计入报告
+        let mut filled = Vec::new();
+        let missing = |record: &serde_json::Map<String, serde_json::Value>, key: &str| {
+            record.get(key).is_none_or(|v| v.is_null())
+        };
+        if missing(record, "created_at") {
+            let stamp = match record.get("updated_at").filter(|v| !v.is_null()) {
+                Some(updated_at) => updated_at.clone(),
+                None => serde_json::json!(now),
+            };
+            filled.push(RepairChange { entry_id: entry_id.clone(), kind: RepairKind::CreatedAtFilled, detail: format!("created_at set to {}", stamp) });
+            record.insert("created_at".to_string(), stamp);
+        }
+        if missing(record, "updated_at") {
+            let stamp = record["created_at"].clone();
+            filled.push(RepairChange { entry_id: entry_id.clone(), kind: RepairKind::UpdatedAtFilled, detail: format!("updated_at set to {}", stamp) });
+            record.insert("updated_at".to_string(), stamp);
+        }
+
+        let mut entry = match serde_json::from_value::<MemoryEntry>(value) {
+            Ok(entry) => entry,
+            Err(e) => {
+                changes.push(RepairChange { entry_id, kind: RepairKind::Dropped, detail: format!("Record {} could not be parsed: {}", index, e) });
+                continue;
+            }
+        };
+        changes.extend(filled);
+
+        let word_count_missing = entry.metadata.as_ref().is_none_or(|m| m.word_count.is_none());
+        if word_count_missing && entry.encrypted_content.is_none() && entry.content_ref.is_none() {
+            entry.refresh_word_stats(settings);
+            let count = entry.metadata.as_ref().and_then(|m| m.word_count).unwrap_or(0);
+            changes.push(RepairChange { entry_id: Some(entry.id.clone()), kind: RepairKind::WordCountRecomputed, detail: format!("word_count set to {}", count) });
+        }
+
+        match positions.get(&entry.id) {
+            Some(&position) => {
+                let kept = &mut entries[position];
+                let detail = if entry.updated_at > kept.updated_at {
+                    let detail = format!("Kept copy updated at {}, removed copy updated at {}", entry.updated_at, kept.updated_at);
+                    *kept = entry;
+                    detail
+                } else {
+                    format!("Kept copy updated at {}, removed copy updated at {}", kept.updated_at, entry.updated_at)
+                };
+                changes.push(RepairChange { entry_id: Some(kept.id.clone()), kind: RepairKind::DuplicateRemoved, detail });
+            }
+            None => {
+                positions.insert(entry.id.clone(), entries.len());
+                entries.push(entry);
+            }
+        }
+    }
+
+    (entries, changes)
+}
+
+/// 从可能损坏的 JSON 文本中提取顶层对象片段
+///
+/// 返回完整闭合的 `{...}` 片段，以及末尾是否存在未闭合（被截断）的对象。
+fn extract_json_objects(content: &str) -> (Vec<&str>, bool) {
+    let mut fragments = Vec::new();
+    let mut depth = 0usize;
+    let mut start = None;
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for (index, c) in content.char_indices() {
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                in_string = false;
+            }
+            continue;
+        }
+
+        match c {
+            '"' if depth > 0 => in_string = true,
+            '{' => {
+                if depth == 0 {
+                    start = Some(index);
+                }
+                depth += 1;
+            }
+            '}' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    if let Some(start) = start.take() {
+                        fragments.push(&content[start..=index]);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    (fragments, depth > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Attachment;
+
+    async fn temp_storage() -> StorageManager {
+        let dir = std::env::temp_dir().join(format!("peach-blossom-test-{}", uuid::Uuid::new_v4()));
+        StorageManager::new(dir.to_str().unwrap()).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_storage_cache_opens_once_until_invalidated() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let cache = StorageCache::default();
+        let dir = std::env::temp_dir().join(format!("peach-blossom-test-{}", uuid::Uuid::new_v4()));
+        let opens = AtomicUsize::new(0);
+        let open = || async {
+            opens.fetch_add(1, Ordering::SeqCst);
+            StorageManager::new(dir.to_str().unwrap()).await
+        };
+
+        // 打开失败不缓存
+        let failed: std::result::Result<StorageManager, String> = cache.get_or_open(|| async { Err("boom".to_string()) }).await;
+        assert!(failed.is_err());
+
+        let (a, b) = tokio::join!(cache.get_or_open(open), cache.get_or_open(open));
+        assert_eq!(a.unwrap().get_data_dir(), dir.as_path());
+        assert_eq!(b.unwrap().get_data_dir(), dir.as_path());
+        cache.get_or_open(open).await.unwrap();
+        assert_eq!(opens.load(Ordering::SeqCst), 1);
+
+        // 各次取得的副本独立设置密码
+        let with_password = cache.get_or_open(open).await.unwrap().with_password(Some("pw".to_string()));
+        assert!(with_password.password.is_some());
+        assert!(cache.get_or_open(open).await.unwrap().password.is_none());
+
+        cache.invalidate().await;
+        cache.get_or_open(open).await.unwrap();
+        assert_eq!(opens.load(Ordering::SeqCst), 2);
+
+        fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    fn tagged_entry(tags: &[&str]) -> MemoryEntry {
+        let mut entry = MemoryEntry::new("标题".to_string(), "内容".to_string(), MemoryType::Text);
+        entry.set_tags(tags.iter().map(|t| t.to_string()).collect());
+        entry
+    }
+
+    #[test]
+    fn test_build_tag_cloud_counts_and_order() {
+        let entries = vec![
+            tagged_entry(&["travel", "family", "summer"]),
+            tagged_entry(&["travel", "summer"]),
+            tagged_entry(&["travel", "beach"]),
+            tagged_entry(&["family"]),
+            MemoryEntry::new("无标签".to_string(), "内容".to_string(), MemoryType::Text),
+        ];
+
+        let cloud = build_tag_cloud(&entries, None);
+        assert_eq!(cloud, vec![
+            ("travel".to_string(), 3),
+            ("family".to_string(), 2),
+            ("summer".to_string(), 2),
+            ("beach".to_string(), 1),
+        ]);
+
+        let top = build_tag_cloud(&entries, Some(2));
+        assert_eq!(top, vec![("travel".to_string(), 3), ("family".to_string(), 2)]);
+    }
+
+    #[tokio::test]
+    async fn test_tag_index_cache_distinct_sorted_and_invalidated() {
+        let storage = temp_storage().await;
+        let mut entries = vec![
+            tagged_entry(&["travel", "family"]),
+            tagged_entry(&["family", "summer"]),
+            tagged_entry(&["travel"]),
+        ];
+        entries[0].set_mood(Some("Calm".to_string()));
+        entries[1].set_mood(Some("happy".to_string()));
+        entries[2].set_mood(Some("calm".to_string()));
+        storage.save_all_entries(&entries, None).await.unwrap();
+
+        let cache = std::sync::Mutex::new(TagIndexCache::default());
+        assert_eq!(storage.all_tags(&cache, None).await.unwrap(), vec!["family", "summer", "travel"]);
+        assert_eq!(storage.all_moods(&cache, None).await.unwrap(), vec!["calm", "happy"]);
+        assert_eq!(cache.lock().unwrap().computations, 1);
+
+        storage.save_entry(&tagged_entry(&["autumn"]), None).await.unwrap();
+        assert_eq!(storage.all_tags(&cache, None).await.unwrap(), vec!["autumn", "family", "summer", "travel"]);
+        assert_eq!(cache.lock().unwrap().computations, 2);
+
+        fs::remove_dir_all(storage.get_data_dir()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_tag_index_cache_sees_bulk_tagging() {
+        let storage = temp_storage().await;
+        let entries = vec![tagged_entry(&["travel"]), tagged_entry(&[])];
+        storage.save_all_entries(&entries, Some("pw")).await.unwrap();
+        let storage = storage.with_password(Some("pw".to_string()));
+
+        let session = Some(7);
+        let cache = std::sync::Mutex::new(TagIndexCache::default());
+        assert_eq!(storage.all_tags(&cache, session).await.unwrap(), vec!["travel"]);
+        assert_eq!(storage.all_tags(&cache, session).await.unwrap(), vec!["travel"]);
+        assert_eq!(cache.lock().unwrap().computations, 1);
+
+        // 批量添加标签不改变条目数与更新时间，缓存仍需失效
+        storage.bulk_add_tag(&[entries[1].id.clone()], "family").await.unwrap();
+        assert_eq!(storage.all_tags(&cache, session).await.unwrap(), vec!["family", "travel"]);
+        assert_eq!(cache.lock().unwrap().computations, 2);
+
+        // 缓存不能代替密码校验：没有会话令牌或换了会话都要重新解密
+        assert!(storage.clone().with_password(Some("wrong".to_string())).all_tags(&cache, None).await.is_err());
+        assert!(storage.clone().with_password(Some("wrong".to_string())).all_tags(&cache, Some(8)).await.is_err());
+        assert!(storage.clone().with_password(None).all_tags(&cache, None).await.is_err());
+        assert_eq!(storage.all_tags(&cache, None).await.unwrap(), vec!["family", "travel"]);
+        assert_eq!(cache.lock().unwrap().computations, 3);
+
+        fs::remove_dir_all(storage.get_data_dir()).await.unwrap();
+    }
+
+    #[test]
+    fn test_build_tag_tree_counts_descendants() {
+        let entries = vec![
+            tagged_entry(&["travel/japan/2023", "travel/japan"]),
+            tagged_entry(&["Travel/France"]),
+            tagged_entry(&["family"]),
+        ];
+
+        let tree = build_tag_tree(&entries);
+        assert_eq!(tree.iter().map(|n| (n.path.as_str(), n.count)).collect::<Vec<_>>(), vec![("family", 1), ("travel", 2)]);
+        let travel = &tree[1];
+        assert_eq!(travel.children.iter().map(|n| (n.name.as_str(), n.count)).collect::<Vec<_>>(), vec![("france", 1), ("japan", 1)]);
+        assert_eq!(travel.children[1].children[0].path, "travel/japan/2023");
+        assert!(tree[0].children.is_empty());
+    }
+
+    #[test]
+    fn test_parent_tag_filter_matches_descendants() {
+        let japan = tagged_entry(&["travel/japan/2023"]);
+        let flat = tagged_entry(&["travel"]);
+        let other = tagged_entry(&["travelling"]);
+
+        let filter = |tag: &str| SearchFilter { tags: Some(vec![tag.to_string()]), ..SearchFilter::default() };
+        for entry in [&japan, &flat] {
+            assert!(matches_filter(entry, &filter("Travel"), None));
+        }
+        assert!(!matches_filter(&other, &filter("travel"), None));
+        assert!(matches_filter(&japan, &filter("travel/japan"), None));
+        assert!(!matches_filter(&flat, &filter("travel/japan"), None));
+    }
+
+    #[tokio::test]
+    async fn test_get_recent_entries_orders_and_truncates() {
+        let storage = temp_storage().await;
+        let now = chrono::Utc::now();
+        for (title, hours_ago) in [("旧", 48), ("最新", 1), ("中", 24)] {
+            let mut entry = MemoryEntry::new(title.to_string(), "内容".to_string(), MemoryType::Text);
+            entry.updated_at = now - chrono::Duration::hours(hours_ago);
+            storage.save_entry(&entry, None).await.unwrap();
+        }
+
+        let recent = storage.get_recent_entries(2).await.unwrap();
+        let titles: Vec<&str> = recent.iter().map(|e| e.title.as_str()).collect();
+        assert_eq!(titles, vec!["最新", "中"]);
+        assert_eq!(storage.get_recent_entries(10).await.unwrap().len(), 3);
+
+        fs::remove_dir_all(storage.get_data_dir()).await.unwrap();
+    }
+
+    fn entry_with_attachments(names: &[&str]) -> MemoryEntry {
+        let mut entry = MemoryEntry::new("相册".to_string(), "照片".to_string(), MemoryType::Image);
+        entry.attachments = Some(names.iter().map(|name| Attachment {
+            id: uuid::Uuid::new_v4().to_string(),
+            file_name: name.to_string(),
+            file_path: format!("attachments/{}", name),
+            file_type: "image/jpeg".to_string(),
+            file_size: 1024,
+            is_encrypted: false,
+            created_at: chrono::Utc::now(),
+            attachment_hash: None,
+        }).collect());
+        entry
+    }
+
+    #[tokio::test]
+    async fn test_memory_storage_crud_and_links() {
+        let storage = MemoryStorage::default();
+        let a = tagged_entry(&["travel"]);
+        let b = tagged_entry(&[]);
+        storage.save_entries(&[a.clone(), b.clone()]).await.unwrap();
+
+        storage.link_entries(&a.id, &b.id).await.unwrap();
+        assert_eq!(storage.get_related_entries(&a.id).await.unwrap()[0].id, b.id);
+        assert_eq!(storage.get_tag_cloud(None).await.unwrap(), vec![("travel".to_string(), 1)]);
+
+        assert!(storage.delete_entry(&b.id, false).await.unwrap());
+        assert!(storage.get_entry(&b.id).await.unwrap().is_none());
+        assert!(storage.get_entry(&a.id).await.unwrap().unwrap().linked_entry_ids.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_backlinks_follow_symmetric_links_and_deletes() {
+        let a = tagged_entry(&[]);
+        let b = tagged_entry(&[]);
+        // c 不与任何条目关联
+        let c = tagged_entry(&[]);
+        // 导入的旧数据只记录了 legacy → a，a 没有反向关联
+        let mut legacy = tagged_entry(&[]);
+        legacy.linked_entry_ids.push(a.id.clone());
+        let storage = MemoryStorage::with_entries(vec![a.clone(), b.clone(), c.clone(), legacy.clone()]);
+
+        storage.link_entries(&a.id, &b.id).await.unwrap();
+        storage.link_entries(&a.id, &b.id).await.unwrap();
+        assert_eq!(storage.get_entry(&b.id).await.unwrap().unwrap().linked_entry_ids, vec![a.id.clone()]);
+        let backlink_ids = |entries: Vec<MemoryEntry>| entries.into_iter().map(|e| e.id).collect::<Vec<_>>();
+        assert_eq!(backlink_ids(storage.get_backlinks(&a.id).await.unwrap()), vec![b.id.clone(), legacy.id.clone()]);
+        assert_eq!(backlink_ids(storage.get_backlinks(&b.id).await.unwrap()), vec![a.id.clone()]);
+        assert!(storage.get_backlinks(&c.id).await.unwrap().is_empty());
+        assert!(storage.get_backlinks("missing").await.is_err());
+
+        // 删除 a 后其他条目不再指向它
+        assert!(storage.delete_entry(&a.id, false).await.unwrap());
+        let remaining = storage.load_entries().await.unwrap();
+        assert!(remaining.iter().all(|e| !e.linked_entry_ids.contains(&a.id)));
+        assert!(storage.get_backlinks(&b.id).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_recompute_word_stats_for_chinese_text() {
+        let settings = UserSettings::default();
+        let mut chinese = MemoryEntry::new("春".to_string(), "桃花".repeat(300), MemoryType::Text);
+        chinese.refresh_word_stats(&settings);
+        let mut locked = tagged_entry(&[]);
+        locked.encrypted_content = Some(EncryptedData::from_result(BackendEncryption::encrypt("秘密", "pw").unwrap()));
+        locked.content = String::new();
+        let storage = MemoryStorage::with_entries(vec![chinese.clone(), locked.clone()]);
+        assert_eq!(chinese.metadata.as_ref().unwrap().reading_time, Some(2));
+
+        // 同样的设置不改变任何条目
+        assert_eq!(storage.recompute_word_stats(&settings).await.unwrap(), 0);
+
+        let faster = UserSettings { reading_speed_cpm: 600, ..UserSettings::default() };
+        assert_eq!(storage.recompute_word_stats(&faster).await.unwrap(), 1);
+        let updated = storage.get_entry(&chinese.id).await.unwrap().unwrap();
+        assert_eq!(updated.metadata.as_ref().unwrap().reading_time, Some(1));
+        assert_eq!(updated.metadata.as_ref().unwrap().word_count, Some(600));
+        assert_eq!(storage.get_entry(&locked.id).await.unwrap().unwrap().metadata.unwrap().word_count, None);
+    }
+
+    #[tokio::test]
+    async fn test_archived_entries_hidden_from_default_views() {
+        let kept = tagged_entry(&["travel"]);
+        let hidden = tagged_entry(&["travel"]);
+        let storage = MemoryStorage::with_entries(vec![kept.clone(), hidden.clone()]);
+
+        assert!(storage.set_archived(&hidden.id, true).await.unwrap().is_archived);
+        assert!(storage.set_archived("missing", true).await.is_err());
+
+        let visible = storage.get_visible_entries().await.unwrap();
+        assert_eq!(visible.len(), 1);
+        assert_eq!(visible[0].id, kept.id);
+        assert_eq!(storage.get_recent_entries(10).await.unwrap().len(), 1);
+        assert_eq!(storage.search_entries(&SearchFilter::default()).await.unwrap().len(), 1);
+
+        // 归档不是删除：仍可显式找到
+        let filter = SearchFilter { include_archived: Some(true), ..SearchFilter::default() };
+        assert_eq!(storage.search_entries(&filter).await.unwrap().len(), 2);
+        assert_eq!(storage.list_archived().await.unwrap()[0].id, hidden.id);
+        assert!(storage.get_entry(&hidden.id).await.unwrap().is_some());
+
+        storage.set_archived(&hidden.id, false).await.unwrap();
+        assert!(storage.list_archived().await.unwrap().is_empty());
+        assert_eq!(storage.get_visible_entries().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_toggle_task_persists() {
+        let mut entry = tagged_entry(&[]);
+        entry.set_tasks(vec![crate::models::Task { text: "买花".to_string(), done: false }]);
+        let storage = MemoryStorage::with_entries(vec![entry.clone()]);
+
+        let toggled = storage.toggle_task(&entry.id, 0).await.unwrap();
+        assert!(toggled.tasks[0].done);
+        assert!(storage.get_entry(&entry.id).await.unwrap().unwrap().tasks[0].done);
+        assert!(storage.toggle_task(&entry.id, 1).await.is_err());
+        assert!(storage.toggle_task("missing", 0).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_task_progress_is_computed_not_stored() {
+        let storage = temp_storage().await;
+        let mut entry = tagged_entry(&[]);
+        entry.set_tasks(vec![
+            crate::models::Task { text: "买花".to_string(), done: true },
+            crate::models::Task { text: "写信".to_string(), done: false },
+        ]);
+        storage.save_entry(&entry, None).await.unwrap();
+
+        let raw = fs::read_to_string(&storage.entries_file).await.unwrap();
+        assert!(!raw.contains("task_progress"));
+
+        // 手工改动任务状态后，读取到的完成情况随之变化
+        fs::write(&storage.entries_file, raw.replace("\"done\": false", "\"done\": true")).await.unwrap();
+        let loaded = storage.get_visible_entries().await.unwrap();
+        assert_eq!(loaded[0].task_progress, Some(crate::models::TaskProgress { done: 2, total: 2 }));
+
+        fs::remove_dir_all(storage.get_data_dir()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_reorder_entries_assigns_positions() {
+        let first = tagged_entry(&[]);
+        let second = tagged_entry(&[]);
+        let third = tagged_entry(&[]);
+        let storage = MemoryStorage::with_entries(vec![first.clone(), second.clone(), third.clone()]);
+
+        let order = vec![third.id.clone(), first.id.clone(), second.id.clone()];
+        assert_eq!(storage.reorder_entries(&order).await.unwrap(), 3);
+        let mut entries = storage.get_all_entries().await.unwrap();
+        sort_entries(&mut entries, SearchSort::Manual);
+        assert_eq!(entries.iter().map(|e| e.id.clone()).collect::<Vec<_>>(), order);
+
+        // ID 重复或不存在时不做修改
+        assert!(storage.reorder_entries(&[first.id.clone(), first.id.clone()]).await.is_err());
+        assert!(storage.reorder_entries(&["missing".to_string()]).await.is_err());
+        assert_eq!(storage.get_entry(&third.id).await.unwrap().unwrap().sort_order, Some(0));
+
+        // 未列出的条目清除位置
+        storage.reorder_entries(std::slice::from_ref(&second.id)).await.unwrap();
+        assert_eq!(storage.get_entry(&second.id).await.unwrap().unwrap().sort_order, Some(0));
+        assert_eq!(storage.get_entry(&third.id).await.unwrap().unwrap().sort_order, None);
+    }
+
+    #[test]
+    fn test_manual_sort_places_unpositioned_entries_by_date() {
+        let at = |created_at: &str, sort_order: Option<i64>| {
+            let mut entry = tagged_entry(&[]);
+            entry.created_at = created_at.parse().unwrap();
+            entry.title = created_at.to_string();
+            entry.sort_order = sort_order;
+            entry
+        };
+        let mut entries = vec![
+            at("2024-01-01T00:00:00Z", None),
+            at("2024-03-01T00:00:00Z", Some(5)),
+            at("2024-05-01T00:00:00Z", None),
+            at("2024-02-01T00:00:00Z", Some(-1)),
+        ];
+
+        sort_entries(&mut entries, SearchSort::Manual);
+        let titles: Vec<&str> = entries.iter().map(|e| e.title.as_str()).collect();
+        assert_eq!(titles, vec![
+            "2024-02-01T00:00:00Z",
+            "2024-03-01T00:00:00Z",
+            "2024-05-01T00:00:00Z",
+            "2024-01-01T00:00:00Z",
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_search_by_location_and_weather() {
+        let mut lake = tagged_entry(&[]);
+        lake.set_context(Some("West Lake, Hangzhou".to_string()), Some("Rainy".to_string()));
+        let mut city = tagged_entry(&[]);
+        city.set_context(Some("Shanghai".to_string()), Some("sunny".to_string()));
+        let storage = MemoryStorage::with_entries(vec![lake.clone(), city.clone(), tagged_entry(&[])]);
+
+        let found = storage.get_entries_by_location("hangzhou").await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, lake.id);
+        assert!(storage.get_entries_by_location("Beijing").await.unwrap().is_empty());
+
+        let filter = SearchFilter { weather: Some("SUNNY".to_string()), ..SearchFilter::default() };
+        assert_eq!(storage.search_entries(&filter).await.unwrap()[0].id, city.id);
+
+        let filter = SearchFilter {
+            location: Some("lake".to_string()),
+            weather: Some("sunny".to_string()),
+            ..SearchFilter::default()
+        };
+        assert!(storage.search_entries(&filter).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_save_new_entry_is_idempotent_on_retry() {
+        let storage = temp_storage().await;
+        let mut entry = MemoryEntry::new("重试".to_string(), "第一次提交".to_string(), MemoryType::Text);
+        entry.id = "client-generated-id".to_string();
+        assert_eq!(storage.save_new_entry(&entry, None).await.unwrap().content, "第一次提交");
+
+        // 同一 client_id 重试：不产生重复，返回第一次保存的条目
+        let mut retry = MemoryEntry::new("重试".to_string(), "第二次提交".to_string(), MemoryType::Text);
+        retry.id = entry.id.clone();
+        assert_eq!(storage.save_new_entry(&retry, None).await.unwrap().content, "第一次提交");
+
+        let entries = storage.get_all_entries().await.unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].id, entry.id);
+
+        fs::remove_dir_all(storage.get_data_dir()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_get_entries_by_ids_keeps_requested_order() {
+        let storage = temp_storage().await;
+        let settings = UserSettings { external_content_threshold: 4, ..UserSettings::default() };
+        storage.save_settings(&settings, None).await.unwrap();
+        let first = MemoryEntry::new("一".to_string(), "一段足够长的正文".to_string(), MemoryType::Text);
+        let second = MemoryEntry::new("二".to_string(), "短".to_string(), MemoryType::Text);
+        storage.save_entry(&first, None).await.unwrap();
+        storage.save_entry(&second, None).await.unwrap();
+
+        let ids = vec![second.id.clone(), "missing".to_string(), first.id.clone(), second.id.clone()];
+        let found = storage.get_entries_by_ids(&ids).await.unwrap();
+        let titles: Vec<&str> = found.iter().map(|e| e.title.as_str()).collect();
+        assert_eq!(titles, vec!["二", "一"]);
+        assert_eq!(found[1].content, first.content);
+
+        fs::remove_dir_all(storage.get_data_dir()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_count_entries_matches_search_results() {
+        let mut lake = tagged_entry(&["旅行/杭州"]);
+        lake.set_context(Some("West Lake".to_string()), None);
+        let mut archived = tagged_entry(&["旅行"]);
+        archived.is_archived = true;
+        let storage = MemoryStorage::with_entries(vec![lake, archived, tagged_entry(&["工作"]), tagged_entry(&[])]);
+
+        let filters = [
+            SearchFilter::default(),
+            SearchFilter { tags: Some(vec!["旅行".to_string()]), ..SearchFilter::default() },
+            SearchFilter { tags: Some(vec!["旅行".to_string()]), include_archived: Some(true), ..SearchFilter::default() },
+            SearchFilter { location: Some("lake".to_string()), ..SearchFilter::default() },
+            SearchFilter { keyword: Some("不存在".to_string()), ..SearchFilter::default() },
+        ];
+        for filter in &filters {
+            let expected = storage.search_entries(filter).await.unwrap().len();
+            assert_eq!(storage.count_entries(filter).await.unwrap(), expected, "{:?}", filter);
+        }
+        assert_eq!(storage.count_entries(&filters[2]).await.unwrap(), 2);
+
+        let invalid = SearchFilter { keyword: Some("OR".to_string()), structured_query: true, ..SearchFilter::default() };
+        assert!(storage.count_entries(&invalid).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_search_structured_keyword_query() {
+        let beach = MemoryEntry::new("Beach day".to_string(), "sun and sand".to_string(), MemoryType::Text);
+        let winter = MemoryEntry::new("Winter beach".to_string(), "cold wind".to_string(), MemoryType::Text);
+        let storage = MemoryStorage::with_entries(vec![beach.clone(), winter.clone()]);
+
+        let filter = SearchFilter { keyword: Some("beach -winter".to_string()), structured_query: true, ..SearchFilter::default() };
+        let found = storage.search_entries(&filter).await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, beach.id);
+
+        // 未开启时整体作为子串匹配
+        let filter = SearchFilter { keyword: Some("beach -winter".to_string()), ..SearchFilter::default() };
+        assert!(storage.search_entries(&filter).await.unwrap().is_empty());
+
+        let filter = SearchFilter { keyword: Some("beach OR".to_string()), structured_query: true, ..SearchFilter::default() };
+        assert!(storage.search_entries(&filter).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_search_by_mood() {
+        let mut calm = tagged_entry(&[]);
+        calm.set_mood(Some("Calm".to_string()));
+        let mut anxious = tagged_entry(&[]);
+        anxious.set_mood(Some("anxious".to_string()));
+        let storage = MemoryStorage::with_entries(vec![calm.clone(), anxious, tagged_entry(&[])]);
+
+        let filter = SearchFilter { mood: Some(" CALM ".to_string()), ..SearchFilter::default() };
+        let results = storage.search_entries(&filter).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].id, calm.id);
+
+        let filter = SearchFilter { mood: Some("joyful".to_string()), ..SearchFilter::default() };
+        assert!(storage.search_entries(&filter).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_get_entry_by_prefix() {
+        let mut first = tagged_entry(&[]);
+        first.id = "a1b2c3d4-0000".to_string();
+        let mut second = tagged_entry(&[]);
+        second.id = "a1b2ffff-0000".to_string();
+        let storage = MemoryStorage::with_entries(vec![first.clone(), second.clone()]);
+
+        assert_eq!(storage.get_entry_by_prefix("a1b2c").await.unwrap().id, first.id);
+        assert_eq!(storage.get_entry_by_prefix(&second.id).await.unwrap().id, second.id);
+
+        let ambiguous = storage.get_entry_by_prefix("a1b2").await.unwrap_err();
+        assert!(ambiguous.to_string().contains("ambiguous"));
+        assert!(storage.get_entry_by_prefix("ffff").await.unwrap_err().to_string().contains("No entry"));
+        assert!(storage.get_entry_by_prefix("  ").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_search_entries_paginated_with_sort() {
+        let base = chrono::Utc::now();
+        let entries: Vec<MemoryEntry> = (0..25)
+            .map(|i| {
+                let mut entry = tagged_entry(if i % 5 == 0 { &["travel"] } else { &[] });
+                entry.title = format!("第{:02}篇", i);
+                entry.created_at = base + chrono::Duration::minutes(i);
+                entry
+            })
+            .collect();
+        let storage = MemoryStorage::with_entries(entries);
+
+        let page = storage.search_entries_paginated(&SearchFilter::default(), 0, 20).await.unwrap();
+        assert_eq!(page.total, 25);
+        assert_eq!(page.entries.len(), 20);
+        assert_eq!(page.entries[0].title, "第24篇");
+
+        let last = storage.search_entries_paginated(&SearchFilter::default(), 20, 20).await.unwrap();
+        assert_eq!(last.entries.len(), 5);
+        assert_eq!(last.entries[4].title, "第00篇");
+
+        let filter = SearchFilter {
+            tags: Some(vec!["travel".to_string()]),
+            sort: Some(SearchSort::OldestFirst),
+            ..SearchFilter::default()
+        };
+        let page = storage.search_entries_paginated(&filter, 1, 2).await.unwrap();
+        assert_eq!(page.total, 5);
+        let titles: Vec<&str> = page.entries.iter().map(|e| e.title.as_str()).collect();
+        assert_eq!(titles, vec!["第05篇", "第10篇"]);
+
+        assert!(storage.search_entries_paginated(&filter, 10, 2).await.unwrap().entries.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_search_by_attachment_name() {
+        let beach = entry_with_attachments(&["Beach_Sunset.JPG", "notes.txt"]);
+        let forest = entry_with_attachments(&["forest.png"]);
+        let plain = MemoryEntry::new("无附件".to_string(), "sunset".to_string(), MemoryType::Text);
+        let storage = MemoryStorage::with_entries(vec![beach.clone(), forest, plain]);
+
+        let filter = SearchFilter { attachment_name: Some("sunset".to_string()), ..SearchFilter::default() };
+        let results = storage.search_entries(&filter).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].id, beach.id);
+
+        let filter = SearchFilter { attachment_name: Some("mountain".to_string()), ..SearchFilter::default() };
+        assert!(storage.search_entries(&filter).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_verify_attachments_reports_missing_and_modified() {
+        let storage = temp_storage().await;
+        let entry = MemoryEntry::new("相册".to_string(), "照片".to_string(), MemoryType::Image);
+        storage.save_entry(&entry, None).await.unwrap();
+
+        let source_dir = storage.get_data_dir().join("source");
+        fs::create_dir_all(&source_dir).await.unwrap();
+        let mut added = Vec::new();
+        for name in ["a.jpg", "b.jpg", "c.jpg"] {
+            let source = source_dir.join(name);
+            fs::write(&source, name.as_bytes()).await.unwrap();
+            added.push(storage.add_attachment(&entry.id, source.to_str().unwrap()).await.unwrap());
+        }
+        assert_eq!(added[0].file_type, "image/jpeg");
+        assert!(added[0].attachment_hash.is_some());
+        // 路径相对数据目录保存
+        assert_eq!(added[0].file_path, format!("attachments/{}-a.jpg", added[0].id));
+
+        fs::remove_file(storage.attachment_path(&added[1])).await.unwrap();
+        // 大小不变但内容改变，只能通过哈希发现
+        fs::write(storage.attachment_path(&added[2]), b"x.jpg").await.unwrap();
+
+        let report = storage.verify_attachments().await.unwrap();
+        assert_eq!(report.ok, vec![added[0].id.clone()]);
+        assert_eq!(report.missing, vec![added[1].id.clone()]);
+        assert_eq!(report.modified, vec![added[2].id.clone()]);
+
+        fs::remove_dir_all(storage.get_data_dir()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_large_content_stored_externally_and_loaded_lazily() {
+        let storage = temp_storage().await;
+        let settings = UserSettings { external_content_threshold: 16, ..UserSettings::default() };
+        storage.save_settings(&settings, None).await.unwrap();
+
+        let long_text = "桃花潭水深千尺".repeat(10);
+        let long = MemoryEntry::new("长文".to_string(), long_text.clone(), MemoryType::Text);
+        let short = MemoryEntry::new("短文".to_string(), "短".to_string(), MemoryType::Text);
+        storage.save_entry(&long, None).await.unwrap();
+        storage.save_entry(&short, None).await.unwrap();
+
+        let raw = fs::read_to_string(&storage.entries_file).await.unwrap();
+        assert!(!raw.contains(&long_text));
+        assert_eq!(list_files(&storage.contents_dir).await.unwrap().len(), 1);
+
+        // 列表不读取外置正文，打开条目时再加载
+        let listed = storage.get_visible_entries().await.unwrap();
+        let listed_long = listed.iter().find(|e| e.id == long.id).unwrap();
+        assert!(listed_long.content.is_empty() && listed_long.content_ref.is_some());
+        assert_eq!(listed.iter().find(|e| e.id == short.id).unwrap().content, "短");
+        assert_eq!(storage.get_entry(&long.id).await.unwrap().unwrap().content, long_text);
+        assert_eq!(storage.get_entry_content(&long.id).await.unwrap(), long_text);
+        assert_eq!(storage.get_all_entries().await.unwrap().iter().find(|e| e.id == long.id).unwrap().content, long_text);
+
+        // 其他修改不影响外置正文；正文变短后改回内联并删除外置文件
+        storage.bulk_add_tag(std::slice::from_ref(&short.id), "misc").await.unwrap();
+        assert_eq!(storage.get_entry_content(&long.id).await.unwrap(), long_text);
+        let mut edited = storage.get_entry(&long.id).await.unwrap().unwrap();
+        edited.update(None, Some("改短了".to_string()), &settings);
+        storage.save_entry(&edited, None).await.unwrap();
+        assert!(list_files(&storage.contents_dir).await.unwrap().is_empty());
+        assert_eq!(storage.get_visible_entries().await.unwrap().iter().find(|e| e.id == long.id).unwrap().content, "改短了");
+
+        fs::remove_dir_all(storage.get_data_dir()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_failed_save_keeps_external_contents() {
+        let storage = temp_storage().await;
+        let settings = UserSettings { external_content_threshold: 16, ..UserSettings::default() };
+        storage.save_settings(&settings, None).await.unwrap();
+        let long = MemoryEntry::new("长文".to_string(), "桃花潭水深千尺".repeat(10), MemoryType::Text);
+        storage.save_entry(&long, None).await.unwrap();
+        assert_eq!(list_files(&storage.contents_dir).await.unwrap().len(), 1);
+
+        // 记忆文件无法替换时保存失败，正文文件不能先被删掉
+        fs::remove_file(&storage.entries_file).await.unwrap();
+        fs::create_dir_all(storage.entries_file.join("blocked")).await.unwrap();
+        assert!(storage.save_entries(&[]).await.is_err());
+        assert_eq!(list_files(&storage.contents_dir).await.unwrap().len(), 1);
+
+        // 写入成功后才清理
+        fs::remove_dir_all(&storage.entries_file).await.unwrap();
+        storage.save_entries(&[]).await.unwrap();
+        assert!(list_files(&storage.contents_dir).await.unwrap().is_empty());
+        let leftovers = list_files(storage.get_data_dir()).await.unwrap();
+        assert!(leftovers.iter().all(|(path, _)| !path.to_string_lossy().ends_with(".tmp")));
+
+        fs::remove_dir_all(storage.get_data_dir()).await.unwrap();
+    }
+
+    #[test]
+    fn test_compact_json_size_on_large_vault() {
+        // 与 `pretty_json` 文档中的数据对应：5000 条、每条 100 字中文正文、带两个标签与一个情感标签
+        let settings = UserSettings::default();
+        let entries: Vec<MemoryEntry> = (0..5000)
+            .map(|i| {
+                let mut entry = MemoryEntry::new(format!("第{}天", i), "桃花流水窅然去".repeat(15).chars().take(100).collect(), MemoryType::Text);
+                entry.add_emotion_tag(EmotionTag::Joy);
+                entry.set_tags(vec!["travel".to_string(), "family".to_string()]);
+                entry.refresh_word_stats(&settings);
+                entry
+            })
+            .collect();
+
+        let pretty = to_json(&entries, true).unwrap().len();
+        let compact = to_json(&entries, false).unwrap().len();
+        // 缩进约 4.57 MB，紧凑约 3.70 MB；时间戳的小数位数不固定，只检查大致比例
+        let saved = (pretty - compact) as f64 / pretty as f64;
+        assert!((0.17..0.21).contains(&saved), "saved {:.1}%", saved * 100.0);
+    }
+
+    #[tokio::test]
+    async fn test_export_json_stream_round_trips() {
+        let storage = temp_storage().await;
+        let settings = UserSettings { external_content_threshold: 16, ..UserSettings::default() };
+        storage.save_settings(&settings, None).await.unwrap();
+        let long = MemoryEntry::new("长文".to_string(), "一段足够长、会被外置保存的正文".to_string(), MemoryType::Text);
+        let mut entries: Vec<MemoryEntry> = (0..50).map(|_| tagged_entry(&["travel"])).collect();
+        entries.push(long.clone());
+        storage.save_all_entries(&entries, None).await.unwrap();
+
+        let dest = std::env::temp_dir().join(format!("peach-blossom-export-{}.json", uuid::Uuid::new_v4()));
+        assert_eq!(storage.export_json_stream(dest.to_str().unwrap(), None).await.unwrap(), 51);
+
+        let export: serde_json::Value = serde_json::from_str(&fs::read_to_string(&dest).await.unwrap()).unwrap();
+        assert_eq!(export["entry_count"], 51);
+        assert!(export["generated_at"].is_string());
+        let (imported, invalid) = importers::validate_native_records(&export["entries"]).unwrap();
+        assert!(invalid.is_empty());
+        assert_eq!(imported.len(), 51);
+        let exported_long = imported.iter().find(|e| e.id == long.id).unwrap();
+        assert_eq!(exported_long.content, long.content);
+        assert!(exported_long.content_ref.is_none());
+
+        fs::remove_file(&dest).await.unwrap();
+        fs::remove_dir_all(storage.get_data_dir()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_export_json_stream_leaves_vault_and_dest_intact() {
+        let storage = temp_storage().await;
+        storage.save_all_entries(&[tagged_entry(&[])], None).await.unwrap();
+        let original = fs::read_to_string(&storage.entries_file).await.unwrap();
+
+        // 不能导出到数据目录内，尤其不能覆盖记忆文件本身
+        assert!(storage.export_json_stream(storage.entries_file.to_str().unwrap(), None).await.is_err());
+        assert_eq!(fs::read_to_string(&storage.entries_file).await.unwrap(), original);
+
+        // 中途失败时既有的导出文件保持不变，也不留下临时文件
+        let export_dir = std::env::temp_dir().join(format!("peach-blossom-export-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&export_dir).await.unwrap();
+        let dest = export_dir.join("export.json");
+        fs::write(&dest, "previous").await.unwrap();
+        fs::write(&storage.entries_file, "[{\"id\": ").await.unwrap();
+        assert!(storage.export_json_stream(dest.to_str().unwrap(), None).await.is_err());
+        assert_eq!(fs::read_to_string(&dest).await.unwrap(), "previous");
+        assert_eq!(list_files(&export_dir).await.unwrap().len(), 1);
+
+        fs::remove_dir_all(&export_dir).await.unwrap();
+        fs::remove_dir_all(storage.get_data_dir()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_export_json_stream_decrypts_encrypted_store() {
+        let storage = temp_storage().await;
+        let entries: Vec<MemoryEntry> = (0..3).map(|_| tagged_entry(&["travel"])).collect();
+        storage.save_all_entries(&entries, Some("pw")).await.unwrap();
+
+        let dest = std::env::temp_dir().join(format!("peach-blossom-export-{}.json", uuid::Uuid::new_v4()));
+        assert!(storage.export_json_stream(dest.to_str().unwrap(), None).await.is_err());
+        assert!(storage.export_json_stream(dest.to_str().unwrap(), Some("wrong")).await.is_err());
+        assert_eq!(storage.get_store_status().await.unwrap().failed_attempts, 1);
+
+        assert_eq!(storage.export_json_stream(dest.to_str().unwrap(), Some("pw")).await.unwrap(), 3);
+        assert_eq!(storage.get_store_status().await.unwrap().failed_attempts, 0);
+        let export: serde_json::Value = serde_json::from_str(&fs::read_to_string(&dest).await.unwrap()).unwrap();
+        assert_eq!(export["entry_count"], 3);
+        let (imported, invalid) = importers::validate_native_records(&export["entries"]).unwrap();
+        assert!(invalid.is_empty());
+        let ids: Vec<&str> = imported.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, entries.iter().map(|e| e.id.as_str()).collect::<Vec<_>>());
+
+        fs::remove_file(&dest).await.unwrap();
+        fs::remove_dir_all(storage.get_data_dir()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_backup_includes_external_contents() {
+        let storage = temp_storage().await;
+        let settings = UserSettings { external_content_threshold: 4, ..UserSettings::default() };
+        storage.save_settings(&settings, None).await.unwrap();
+        let entry = MemoryEntry::new("长文".to_string(), "一段足够长的正文".to_string(), MemoryType::Text);
+        storage.save_entry(&entry, None).await.unwrap();
+
+        let backup_root = storage.get_data_dir().join("backups");
+        let backup_dir = storage.backup_data(backup_root.to_str().unwrap()).await.unwrap();
+        fs::remove_dir_all(&storage.contents_dir).await.unwrap();
+        assert!(storage.get_entry(&entry.id).await.is_err());
+
+        let report = storage.restore_data(backup_dir.to_str().unwrap()).await.unwrap();
+        assert!(report.restored);
+        assert_eq!(storage.get_entry(&entry.id).await.unwrap().unwrap().content, entry.content);
+
+        fs::remove_dir_all(storage.get_data_dir()).await.unwrap();
+    }
+
+    #[test]
+    fn test_merge_entries_strategies() {
+        let shared = MemoryEntry::new("共同".to_string(), "两台设备都有".to_string(), MemoryType::Text);
+        let mut edited = shared.clone();
+        edited.update(None, Some("另一台设备上改过".to_string()), &UserSettings::default());
+        edited.updated_at = shared.updated_at + chrono::Duration::seconds(1);
+        let new = MemoryEntry::new("新的".to_string(), "只在另一台设备".to_string(), MemoryType::Text);
+        let incoming = vec![edited.clone(), new.clone(), shared.clone()];
+
+        let (merged, report) = merge_entries(vec![shared.clone()], incoming.clone(), MergeStrategy::KeepNewer);
+        assert_eq!(report, MergeReport { added: 1, updated: 1, skipped: 1 });
+        assert_eq!(merged[0].content, edited.content);
+
+        let (merged, report) = merge_entries(vec![edited.clone()], incoming.clone(), MergeStrategy::KeepNewer);
+        assert_eq!(report, MergeReport { added: 1, updated: 0, skipped: 2 });
+        assert_eq!(merged[0].content, edited.content);
+
+        let (merged, report) = merge_entries(vec![shared.clone()], incoming.clone(), MergeStrategy::KeepBoth);
+        assert_eq!(report, MergeReport { added: 2, updated: 0, skipped: 1 });
+        assert_eq!(merged.len(), 3);
+        assert_ne!(merged[1].id, shared.id);
+        assert_eq!(merged[1].content, edited.content);
+
+        let (merged, report) = merge_entries(vec![shared.clone()], incoming, MergeStrategy::Skip);
+        assert_eq!(report, MergeReport { added: 1, updated: 0, skipped: 2 });
+        assert_eq!(merged[0].content, shared.content);
+    }
+
+    #[tokio::test]
+    async fn test_merge_vault_with_different_password() {
+        let storage = temp_storage().await;
+        let other = temp_storage().await;
+        let local = MemoryEntry::new("本地".to_string(), "这台设备".to_string(), MemoryType::Text);
+        let remote = MemoryEntry::new("远端".to_string(), "另一台设备".to_string(), MemoryType::Text);
+        storage.save_entry(&local, None).await.unwrap();
+        other.save_entry(&remote, Some("other-pass")).await.unwrap();
+
+        let other_path = other.get_data_dir().to_str().unwrap();
+        assert!(storage.merge_vault(other_path, None, MergeStrategy::KeepNewer).await.is_err());
+        assert!(storage.merge_vault(storage.get_data_dir().to_str().unwrap(), None, MergeStrategy::KeepNewer).await.is_err());
+
+        let report = storage.merge_vault(other_path, Some("other-pass"), MergeStrategy::KeepNewer).await.unwrap();
+        assert_eq!(report, MergeReport { added: 1, updated: 0, skipped: 0 });
+        let ids: Vec<String> = storage.get_all_entries().await.unwrap().into_iter().map(|e| e.id).collect();
+        assert_eq!(ids.len(), 2);
+        assert!(ids.contains(&local.id) && ids.contains(&remote.id));
+
+        // 再次合并时全部相同，不再写入
+        let report = storage.merge_vault(other_path, Some("other-pass"), MergeStrategy::KeepBoth).await.unwrap();
+        assert_eq!(report, MergeReport { added: 0, updated: 0, skipped: 1 });
+
+        fs::remove_dir_all(storage.get_data_dir()).await.unwrap();
+        fs::remove_dir_all(other.get_data_dir()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_merge_vault_rejects_attachment_path_traversal() {
+        let storage = temp_storage().await;
+        let other = temp_storage().await;
+        let outside = temp_storage().await;
+        fs::write(outside.get_data_dir().join("secret.txt"), b"secret").await.unwrap();
+
+        let entry = tagged_entry(&[]);
+        other.save_entry(&entry, None).await.unwrap();
+        let photo = other.get_data_dir().join("photo.jpg");
+        fs::write(&photo, b"jpeg").await.unwrap();
+        let good = other.add_attachment(&entry.id, photo.to_str().unwrap()).await.unwrap();
+
+        // 篡改另一保险库：读取其目录之外的文件，或把副本写到本地附件目录之外
+        let mut crafted = other.get_entry(&entry.id).await.unwrap().unwrap();
+        let attachments = crafted.attachments.as_mut().unwrap();
+        let mut escape_read = good.clone();
+        escape_read.id = "read".to_string();
+        escape_read.file_path = outside.get_data_dir().join("secret.txt").to_string_lossy().to_string();
+        let mut relative_read = good.clone();
+        relative_read.id = "relative".to_string();
+        relative_read.file_path = "../../secret.txt".to_string();
+        let mut escape_write = good.clone();
+        escape_write.id = "../../escape".to_string();
+        escape_write.file_name = "../escape.jpg".to_string();
+        attachments.extend([escape_read, relative_read, escape_write]);
+        other.save_entry(&crafted, None).await.unwrap();
+
+        let report = storage.merge_vault(other.get_data_dir().to_str().unwrap(), None, MergeStrategy::KeepNewer).await.unwrap();
+        assert_eq!(report.added, 1);
+
+        let merged = storage.get_entry(&entry.id).await.unwrap().unwrap().attachments.unwrap();
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0].id, good.id);
+        assert_ne!(merged[1].id, "../../escape");
+        assert_eq!(merged[1].file_name, "escape.jpg");
+        for attachment in &merged {
+            let path = storage.attachment_path(attachment);
+            assert!(path.starts_with(&storage.attachments_dir));
+            assert_eq!(fs::read(&path).await.unwrap(), b"jpeg");
+        }
+        assert_eq!(list_files(&storage.attachments_dir).await.unwrap().len(), 2);
+        assert!(!storage.get_data_dir().join("escape.jpg").exists());
+
+        for dir in [&storage, &other, &outside] {
+            fs::remove_dir_all(dir.get_data_dir()).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_vault_security_info_reads_header_without_password() {
+        let storage = temp_storage().await;
+        assert!(!storage.get_vault_security_info().await.unwrap().encrypted);
+
+        storage.save_entry(&tagged_entry(&[]), None).await.unwrap();
+        assert_eq!(storage.get_vault_security_info().await.unwrap(), VaultSecurityInfo::default());
+
+        storage.save_all_entries(&[tagged_entry(&[])], Some("password")).await.unwrap();
+        let info = storage.get_vault_security_info().await.unwrap();
+        assert!(info.encrypted);
+        assert_eq!(info.algorithm.as_deref(), Some("AES-256-GCM"));
+        assert_eq!(info.kdf.as_deref(), Some("argon2id"));
+        assert_eq!(info.format_version, Some(1));
+        let params = info.argon2_params.unwrap();
+        assert_eq!((params.memory_kib, params.iterations, params.parallelism, params.version), (19456, 2, 1, 0x13));
+        assert!(!info.below_recommended);
+
+        fs::remove_dir_all(storage.get_data_dir()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_storage_usage_sums_components() {
+        let storage = temp_storage().await;
+        let entry = tagged_entry(&["travel"]);
+        storage.save_entry(&entry, None).await.unwrap();
+        storage.save_settings(&UserSettings::default(), None).await.unwrap();
+        let source = storage.get_data_dir().join("photo.jpg");
+        fs::write(&source, vec![0u8; 1000]).await.unwrap();
+        storage.add_attachment(&entry.id, source.to_str().unwrap()).await.unwrap();
+
+        let usage = storage.get_storage_usage().await.unwrap();
+        let size = |path: &PathBuf| std::fs::metadata(path).unwrap().len();
+        assert_eq!(usage.entries_size, size(&storage.entries_file));
+        assert_eq!(usage.settings_size, size(&storage.settings_file));
+        assert_eq!((usage.dream_config_size, usage.contents_size), (0, 0));
+        assert_eq!(usage.attachments_size, 1000);
+        assert_eq!(usage.total_size, usage.entries_size + usage.settings_size + 1000);
+
+        fs::remove_dir_all(storage.get_data_dir()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_storage_report_sizes_and_growth() {
+        let storage = temp_storage().await;
+        let now = chrono::Utc::now();
+        let recent = MemoryEntry::new("最近".to_string(), "新的一天".to_string(), MemoryType::Text);
+        let mut old = MemoryEntry::new("很久以前".to_string(), "旧日记".to_string(), MemoryType::Text);
+        old.created_at = now - chrono::Duration::days(90);
+        storage.save_entry(&old, None).await.unwrap();
+        storage.save_entry(&recent, None).await.unwrap();
+
+        let source = storage.get_data_dir().join("photo.jpg");
+        fs::write(&source, vec![0u8; 1000]).await.unwrap();
+        storage.add_attachment(&recent.id, source.to_str().unwrap()).await.unwrap();
+
+        let report = storage.get_storage_report(now).await.unwrap();
+        let entries_size = fs::metadata(&storage.entries_file).await.unwrap().len();
+        assert_eq!(report.files, vec![FileSize { name: "memories.json".to_string(), size: entries_size }]);
+        assert_eq!(report.entry_count, 2);
+        assert_eq!((report.attachment_count, report.attachments_size), (1, 1000));
+        assert_eq!(report.total_size, entries_size + 1000);
+
+        // 只有最近的条目与附件计入增长
+        let recent_json = serde_json::to_string(&storage.get_entry(&recent.id).await.unwrap().unwrap()).unwrap();
+        assert_eq!(report.recent_growth_bytes, recent_json.len() as u64 + 1000);
+        assert_eq!(report.projected_size_in_year, report.total_size + report.recent_growth_bytes * 365 / 30);
+        assert!(!report.large_entries_file);
+
+        let settings = UserSettings { large_file_warning_mb: 0, ..UserSettings::default() };
+        storage.save_settings(&settings, None).await.unwrap();
+        assert!(storage.get_storage_report(now).await.unwrap().large_entries_file);
+
+        fs::remove_dir_all(storage.get_data_dir()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_prune_orphaned_attachments() {
+        let storage = temp_storage().await;
+        let kept = MemoryEntry::new("保留".to_string(), "照片".to_string(), MemoryType::Image);
+        let deleted = MemoryEntry::new("删除".to_string(), "照片".to_string(), MemoryType::Image);
+        storage.save_entry(&kept, None).await.unwrap();
+        storage.save_entry(&deleted, None).await.unwrap();
+
+        let source = storage.get_data_dir().join("photo.jpg");
+        fs::write(&source, b"12345").await.unwrap();
+        let kept_attachment = storage.add_attachment(&kept.id, source.to_str().unwrap()).await.unwrap();
+        let orphan = storage.add_attachment(&deleted.id, source.to_str().unwrap()).await.unwrap();
+        storage.delete_entry(&deleted.id, false).await.unwrap();
+
+        let nested = storage.attachments_dir.join("nested");
+        fs::create_dir_all(&nested).await.unwrap();
+        fs::write(nested.join("stray.bin"), b"xyz").await.unwrap();
+
+        let orphan_path = storage.attachment_path(&orphan);
+        let orphans = storage.find_orphaned_attachments().await.unwrap();
+        assert_eq!(orphans.len(), 2);
+        assert!(orphans.iter().any(|o| Path::new(&o.path) == orphan_path));
+
+        let preview = storage.prune_orphaned_attachments(true).await.unwrap();
+        assert_eq!(preview.reclaimed_bytes, 8);
+        assert!(orphan_path.exists());
+
+        let report = storage.prune_orphaned_attachments(false).await.unwrap();
+        assert_eq!(report.files.len(), 2);
+        assert_eq!(report.reclaimed_bytes, 8);
+        assert!(!orphan_path.exists());
+        assert!(storage.attachment_path(&kept_attachment).exists());
+        assert!(storage.find_orphaned_attachments().await.unwrap().is_empty());
+
+        fs::remove_dir_all(storage.get_data_dir()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_prune_after_data_dir_migration_keeps_referenced_files() {
+        let default = temp_storage().await;
+        let entry = tagged_entry(&[]);
+        default.save_entry(&entry, None).await.unwrap();
+        let photo = default.get_data_dir().join("photo.jpg");
+        fs::write(&photo, b"jpeg").await.unwrap();
+        let attachment = default.add_attachment(&entry.id, photo.to_str().unwrap()).await.unwrap();
+
+        // 旧版本在另一数据目录下写入的绝对路径，文件已随目录复制过来
+        let legacy = tagged_entry(&[]);
+        default.save_entry(&legacy, None).await.unwrap();
+        let legacy_attachment = default.add_attachment(&legacy.id, photo.to_str().unwrap()).await.unwrap();
+        let mut stored = default.get_entry(&legacy.id).await.unwrap().unwrap();
+        stored.attachments.as_mut().unwrap()[0].file_path = format!(
+            "/previous/data/attachments/{}-photo.jpg",
+            legacy_attachment.id,
+        );
+        default.save_entry(&stored, None).await.unwrap();
+
+        let custom = default.get_data_dir().join("moved");
+        default.set_data_dir(Some(custom.to_str().unwrap()), true, None).await.unwrap();
+        let storage = StorageManager::open_with_override(default.get_data_dir().to_str().unwrap()).await.unwrap();
+
+        assert!(storage.find_orphaned_attachments().await.unwrap().is_empty());
+        let report = storage.prune_orphaned_attachments(false).await.unwrap();
+        assert!(report.files.is_empty());
+        assert!(storage.attachment_path(&attachment).exists());
+        assert!(custom.join("attachments").join(format!("{}-photo.jpg", legacy_attachment.id)).exists());
+
+        fs::remove_dir_all(default.get_data_dir()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_bulk_delete_reports_missing_ids() {
+        let storage = temp_storage().await;
+        let a = tagged_entry(&[]);
+        let b = tagged_entry(&[]);
+        let c = tagged_entry(&[]);
+        for entry in [&a, &b, &c] {
+            storage.save_entry(entry, None).await.unwrap();
+        }
+        storage.link_entries(&a.id, &c.id).await.unwrap();
+
+        let ids = vec![a.id.clone(), "missing".to_string(), b.id.clone(), a.id.clone()];
+        let result = storage.bulk_delete_entries(&ids, false).await.unwrap();
+        assert_eq!(result.succeeded, vec![a.id.clone(), b.id.clone()]);
+        assert_eq!(result.failed, vec![
+            ("missing".to_string(), "not found".to_string()),
+            (a.id.clone(), "not found".to_string()),
+        ]);
+
+        let remaining = storage.get_all_entries().await.unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert!(remaining[0].linked_entry_ids.is_empty());
+
+        fs::remove_dir_all(storage.get_data_dir()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_delete_leaves_file_untouched() {
+        let storage = temp_storage().await;
+        let a = tagged_entry(&[]);
+        let b = tagged_entry(&[]);
+        storage.save_entry(&a, None).await.unwrap();
+        storage.save_entry(&b, None).await.unwrap();
+        let before = fs::read(&storage.entries_file).await.unwrap();
+
+        assert!(storage.delete_entry(&a.id, true).await.unwrap());
+        assert!(!storage.delete_entry("missing", true).await.unwrap());
+        let ids = vec![a.id.clone(), "missing".to_string(), b.id.clone()];
+        let preview = storage.bulk_delete_entries(&ids, true).await.unwrap();
+        assert_eq!(fs::read(&storage.entries_file).await.unwrap(), before);
+
+        let applied = storage.bulk_delete_entries(&ids, false).await.unwrap();
+        assert_eq!(preview, applied);
+        assert_eq!(applied.succeeded.len(), 2);
+        assert!(storage.get_all_entries().await.unwrap().is_empty());
+
+        fs::remove_dir_all(storage.get_data_dir()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_bulk_add_tag_reports_each_outcome() {
+        let storage = temp_storage().await;
+        let untagged = tagged_entry(&["family"]);
+        let tagged = tagged_entry(&["travel"]);
+        storage.save_entry(&untagged, None).await.unwrap();
+        storage.save_entry(&tagged, None).await.unwrap();
+
+        let ids = vec![untagged.id.clone(), "missing".to_string(), tagged.id.clone()];
+        let result = storage.bulk_add_tag(&ids, "  Travel ").await.unwrap();
+        assert_eq!(result.succeeded, vec![untagged.id.clone()]);
+        assert_eq!(result.failed, vec![
+            ("missing".to_string(), "not found".to_string()),
+            (tagged.id.clone(), "already tagged".to_string()),
+        ]);
+
+        let updated = storage.get_entry(&untagged.id).await.unwrap().unwrap();
+        assert_eq!(updated.metadata.unwrap().tags.unwrap(), vec!["family", "travel"]);
+        assert!(storage.bulk_add_tag(&ids, "   ").await.is_err());
+
+        fs::remove_dir_all(storage.get_data_dir()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_journal_records_imports_and_attachments() {
+        let storage = temp_storage().await;
+        let entry = tagged_entry(&[]);
+        storage.save_entry(&entry, None).await.unwrap();
+        let payload = storage.export_entry_sealed(&entry.id, "share").await.unwrap();
+
+        let storage = storage.with_journal();
+        let imported = storage.import_entry_sealed(&payload, "share").await.unwrap();
+        let (before, after) = storage.take_journal().unwrap();
+        let record = crate::undo::UndoRecord::diff("导入条目", &before, &after).unwrap();
+        let mut reverted = after.clone();
+        record.revert(&mut reverted).unwrap();
+        assert!(reverted.iter().all(|e| e.id != imported.id));
+
+        let photo = storage.get_data_dir().join("photo.jpg");
+        fs::write(&photo, b"jpeg bytes").await.unwrap();
+        storage.add_attachment(&entry.id, photo.to_str().unwrap()).await.unwrap();
+        let (before, after) = storage.take_journal().unwrap();
+        assert!(crate::undo::UndoRecord::diff("添加附件", &before, &after).is_some());
+
+        fs::remove_dir_all(storage.get_data_dir()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_journal_records_encrypted_bulk_changes_without_reloading() {
+        let storage = temp_storage().await;
+        let a = tagged_entry(&[]);
+        let b = tagged_entry(&[]);
+        storage.save_all_entries(&[a.clone(), b.clone()], Some("pw")).await.unwrap();
+
+        // 未提供密码时报错，而不是当作空存储
+        let ids = vec![a.id.clone()];
+        assert!(storage.bulk_add_tag(&ids, "travel").await.is_err());
+
+        let storage = storage.with_password(Some("pw".to_string())).with_journal();
+        assert!(storage.take_journal().is_none());
+        storage.bulk_add_tag(&ids, "travel").await.unwrap();
+        let (before, after) = storage.take_journal().unwrap();
+        assert_eq!(before.len(), 2);
+        assert!(before[0].metadata.as_ref().and_then(|m| m.tags.clone()).unwrap_or_default().is_empty());
+        assert_eq!(after[0].metadata.as_ref().unwrap().tags.clone().unwrap(), vec!["travel"]);
+        assert!(storage.take_journal().is_none());
+
+        storage.bulk_delete_entries(std::slice::from_ref(&b.id), false).await.unwrap();
+        let (before, after) = storage.take_journal().unwrap();
+        assert_eq!((before.len(), after.len()), (2, 1));
+
+        // 预览不写入，不产生记录
+        storage.bulk_delete_entries(&ids, true).await.unwrap();
+        assert!(storage.take_journal().is_none());
+
+        fs::remove_dir_all(storage.get_data_dir()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_export_with_attachments_round_trip() {
+        let source = temp_storage().await;
+        let entry = MemoryEntry::new("相册".to_string(), "照片".to_string(), MemoryType::Image);
+        source.save_entry(&entry, None).await.unwrap();
+        let photo = source.get_data_dir().join("photo.jpg");
+        fs::write(&photo, b"jpeg bytes").await.unwrap();
+        source.add_attachment(&entry.id, photo.to_str().unwrap()).await.unwrap();
+
+        let export_root = source.get_data_dir().join("exports");
+        let report = source.export_with_attachments(export_root.to_str().unwrap()).await.unwrap();
+        assert_eq!((report.entries, report.attachments), (1, 1));
+        assert!(report.missing.is_empty());
+
+        let exported: Vec<MemoryEntry> = serde_json::from_str(
+            &fs::read_to_string(PathBuf::from(&report.path).join(EXPORT_ENTRIES_FILE)).await.unwrap(),
+        ).unwrap();
+        let relative = &exported[0].attachments.as_ref().unwrap()[0].file_path;
+        assert!(relative.starts_with("attachments/"));
+
+        let target = temp_storage().await;
+        let imported = target.import_with_attachments(&report.path).await.unwrap();
+        assert_eq!(imported.imported, 1);
+        assert!(imported.skipped.is_empty());
+
+        let restored = target.get_entry(&entry.id).await.unwrap().unwrap();
+        let path = target.attachment_path(&restored.attachments.unwrap()[0]);
+        assert!(path.starts_with(&target.attachments_dir));
+        assert_eq!(fs::read(&path).await.unwrap(), b"jpeg bytes");
+        assert!(target.verify_attachments().await.unwrap().modified.is_empty());
+
+        fs::remove_dir_all(source.get_data_dir()).await.unwrap();
+        fs::remove_dir_all(target.get_data_dir()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_attachments_and_sharing_in_encrypted_vault() {
+        let storage = temp_storage().await;
+        let entry = MemoryEntry::new("相册".to_string(), "照片".to_string(), MemoryType::Image);
+        storage.save_entry(&entry, Some("vault")).await.unwrap();
+        let storage = storage.with_password(Some("vault".to_string()));
+
+        let photo = storage.get_data_dir().join("photo.jpg");
+        fs::write(&photo, b"jpeg bytes").await.unwrap();
+        storage.add_attachment(&entry.id, photo.to_str().unwrap()).await.unwrap();
+        assert!(storage.verify_attachments().await.unwrap().missing.is_empty());
+
+        let payload = storage.export_entry_sealed(&entry.id, "share").await.unwrap();
+        storage.import_entry_sealed(&payload, "share").await.unwrap();
+
+        let export_root = storage.get_data_dir().join("exports");
+        let report = storage.export_with_attachments(export_root.to_str().unwrap()).await.unwrap();
+        assert_eq!((report.entries, report.attachments), (2, 2));
+        assert_eq!(storage.import_with_attachments(&report.path).await.unwrap().imported, 2);
+
+        // 所有写入都保持加密
+        assert!(storage.load_entries_for(None).await.is_err());
+        assert_eq!(storage.load_entries().await.unwrap().len(), 4);
+
+        fs::remove_dir_all(storage.get_data_dir()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_search_filters_custom_emotions() {
+        let mut awe = tagged_entry(&[]);
+        awe.add_emotion_tag(EmotionTag::try_from("Awe".to_string()).unwrap());
+        let mut joy = tagged_entry(&[]);
+        joy.add_emotion_tag(EmotionTag::Joy);
+        let storage = MemoryStorage::with_entries(vec![awe.clone(), joy.clone()]);
+
+        let filter: SearchFilter = serde_json::from_str(r#"{"emotion_tags": [" AWE "]}"#).unwrap();
+        let results = storage.search_entries(&filter).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].id, awe.id);
+
+        let filter = SearchFilter { emotion_tags: Some(vec![EmotionTag::Joy]), ..SearchFilter::default() };
+        assert_eq!(storage.search_entries(&filter).await.unwrap()[0].id, joy.id);
+    }
+
+    #[tokio::test]
+    async fn test_search_filters_by_min_emotion_intensity() {
+        let mut intense = tagged_entry(&[]);
+        intense.set_emotion_intensities(HashMap::from([("joy".to_string(), 5), ("regret".to_string(), 1)])).unwrap();
+        let mut neutral = tagged_entry(&[]);
+        neutral.add_emotion_tag(EmotionTag::Regret);
+        let storage = MemoryStorage::with_entries(vec![intense.clone(), neutral.clone(), tagged_entry(&[])]);
+
+        let ids = |entries: Vec<MemoryEntry>| entries.into_iter().map(|e| e.id).collect::<Vec<_>>();
+        let filter = SearchFilter { min_emotion_intensity: Some(3), ..SearchFilter::default() };
+        assert_eq!(ids(storage.search_entries(&filter).await.unwrap()), vec![intense.id.clone(), neutral.id.clone()]);
+
+        let filter = SearchFilter { min_emotion_intensity: Some(4), ..SearchFilter::default() };
+        assert_eq!(ids(storage.search_entries(&filter).await.unwrap()), vec![intense.id.clone()]);
+
+        // 只看指定标签的强度
+        let filter = SearchFilter {
+            emotion_tags: Some(vec![EmotionTag::Regret]),
+            min_emotion_intensity: Some(2),
+            ..SearchFilter::default()
+        };
+        assert_eq!(ids(storage.search_entries(&filter).await.unwrap()), vec![neutral.id.clone()]);
+    }
+
+    fn emotion_entry(memory_type: MemoryType, created_at: &str, tags: Vec<EmotionTag>) -> MemoryEntry {
+        let mut entry = MemoryEntry::new("标题".to_string(), "内容".to_string(), memory_type);
+        entry.created_at = created_at.parse().unwrap();
+        entry.emotion_tags = tags;
+        entry
+    }
+
+    #[tokio::test]
+    async fn test_get_entries_by_emotion_and_type() {
+        let old_text = emotion_entry(MemoryType::Text, "2024-01-01T00:00:00Z", vec![EmotionTag::Joy]);
+        let new_image = emotion_entry(MemoryType::Image, "2024-03-01T00:00:00Z", vec![EmotionTag::Joy, EmotionTag::Hope]);
+        let mid_text = emotion_entry(MemoryType::Text, "2024-02-01T00:00:00Z", vec![EmotionTag::Joy]);
+        let sad = emotion_entry(MemoryType::Text, "2024-04-01T00:00:00Z", vec![EmotionTag::Sadness]);
+        let storage = MemoryStorage::with_entries(vec![old_text.clone(), new_image.clone(), mid_text.clone(), sad]);
+
+        let joyful = storage.get_entries_by_emotion(&EmotionTag::Joy, None, false).await.unwrap();
+        let ids: Vec<&str> = joyful.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, vec![new_image.id.as_str(), mid_text.id.as_str(), old_text.id.as_str()]);
+
+        let joyful_text = storage.get_entries_by_emotion(&EmotionTag::Joy, Some(&MemoryType::Text), false).await.unwrap();
+        let ids: Vec<&str> = joyful_text.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, vec![mid_text.id.as_str(), old_text.id.as_str()]);
+
+        assert!(storage.get_entries_by_emotion(&EmotionTag::Hope, Some(&MemoryType::Audio), false).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_get_entries_by_emotion_hides_archived() {
+        let kept = emotion_entry(MemoryType::Text, "2024-01-01T00:00:00Z", vec![EmotionTag::Joy]);
+        let mut archived = emotion_entry(MemoryType::Text, "2024-02-01T00:00:00Z", vec![EmotionTag::Joy]);
+        archived.is_archived = true;
+        let storage = MemoryStorage::with_entries(vec![kept.clone(), archived.clone()]);
+
+        let ids = |entries: Vec<MemoryEntry>| entries.into_iter().map(|e| e.id).collect::<Vec<_>>();
+        assert_eq!(ids(storage.get_entries_by_emotion(&EmotionTag::Joy, None, false).await.unwrap()), vec![kept.id.clone()]);
+        assert_eq!(ids(storage.get_entries_by_emotion(&EmotionTag::Joy, None, true).await.unwrap()), vec![archived.id, kept.id]);
+    }
+
+    #[tokio::test]
+    async fn test_link_entries_and_cleanup_on_delete() {
+        let storage = temp_storage().await;
+        let a = MemoryEntry::new("甲".to_string(), "内容".to_string(), MemoryType::Text);
+        let b = MemoryEntry::new("乙".to_string(), "内容".to_string(), MemoryType::Text);
+        storage.save_entry(&a, None).await.unwrap();
+        storage.save_entry(&b, None).await.unwrap();
+
+        assert!(storage.link_entries(&a.id, "missing").await.is_err());
+        storage.link_entries(&a.id, &b.id).await.unwrap();
+        storage.link_entries(&b.id, &a.id).await.unwrap();
+
+        let related = storage.get_related_entries(&a.id).await.unwrap();
+        assert_eq!(related.len(), 1);
+        assert_eq!(related[0].id, b.id);
+        assert_eq!(storage.get_related_entries(&b.id).await.unwrap()[0].id, a.id);
+
+        storage.delete_entry(&b.id, false).await.unwrap();
+        let a_after = storage.get_entry(&a.id).await.unwrap().unwrap();
+        assert!(a_after.linked_entry_ids.is_empty());
+
+        fs::remove_dir_all(storage.get_data_dir()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_lock_entry_keeps_other_entries_readable() {
+        let storage = temp_storage().await;
+        let private = MemoryEntry::new("私密".to_string(), "只给自己看".to_string(), MemoryType::Text);
+        let public = MemoryEntry::new("公开".to_string(), "桃花开了".to_string(), MemoryType::Text);
+        storage.save_entry(&private, None).await.unwrap();
+        storage.save_entry(&public, None).await.unwrap();
+
+        let locked = storage.lock_entry(&private.id, "entry-secret").await.unwrap();
+        assert!(locked.is_encrypted);
+        assert!(locked.content.is_empty());
+        assert!(storage.lock_entry(&private.id, "entry-secret").await.is_err());
+
+        let stored = storage.get_entry(&private.id).await.unwrap().unwrap();
+        assert_eq!(stored.title, "私密");
+        assert!(stored.content.is_empty());
+        assert_eq!(storage.get_entry(&public.id).await.unwrap().unwrap().content, "桃花开了");
+
+        assert!(reveal_entry_content(&stored, "wrong").is_err());
+        assert_eq!(reveal_entry_content(&stored, "entry-secret").unwrap(), "只给自己看");
+
+        let mut unlocked = stored.clone();
+        unlock_entry_content(&mut unlocked, "entry-secret").unwrap();
+        assert!(!unlocked.is_encrypted);
+        assert!(unlocked.encrypted_content.is_none());
+        assert_eq!(unlocked.content, "只给自己看");
+
+        fs::remove_dir_all(storage.get_data_dir()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_lock_entry_in_encrypted_vault() {
+        let storage = temp_storage().await;
+        let entry = tagged_entry(&[]);
+        storage.save_entry(&entry, Some("vault")).await.unwrap();
+        let storage = storage.with_password(Some("vault".to_string())).with_journal();
+
+        storage.lock_entry(&entry.id, "entry-secret").await.unwrap();
+        let (before, after) = storage.take_journal().unwrap();
+        assert!(!before[0].is_encrypted);
+        assert!(after[0].is_encrypted);
+
+        // 存储仍以保险库密码加密
+        assert!(storage.load_entries_for(None).await.is_err());
+        let stored = storage.get_entry(&entry.id).await.unwrap().unwrap();
+        assert_eq!(reveal_entry_content(&stored, "entry-secret").unwrap(), entry.content);
+
+        fs::remove_dir_all(storage.get_data_dir()).await.unwrap();
+    }
+
+    #[test]
+    fn test_reveal_locked_entries_with_shared_password() {
+        let lock = |content: &str, password: &str| {
+            let mut entry = MemoryEntry::new("加锁".to_string(), String::new(), MemoryType::Text);
+            entry.encrypted_content = Some(EncryptedData::from_result(BackendEncryption::encrypt(content, password).unwrap()));
+            entry.is_encrypted = true;
+            entry
+        };
+        let mut entries = vec![lock("一", "shared"), tagged_entry(&[]), lock("二", "shared"), lock("三", "other")];
+
+        assert!(reveal_locked_entries(&mut entries.clone(), "wrong").is_err());
+        assert_eq!(reveal_locked_entries(&mut entries, "shared").unwrap(), 2);
+        let contents: Vec<&str> = entries.iter().map(|e| e.content.as_str()).collect();
+        assert_eq!(contents, vec!["一", "内容", "二", ""]);
+        assert!(entries[0].encrypted_content.is_some());
+        assert_eq!(reveal_locked_entries(&mut [tagged_entry(&[])], "any").unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_sealed_entry_export_import() {
+        let storage = temp_storage().await;
+        let entry = MemoryEntry::new("桃花".to_string(), "春日".to_string(), MemoryType::Text);
+        storage.save_entry(&entry, None).await.unwrap();
+
+        let payload = storage.export_entry_sealed(&entry.id, "share_password").await.unwrap();
+        assert!(storage.import_entry_sealed(&payload, "wrong_password").await.is_err());
+
+        let imported = storage.import_entry_sealed(&payload, "share_password").await.unwrap();
+        assert_ne!(imported.id, entry.id);
+        assert_eq!(imported.content, entry.content);
+        assert_eq!(storage.get_all_entries().await.unwrap().len(), 2);
+
+        fs::remove_dir_all(storage.get_data_dir()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_share_file_round_trip() {
+        let storage = temp_storage().await;
+        let mut entry = MemoryEntry::new("桃花".to_string(), "春日".to_string(), MemoryType::Text);
+        entry.updated_at = "2024-03-01T08:00:00Z".parse().unwrap();
+        entry.created_at = entry.updated_at;
+        storage.save_entry(&entry, None).await.unwrap();
+
+        let target = storage.get_data_dir().join("shared");
+        let path = storage
+            .export_entry_encrypted(&entry.id, "one-time", target.to_str().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(path.extension().unwrap(), SHARE_FILE_EXTENSION);
+
+        let path = path.to_str().unwrap();
+        assert!(storage.import_entry_encrypted(path, "wrong").await.is_err());
+
+        // 同一保险库中导入，原 ID 已存在，必须分配新 ID
+        let imported = storage.import_entry_encrypted(path, "one-time").await.unwrap();
+        assert_ne!(imported.id, entry.id);
+        assert_eq!(imported.title, entry.title);
+        assert_eq!(imported.created_at, entry.created_at);
+        assert!(imported.updated_at > entry.updated_at);
+        assert_eq!(storage.get_all_entries().await.unwrap().len(), 2);
+
+        fs::remove_dir_all(storage.get_data_dir()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_verify_entries_integrity_flags_bit_rot() {
+        let storage = temp_storage().await;
+        let intact = MemoryEntry::new("完好".to_string(), "桃花依旧笑春风".to_string(), MemoryType::Text);
+        let damaged = MemoryEntry::new("损坏".to_string(), "人面不知何处去".to_string(), MemoryType::Text);
+        storage.save_entry(&intact, None).await.unwrap();
+        storage.save_entry(&damaged, None).await.unwrap();
+
+        let report = storage.verify_entries_integrity().await.unwrap();
+        assert_eq!((report.checked, report.unhashed), (2, 0));
+        assert!(report.mismatched.is_empty());
+
+        // 模拟磁盘静默损坏：JSON 仍然合法，但正文变了
+        let raw = fs::read_to_string(&storage.entries_file).await.unwrap();
+        fs::write(&storage.entries_file, raw.replace("人面不知何处去", "人面不知何处来")).await.unwrap();
+
+        // 旧数据没有哈希时单独计数，不误报
+        let mut values: Vec<serde_json::Value> = serde_json::from_str(&fs::read_to_string(&storage.entries_file).await.unwrap()).unwrap();
+        let legacy = values.iter_mut().find(|v| v["id"] == intact.id.as_str()).unwrap();
+        legacy.as_object_mut().unwrap().remove("content_hash");
+        fs::write(&storage.entries_file, serde_json::to_string(&values).unwrap()).await.unwrap();
+
+        let report = storage.verify_entries_integrity().await.unwrap();
+        assert_eq!((report.checked, report.unhashed), (1, 1));
+        assert_eq!(report.mismatched.len(), 1);
+        assert_eq!(report.mismatched[0].entry_id, damaged.id);
+
+        fs::remove_dir_all(storage.get_data_dir()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_pretty_and_compact_json_load_identically() {
+        let storage = temp_storage().await;
+        let entry = MemoryEntry::new("桃花".to_string(), "春风十里".to_string(), MemoryType::Text);
+        storage.save_entry(&entry, None).await.unwrap();
+        let pretty = fs::read_to_string(&storage.entries_file).await.unwrap();
+        assert!(pretty.contains('\n'));
+        let pretty_entries = storage.get_all_entries().await.unwrap();
+
+        let settings = UserSettings { pretty_json: false, ..UserSettings::default() };
+        storage.save_settings(&settings, None).await.unwrap();
+        assert!(!fs::read_to_string(&storage.settings_file).await.unwrap().contains('\n'));
+        storage.save_entry(&entry, None).await.unwrap();
+        let compact = fs::read_to_string(&storage.entries_file).await.unwrap();
+        assert!(!compact.contains('\n'));
+        assert!(compact.len() < pretty.len());
+
+        let compact_entries = storage.get_all_entries().await.unwrap();
+        assert_eq!(
+            serde_json::to_value(&compact_entries).unwrap(),
+            serde_json::to_value(&pretty_entries).unwrap()
+        );
+
+        // 加密存储内部始终使用紧凑格式
+        storage.save_settings(&UserSettings::default(), None).await.unwrap();
+        storage.save_entry(&entry, Some("secret")).await.unwrap();
+        let content = fs::read_to_string(&storage.entries_file).await.unwrap();
+        let encrypted: EncryptionResult = serde_json::from_str(&content).unwrap();
+        assert!(!decrypt_content(encrypted, "secret").unwrap().contains('\n'));
+
+        fs::remove_dir_all(storage.get_data_dir()).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[allow(clippy::field_reassign_with_default)]
+    async fn test_metadata_encryption_plaintext_then_encrypted() {
+        let storage = temp_storage().await;
+        let password = "master_password";
+        let mut settings = UserSettings::default();
+        settings.encrypt_metadata = true;
+
+        // 首次开启时仍以明文保存，用于确立标志
+        storage.save_settings(&settings, Some(password)).await.unwrap();
+        let content = fs::read_to_string(&storage.settings_file).await.unwrap();
+        assert!(serde_json::from_str::<UserSettings>(&content).unwrap().encrypt_metadata);
+
+        // 之后的保存将被加密
+        storage.save_settings(&settings, Some(password)).await.unwrap();
+        let content = fs::read_to_string(&storage.settings_file).await.unwrap();
+        assert!(serde_json::from_str::<EncryptionResult>(&content).is_ok());
+        assert!(storage.load_settings(None).await.is_err());
+        assert!(storage.load_settings(Some(password)).await.unwrap().encrypt_metadata);
+
+        let config = DreamConfig { interval_minutes: 15, ..DreamConfig::default() };
+        assert!(storage.save_dream_config(&config, None).await.is_err());
+        storage.save_dream_config(&config, Some(password)).await.unwrap();
+        let content = fs::read_to_string(&storage.dream_config_file).await.unwrap();
+        assert!(serde_json::from_str::<EncryptionResult>(&content).is_ok());
+        assert_eq!(storage.load_dream_config(Some(password)).await.unwrap().interval_minutes, 15);
+
+        fs::remove_dir_all(storage.get_data_dir()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_search_with_highlights_reports_char_offsets() {
+        let hit = MemoryEntry::new("桃花 Rain".to_string(), "春雨rain，rain".to_string(), MemoryType::Text);
+        let miss = MemoryEntry::new("秋".to_string(), "落叶".to_string(), MemoryType::Text);
+        let storage = MemoryStorage::with_entries(vec![hit.clone(), miss]);
+
+        let results = storage.search_with_highlights("RAIN").await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].entry_id, hit.id);
+        let spans: Vec<_> = results[0].highlights.iter().map(|h| (h.field, h.start, h.end)).collect();
+        assert_eq!(spans, vec![
+            (HighlightField::Title, 3, 7),
+            (HighlightField::Content, 2, 6),
+            (HighlightField::Content, 7, 11),
+        ]);
+        assert!(storage.search_with_highlights("").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_corrupt_config_files_fall_back_to_defaults() {
+        let storage = temp_storage().await;
+        fs::write(&storage.settings_file, "{\"timezone\": \"+09:00\",").await.unwrap();
+        fs::write(&storage.dream_config_file, [0xFF, 0xFE, b'{', 0x80]).await.unwrap();
+
+        let settings = storage.load_settings(None).await.unwrap();
+        assert_eq!(settings.timezone, UserSettings::default().timezone);
+        let config = storage.load_dream_config(None).await.unwrap();
+        assert_eq!(config.interval_minutes, DreamConfig::default().interval_minutes);
+
+        // 读取不改动磁盘
+        assert_eq!(fs::read_to_string(&storage.settings_file).await.unwrap(), "{\"timezone\": \"+09:00\",");
+        assert!(!storage.get_data_dir().join("settings.json.bad").exists());
+
+        // 保存时损坏的文件被改名留存，原位置写入新内容
+        storage.save_settings(&settings, None).await.unwrap();
+        storage.save_dream_config(&config, None).await.unwrap();
+        let bad_settings = storage.get_data_dir().join("settings.json.bad");
+        assert_eq!(fs::read_to_string(&bad_settings).await.unwrap(), "{\"timezone\": \"+09:00\",");
+        assert_eq!(fs::read(storage.get_data_dir().join("dream_config.json.bad")).await.unwrap(), [0xFF, 0xFE, b'{', 0x80]);
+        assert_eq!(storage.load_settings(None).await.unwrap().timezone, settings.timezone);
+        assert!(storage.dream_config_file.exists());
+
+        // 加密设置在密码错误时仍报错，且不被当作损坏
+        let encrypted = UserSettings { encrypt_metadata: true, ..UserSettings::default() };
+        storage.save_settings(&encrypted, Some("right")).await.unwrap();
+        storage.save_settings(&encrypted, Some("right")).await.unwrap();
+        assert!(storage.load_settings(Some("wrong")).await.is_err());
+        assert!(storage.settings_file.exists());
+
+        fs::remove_dir_all(storage.get_data_dir()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_backup_failure_leaves_no_partial_backup() {
+        let storage = temp_storage().await;
+        let entry = MemoryEntry::new("桃花".to_string(), "春日".to_string(), MemoryType::Text);
+        storage.save_entry(&entry, None).await.unwrap();
+        storage.save_settings(&UserSettings::default(), None).await.unwrap();
+
+        let backup_root = storage.get_data_dir().join("backups");
+        let calls = std::sync::atomic::AtomicUsize::new(0);
+        let result = storage
+            .backup_data_with(backup_root.to_str().unwrap(), |from, to| {
+                if calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst) == 1 {
+                    return Err(std::io::Error::other("injected failure"));
+                }
+                std::fs::copy(from, to)
+            })
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!(std::fs::read_dir(&backup_root).unwrap().count(), 0);
+
+        let backup_dir = storage.backup_data(backup_root.to_str().unwrap()).await.unwrap();
+        assert!(backup_dir.join("memories_backup.json").exists());
+        assert!(backup_dir.join("settings.json").exists());
+
+        fs::remove_dir_all(storage.get_data_dir()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_plaintext_migrates_to_encrypted_on_first_password_load() {
+        let storage = temp_storage().await;
+        let password = "master_password";
+        let entry = MemoryEntry::new("桃花".to_string(), "春日".to_string(), MemoryType::Text);
+        storage.save_entry(&entry, None).await.unwrap();
+
+        // 未开启迁移时保持明文
+        let entries = storage.load_entries_with_password(password).await.unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(storage.get_all_entries().await.unwrap().len(), 1);
+
+        let settings = UserSettings { migrate_to_encrypted: true, ..UserSettings::default() };
+        storage.save_settings(&settings, None).await.unwrap();
+
+        let entries = storage.load_entries_with_password(password).await.unwrap();
+        assert_eq!(entries[0].id, entry.id);
+        let content = fs::read_to_string(&storage.entries_file).await.unwrap();
+        assert!(serde_json::from_str::<EncryptionResult>(&content).is_ok());
+
+        // 迁移后再次读取走解密路径
+        let entries = storage.load_entries_with_password(password).await.unwrap();
+        assert_eq!(entries[0].id, entry.id);
+        assert!(storage.load_entries_with_password("wrong_password").await.is_err());
+
+        fs::remove_dir_all(storage.get_data_dir()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_password_attempts_back_off_and_persist() {
+        let storage = temp_storage().await;
+        let entry = MemoryEntry::new("桃花".to_string(), "春日".to_string(), MemoryType::Text);
+        storage.save_all_entries(std::slice::from_ref(&entry), Some("correct")).await.unwrap();
+
+        let delays = std::sync::Mutex::new(Vec::new());
+        for _ in 0..6 {
+            let result = storage
+                .load_entries_throttled_with("wrong", |d| {
+                    delays.lock().unwrap().push(d.as_secs());
+                    async {}
+                })
+                .await;
+            assert!(result.is_err());
+        }
+        // 前三次不等待，之后每次等待时间翻倍
+        assert_eq!(*delays.lock().unwrap(), vec![1, 2, 4]);
+
+        // 状态持久化在数据目录，新的管理器实例同样可见
+        let reopened = StorageManager::new(storage.get_data_dir().to_str().unwrap()).await.unwrap();
+        let status = reopened.get_store_status().await.unwrap();
+        assert!(status.encrypted);
+        assert_eq!(status.failed_attempts, 6);
+        assert_eq!(status.next_attempt_delay_secs, 8);
+
+        let loaded = reopened.load_entries_throttled_with("correct", |_| async {}).await.unwrap();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(reopened.get_store_status().await.unwrap().failed_attempts, 0);
+
+        fs::remove_dir_all(storage.get_data_dir()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_password_attempts_only_written_on_change() {
+        let storage = temp_storage().await;
+        storage.save_all_entries(&[tagged_entry(&[])], Some("correct")).await.unwrap();
+
+        // 成功且此前没有失败时不写入
+        storage.load_entries_throttled_with("correct", |_| async {}).await.unwrap();
+        assert!(!storage.attempts_file.exists());
+
+        // 只读实例不写入
+        let read_only = StorageManager::new_read_only(storage.get_data_dir().to_str().unwrap()).await.unwrap();
+        assert!(read_only.load_entries_throttled_with("wrong", |_| async {}).await.is_err());
+        assert!(!storage.attempts_file.exists());
+
+        // 密文损坏不计为密码错误
+        let mut encrypted: EncryptionResult = serde_json::from_str(&fs::read_to_string(&storage.entries_file).await.unwrap()).unwrap();
+        let original = encrypted.clone();
+        encrypted.nonce = "AAAA".to_string();
+        fs::write(&storage.entries_file, serde_json::to_string(&encrypted).unwrap()).await.unwrap();
+        assert!(storage.load_entries_throttled_with("correct", |_| async {}).await.is_err());
+        assert!(!storage.attempts_file.exists());
+
+        fs::write(&storage.entries_file, serde_json::to_string(&original).unwrap()).await.unwrap();
+        assert!(storage.load_entries_throttled_with("wrong", |_| async {}).await.is_err());
+        assert_eq!(storage.get_store_status().await.unwrap().failed_attempts, 1);
+
+        fs::remove_dir_all(storage.get_data_dir()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_require_password_refuses_plaintext_save() {
+        let storage = temp_storage().await;
+        let settings = UserSettings { require_password: true, ..UserSettings::default() };
+        storage.save_settings(&settings, None).await.unwrap();
+
+        let first = MemoryEntry::new("桃花".to_string(), "春日".to_string(), MemoryType::Text);
+        assert!(storage.save_entry(&first, None).await.is_err());
+        assert!(!storage.entries_file.exists());
+
+        storage.save_entry(&first, Some("secret")).await.unwrap();
+        let second = MemoryEntry::new("细雨".to_string(), "夜里".to_string(), MemoryType::Text);
+        storage.save_entry(&second, Some("secret")).await.unwrap();
+        assert_eq!(storage.load_entries_with_password("secret").await.unwrap().len(), 2);
+
+        // 即使关闭设置，已加密的存储也不能混入明文
+        storage.save_settings(&UserSettings::default(), None).await.unwrap();
+        let before = fs::read(&storage.entries_file).await.unwrap();
+        assert!(storage.save_entry(&second, None).await.is_err());
+        assert_eq!(fs::read(&storage.entries_file).await.unwrap(), before);
+
+        fs::remove_dir_all(storage.get_data_dir()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_password_hint_round_trip_and_validation() {
+        let storage = temp_storage().await;
+        assert_eq!(storage.get_password_hint().await.unwrap(), None);
+
+        let entry = MemoryEntry::new("桃花".to_string(), "春日".to_string(), MemoryType::Text);
+        storage.save_entry(&entry, Some("Peach2024")).await.unwrap();
+
+        assert!(storage.set_password_hint("my password is peach2024", "Peach2024").await.is_err());
+        assert!(storage.set_password_hint("老家的那棵树", "wrong").await.is_err());
+        assert!(storage.set_password_hint(&"长".repeat(201), "Peach2024").await.is_err());
+
+        storage.set_password_hint("  老家的那棵树 ", "Peach2024").await.unwrap();
+        assert_eq!(storage.get_password_hint().await.unwrap().as_deref(), Some("老家的那棵树"));
+        let raw = fs::read_to_string(&storage.hint_file).await.unwrap();
+        assert!(!raw.contains("老家"));
+
+        storage.set_password_hint("", "Peach2024").await.unwrap();
+        assert_eq!(storage.get_password_hint().await.unwrap(), None);
+
+        fs::remove_dir_all(storage.get_data_dir()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_restore_verifies_backup_manifest() {
+        let storage = temp_storage().await;
+        let entry = MemoryEntry::new("桃花".to_string(), "春日".to_string(), MemoryType::Text);
+        storage.save_entry(&entry, None).await.unwrap();
+
+        let backup_root = storage.get_data_dir().join("backups");
+        let backup_dir = storage.backup_data(backup_root.to_str().unwrap()).await.unwrap();
+        assert!(backup_dir.join(BACKUP_MANIFEST).exists());
+
+        storage.delete_entry(&entry.id, false).await.unwrap();
+        let report = storage.restore_data(backup_dir.to_str().unwrap()).await.unwrap();
+        assert!(report.restored);
+        assert_eq!(report.verified, vec!["memories_backup.json"]);
+        assert!(storage.get_entry(&entry.id).await.unwrap().is_some());
+
+        // 篡改备份后拒绝恢复，现有数据保持不变
+        fs::write(backup_dir.join("memories_backup.json"), "[]").await.unwrap();
+        let report = storage.restore_data(backup_dir.to_str().unwrap()).await.unwrap();
+        assert!(!report.restored);
+        assert_eq!(report.corrupted, vec!["memories_backup.json"]);
+        assert!(storage.get_entry(&entry.id).await.unwrap().is_some());
+
+        fs::remove_dir_all(storage.get_data_dir()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_validate_backup_reports_without_restoring() {
+        let storage = temp_storage().await;
+        storage.save_entry(&tagged_entry(&["travel"]), None).await.unwrap();
+        storage.save_entry(&tagged_entry(&[]), None).await.unwrap();
+        let backup_root = storage.get_data_dir().join("backups");
+        let backup_dir = storage.backup_data(backup_root.to_str().unwrap()).await.unwrap();
+        let dir = backup_dir.to_str().unwrap();
+
+        let validation = storage.validate_backup(dir, None).await.unwrap();
+        assert!(validation.valid);
+        assert!(!validation.encrypted);
+        assert_eq!(validation.entry_count, Some(2));
+        assert_eq!(validation.password_ok, None);
+
+        // 文件被篡改为无法解析的内容
+        let entries_backup = backup_dir.join("memories_backup.json");
+        fs::write(&entries_backup, "{ not json").await.unwrap();
+        let validation = storage.validate_backup(dir, None).await.unwrap();
+        assert!(!validation.valid);
+        assert_eq!(validation.corrupted, vec!["memories_backup.json"]);
+        assert_eq!(validation.entry_count, None);
+        assert_eq!(fs::read_to_string(&entries_backup).await.unwrap(), "{ not json");
+
+        // 文件缺失
+        fs::remove_file(&entries_backup).await.unwrap();
+        let validation = storage.validate_backup(dir, None).await.unwrap();
+        assert!(!validation.valid);
+        assert_eq!(validation.missing, vec!["memories_backup.json"]);
+
+        fs::remove_file(backup_dir.join(BACKUP_MANIFEST)).await.unwrap();
+        let validation = storage.validate_backup(dir, None).await.unwrap();
+        assert_eq!(validation.missing, vec![BACKUP_MANIFEST]);
+
+        fs::remove_dir_all(storage.get_data_dir()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_validate_encrypted_backup_with_password() {
+        let storage = temp_storage().await;
+        storage.save_entry(&tagged_entry(&[]), Some("password")).await.unwrap();
+        let backup_root = storage.get_data_dir().join("backups");
+        let backup_dir = storage.backup_data(backup_root.to_str().unwrap()).await.unwrap();
+        let dir = backup_dir.to_str().unwrap();
+
+        let validation = storage.validate_backup(dir, None).await.unwrap();
+        assert!(validation.valid && validation.encrypted);
+        assert_eq!((validation.entry_count, validation.password_ok), (None, None));
+
+        let validation = storage.validate_backup(dir, Some("wrong")).await.unwrap();
+        assert!(validation.valid);
+        assert_eq!((validation.entry_count, validation.password_ok), (None, Some(false)));
+
+        let validation = storage.validate_backup(dir, Some("password")).await.unwrap();
+        assert_eq!((validation.entry_count, validation.password_ok), (Some(1), Some(true)));
+
+        fs::remove_dir_all(storage.get_data_dir()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_backup_and_restore_reject_path_traversal() {
+        let storage = temp_storage().await;
+        storage.save_entry(&tagged_entry(&[]), None).await.unwrap();
+        let data_dir = storage.get_data_dir().to_path_buf();
+
+        let traversal = data_dir.join("backups").join("..").join("..").join("escaped");
+        assert!(storage.backup_data(traversal.to_str().unwrap()).await.is_err());
+        assert!(storage.backup_data("").await.is_err());
+        assert!(storage.restore_data(traversal.to_str().unwrap()).await.is_err());
+        assert!(!data_dir.parent().unwrap().join("escaped").exists());
+
+        // 清单中指向备份目录之外或外置正文目录之外的路径视为损坏，拒绝恢复
+        let backup_root = data_dir.join("backups");
+        let backup_dir = storage.backup_data(backup_root.to_str().unwrap()).await.unwrap();
+        let outside = data_dir.join("outside.json");
+        fs::write(&outside, "[]").await.unwrap();
+        let hash = BackendEncryption::hash_sha256_bytes(b"[]");
+        let manifest_path = backup_dir.join(BACKUP_MANIFEST);
+        let mut manifest: BackupManifest = serde_json::from_str(&fs::read_to_string(&manifest_path).await.unwrap()).unwrap();
+        manifest.files.insert("../../outside.json".to_string(), hash.clone());
+        manifest.files.insert(format!("{}/../../outside.json", CONTENTS_DIR), hash);
+        fs::write(&manifest_path, serde_json::to_string(&manifest).unwrap()).await.unwrap();
+
+        let entries_before = fs::read(&storage.entries_file).await.unwrap();
+        let report = storage.restore_data(backup_dir.to_str().unwrap()).await.unwrap();
+        assert!(!report.restored);
+        assert_eq!(report.corrupted.len(), 2);
+        assert_eq!(fs::read(&storage.entries_file).await.unwrap(), entries_before);
+        assert!(!storage.validate_backup(backup_dir.to_str().unwrap(), None).await.unwrap().valid);
+
+        fs::remove_dir_all(&data_dir).await.unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_restore_refuses_symlinks() {
+        let storage = temp_storage().await;
+        storage.save_entry(&tagged_entry(&[]), None).await.unwrap();
+        let data_dir = storage.get_data_dir().to_path_buf();
+        let backup_root = data_dir.join("backups");
+        let backup_dir = storage.backup_data(backup_root.to_str().unwrap()).await.unwrap();
+
+        // 恢复目标是指向数据目录之外的符号链接
+        let victim = data_dir.join("victim.txt");
+        fs::write(&victim, "keep me").await.unwrap();
+        fs::remove_file(&storage.entries_file).await.unwrap();
+        std::os::unix::fs::symlink(&victim, &storage.entries_file).unwrap();
+        assert!(storage.restore_data(backup_dir.to_str().unwrap()).await.is_err());
+        assert_eq!(fs::read_to_string(&victim).await.unwrap(), "keep me");
+
+        // 备份中的文件被替换为符号链接
+        let entries_backup = backup_dir.join("memories_backup.json");
+        fs::rename(&entries_backup, data_dir.join("moved.json")).await.unwrap();
+        std::os::unix::fs::symlink(data_dir.join("moved.json"), &entries_backup).unwrap();
+        let report = storage.restore_data(backup_dir.to_str().unwrap()).await.unwrap();
+        assert!(!report.restored);
+        assert_eq!(report.corrupted, vec!["memories_backup.json"]);
+
+        fs::remove_dir_all(&data_dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_wipe_vault_requires_confirmation() {
+        let storage = temp_storage().await;
+        let entry = MemoryEntry::new("桃花".to_string(), "春日".to_string(), MemoryType::Text);
+        storage.save_entry(&entry, None).await.unwrap();
+        storage.save_settings(&UserSettings::default(), None).await.unwrap();
+        fs::create_dir_all(storage.attachments_dir.join("photos")).await.unwrap();
+        fs::write(storage.attachments_dir.join("photos").join("a.jpg"), b"jpeg").await.unwrap();
+        fs::write(&storage.attempts_file, "{}").await.unwrap();
+
+        assert!(storage.wipe_vault("delete all memories", false).await.is_err());
+
+        let before = fs::read(&storage.entries_file).await.unwrap();
+        storage.wipe_vault("", true).await.unwrap();
+        assert_eq!(fs::read(&storage.entries_file).await.unwrap(), before);
+        assert!(storage.attachments_dir.join("photos").join("a.jpg").exists());
+        assert!(storage.entries_file.exists());
+
+        // 修复前的备份、损坏配置的留存与中断写入的临时文件同样保存着用户数据
+        let copies = [
+            storage.get_data_dir().join("memories.json.20260101000000.bak"),
+            storage.get_data_dir().join("memories.json.corrupt"),
+            storage.get_data_dir().join("settings.json.bad"),
+            storage.get_data_dir().join("dream_config.json.bad"),
+            temp_path_for(&storage.entries_file),
+        ];
+        for copy in &copies {
+            fs::write(copy, "桃花 春日").await.unwrap();
+        }
+        let unrelated = storage.get_data_dir().join("notes.bak");
+        fs::write(&unrelated, "keep").await.unwrap();
+
+        let planned = storage.wipe_vault("", true).await.unwrap();
+        for copy in &copies {
+            assert!(copy.exists());
+            assert!(planned.contains(&copy.to_string_lossy().to_string()));
+        }
+
+        let removed = storage.wipe_vault(WIPE_CONFIRM_PHRASE, false).await.unwrap();
+        assert_eq!(removed, planned);
+        assert_eq!(removed.len(), 10);
+        assert!(!storage.entries_file.exists());
+        assert!(!storage.attempts_file.exists());
+        assert!(!storage.settings_file.exists());
+        assert!(!storage.attachments_dir.exists());
+        assert!(copies.iter().all(|copy| !copy.exists()));
+        assert!(unrelated.exists());
+
+        fs::remove_dir_all(storage.get_data_dir()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_repair_vault_salvages_valid_entries() {
+        let storage = temp_storage().await;
+        let first = MemoryEntry::new("桃花".to_string(), "春日".to_string(), MemoryType::Text);
+        let second = MemoryEntry::new("细雨".to_string(), "夜里".to_string(), MemoryType::Text);
+        let content = format!(
+            "[{},{{\"id\": \"broken\"}},{},{{\"id\": \"trunc",
+            serde_json::to_string(&first).unwrap(),
+            serde_json::to_string(&second).unwrap(),
+        );
+        fs::write(&storage.entries_file, content).await.unwrap();
+
+        let report = storage.repair_vault().await.unwrap();
+        assert_eq!(report.recovered, 2);
+        assert_eq!(report.dropped, 2);
+        assert!(PathBuf::from(report.corrupt_file.unwrap()).exists());
+
+        let entries = storage.get_all_entries().await.unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].id, first.id);
+        assert_eq!(entries[1].id, second.id);
+
+        fs::remove_dir_all(storage.get_data_dir()).await.unwrap();
+    }
+
+    /// 字数完整的条目记录，避免干扰其他修复类别的断言
+    fn counted_value(title: &str) -> serde_json::Value {
+        let mut entry = MemoryEntry::new(title.to_string(), "内容".to_string(), MemoryType::Text);
+        entry.refresh_word_stats(&UserSettings::default());
+        serde_json::to_value(entry).unwrap()
+    }
+
+    #[test]
+    fn test_sanitize_fills_missing_timestamps() {
+        let now = chrono::Utc::now();
+        let mut no_updated = counted_value("缺少更新时间");
+        let created_at = no_updated["created_at"].clone();
+        no_updated.as_object_mut().unwrap().remove("updated_at");
+        let mut no_times = counted_value("缺少时间");
+        no_times["created_at"] = serde_json::Value::Null;
+        no_times.as_object_mut().unwrap().remove("updated_at");
+
+        let (entries, changes) = sanitize_entries(vec![no_updated, no_times], now, &UserSettings::default());
+        assert_eq!(serde_json::to_value(entries[0].updated_at).unwrap(), created_at);
+        assert_eq!(entries[1].created_at, now);
+        assert_eq!(entries[1].updated_at, now);
+
+        let kinds: Vec<RepairKind> = changes.iter().map(|c| c.kind).collect();
+        assert_eq!(kinds, vec![RepairKind::UpdatedAtFilled, RepairKind::CreatedAtFilled, RepairKind::UpdatedAtFilled]);
+    }
+
+    #[test]
+    fn test_sanitize_keeps_newest_duplicate() {
+        let older = counted_value("旧版本");
+        let mut newer = older.clone();
+        newer["title"] = serde_json::json!("新版本");
+        newer["updated_at"] = serde_json::json!(chrono::Utc::now() + chrono::Duration::hours(1));
+        let other = counted_value("其他");
+
+        let (entries, changes) = sanitize_entries(vec![older, other, newer], chrono::Utc::now(), &UserSettings::default());
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].title, "新版本");
+        assert_eq!(entries[1].title, "其他");
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].kind, RepairKind::DuplicateRemoved);
+        assert_eq!(changes[0].entry_id.as_deref(), Some(entries[0].id.as_str()));
+    }
+
+    #[test]
+    fn test_sanitize_recomputes_missing_word_counts_and_drops_invalid() {
+        let mut missing = counted_value("缺少字数");
+        missing["metadata"] = serde_json::Value::Null;
+        let invalid = serde_json::json!({"id": "broken", "title": 3});
+
+        let (entries, changes) = sanitize_entries(vec![missing, invalid, serde_json::Value::Null], chrono::Utc::now(), &UserSettings::default());
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].metadata.as_ref().unwrap().word_count, Some(2));
+
+        let kinds: Vec<RepairKind> = changes.iter().map(|c| c.kind).collect();
+        assert_eq!(kinds, vec![RepairKind::WordCountRecomputed, RepairKind::Dropped, RepairKind::Dropped]);
+        assert_eq!(changes[1].entry_id.as_deref(), Some("broken"));
+    }
+
+    #[tokio::test]
+    async fn test_repair_store_backs_up_before_writing() {
+        let storage = temp_storage().await;
+        let clean = counted_value("完好");
+        fs::write(&storage.entries_file, serde_json::to_string(&vec![clean.clone()]).unwrap()).await.unwrap();
+        let report = storage.repair_store().await.unwrap();
+        assert!(report.changes.is_empty());
+        assert!(report.backup_file.is_none());
+
+        let original = serde_json::to_string(&vec![clean.clone(), clean]).unwrap();
+        fs::write(&storage.entries_file, &original).await.unwrap();
+        let report = storage.repair_store().await.unwrap();
+        assert_eq!(report.entry_count, 1);
+        assert_eq!(report.changes.len(), 1);
+
+        let backup = fs::read_to_string(report.backup_file.unwrap()).await.unwrap();
+        assert_eq!(backup, original);
+        assert_eq!(storage.get_all_entries().await.unwrap().len(), 1);
+
+        fs::remove_dir_all(storage.get_data_dir()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_data_dir_override_opens_new_location() {
+        let default = temp_storage().await;
+        let custom = default.get_data_dir().join("synced").join("diary");
+        let custom_str = custom.to_str().unwrap();
+
+        assert!(default.set_data_dir(Some("relative/dir"), false, None).await.is_err());
+
+        let active = default.set_data_dir(Some(custom_str), false, None).await.unwrap();
+        assert_eq!(active, custom);
+        assert!(custom.exists());
+        assert_eq!(default.data_dir_override().await.as_deref(), Some(custom_str));
+
+        let storage = StorageManager::open_with_override(default.get_data_dir().to_str().unwrap()).await.unwrap();
+        assert_eq!(storage.get_data_dir(), custom.as_path());
+        let entry = tagged_entry(&[]);
+        storage.save_entry(&entry, None).await.unwrap();
+        assert!(custom.join("memories.json").exists());
+        assert!(default.get_all_entries().await.unwrap().is_empty());
+
+        // 清除覆盖后回到默认目录
+        default.set_data_dir(None, false, None).await.unwrap();
+        let storage = StorageManager::open_with_override(default.get_data_dir().to_str().unwrap()).await.unwrap();
+        assert_eq!(storage.get_data_dir(), default.get_data_dir());
+
+        fs::remove_dir_all(default.get_data_dir()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_set_data_dir_migrates_copy_of_existing_files() {
+        let default = temp_storage().await;
+        let entry = tagged_entry(&["travel"]);
+        default.save_entry(&entry, None).await.unwrap();
+        let photo = default.get_data_dir().join("photo.jpg");
+        fs::write(&photo, b"jpeg").await.unwrap();
+        let attachment = default.add_attachment(&entry.id, photo.to_str().unwrap()).await.unwrap();
+
+        // 旧版本保存的绝对路径
+        let legacy = tagged_entry(&[]);
+        default.save_entry(&legacy, None).await.unwrap();
+        let mut legacy_attachment = default.add_attachment(&legacy.id, photo.to_str().unwrap()).await.unwrap();
+        legacy_attachment.file_path = default.attachment_path(&legacy_attachment).to_string_lossy().to_string();
+        let mut stored = default.get_entry(&legacy.id).await.unwrap().unwrap();
+        stored.attachments = Some(vec![legacy_attachment.clone()]);
+        default.save_entry(&stored, None).await.unwrap();
+
+        let custom = default.get_data_dir().join("moved");
+        default.set_data_dir(Some(custom.to_str().unwrap()), true, None).await.unwrap();
+
+        let storage = StorageManager::open_with_override(default.get_data_dir().to_str().unwrap()).await.unwrap();
+        assert_eq!(storage.get_data_dir(), custom.as_path());
+        assert!(storage.get_entry(&entry.id).await.unwrap().is_some());
+        assert_eq!(storage.get_tag_cloud(None).await.unwrap(), vec![("travel".to_string(), 1)]);
+
+        // 两种路径都指向新目录中的副本
+        let migrated = storage.get_entry(&legacy.id).await.unwrap().unwrap().attachments.unwrap();
+        for path in [storage.attachment_path(&attachment), storage.attachment_path(&migrated[0])] {
+            assert!(path.starts_with(&custom), "{} is outside the new directory", path.display());
+            assert_eq!(fs::read(&path).await.unwrap(), b"jpeg");
+        }
+
+        // 原文件保留，目标已有数据时拒绝再次迁移
+        assert!(default.attachment_path(&attachment).exists());
+        assert!(Path::new(&legacy_attachment.file_path).exists());
+        assert_eq!(default.get_all_entries().await.unwrap().len(), 2);
+        let occupied = StorageManager::new(default.get_data_dir().join("occupied").to_str().unwrap()).await.unwrap();
+        occupied.save_entry(&tagged_entry(&[]), None).await.unwrap();
+        assert!(default.set_data_dir(Some(occupied.get_data_dir().to_str().unwrap()), true, None).await.is_err());
+        assert_eq!(default.data_dir_override().await.as_deref(), custom.to_str());
+
+        // 删除原目录的附件后，新目录中的附件仍然完整
+        fs::remove_dir_all(&default.attachments_dir).await.unwrap();
+        let report = storage.verify_attachments().await.unwrap();
+        assert_eq!(report.ok.len(), 2);
+        assert!(report.missing.is_empty());
+
+        fs::remove_dir_all(default.get_data_dir()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_import_rejects_attachment_path_traversal() {
+        let storage = temp_storage().await;
+        let root = storage.get_data_dir().join("untrusted");
+        let export_dir = root.join("export");
+        fs::create_dir_all(export_dir.join(EXPORT_ATTACHMENTS_DIR)).await.unwrap();
+        fs::write(root.join("secret.txt"), b"secret").await.unwrap();
+        fs::write(export_dir.join("attachments/photo.jpg"), b"jpeg").await.unwrap();
+
+        let existing = tagged_entry(&[]);
+        storage.save_entry(&existing, None).await.unwrap();
+
+        let attachment = |id: &str, file_name: &str, file_path: String| Attachment {
+            id: id.to_string(),
+            file_name: file_name.to_string(),
+            file_path,
+            file_type: "image/jpeg".to_string(),
+            file_size: 4,
+            is_encrypted: false,
+            created_at: chrono::Utc::now(),
+            attachment_hash: None,
+        };
+        // 条目 ID 与本地冲突，附件 ID 与文件名试图写到附件目录之外
+        let mut crafted = existing.clone();
+        crafted.attachments = Some(vec![
+            attachment("../../escape", "../../../escape.jpg", "attachments/photo.jpg".to_string()),
+            attachment("a", "secret.txt", "../secret.txt".to_string()),
+            attachment("b", "secret.txt", root.join("secret.txt").to_string_lossy().to_string()),
+        ]);
+        fs::write(export_dir.join(EXPORT_ENTRIES_FILE), serde_json::to_string(&[crafted]).unwrap()).await.unwrap();
+
+        let report = storage.import_with_attachments(export_dir.to_str().unwrap()).await.unwrap();
+        assert_eq!(report.imported, 1);
+        assert_eq!(report.skipped.len(), 2);
+
+        let imported = storage.get_all_entries().await.unwrap()
+            .into_iter()
+            .find(|e| e.id != existing.id)
+            .unwrap();
+        let attachments = imported.attachments.unwrap();
+        assert_eq!(attachments.len(), 1);
+        assert_ne!(attachments[0].id, "../../escape");
+        assert_eq!(attachments[0].file_name, "escape.jpg");
+        let path = storage.attachment_path(&attachments[0]);
+        assert!(path.starts_with(&storage.attachments_dir));
+        assert_eq!(fs::read(&path).await.unwrap(), b"jpeg");
+
+        // 只有导入的那一个附件落入附件目录，目录之外没有写入
+        assert_eq!(list_files(&storage.attachments_dir).await.unwrap().len(), 1);
+        assert!(!storage.get_data_dir().join("escape.jpg").exists());
+        assert!(!root.join("escape.jpg").exists());
+
+        fs::remove_dir_all(storage.get_data_dir()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_import_skips_invalid_records() {
+        let storage = temp_storage().await;
+        let export_dir = storage.get_data_dir().join("export");
+        fs::create_dir_all(&export_dir).await.unwrap();
+
+        let valid = tagged_entry(&[]);
+        let mut invalid = serde_json::to_value(&valid).unwrap();
+        invalid["id"] = serde_json::json!("broken");
+        invalid["updated_at"] = serde_json::json!(12);
+        let records = serde_json::json!([invalid, serde_json::to_value(&valid).unwrap(), { "title": "残缺" }]);
+        fs::write(export_dir.join(EXPORT_ENTRIES_FILE), records.to_string()).await.unwrap();
+
+        let report = storage.import_with_attachments(export_dir.to_str().unwrap()).await.unwrap();
+        assert_eq!(report.imported, 1);
+        assert_eq!(report.invalid.len(), 2);
+        assert_eq!(report.invalid[0].id.as_deref(), Some("broken"));
+        assert_eq!(report.invalid[1].index, 2);
+        assert!(report.invalid[1].errors.contains(&"missing `id`".to_string()));
+        assert_eq!(storage.get_all_entries().await.unwrap()[0].id, valid.id);
+
+        fs::remove_dir_all(storage.get_data_dir()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_reads_work_on_encrypted_vault_with_password() {
+        let storage = temp_storage().await;
+        let entry = tagged_entry(&["travel"]);
+        storage.save_entry(&entry, Some("vault_password")).await.unwrap();
+
+        assert!(storage.get_entry(&entry.id).await.is_err());
+        assert!(storage.get_random_entry().await.is_err());
+
+        let unlocked = StorageManager::new(storage.get_data_dir().to_str().unwrap()).await.unwrap()
+            .with_password(Some("vault_password".to_string()));
+        assert!(unlocked.get_entry(&entry.id).await.unwrap().is_some());
+        assert_eq!(unlocked.search_entries(&SearchFilter::default()).await.unwrap().len(), 1);
+        assert_eq!(unlocked.get_random_entry().await.unwrap().unwrap().id, entry.id);
+
+        // 通过带密码的存储写入后仍保持加密
+        let other = tagged_entry(&[]);
+        unlocked.save_entry(&other, Some("vault_password")).await.unwrap();
+        unlocked.link_entries(&entry.id, &other.id).await.unwrap();
+        assert!(storage.is_entries_encrypted().await);
+        assert_eq!(unlocked.get_related_entries(&entry.id).await.unwrap()[0].id, other.id);
+
+        fs::remove_dir_all(storage.get_data_dir()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_change_password_reencrypts_store() {
+        let storage = temp_storage().await;
+        let entry = tagged_entry(&["travel"]);
+        storage.save_entry(&entry, Some("old_password")).await.unwrap();
+
+        // 旧密码错误时不改动文件
+        assert!(storage.change_password("wrong_password", "new_password", |_, _| {}).await.is_err());
+        assert_eq!(storage.load_entries_with_password("old_password").await.unwrap().len(), 1);
+
+        let progress = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorder = progress.clone();
+        let count = storage.change_password("old_password", "new_password", move |processed, total| {
+            recorder.lock().unwrap().push((processed, total));
+        }).await.unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(*progress.lock().unwrap(), vec![(1, 1)]);
+
+        assert!(storage.load_entries_with_password("old_password").await.is_err());
+        assert_eq!(storage.load_entries_with_password("new_password").await.unwrap()[0].id, entry.id);
+
+        fs::remove_dir_all(storage.get_data_dir()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_replace_all_keeps_files_consistent_on_failure() {
+        let storage = temp_storage().await;
+        let dir = storage.get_data_dir().to_path_buf();
+        let first = dir.join("first.json");
+        fs::write(&first, "old").await.unwrap();
+        let originals = vec!["old".to_string(), "old".to_string()];
+        let contents = vec!["new".to_string(), "new".to_string()];
+
+        // 第二个临时文件写入失败：第一个文件保持原样
+        let unwritable = dir.join("missing").join("second.json");
+        assert!(replace_all(&[first.clone(), unwritable], &contents, &originals).await.is_err());
+        assert_eq!(fs::read_to_string(&first).await.unwrap(), "old");
+
+        // 第二个文件重命名失败：已替换的第一个文件写回原内容
+        let occupied = dir.join("occupied");
+        fs::create_dir_all(occupied.join("child")).await.unwrap();
+        assert!(replace_all(&[first.clone(), occupied], &contents, &originals).await.is_err());
+        assert_eq!(fs::read_to_string(&first).await.unwrap(), "old");
+
+        let leftovers = list_files(&dir).await.unwrap();
+        assert!(leftovers.iter().all(|(path, _)| !path.to_string_lossy().ends_with(".tmp")));
+
+        replace_all(std::slice::from_ref(&first), &contents[..1], &originals[..1]).await.unwrap();
+        assert_eq!(fs::read_to_string(&first).await.unwrap(), "new");
+
+        fs::remove_dir_all(storage.get_data_dir()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_rotate_encryption_changes_salt_and_keeps_password() {
+        let storage = temp_storage().await;
+        let entry = tagged_entry(&["travel"]);
+        storage.save_entry(&entry, Some("password")).await.unwrap();
+        let read_salt = || async {
+            let content = fs::read_to_string(&storage.entries_file).await.unwrap();
+            serde_json::from_str::<EncryptionResult>(&content).unwrap().salt
+        };
+        let before = read_salt().await;
+
+        assert!(storage.rotate_encryption("wrong_password", |_, _| {}).await.is_err());
+        assert_eq!(read_salt().await, before);
+
+        assert_eq!(storage.rotate_encryption("password", |_, _| {}).await.unwrap(), 1);
+        assert_ne!(read_salt().await, before);
+        assert_eq!(storage.load_entries_with_password("password").await.unwrap()[0].id, entry.id);
+
+        fs::remove_dir_all(storage.get_data_dir()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_read_only_storage_refuses_writes() {
+        let missing = std::env::temp_dir().join(format!("peach-blossom-test-{}", uuid::Uuid::new_v4()));
+        assert!(StorageManager::new_read_only(missing.to_str().unwrap()).await.is_err());
+        assert!(!missing.exists());
+
+        let storage = temp_storage().await;
+        let entry = tagged_entry(&["travel"]);
+        storage.save_entry(&entry, None).await.unwrap();
+
+        let reader = StorageManager::new_read_only(storage.get_data_dir().to_str().unwrap()).await.unwrap();
+        assert_eq!(reader.get_all_entries().await.unwrap().len(), 1);
+
+        let err = reader.save_entry(&tagged_entry(&[]), None).await.unwrap_err();
+        assert!(err.to_string().contains("read-only"));
+        assert!(reader.save_settings(&UserSettings::default(), None).await.is_err());
+        assert!(!storage.settings_file.exists());
+        assert_eq!(storage.get_all_entries().await.unwrap().len(), 1);
+
+        fs::remove_dir_all(storage.get_data_dir()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_export_stats_json_writes_envelope() {
+        let storage = temp_storage().await;
+        storage.save_entry(&tagged_entry(&[]), None).await.unwrap();
+        storage.save_entry(&tagged_entry(&[]), None).await.unwrap();
+
+        let dest = storage.get_data_dir().join("stats.json");
+        let path = storage.export_stats_json(dest.to_str().unwrap()).await.unwrap();
+
+        let export: serde_json::Value = serde_json::from_str(&fs::read_to_string(path).await.unwrap()).unwrap();
+        assert_eq!(export["entry_count"], 2);
+        assert_eq!(export["stats"]["total_entries"], 2);
+        assert_eq!(export["stats"]["current_streak"], 1);
+        assert!(export["generated_at"].is_string());
+
+        fs::remove_dir_all(storage.get_data_dir()).await.unwrap();
+    }
 }
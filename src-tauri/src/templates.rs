@@ -0,0 +1,98 @@
+/**
+ * 条目模板模块
+ * 内置写作提示模板，并按条目的情感标签推荐模板
+ */
+
+use crate::models::{EmotionTag, EntryTemplate, MemoryType};
+
+/// 构造一个文字模板
+fn template(id: &str, name: &str, title: &str, content: &str, emotion_tags: Vec<EmotionTag>) -> EntryTemplate {
+    EntryTemplate {
+        id: id.to_string(),
+        name: name.to_string(),
+        title: title.to_string(),
+        content: content.to_string(),
+        memory_type: MemoryType::Text,
+        emotion_tags,
+    }
+}
+
+/// 内置模板
+pub fn builtin_templates() -> Vec<EntryTemplate> {
+    vec![
+        template(
+            "daily",
+            "今日记事",
+            "今天",
+            "今天发生了什么？\n\n让我印象最深的一刻：\n",
+            Vec::new(),
+        ),
+        template(
+            "gratitude",
+            "感恩时刻",
+            "值得感谢的事",
+            "今天想感谢的三件事：\n1. \n2. \n3. \n",
+            vec![EmotionTag::Joy, EmotionTag::Hope],
+        ),
+        template(
+            "reflection",
+            "反思与释怀",
+            "如果重来一次",
+            "那件让我后悔的事：\n\n当时我为什么那样做：\n\n如果重来，我会：\n\n我想对当时的自己说：\n",
+            vec![EmotionTag::Regret, EmotionTag::Sadness],
+        ),
+        template(
+            "letter",
+            "写给过去的信",
+            "致从前",
+            "想念的人或时光：\n\n还记得的细节：\n\n想告诉 TA 的话：\n",
+            vec![EmotionTag::Nostalgia, EmotionTag::Attachment],
+        ),
+        template(
+            "future",
+            "写给未来的自己",
+            "致未来",
+            "我正在坚持的事：\n\n希望一年后的自己：\n",
+            vec![EmotionTag::Hope, EmotionTag::Persistence],
+        ),
+    ]
+}
+
+/// 按情感标签推荐模板
+///
+/// 只返回至少关联一个给定标签的模板，匹配标签多的排在前面，匹配数相同时保持原顺序。
+pub fn suggest_templates_for_emotions(templates: &[EntryTemplate], tags: &[EmotionTag]) -> Vec<EntryTemplate> {
+    let mut matches: Vec<(usize, &EntryTemplate)> = templates
+        .iter()
+        .map(|template| (template.emotion_tags.iter().filter(|tag| tags.contains(tag)).count(), template))
+        .filter(|(count, _)| *count > 0)
+        .collect();
+    matches.sort_by_key(|(count, _)| std::cmp::Reverse(*count));
+    matches.into_iter().map(|(_, template)| template.clone()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_suggest_templates_for_regret() {
+        let suggestions = suggest_templates_for_emotions(&builtin_templates(), &[EmotionTag::Regret]);
+        let ids: Vec<&str> = suggestions.iter().map(|t| t.id.as_str()).collect();
+        assert_eq!(ids, vec!["reflection"]);
+    }
+
+    #[test]
+    fn test_suggestions_rank_by_matching_tags() {
+        let suggestions = suggest_templates_for_emotions(
+            &builtin_templates(),
+            &[EmotionTag::Hope, EmotionTag::Persistence],
+        );
+        let ids: Vec<&str> = suggestions.iter().map(|t| t.id.as_str()).collect();
+        assert_eq!(ids, vec!["future", "gratitude"]);
+
+        assert!(suggest_templates_for_emotions(&builtin_templates(), &[]).is_empty());
+        let custom = EmotionTag::try_from("平静".to_string()).unwrap();
+        assert!(suggest_templates_for_emotions(&builtin_templates(), &[custom]).is_empty());
+    }
+}
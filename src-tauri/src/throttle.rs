@@ -0,0 +1,96 @@
+/**
+ * 解密尝试限流模块
+ * 连续解密失败后按指数退避延长等待时间，提高在线暴力破解的成本
+ */
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+/// 解密尝试限流器
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AttemptThrottle {
+    failures: u32,
+    locked_until: Option<DateTime<Utc>>,
+}
+
+impl AttemptThrottle {
+    /// 不触发等待的连续失败次数
+    const FREE_ATTEMPTS: u32 = 3;
+    const BASE_DELAY_SECS: i64 = 1;
+    const MAX_DELAY_SECS: i64 = 300;
+
+    /// 创建新的限流器
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 连续失败次数
+    pub fn failures(&self) -> u32 {
+        self.failures
+    }
+
+    /// 检查当前是否允许尝试，被锁定时返回剩余等待时间
+    pub fn check(&self, now: DateTime<Utc>) -> Result<(), Duration> {
+        match self.locked_until {
+            Some(until) if until > now => Err(until - now),
+            _ => Ok(()),
+        }
+    }
+
+    /// 记录一次失败，并按失败次数计算下一次允许尝试的时间
+    pub fn record_failure(&mut self, now: DateTime<Utc>) {
+        self.failures = self.failures.saturating_add(1);
+        let delay = Self::delay_for(self.failures);
+        self.locked_until = if delay > Duration::zero() { Some(now + delay) } else { None };
+    }
+
+    /// 记录一次成功，重置计数
+    pub fn record_success(&mut self) {
+        self.failures = 0;
+        self.locked_until = None;
+    }
+
+    /// 计算给定失败次数对应的等待时间
+    pub fn delay_for(failures: u32) -> Duration {
+        if failures < Self::FREE_ATTEMPTS {
+            return Duration::zero();
+        }
+
+        let exponent = (failures - Self::FREE_ATTEMPTS).min(16);
+        let secs = (Self::BASE_DELAY_SECS << exponent).min(Self::MAX_DELAY_SECS);
+        Duration::seconds(secs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_grows_and_resets() {
+        let now = Utc::now();
+        let mut throttle = AttemptThrottle::new();
+
+        throttle.record_failure(now);
+        throttle.record_failure(now);
+        assert!(throttle.check(now).is_ok());
+
+        throttle.record_failure(now);
+        assert_eq!(throttle.check(now).unwrap_err(), Duration::seconds(1));
+        assert!(throttle.check(now + Duration::seconds(1)).is_ok());
+
+        throttle.record_failure(now);
+        assert_eq!(throttle.check(now).unwrap_err(), Duration::seconds(2));
+
+        throttle.record_success();
+        assert_eq!(throttle.failures(), 0);
+        assert!(throttle.check(now).is_ok());
+    }
+
+    #[test]
+    fn test_delay_is_capped() {
+        assert_eq!(AttemptThrottle::delay_for(2), Duration::zero());
+        assert_eq!(AttemptThrottle::delay_for(5), Duration::seconds(4));
+        assert_eq!(AttemptThrottle::delay_for(100), Duration::seconds(300));
+    }
+}
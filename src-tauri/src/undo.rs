@@ -0,0 +1,272 @@
+/**
+ * 撤销/重做模块
+ * 记录每次修改前后发生变化的条目，撤销时恢复修改前的状态
+ */
+
+use crate::models::MemoryEntry;
+use anyhow::{Result, anyhow};
+use std::collections::VecDeque;
+
+/// 最多保留的撤销步数
+pub const MAX_UNDO_DEPTH: usize = 50;
+
+/// 条目的状态及其在列表中的位置，None 表示条目不存在
+pub type EntryState = Option<(usize, MemoryEntry)>;
+
+/// 单个条目在修改前后的状态
+#[derive(Debug, Clone)]
+pub struct EntryChange {
+    pub entry_id: String,
+    pub before: EntryState,
+    pub after: EntryState,
+}
+
+/// 一次可撤销的修改
+#[derive(Debug, Clone)]
+pub struct UndoRecord {
+    /// 修改的描述，如“删除条目”
+    pub label: String,
+    pub changes: Vec<EntryChange>,
+}
+
+impl UndoRecord {
+    /// 比较修改前后的条目列表，只记录发生变化的条目；没有变化时返回 None
+    pub fn diff(label: &str, before: &[MemoryEntry], after: &[MemoryEntry]) -> Option<Self> {
+        let mut changes = Vec::new();
+
+        for (index, old) in before.iter().enumerate() {
+            let new = find_entry(after, &old.id);
+            if !same_entry(Some(old), new.map(|(_, e)| e)) {
+                changes.push(EntryChange {
+                    entry_id: old.id.clone(),
+                    before: Some((index, old.clone())),
+                    after: new.map(|(i, e)| (i, e.clone())),
+                });
+            }
+        }
+        for (index, new) in after.iter().enumerate() {
+            if find_entry(before, &new.id).is_none() {
+                changes.push(EntryChange {
+                    entry_id: new.id.clone(),
+                    before: None,
+                    after: Some((index, new.clone())),
+                });
+            }
+        }
+
+        if changes.is_empty() {
+            None
+        } else {
+            Some(Self { label: label.to_string(), changes })
+        }
+    }
+
+    /// 撤销：把条目恢复为修改前的状态
+    ///
+    /// 条目在记录之后又被改动时返回错误，且不修改 `entries`。
+    pub fn revert(&self, entries: &mut Vec<MemoryEntry>) -> Result<()> {
+        let states: Vec<_> = self.changes.iter().map(|c| (&c.entry_id, &c.after, &c.before)).collect();
+        apply_states(entries, &states)
+    }
+
+    /// 重做：再次应用修改后的状态
+    pub fn reapply(&self, entries: &mut Vec<MemoryEntry>) -> Result<()> {
+        let states: Vec<_> = self.changes.iter().map(|c| (&c.entry_id, &c.before, &c.after)).collect();
+        apply_states(entries, &states)
+    }
+}
+
+/// 有界的撤销栈与重做栈（仅保存在内存中）
+#[derive(Debug, Default)]
+pub struct UndoStack {
+    undo: VecDeque<UndoRecord>,
+    redo: Vec<UndoRecord>,
+}
+
+impl UndoStack {
+    /// 记录新的修改，超出上限时丢弃最早的记录，并清空重做栈
+    pub fn push(&mut self, record: UndoRecord) {
+        self.undo.push_back(record);
+        if self.undo.len() > MAX_UNDO_DEPTH {
+            self.undo.pop_front();
+        }
+        self.redo.clear();
+    }
+
+    /// 查看最近一次可撤销的修改（不取出）
+    pub fn peek_undo(&self) -> Option<&UndoRecord> {
+        self.undo.back()
+    }
+
+    /// 查看最近一次撤销的修改（不取出）
+    pub fn peek_redo(&self) -> Option<&UndoRecord> {
+        self.redo.last()
+    }
+
+    /// 取出最近一次可撤销的修改
+    pub fn pop_undo(&mut self) -> Option<UndoRecord> {
+        self.undo.pop_back()
+    }
+
+    /// 取出最近一次撤销的修改
+    pub fn pop_redo(&mut self) -> Option<UndoRecord> {
+        self.redo.pop()
+    }
+
+    /// 撤销成功后放入重做栈
+    pub fn push_redo(&mut self, record: UndoRecord) {
+        self.redo.push(record);
+    }
+
+    /// 重做成功后放回撤销栈（不清空重做栈）
+    pub fn push_undone(&mut self, record: UndoRecord) {
+        self.undo.push_back(record);
+        if self.undo.len() > MAX_UNDO_DEPTH {
+            self.undo.pop_front();
+        }
+    }
+
+    /// 存储已被其他修改改变，清空全部记录
+    pub fn invalidate(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
+
+/// 按 ID 查找条目及其位置
+fn find_entry<'a>(entries: &'a [MemoryEntry], entry_id: &str) -> Option<(usize, &'a MemoryEntry)> {
+    entries.iter().enumerate().find(|(_, e)| e.id == entry_id)
+}
+
+/// 比较两个条目状态是否完全相同
+fn same_entry(a: Option<&MemoryEntry>, b: Option<&MemoryEntry>) -> bool {
+    match (a, b) {
+        (None, None) => true,
+        (Some(a), Some(b)) => serde_json::to_value(a).ok() == serde_json::to_value(b).ok(),
+        _ => false,
+    }
+}
+
+/// 校验每个条目当前为 `expected` 状态后，改为 `target` 状态
+fn apply_states(
+    entries: &mut Vec<MemoryEntry>,
+    states: &[(&String, &EntryState, &EntryState)],
+) -> Result<()> {
+    for (entry_id, expected, _) in states {
+        let current = find_entry(entries, entry_id).map(|(_, e)| e);
+        if !same_entry(current, expected.as_ref().map(|(_, e)| e)) {
+            return Err(anyhow!("Entry {} has changed since this action, cannot undo", entry_id));
+        }
+    }
+
+    for (entry_id, _, _) in states {
+        entries.retain(|e| &e.id != *entry_id);
+    }
+
+    // 按原位置从前往后插回，保证位置与修改前一致
+    let mut restored: Vec<&(usize, MemoryEntry)> = states.iter().filter_map(|(_, _, target)| target.as_ref()).collect();
+    restored.sort_by_key(|(index, _)| *index);
+    for (index, entry) in restored {
+        let index = (*index).min(entries.len());
+        entries.insert(index, entry.clone());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::MemoryType;
+
+    fn entries(titles: &[&str]) -> Vec<MemoryEntry> {
+        titles
+            .iter()
+            .map(|t| MemoryEntry::new(t.to_string(), "内容".to_string(), MemoryType::Text))
+            .collect()
+    }
+
+    fn titles(entries: &[MemoryEntry]) -> Vec<&str> {
+        entries.iter().map(|e| e.title.as_str()).collect()
+    }
+
+    #[test]
+    fn test_undo_and_redo_delete() {
+        let before = entries(&["一", "二", "三"]);
+        let mut store = before.clone();
+        store.remove(1);
+
+        let record = UndoRecord::diff("删除条目", &before, &store).unwrap();
+        assert_eq!(record.changes.len(), 1);
+
+        record.revert(&mut store).unwrap();
+        assert_eq!(titles(&store), vec!["一", "二", "三"]);
+
+        record.reapply(&mut store).unwrap();
+        assert_eq!(titles(&store), vec!["一", "三"]);
+    }
+
+    #[test]
+    fn test_undo_edit_and_create() {
+        let before = entries(&["桃花"]);
+        let mut store = before.clone();
+        store[0].title = "桃花笺".to_string();
+        store.extend(entries(&["新条目"]));
+
+        let record = UndoRecord::diff("编辑条目", &before, &store).unwrap();
+        record.revert(&mut store).unwrap();
+        assert_eq!(titles(&store), vec!["桃花"]);
+        assert_eq!(store[0].id, before[0].id);
+
+        assert!(UndoRecord::diff("无变化", &store, &before).is_none());
+    }
+
+    #[test]
+    fn test_undo_refuses_when_store_changed() {
+        let before = entries(&["一"]);
+        let mut store = before.clone();
+        store[0].title = "改一次".to_string();
+        let record = UndoRecord::diff("编辑条目", &before, &store).unwrap();
+
+        // 记录之后条目又被改动
+        store[0].title = "改两次".to_string();
+        assert!(record.revert(&mut store).is_err());
+        assert_eq!(titles(&store), vec!["改两次"]);
+    }
+
+    #[test]
+    fn test_stack_is_bounded_and_push_clears_redo() {
+        let mut stack = UndoStack::default();
+        let before = entries(&["一"]);
+        for i in 0..MAX_UNDO_DEPTH + 5 {
+            let mut after = before.clone();
+            after[0].title = i.to_string();
+            stack.push(UndoRecord::diff(&i.to_string(), &before, &after).unwrap());
+        }
+        assert_eq!(stack.undo.len(), MAX_UNDO_DEPTH);
+
+        let record = stack.pop_undo().unwrap();
+        assert_eq!(record.label, (MAX_UNDO_DEPTH + 4).to_string());
+        stack.push_redo(record.clone());
+        stack.push(record);
+        assert!(stack.pop_redo().is_none());
+    }
+
+    #[test]
+    fn test_peek_leaves_record_on_stack() {
+        let mut stack = UndoStack::default();
+        let before = entries(&["一"]);
+        let mut after = before.clone();
+        after[0].title = "二".to_string();
+        stack.push(UndoRecord::diff("编辑条目", &before, &after).unwrap());
+
+        assert_eq!(stack.peek_undo().unwrap().label, "编辑条目");
+        assert_eq!(stack.peek_undo().unwrap().label, "编辑条目");
+        assert!(stack.peek_redo().is_none());
+
+        let record = stack.pop_undo().unwrap();
+        stack.push_redo(record);
+        assert!(stack.peek_undo().is_none());
+        assert_eq!(stack.peek_redo().unwrap().label, "编辑条目");
+    }
+}
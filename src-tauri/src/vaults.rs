@@ -0,0 +1,233 @@
+/**
+ * 多保险库模块
+ * 命名保险库位于数据目录下的 `vaults/<名称>`，各自拥有独立的记忆与设置文件；
+ * 当前使用的保险库记录在数据目录顶层的 `app.json` 中
+ */
+
+use crate::models::{AppConfig, VaultInfo};
+use anyhow::{Result, anyhow};
+use std::path::PathBuf;
+use tokio::fs;
+
+/// 默认保险库名称，对应数据目录本身（兼容引入多保险库之前的数据）
+pub const DEFAULT_VAULT: &str = "default";
+
+/// 保险库名称的最大字符数
+const MAX_VAULT_NAME_CHARS: usize = 64;
+
+/// 保险库注册表
+pub struct VaultRegistry {
+    root: PathBuf,
+    config_file: PathBuf,
+    vaults_dir: PathBuf,
+}
+
+impl VaultRegistry {
+    /// 以应用数据目录为根创建注册表
+    pub fn new(app_data_dir: &str) -> Self {
+        let root = PathBuf::from(app_data_dir);
+        Self {
+            config_file: root.join("app.json"),
+            vaults_dir: root.join("vaults"),
+            root,
+        }
+    }
+
+    /// 保险库的数据目录，默认保险库即数据目录本身
+    pub fn vault_dir(&self, name: &str) -> PathBuf {
+        if name == DEFAULT_VAULT {
+            self.root.clone()
+        } else {
+            self.vaults_dir.join(name)
+        }
+    }
+
+    /// 当前使用的保险库名称
+    ///
+    /// `app.json` 不存在、无法解析或记录的保险库已不存在时使用默认保险库。
+    pub async fn active(&self) -> String {
+        let config = self.load_config().await;
+        match config.active_vault {
+            Some(name) if validate_vault_name(&name).is_ok() && self.vault_dir(&name).is_dir() => name,
+            _ => DEFAULT_VAULT.to_string(),
+        }
+    }
+
+    /// 列出所有保险库，默认保险库在最前，其余按名称排序
+    pub async fn list(&self) -> Result<Vec<VaultInfo>> {
+        let active = self.active().await;
+        let mut names = Vec::new();
+        if self.vaults_dir.is_dir() {
+            let mut items = fs::read_dir(&self.vaults_dir).await
+                .map_err(|e| anyhow!("Failed to read vaults directory: {}", e))?;
+            while let Some(item) = items.next_entry().await
+                .map_err(|e| anyhow!("Failed to read vaults directory: {}", e))? {
+                if item.path().is_dir() {
+                    if let Some(name) = item.file_name().to_str() {
+                        names.push(name.to_string());
+                    }
+                }
+            }
+        }
+        names.sort();
+        names.insert(0, DEFAULT_VAULT.to_string());
+
+        Ok(names
+            .into_iter()
+            .map(|name| VaultInfo { active: name == active, name })
+            .collect())
+    }
+
+    /// 创建新的空保险库，返回规范化后的名称
+    pub async fn create(&self, name: &str) -> Result<String> {
+        let name = validate_vault_name(name)?;
+        let dir = self.vault_dir(&name);
+        if dir.exists() {
+            return Err(anyhow!("Vault '{}' already exists", name));
+        }
+
+        fs::create_dir_all(&dir).await
+            .map_err(|e| anyhow!("Failed to create vault directory: {}", e))?;
+        Ok(name)
+    }
+
+    /// 切换当前保险库
+    pub async fn switch(&self, name: &str) -> Result<()> {
+        let name = name.trim();
+        if name != DEFAULT_VAULT {
+            validate_vault_name(name)?;
+            if !self.vault_dir(name).is_dir() {
+                return Err(anyhow!("Vault '{}' does not exist", name));
+            }
+        }
+
+        let mut config = self.load_config().await;
+        config.active_vault = Some(name.to_string());
+        self.save_config(&config).await
+    }
+
+    /// 删除保险库及其全部数据
+    ///
+    /// 默认保险库与当前使用的保险库不能删除。
+    pub async fn delete(&self, name: &str) -> Result<()> {
+        let name = name.trim();
+        if name == DEFAULT_VAULT {
+            return Err(anyhow!("The default vault cannot be deleted"));
+        }
+        validate_vault_name(name)?;
+        if self.active().await == name {
+            return Err(anyhow!("Cannot delete the active vault, switch to another vault first"));
+        }
+
+        let dir = self.vault_dir(name);
+        if !dir.is_dir() {
+            return Err(anyhow!("Vault '{}' does not exist", name));
+        }
+        fs::remove_dir_all(&dir).await
+            .map_err(|e| anyhow!("Failed to delete vault: {}", e))
+    }
+
+    /// 读取 `app.json`，不存在或无法解析时返回默认值
+    async fn load_config(&self) -> AppConfig {
+        match fs::read_to_string(&self.config_file).await {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_default(),
+            Err(_) => AppConfig::default(),
+        }
+    }
+
+    /// 写入 `app.json`
+    async fn save_config(&self, config: &AppConfig) -> Result<()> {
+        fs::create_dir_all(&self.root).await
+            .map_err(|e| anyhow!("Failed to create data directory: {}", e))?;
+        let content = serde_json::to_string_pretty(config)
+            .map_err(|e| anyhow!("Failed to serialize app config: {}", e))?;
+        fs::write(&self.config_file, content).await
+            .map_err(|e| anyhow!("Failed to write app config: {}", e))
+    }
+}
+
+/// 校验保险库名称：只允许文字、数字、空格、`-` 和 `_`，不能与默认保险库重名
+fn validate_vault_name(name: &str) -> Result<String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(anyhow!("Vault name cannot be empty"));
+    }
+    if name.chars().count() > MAX_VAULT_NAME_CHARS {
+        return Err(anyhow!("Vault name must be at most {} characters", MAX_VAULT_NAME_CHARS));
+    }
+    if name == DEFAULT_VAULT {
+        return Err(anyhow!("'{}' is reserved", DEFAULT_VAULT));
+    }
+    if !name.chars().all(|c| c.is_alphanumeric() || c == ' ' || c == '-' || c == '_') {
+        return Err(anyhow!("Vault name may only contain letters, digits, spaces, '-' and '_'"));
+    }
+    Ok(name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{MemoryEntry, MemoryType};
+    use crate::storage::{Storage, StorageManager};
+
+    fn temp_registry() -> VaultRegistry {
+        let dir = std::env::temp_dir().join(format!("peach-blossom-test-{}", uuid::Uuid::new_v4()));
+        VaultRegistry::new(dir.to_str().unwrap())
+    }
+
+    async fn open_active(registry: &VaultRegistry) -> StorageManager {
+        let dir = registry.vault_dir(&registry.active().await);
+        StorageManager::new(dir.to_str().unwrap()).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_vaults_keep_entries_isolated() {
+        let registry = temp_registry();
+        assert_eq!(registry.active().await, DEFAULT_VAULT);
+
+        let personal = MemoryEntry::new("日记".to_string(), "周末去看桃花".to_string(), MemoryType::Text);
+        open_active(&registry).await.save_entry(&personal, None).await.unwrap();
+
+        assert_eq!(registry.create(" 工作 ").await.unwrap(), "工作");
+        assert!(registry.create("工作").await.is_err());
+        registry.switch("工作").await.unwrap();
+        assert_eq!(registry.active().await, "工作");
+
+        let work = open_active(&registry).await;
+        assert!(work.get_all_entries().await.unwrap().is_empty());
+        let meeting = MemoryEntry::new("会议".to_string(), "季度复盘".to_string(), MemoryType::Text);
+        work.save_entry(&meeting, None).await.unwrap();
+
+        registry.switch(DEFAULT_VAULT).await.unwrap();
+        let entries = open_active(&registry).await.get_all_entries().await.unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].id, personal.id);
+
+        let names: Vec<(String, bool)> = registry.list().await.unwrap().into_iter().map(|v| (v.name, v.active)).collect();
+        assert_eq!(names, vec![(DEFAULT_VAULT.to_string(), true), ("工作".to_string(), false)]);
+
+        fs::remove_dir_all(&registry.root).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_vault_names_and_deletion_rules() {
+        let registry = temp_registry();
+        for name in ["", "  ", "../escape", "a/b", ".hidden", DEFAULT_VAULT] {
+            assert!(registry.create(name).await.is_err(), "expected error for {:?}", name);
+        }
+        assert!(registry.switch("missing").await.is_err());
+        assert!(registry.switch("..").await.is_err());
+
+        registry.create("travel").await.unwrap();
+        registry.switch("travel").await.unwrap();
+        assert!(registry.delete("travel").await.is_err());
+        assert!(registry.delete(DEFAULT_VAULT).await.is_err());
+
+        registry.switch(DEFAULT_VAULT).await.unwrap();
+        registry.delete("travel").await.unwrap();
+        assert!(!registry.vault_dir("travel").exists());
+        assert_eq!(registry.list().await.unwrap().len(), 1);
+
+        fs::remove_dir_all(&registry.root).await.unwrap();
+    }
+}